@@ -60,6 +60,23 @@ echo "======================== build aarch32 (sabre) ===========================
 )
 
 
+echo "===================== check aarch32 (sabre, MCS) ==============================="
+(
+    export PATH="${armv7_toolchain_dir}/bin:${PATH}"
+
+    # Only one qemu test runs an MCS kernel, so make sure everything
+    # behind KernelIsMCS still compiles. The qemu test project's kernel
+    # is new enough to have MCS support.
+    mcs_config="${dir}/target/sel4-mcs.toml"
+    sed 's/^\[sel4\.config\]$/&\nKernelIsMCS = true/' \
+        "${dir}/qemu-test/test-project/sel4.toml" > "${mcs_config}"
+
+    SEL4_PLATFORM=sabre \
+        SEL4_CONFIG_PATH="${mcs_config}" \
+        cargo xcheck --target armv7-unknown-linux-gnueabihf --features "test_support"
+)
+
+
 echo "==================== ./ferros-test/test-macro-impl ==========================="
 (
     cd ferros-test/test-macro-impl
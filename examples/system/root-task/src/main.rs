@@ -161,7 +161,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
             .expect("Device untyped was not the right size!");
         let iomuxc_mem = iomux_vspace.map_region(
             UnmappedMemoryRegion::new_device(iomuxc_ut, slots)?,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT & !arch::vm_attributes::PAGE_CACHEABLE,
         )?;
         let params = iomux::ProcParams {
@@ -171,7 +171,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
         let stack_mem: UnmappedMemoryRegion<<resources::Iomux as ElfProc>::StackSizeBits, _> =
             UnmappedMemoryRegion::new(ut, slots).unwrap();
         let stack_mem =
-            root_vspace.map_region(stack_mem, CapRights::RW, arch::vm_attributes::DEFAULT)?;
+            root_vspace.map_region(stack_mem, rights::RW, arch::vm_attributes::DEFAULT)?;
         let mut iomux_process = StandardProcess::new::<iomux::ProcParams<_>, _>(
             &mut iomux_vspace,
             iomux_cnode,
@@ -313,7 +313,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
         let (mem_slots, _tcpip_slots) = tcpip_slots.alloc();
         let socket_buffer_mem = tcpip_vspace.map_region_and_move(
             socket_buffer_mem_unmapped,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT,
             &root_cnode,
             mem_slots,
@@ -327,7 +327,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
             .expect("Device untyped was not the right size!");
         let gpt_mem = tcpip_vspace.map_region(
             UnmappedMemoryRegion::new_device(gpt_ut, slots)?,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT & !arch::vm_attributes::PAGE_CACHEABLE,
         )?;
         let params = tcpip::ProcParams {
@@ -342,7 +342,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
         let stack_mem: UnmappedMemoryRegion<<resources::TcpIp as ElfProc>::StackSizeBits, _> =
             UnmappedMemoryRegion::new(ut, slots).unwrap();
         let stack_mem =
-            root_vspace.map_region(stack_mem, CapRights::RW, arch::vm_attributes::DEFAULT)?;
+            root_vspace.map_region(stack_mem, rights::RW, arch::vm_attributes::DEFAULT)?;
         let mut tcpip_process = StandardProcess::new::<tcpip::ProcParams<_>, _>(
             &mut tcpip_vspace,
             tcpip_cnode,
@@ -370,7 +370,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
             .expect("Device untyped was not the right size!");
         let enet_mem = enet_vspace.map_region(
             UnmappedMemoryRegion::new_device(enet_ut, slots)?,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT & !arch::vm_attributes::PAGE_CACHEABLE,
         )?;
         let dma_mem_unmapped: UnmappedMemoryRegion<enet::EthDmaMemSizeInBits, _> =
@@ -378,7 +378,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
         let (mem_slots, _enet_slots) = enet_slots.alloc();
         let dma_mem = enet_vspace.map_region_and_move(
             dma_mem_unmapped,
-            CapRights::RW,
+            rights::RW,
             // NOTE: driver expects uncached DMA memory for the time being
             arch::vm_attributes::DEFAULT & !arch::vm_attributes::PAGE_CACHEABLE,
             &root_cnode,
@@ -394,7 +394,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
         let stack_mem: UnmappedMemoryRegion<<resources::Enet as ElfProc>::StackSizeBits, _> =
             UnmappedMemoryRegion::new(ut, slots).unwrap();
         let stack_mem =
-            root_vspace.map_region(stack_mem, CapRights::RW, arch::vm_attributes::DEFAULT)?;
+            root_vspace.map_region(stack_mem, rights::RW, arch::vm_attributes::DEFAULT)?;
         let mut enet_process = StandardProcess::new::<enet::ProcParams<_>, _>(
             &mut enet_vspace,
             enet_cnode,
@@ -442,7 +442,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
         let (mem_slots, pstorage_slots) = pstorage_slots.alloc();
         let storage_buffer = pstorage_vspace.map_region_and_move(
             storage_buffer_unmapped,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT,
             &root_cnode,
             mem_slots,
@@ -454,7 +454,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
         let (mem_slots, _pstorage_slots) = pstorage_slots.alloc();
         let scratchpad_buffer = pstorage_vspace.map_region_and_move(
             scratchpad_buffer_unmapped,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT,
             &root_cnode,
             mem_slots,
@@ -468,7 +468,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
             .expect("Device untyped was not the right size!");
         let spi1_mem = pstorage_vspace.map_region(
             UnmappedMemoryRegion::new_device(spi1_ut, slots)?,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT & !arch::vm_attributes::PAGE_CACHEABLE,
         )?;
         let gpio3_ut = dev_allocator
@@ -480,7 +480,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
             .expect("Device untyped was not the right size!");
         let gpio3_mem = pstorage_vspace.map_region(
             UnmappedMemoryRegion::new_device(gpio3_ut, slots)?,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT & !arch::vm_attributes::PAGE_CACHEABLE,
         )?;
         let params = persistent_storage::ProcParams {
@@ -496,7 +496,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
             _,
         > = UnmappedMemoryRegion::new(ut, slots).unwrap();
         let stack_mem =
-            root_vspace.map_region(stack_mem, CapRights::RW, arch::vm_attributes::DEFAULT)?;
+            root_vspace.map_region(stack_mem, rights::RW, arch::vm_attributes::DEFAULT)?;
         let mut pstorage_process = StandardProcess::new::<persistent_storage::ProcParams<_>, _>(
             &mut pstorage_vspace,
             pstorage_cnode,
@@ -555,7 +555,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
         )?;
         let uart1_mem = console_vspace.map_region(
             UnmappedMemoryRegion::new_device(uart1_ut, slots)?,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT & !arch::vm_attributes::PAGE_CACHEABLE,
         )?;
         let console_buffer_unmapped: UnmappedMemoryRegion<console::ConsoleBufferSizeBits, _> =
@@ -563,7 +563,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
         let (mem_slots, _console_slots) = console_slots.alloc();
         let console_buffer = console_vspace.map_region_and_move(
             console_buffer_unmapped,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT,
             &root_cnode,
             mem_slots,
@@ -578,7 +578,7 @@ fn run(raw_bootinfo: &'static selfe_sys::seL4_BootInfo) -> Result<(), TopLevelEr
         let stack_mem: UnmappedMemoryRegion<<resources::Console as ElfProc>::StackSizeBits, _> =
             UnmappedMemoryRegion::new(ut, slots).unwrap();
         let stack_mem =
-            root_vspace.map_region(stack_mem, CapRights::RW, arch::vm_attributes::DEFAULT)?;
+            root_vspace.map_region(stack_mem, rights::RW, arch::vm_attributes::DEFAULT)?;
         let mut console_process = StandardProcess::new::<console::ProcParams<_>, _>(
             &mut console_vspace,
             console_cnode,
@@ -70,6 +70,42 @@ fn round_up_to_page_boundary(addr: u64) -> u64 {
     }
 }
 
+/// The number of pages a thread's TLS block takes up, given the size and
+/// alignment of the elf's `PT_TLS` segment. The exact layout depends on the
+/// target's TLS variant; this covers the largest of them, which has a
+/// 16-byte TCB ahead of the data and a self-pointer after it.
+fn tls_block_pages(mem_size: u64, align: u64) -> u64 {
+    let align = std::cmp::max(align, 1);
+    let round_up = |n: u64| (n + align - 1) / align * align;
+    let block_size = round_up(16) + round_up(mem_size) + 8;
+    round_up_to_page_boundary(block_size) >> 12
+}
+
+/// What it takes to set up one instance of an elf process.
+#[derive(Debug, PartialEq)]
+struct ElfPageCosts {
+    /// Read-only pages, mapped from the user image and shared between
+    /// instances. These need a slot per instance, but no memory.
+    shared_pages: u64,
+    /// Pages retyped from each instance's own memory to hold its
+    /// writable segments. The memory comes as a single untyped, so this
+    /// is the writable page count rounded up to a power of two.
+    instance_pages: u64,
+    /// The size of the untyped each instance needs, as a bitsize.
+    required_memory_bits: u64,
+}
+
+impl ElfPageCosts {
+    fn new(read_only_pages: u64, writable_pages: u64) -> Self {
+        let required_memory_bits = (writable_pages as f64).log2().ceil() as u64 + 12;
+        ElfPageCosts {
+            shared_pages: read_only_pages,
+            instance_pages: 1 << (required_memory_bits - 12),
+            required_memory_bits,
+        }
+    }
+}
+
 impl Resource for ElfResource {
     fn path(&self) -> &Path {
         &self.path
@@ -89,6 +125,7 @@ impl Resource for ElfResource {
 
         let mut read_only_pages = 0;
         let mut writable_pages = 0;
+        let mut tls_pages = 0;
 
         for ph in elf_file
             .program_iter()
@@ -105,6 +142,13 @@ impl Resource for ElfResource {
             }
         }
 
+        if let Some(ph) = elf_file
+            .program_iter()
+            .find(|h| h.get_type() == Ok(xmas_elf::program::Type::Tls))
+        {
+            tls_pages = tls_block_pages(ph.mem_size(), ph.align());
+        }
+
         let stack_size_bits = self
             .stack_size_bits
             .map(|ssb| ssb as u64)
@@ -116,8 +160,9 @@ impl Resource for ElfResource {
                 16u64
             });
 
-        let required_memory_bits = (writable_pages as f64).log2().ceil() as u32 + 12;
-        let required_pages = (1 << (required_memory_bits - 12)) + read_only_pages;
+        // The initial thread's TLS block is made from the same memory as
+        // the writable segments
+        let costs = ElfPageCosts::new(read_only_pages, writable_pages + tls_pages);
 
         format!(
             r#"
@@ -125,7 +170,10 @@ pub struct {} {{ }}
 impl ferros::vspace::ElfProc for {} {{
     const IMAGE_NAME: &'static str = "{}";
     type RequiredPages = {};
+    type SharedPages = {};
+    type InstancePages = {};
     type WritablePages = {};
+    type TLSPages = {};
     type RequiredMemoryBits = {};
     type StackSizeBits = {};
 }}
@@ -133,9 +181,12 @@ impl ferros::vspace::ElfProc for {} {{
             self.type_name,
             self.type_name,
             self.image_name,
-            format_as_typenum(required_pages),
+            format_as_typenum(costs.shared_pages + costs.instance_pages),
+            format_as_typenum(costs.shared_pages),
+            format_as_typenum(costs.instance_pages),
             format_as_typenum(writable_pages),
-            format_as_typenum(required_memory_bits.into()),
+            format_as_typenum(tls_pages),
+            format_as_typenum(costs.required_memory_bits),
             format_as_typenum(stack_size_bits)
         )
     }
@@ -185,4 +236,41 @@ mod test {
         assert_eq!(format_as_typenum(4), "typenum::UInt<typenum::UInt<typenum::UInt<typenum::UTerm, typenum::B1>, typenum::B0>, typenum::B0>".to_string());
     }
 
+    #[test]
+    fn test_elf_page_costs() {
+        assert_eq!(
+            ElfPageCosts::new(10, 3),
+            ElfPageCosts {
+                shared_pages: 10,
+                instance_pages: 4,
+                required_memory_bits: 14,
+            }
+        );
+        assert_eq!(
+            ElfPageCosts::new(7, 4),
+            ElfPageCosts {
+                shared_pages: 7,
+                instance_pages: 4,
+                required_memory_bits: 14,
+            }
+        );
+        assert_eq!(
+            ElfPageCosts::new(5, 1),
+            ElfPageCosts {
+                shared_pages: 5,
+                instance_pages: 1,
+                required_memory_bits: 12,
+            }
+        );
+    }
+
+    #[test]
+    fn test_tls_block_pages() {
+        assert_eq!(tls_block_pages(0x20, 8), 1);
+        assert_eq!(tls_block_pages(0x1000 - 24, 8), 1);
+        assert_eq!(tls_block_pages(0x1000 - 23, 8), 2);
+        assert_eq!(tls_block_pages(0x1000, 0x1000), 3);
+        assert_eq!(tls_block_pages(0x10, 0), 1);
+    }
+
 }
@@ -23,6 +23,11 @@ pub mod alloc {
             Ok((LocalCap(PhantomData), UTBuddy(PhantomData)))
         }
     }
+
+    pub mod micro_alloc {
+        pub struct Allocator;
+        pub struct DeviceAllocator;
+    }
 }
 pub mod arch {
     pub struct PagingRoot;
@@ -38,6 +43,13 @@ pub mod vspace {
     use core::marker::PhantomData;
     pub struct ScratchRegion<'a, 'b, T = ()>(pub PhantomData<&'a T>, pub PhantomData<&'b T>);
     pub struct MappedMemoryRegion<T, SS: SharedStatus>(PhantomData<T>, PhantomData<SS>);
+    pub struct VSpace<State = vspace_state::Imaged, Role = crate::cap::role::Local>(
+        PhantomData<State>,
+        PhantomData<Role>,
+    );
+    pub mod vspace_state {
+        pub struct Imaged;
+    }
     pub trait SharedStatus {}
     pub mod shared_status {
         pub struct Exclusive;
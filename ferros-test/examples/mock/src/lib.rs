@@ -1,3 +1,4 @@
+use ferros::alloc::micro_alloc::{Allocator, DeviceAllocator};
 use ferros::bootstrap::*;
 use ferros::cap::*;
 use ferros::test_support::*;
@@ -56,6 +57,14 @@ fn localcap_localcnode_parameter(node: &LocalCap<LocalCNode>) {}
 #[ferros_test]
 fn localcap_threadpriorityauthority_parameter(tpa: &LocalCap<ThreadPriorityAuthority>) {}
 
+#[ferros_test]
+fn harness_parameters(
+    vspace: &mut VSpace,
+    device_allocator: &mut DeviceAllocator,
+    allocator: &mut Allocator,
+) {
+}
+
 #[ferros_test]
 fn userimage_parameter(image: &UserImage<ferros::cap::role::Local>) {}
 
@@ -199,6 +199,7 @@ fn local_allocations<G: IdGenerator>(
             }
             ParamKind::IRQControl => (parse_quote!({}), irq_control.clone()),
             ParamKind::VSpaceScratch => (parse_quote!({}), scratch.clone()),
+            ParamKind::Harness(resource) => (parse_quote!({}), resource.input_ident()),
             ParamKind::MappedMemoryRegion => {
                 // TODO - be sure that split/alloc prevents making too-small of regions
                 // such that page alignment would be violated
@@ -266,6 +267,11 @@ fn run_test_decl() -> FnDecl {
             ferros::cap::LocalCap<ferros::cap::ASIDPool<ferros::test_support::MaxTestASIDPoolSize>>
     ));
     run_test_inputs.push(parse_quote!(scratch: &mut ferros::vspace::ScratchRegion));
+    for resource in HarnessResource::ALL.iter() {
+        let ident = resource.input_ident();
+        let ty = resource.input_type();
+        run_test_inputs.push(parse_quote!(#ident: &mut #ty));
+    }
     run_test_inputs.push(parse_quote!(
         mapped_memory_region:
             ferros::vspace::MappedMemoryRegion<
@@ -349,6 +355,10 @@ mod tests {
                 asid_pool: ferros::cap::LocalCap<
                     ferros::cap::ASIDPool<ferros::test_support::MaxTestASIDPoolSize>>,
                 scratch: &mut ferros::vspace::ScratchRegion,
+                local_vspace: &mut ferros::vspace::VSpace<
+                    ferros::vspace::vspace_state::Imaged, ferros::cap::role::Local,>,
+                device_allocator: &mut ferros::alloc::micro_alloc::DeviceAllocator,
+                allocator: &mut ferros::alloc::micro_alloc::Allocator,
                 mapped_memory_region: ferros::vspace::MappedMemoryRegion<
                     ferros::test_support::MaxMappedMemoryRegionBitSize, ferros::vspace::shared_status::Exclusive,>,
                 local_cnode: &ferros::cap::LocalCap<ferros::cap::LocalCNode>,
@@ -412,6 +422,10 @@ mod tests {
                 asid_pool: ferros::cap::LocalCap<
                     ferros::cap::ASIDPool<ferros::test_support::MaxTestASIDPoolSize>>,
                 scratch: &mut ferros::vspace::ScratchRegion,
+                local_vspace: &mut ferros::vspace::VSpace<
+                    ferros::vspace::vspace_state::Imaged, ferros::cap::role::Local,>,
+                device_allocator: &mut ferros::alloc::micro_alloc::DeviceAllocator,
+                allocator: &mut ferros::alloc::micro_alloc::Allocator,
                 mapped_memory_region: ferros::vspace::MappedMemoryRegion<
                     ferros::test_support::MaxMappedMemoryRegionBitSize, ferros::vspace::shared_status::Exclusive,>,
                 local_cnode: &ferros::cap::LocalCap<ferros::cap::LocalCNode>,
@@ -479,6 +493,10 @@ mod tests {
                 asid_pool: ferros::cap::LocalCap<
                     ferros::cap::ASIDPool<ferros::test_support::MaxTestASIDPoolSize>>,
                 scratch: &mut ferros::vspace::ScratchRegion,
+                local_vspace: &mut ferros::vspace::VSpace<
+                    ferros::vspace::vspace_state::Imaged, ferros::cap::role::Local,>,
+                device_allocator: &mut ferros::alloc::micro_alloc::DeviceAllocator,
+                allocator: &mut ferros::alloc::micro_alloc::Allocator,
                 mapped_memory_region: ferros::vspace::MappedMemoryRegion<
                     ferros::test_support::MaxMappedMemoryRegionBitSize, ferros::vspace::shared_status::Exclusive,>,
                 local_cnode: &ferros::cap::LocalCap<ferros::cap::LocalCNode>,
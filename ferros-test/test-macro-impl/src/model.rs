@@ -1,5 +1,5 @@
 use proc_macro2::{Span, TokenStream as TokenStream2};
-use syn::{Error as SynError, Ident, ItemFn};
+use syn::{parse_quote, Error as SynError, Ident, ItemFn, Type};
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct SynContent {
@@ -41,6 +41,7 @@ pub(crate) enum ParamKind {
     ASIDPool { count: usize },
     MappedMemoryRegion,
     VSpaceScratch,
+    Harness(HarnessResource),
     CNode,
     ThreadPriorityAuthority,
     UserImage,
@@ -48,13 +49,67 @@ pub(crate) enum ParamKind {
     PagingRoot,
 }
 
+/// Harness state a test borrows as `&mut T`. Each test is handed its
+/// own copy, which the harness undoes whatever was done with once the
+/// test returns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum HarnessResource {
+    VSpace,
+    DeviceAllocator,
+    Allocator,
+}
+
+impl HarnessResource {
+    /// In the order they're passed to a runnable test.
+    pub(crate) const ALL: [HarnessResource; 3] = [
+        HarnessResource::VSpace,
+        HarnessResource::DeviceAllocator,
+        HarnessResource::Allocator,
+    ];
+
+    /// The last segment of the type a test asks for this resource by.
+    pub(crate) fn type_name(self) -> &'static str {
+        match self {
+            HarnessResource::VSpace => "VSpace",
+            HarnessResource::DeviceAllocator => "DeviceAllocator",
+            HarnessResource::Allocator => "Allocator",
+        }
+    }
+
+    /// The runnable test's input this resource is passed in.
+    pub(crate) fn input_ident(self) -> Ident {
+        let name = match self {
+            HarnessResource::VSpace => "local_vspace",
+            HarnessResource::DeviceAllocator => "device_allocator",
+            HarnessResource::Allocator => "allocator",
+        };
+        Ident::new(name, Span::call_site())
+    }
+
+    /// The full type this resource is passed as, behind a `&mut`.
+    pub(crate) fn input_type(self) -> Type {
+        match self {
+            HarnessResource::VSpace => parse_quote!(
+                ferros::vspace::VSpace<
+                    ferros::vspace::vspace_state::Imaged,
+                    ferros::cap::role::Local,
+                >
+            ),
+            HarnessResource::DeviceAllocator => {
+                parse_quote!(ferros::alloc::micro_alloc::DeviceAllocator)
+            }
+            HarnessResource::Allocator => parse_quote!(ferros::alloc::micro_alloc::Allocator),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum ParseError {
     InvalidArgumentType { msg: String, span: Span },
     InvalidTestAttribute { span: Span },
     InvalidTestFn { span: Span },
     InvalidReturnType { span: Span },
-    ArgumentConstraint { msg: &'static str, span: Span },
+    ArgumentConstraint { msg: String, span: Span },
 }
 
 impl std::fmt::Display for ParseError {
@@ -64,7 +119,7 @@ impl std::fmt::Display for ParseError {
             ParseError::InvalidTestAttribute { .. } => "Invalid test attribute found. Try `#[ferros_test]` or `#[ferros_test(process)]` or `#[ferros_test(local)]`",
             ParseError::InvalidTestFn { .. } => "Test function could not be parsed as a fn item",
             ParseError::InvalidReturnType { .. } => "Invalid return type, prefer returning either TestOutcome or a Result<T, E> type",
-            ParseError::ArgumentConstraint { msg, .. } => msg,
+            ParseError::ArgumentConstraint { msg, .. } => &msg,
         };
         f.write_str(s)
     }
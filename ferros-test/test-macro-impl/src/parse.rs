@@ -69,29 +69,18 @@ fn extract_expected_resources<'a>(
 }
 
 fn validate_param_collection(params: &[Param]) -> Result<(), ParseError> {
-    let mut scratch_count = 0;
-    let mut irq_control_count = 0;
-    for p in params {
-        match p.kind {
-            ParamKind::VSpaceScratch => {
-                scratch_count += 1;
-                if scratch_count > 1 {
-                    return Err(ParseError::ArgumentConstraint {
-                        msg: "Only a single scratch argument may be specified.",
-                        span: p.original_ident.span(),
-                    });
-                }
-            }
-            ParamKind::IRQControl => {
-                irq_control_count += 1;
-                if irq_control_count > 1 {
-                    return Err(ParseError::ArgumentConstraint {
-                        msg: "Only a single IRQControl argument may be specified.",
-                        span: p.original_ident.span(),
-                    });
-                }
-            }
-            _ => (),
+    for (i, p) in params.iter().enumerate() {
+        let name = match p.kind {
+            ParamKind::VSpaceScratch => "scratch",
+            ParamKind::IRQControl => "IRQControl",
+            ParamKind::Harness(resource) => resource.type_name(),
+            _ => continue,
+        };
+        if params[..i].iter().any(|earlier| earlier.kind == p.kind) {
+            return Err(ParseError::ArgumentConstraint {
+                msg: format!("Only a single {} argument may be specified.", name),
+                span: p.original_ident.span(),
+            });
         }
     }
     Ok(())
@@ -159,13 +148,31 @@ impl ParamKind {
                 span: type_path.span(),
             })?
             .into_value();
+        let type_name = segment.ident.to_string();
+        if let Some(resource) = HarnessResource::ALL
+            .iter()
+            .copied()
+            .find(|r| r.type_name() == type_name)
+        {
+            return if arg_kind == ArgKind::RefMut {
+                Ok(ParamKind::Harness(resource))
+            } else {
+                Err(ParseError::InvalidArgumentType {
+                    msg: format!(
+                        "The only supported test function argument for {0} is &mut {0}, the test's own copy of the harness' {0}",
+                        type_name
+                    ),
+                    span: segment.span(),
+                })
+            };
+        }
         // NB - This match region is a rich location for convenience enhancements
         // to expand or restrict the range of injectable objects.
         // E.G:
         //    * Increase validation of generic parameters, like enforce that Role must be Local
         //    * Support Cap<T, role::Local> in addition to LocalCap
         //    * Support &LocalCap<CNode<role::Local>> in addition to &LocalCap<LocalCNode>
-        let kind = match segment.ident.to_string().as_ref() {
+        let kind = match type_name.as_ref() {
             "LocalCNodeSlots" => ParamKind::CNodeSlots {
                 count: extract_first_argument_as_unsigned(&segment.arguments)?,
             },
@@ -495,4 +502,91 @@ mod tests {
             panic!("Should have produced an ArgumentConstraint error")
         }
     }
+
+    #[test]
+    fn parse_model_accepts_mutable_vspace_param() {
+        let user_fn = quote! {
+            fn user_fn(local_vspace: &mut VSpace) {
+            }
+        };
+
+        let content = SynContent::parse(quote!(), user_fn).expect("SynContent not parsed");
+        let model = TestModel::parse(content).expect("TestModel not parsed");
+        assert_eq!(1, model.resources.len());
+        assert_eq!(
+            ParamKind::Harness(HarnessResource::VSpace),
+            model.resources[0].kind
+        );
+    }
+
+    #[test]
+    fn parse_model_accepts_one_of_each_harness_param() {
+        let user_fn = quote! {
+            fn user_fn(a: &mut Allocator, v: &mut VSpace, d: &mut DeviceAllocator) {
+            }
+        };
+
+        let content = SynContent::parse(quote!(), user_fn).expect("SynContent not parsed");
+        let model = TestModel::parse(content).expect("TestModel not parsed");
+        let kinds: Vec<_> = model.resources.into_iter().map(|p| p.kind).collect();
+        assert_eq!(
+            vec![
+                ParamKind::Harness(HarnessResource::Allocator),
+                ParamKind::Harness(HarnessResource::VSpace),
+                ParamKind::Harness(HarnessResource::DeviceAllocator),
+            ],
+            kinds
+        );
+    }
+
+    #[test]
+    fn parse_model_rejects_shared_vspace_param() {
+        let user_fn = quote! {
+            fn user_fn(local_vspace: &VSpace) {
+            }
+        };
+
+        let content = SynContent::parse(quote!(), user_fn).expect("SynContent not parsed");
+        if let ParseError::InvalidArgumentType { .. } =
+            TestModel::parse(content).expect_err("TestModel parse should have failed")
+        {
+            // Cool
+        } else {
+            panic!("Should have produced an InvalidArgumentType error")
+        }
+    }
+
+    #[test]
+    fn parse_model_rejects_multiple_device_allocator_params() {
+        let user_fn = quote! {
+            fn user_fn(a: &mut DeviceAllocator, b: &mut DeviceAllocator) {
+            }
+        };
+
+        let content = SynContent::parse(quote!(), user_fn).expect("SynContent not parsed");
+        if let ParseError::ArgumentConstraint { .. } =
+            TestModel::parse(content).expect_err("TestModel parse should have failed")
+        {
+            // Cool
+        } else {
+            panic!("Should have produced an ArgumentConstraint error")
+        }
+    }
+
+    #[test]
+    fn parse_model_rejects_multiple_allocator_params() {
+        let user_fn = quote! {
+            fn user_fn(a: &mut Allocator, b: &mut Allocator) {
+            }
+        };
+
+        let content = SynContent::parse(quote!(), user_fn).expect("SynContent not parsed");
+        if let ParseError::ArgumentConstraint { .. } =
+            TestModel::parse(content).expect_err("TestModel parse should have failed")
+        {
+            // Cool
+        } else {
+            panic!("Should have produced an ArgumentConstraint error")
+        }
+    }
 }
@@ -7,7 +7,9 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use rexpect::process::signal::Signal;
 use rexpect::session::spawn_command;
+use std::fs;
 use std::io::{self, Write};
+use std::path::PathBuf;
 use std::process::Command;
 use std::sync::Mutex;
 
@@ -15,8 +17,9 @@ lazy_static! {
     static ref SEQUENTIAL_TEST_MUTEX: Mutex<()> = Mutex::new(());
 }
 macro_rules! sequential_test {
-    (fn $name:ident() $body:block) => {
+    ($(#[$attr:meta])* fn $name:ident() $body:block) => {
         #[test]
+        $(#[$attr])*
         fn $name() {
             let _guard = $crate::SEQUENTIAL_TEST_MUTEX.lock();
             {
@@ -32,6 +35,10 @@ pub enum TestPlatform {
     VirtTx1Aarch64,
     /// The sabre aarch32
     SabreAarch32,
+    /// The pc99 x86_64
+    Pc99X86_64,
+    /// A virtual riscv64 platform, simulated on the spike machine
+    VirtRiscv64,
 }
 
 impl TestPlatform {
@@ -39,16 +46,46 @@ impl TestPlatform {
         match self {
             TestPlatform::VirtTx1Aarch64 => "aarch64",
             TestPlatform::SabreAarch32 => "aarch32",
+            TestPlatform::Pc99X86_64 => "x86_64",
+            TestPlatform::VirtRiscv64 => "riscv64",
         }
     }
     fn platform(&self) -> &'static str {
         match self {
             TestPlatform::VirtTx1Aarch64 => "virt",
             TestPlatform::SabreAarch32 => "sabre",
+            TestPlatform::Pc99X86_64 => "pc99",
+            TestPlatform::VirtRiscv64 => "spike",
         }
     }
 }
 
+/// Which flavour of kernel the test project is built with
+#[derive(Debug, Clone, Copy)]
+pub enum KernelConfig {
+    /// The kernel as configured in test-project/sel4.toml
+    Classic,
+    /// The same, with the mixed-criticality (MCS) scheduler
+    Mcs,
+}
+
+const SEL4_CONFIG_PATH: &str = "test-project/sel4.toml";
+const MCS_SEL4_CONFIG_PATH: &str = "test-project/sel4-mcs.toml";
+
+/// Writes an MCS-enabled copy of the test project's sel4.toml beside the
+/// original, which is left alone, and returns the copy's absolute path.
+fn write_mcs_config() -> PathBuf {
+    let original = fs::read_to_string(SEL4_CONFIG_PATH).expect("Couldn't read sel4.toml");
+    let mcs = original.replacen(
+        "\n[sel4.config]\n",
+        "\n[sel4.config]\nKernelIsMCS = true\n",
+        1,
+    );
+    assert_ne!(mcs, original, "sel4.toml has no [sel4.config] table");
+    fs::write(MCS_SEL4_CONFIG_PATH, mcs).expect("Couldn't write sel4-mcs.toml");
+    fs::canonicalize(MCS_SEL4_CONFIG_PATH).expect("Couldn't find sel4-mcs.toml")
+}
+
 fn run_qemu_test<F>(
     test_case: &str,
     pass_line: Regex,
@@ -56,9 +93,15 @@ fn run_qemu_test<F>(
     ready_line_and_func: Option<(Regex, F)>,
     serial_override: Option<&str>,
     test_platform: TestPlatform,
+    kernel_config: KernelConfig,
 ) where
     F: Fn(),
 {
+    let sel4_config_path = match kernel_config {
+        KernelConfig::Classic => None,
+        KernelConfig::Mcs => Some(write_mcs_config()),
+    };
+
     let rust_identifier_regex: Regex =
         Regex::new("(^[a-zA-Z][a-zA-Z0-9_]*$)|(^_[a-zA-Z0-9_]+$)").unwrap();
     let is_rust_id = |s| rust_identifier_regex.is_match(s);
@@ -79,6 +122,9 @@ fn run_qemu_test<F>(
         .arg("-v")
         .current_dir("test-project")
         .env("TEST_CASE", test_case);
+    if let Some(path) = &sel4_config_path {
+        build_command.env("SEL4_CONFIG_PATH", path);
+    }
 
     println!(r#"running: TEST_CASE={} {:?}"#, test_case, build_command);
     let build_result = build_command.output().expect("Couldn't run `selfe build`");
@@ -103,6 +149,9 @@ fn run_qemu_test<F>(
         .arg("-v")
         .current_dir("test-project")
         .env("TEST_CASE", test_case);
+    if let Some(path) = &sel4_config_path {
+        sim_command.env("SEL4_CONFIG_PATH", path);
+    }
 
     println!(r#"running: TEST_CASE={} {:?}"#, test_case, sim_command);
 
@@ -161,11 +210,12 @@ mod tests {
         fn unified_tests_sabre() {
             run_qemu_test::<fn()>(
                 "unified_tests",
-                Regex::new(".*test result: ok\\. 23 passed;.*").unwrap(),
+                Regex::new(".*test result: ok\\. 43 passed;.*").unwrap(),
                 Regex::new(".*Root task should never return from main.*").unwrap(),
                 None,
                 None,
                 TestPlatform::SabreAarch32,
+                KernelConfig::Classic,
             );
         }
     }
@@ -174,11 +224,54 @@ mod tests {
         fn unified_tests_virt() {
             run_qemu_test::<fn()>(
                 "unified_tests",
-                Regex::new(".*test result: ok\\. 23 passed;.*").unwrap(),
+                Regex::new(".*test result: ok\\. 43 passed;.*").unwrap(),
                 Regex::new(".*Root task should never return from main.*").unwrap(),
                 None,
                 None,
                 TestPlatform::VirtTx1Aarch64,
+                KernelConfig::Classic,
+            );
+        }
+    }
+
+    sequential_test! {
+        fn unified_tests_pc99() {
+            run_qemu_test::<fn()>(
+                "unified_tests",
+                Regex::new(".*test result: ok\\. 41 passed;.*").unwrap(),
+                Regex::new(".*Root task should never return from main.*").unwrap(),
+                None,
+                None,
+                TestPlatform::Pc99X86_64,
+                KernelConfig::Classic,
+            );
+        }
+    }
+
+    sequential_test! {
+        fn unified_tests_riscv64() {
+            run_qemu_test::<fn()>(
+                "unified_tests",
+                Regex::new(".*test result: ok\\. 41 passed;.*").unwrap(),
+                Regex::new(".*Root task should never return from main.*").unwrap(),
+                None,
+                None,
+                TestPlatform::VirtRiscv64,
+                KernelConfig::Classic,
+            );
+        }
+    }
+
+    sequential_test! {
+        fn sched_context_throttling_sabre_mcs() {
+            run_qemu_test::<fn()>(
+                "sched_context_throttling",
+                Regex::new(".*test result: ok\\. 1 passed;.*").unwrap(),
+                Regex::new(".*(test result: FAILED|Root task should never return from main).*").unwrap(),
+                None,
+                None,
+                TestPlatform::SabreAarch32,
+                KernelConfig::Mcs,
             );
         }
     }
@@ -199,6 +292,7 @@ mod tests {
                 })),
                 Some("-serial tcp:localhost:8888,server,nowait,nodelay -serial mon:stdio"),
                 TestPlatform::SabreAarch32,
+                KernelConfig::Classic,
             );
         }
     }
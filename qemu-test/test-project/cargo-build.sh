@@ -14,5 +14,17 @@ fi
 echo "======================= building elf-process ======================"
 cargo xbuild -p elf-process $@;
 
+echo "======================= building tls-process ======================"
+cargo xbuild -p tls-process $@;
+
+echo "===================== building startup-process ===================="
+cargo xbuild -p startup-process $@;
+
+# Linked as a static position-independent executable, in place of the
+# -no-pie build flags every other package is built with
+echo "======================= building pie-process ======================"
+RUSTFLAGS="-C relocation-model=pie -C link-arg=-static-pie -C link-arg=-nostdlib" \
+    cargo xbuild -p pie-process $@;
+
 echo "======================== building root-task ======================="
 cargo xbuild -p root-task $@;
@@ -0,0 +1,12 @@
+#![no_std]
+
+use ferros::cap::*;
+use ferros::userland::{RetypeForSetup, Sender};
+
+pub struct ProcParams<Role: CNodeRole> {
+    pub outcome_sender: Sender<bool, Role>,
+}
+
+impl RetypeForSetup for ProcParams<role::Local> {
+    type Output = ProcParams<role::Child>;
+}
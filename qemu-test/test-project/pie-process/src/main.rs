@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+use ferros::cap::*;
+extern crate selfe_runtime;
+
+use pie_process::ProcParams;
+
+static VALUE: usize = 0x5EED;
+
+// Stored as VALUE's link-time address, which only a relative relocation
+// turns into the address it was loaded at
+static POINTER: &usize = &VALUE;
+
+#[no_mangle]
+pub extern "C" fn _start(params: ProcParams<role::Local>) -> ! {
+    let relocated = unsafe {
+        let pointer = core::ptr::read_volatile(&POINTER as *const &usize);
+        pointer as *const usize == &VALUE as *const usize
+            && core::ptr::read_volatile(pointer) == 0x5EED
+    };
+
+    params
+        .outcome_sender
+        .blocking_send(&relocated)
+        .expect("Could not report the relocation check");
+
+    unsafe {
+        loop {
+            selfe_sys::seL4_Yield();
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use ferros_build::*;
+use selfe_config::build_helpers::*;
 use std::env;
 use std::path::Path;
 
@@ -12,6 +13,10 @@ fn main() {
 
     println!("cargo:rustc-cfg=test_case=\"{}\"", test_case);
 
+    // Which tests get built depends on kernel features, e.g. KernelIsMCS
+    BuildEnv::request_reruns();
+    load_config_from_env_or_default().print_boolean_feature_flags();
+
     let out_dir = Path::new(&std::env::var_os("OUT_DIR").unwrap()).to_owned();
     let bin_dir = out_dir.join("..").join("..").join("..");
     let resources = out_dir.join("resources.rs");
@@ -23,5 +28,34 @@ fn main() {
         stack_size_bits: None,
     };
 
-    embed_resources(&resources, vec![&elf_proc as &dyn Resource]);
+    let tls_proc = ElfResource {
+        path: bin_dir.join("tls-process"),
+        image_name: "tls-process".to_owned(),
+        type_name: "TlsProcess".to_owned(),
+        stack_size_bits: None,
+    };
+
+    let pie_proc = ElfResource {
+        path: bin_dir.join("pie-process"),
+        image_name: "pie-process".to_owned(),
+        type_name: "PieProcess".to_owned(),
+        stack_size_bits: None,
+    };
+
+    let startup_proc = ElfResource {
+        path: bin_dir.join("startup-process"),
+        image_name: "startup-process".to_owned(),
+        type_name: "StartupProcess".to_owned(),
+        stack_size_bits: None,
+    };
+
+    embed_resources(
+        &resources,
+        vec![
+            &elf_proc as &dyn Resource,
+            &tls_proc as &dyn Resource,
+            &pie_proc as &dyn Resource,
+            &startup_proc as &dyn Resource,
+        ],
+    );
 }
@@ -0,0 +1,132 @@
+use typenum::*;
+
+use ferros::alloc::{smart_alloc, ut_buddy};
+use ferros::arch;
+use ferros::bootstrap::UserImage;
+use ferros::cap::{retype, role, ASIDPool, LocalCNode, LocalCNodeSlots, LocalCap, Untyped};
+use ferros::userland::rights;
+use ferros::vspace::*;
+
+use super::TopLevelError;
+
+#[ferros_test::ferros_test]
+pub fn address_space_reuse(
+    local_slots: LocalCNodeSlots<U2048>,
+    local_ut: LocalCap<Untyped<U18>>,
+    asid_pool: LocalCap<ASIDPool<U1>>,
+    root_cnode: &LocalCap<LocalCNode>,
+    user_image: &UserImage<role::Local>,
+) -> Result<(), TopLevelError> {
+    let uts = ut_buddy(local_ut);
+
+    smart_alloc!(|slots: local_slots, ut: uts| {
+        let (asid, _asid_pool) = asid_pool.alloc();
+        let vspace_slots: LocalCNodeSlots<U1024> = slots;
+        let vspace_ut: LocalCap<Untyped<U15>> = ut;
+        let mut vspace = VSpace::new(
+            retype(ut, slots)?,
+            asid,
+            vspace_slots.weaken(),
+            vspace_ut.weaken(),
+            ProcessCodeImageConfig::ReadOnly,
+            user_image,
+            root_cnode,
+        )?;
+
+        let region: UnmappedMemoryRegion<U14, shared_status::Exclusive> =
+            UnmappedMemoryRegion::new(ut, slots)?;
+    });
+
+    let page_bytes = arch::PageBytes::USIZE;
+    let free_before = vspace.free_address_space();
+
+    // Unmapped address space is handed out again
+    let mapped = vspace.map_region(region, rights::RW, arch::vm_attributes::DEFAULT)?;
+    let first_vaddr = mapped.vaddr();
+    let size_bytes = mapped.size_bytes();
+    if vspace.free_address_space() != free_before - size_bytes
+        || vspace.is_address_range_free(first_vaddr, size_bytes)
+    {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Mapping a region should claim its address space",
+        ));
+    }
+    let region = vspace.unmap_region(mapped)?;
+    let mapped = vspace.map_region(region, rights::RW, arch::vm_attributes::DEFAULT)?;
+    if mapped.vaddr() != first_vaddr {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Unmapped address space should be reused",
+        ));
+    }
+    let region = vspace.unmap_region(mapped)?;
+    if vspace.free_address_space() != free_before {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Unmapping a region should free its address space",
+        ));
+    }
+
+    // Reserved address space is skipped over
+    vspace.reserve_address_range(first_vaddr, page_bytes)?;
+    match vspace.reserve_address_range(first_vaddr, page_bytes) {
+        Err(VSpaceError::AddressRangeUnavailable) => (),
+        _ => {
+            return Err(TopLevelError::TestAssertionFailure(
+                "Reserving address space twice should fail",
+            ))
+        }
+    }
+    let mapped = vspace.map_region(region, rights::RW, arch::vm_attributes::DEFAULT)?;
+    if mapped.vaddr() <= first_vaddr {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Reserved address space should not be mapped into",
+        ));
+    }
+    let region = vspace.unmap_region(mapped)?;
+
+    // Unless asked for, and then it stays reserved when unmapped
+    let mapped = vspace
+        .map_region_at_addr(
+            region,
+            first_vaddr,
+            rights::RW,
+            arch::vm_attributes::DEFAULT,
+        )
+        .map_err(|(e, _)| e)?;
+    let region = vspace.unmap_region(mapped)?;
+    if vspace.is_address_range_free(first_vaddr, page_bytes)
+        || !vspace.is_address_range_free(first_vaddr + page_bytes, size_bytes - page_bytes)
+    {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Unmapping a region should leave reserved address space reserved",
+        ));
+    }
+    match vspace.release_address_range(first_vaddr, size_bytes) {
+        Err(VSpaceError::AddressRangeNotReserved) => (),
+        _ => {
+            return Err(TopLevelError::TestAssertionFailure(
+                "Only the range reserved should be released",
+            ))
+        }
+    }
+    vspace.release_address_range(first_vaddr, page_bytes)?;
+
+    // Alignment is honoured
+    let align = 16 * size_bytes;
+    let mapped =
+        vspace.map_region_aligned(region, rights::RW, arch::vm_attributes::DEFAULT, align)?;
+    let aligned = mapped.vaddr() % align == 0;
+    let _ = vspace.unmap_region(mapped)?;
+    if !aligned {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Aligned mappings should be aligned",
+        ));
+    }
+
+    if vspace.free_address_space() == free_before && vspace.largest_free_address_range() >= align {
+        Ok(())
+    } else {
+        Err(TopLevelError::TestAssertionFailure(
+            "All address space should be free again",
+        ))
+    }
+}
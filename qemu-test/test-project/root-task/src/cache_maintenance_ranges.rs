@@ -0,0 +1,51 @@
+use typenum::*;
+
+use ferros::arch;
+use ferros::vspace::*;
+
+use super::TopLevelError;
+
+#[ferros_test::ferros_test]
+pub fn cache_maintenance_ranges(
+    local_mapped_region: MappedMemoryRegion<U17, shared_status::Exclusive>,
+) -> Result<(), TopLevelError> {
+    let mut region = local_mapped_region;
+    let page_bytes = arch::PageBytes::USIZE;
+
+    // A range straddling the boundary between the first two pages,
+    // as a DMA descriptor ring might.
+    let vaddr = region.vaddr() + page_bytes - 64;
+    let size = 128;
+    for (i, byte) in region.as_mut_slice()[page_bytes - 64..page_bytes + 64]
+        .iter_mut()
+        .enumerate()
+    {
+        *byte = i as u8;
+    }
+
+    // Once cleaned, the data is in memory, so invalidating the cache
+    // over it loses nothing.
+    region.clean_range(vaddr, size)?;
+    region.invalidate_range(vaddr, size)?;
+    region.unify_instruction_range(vaddr, size)?;
+    let written = region.as_slice()[page_bytes - 64..page_bytes + 64]
+        .iter()
+        .enumerate()
+        .all(|(i, byte)| *byte == i as u8);
+    if !written {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Cleaning then invalidating a range should leave its data in place",
+        ));
+    }
+
+    // A range lying outside the region is left alone.
+    region.clean_range(region.vaddr() + region.size_bytes(), size)?;
+    region.invalidate_range(region.vaddr() - page_bytes, page_bytes)?;
+
+    let weak = region.weaken();
+    weak.clean_range(vaddr, size)?;
+    weak.invalidate_range(vaddr, size)?;
+    weak.unify_instruction_range(vaddr, size)?;
+
+    Ok(())
+}
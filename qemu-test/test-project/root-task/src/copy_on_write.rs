@@ -0,0 +1,182 @@
+use typenum::*;
+
+use ferros::alloc::ut_buddy::weak_ut_buddy;
+use ferros::alloc::{smart_alloc, ut_buddy};
+use ferros::arch::{self, fault::Fault};
+use ferros::bootstrap::UserImage;
+use ferros::cap::{
+    retype, retype_cnode, role, ASIDPool, Badge, LocalCNode, LocalCNodeSlots, LocalCap,
+    ThreadPriorityAuthority, Untyped,
+};
+use ferros::userland::{rights, CapRights, FaultSinkSetup, Pager, RetypeForSetup, StandardProcess};
+use ferros::vspace::*;
+
+use super::TopLevelError;
+
+const FAILURE_ADDRESS: usize = 0x9999_9990;
+const SUCCESS_ADDRESS: usize = 0x8888_8880;
+const PATTERN: usize = 0xC0FF_EE00;
+
+#[ferros_test::ferros_test]
+pub fn copy_on_write(
+    local_slots: LocalCNodeSlots<U32768>,
+    local_ut: LocalCap<Untyped<U20>>,
+    asid_pool: LocalCap<ASIDPool<U1>>,
+    local_mapped_region: MappedMemoryRegion<U17, shared_status::Exclusive>,
+    local_vspace: &mut VSpace,
+    local_vspace_scratch: &mut ScratchRegion,
+    root_cnode: &LocalCap<LocalCNode>,
+    user_image: &UserImage<role::Local>,
+    tpa: &LocalCap<ThreadPriorityAuthority>,
+) -> Result<(), TopLevelError> {
+    let (stack_region, mut source_region) = local_mapped_region.split()?;
+    let page_count = source_region.size_bytes() / arch::PageBytes::USIZE;
+    for (i, page) in source_region
+        .as_mut_slice()
+        .chunks_exact_mut(arch::PageBytes::USIZE)
+        .enumerate()
+    {
+        page[..core::mem::size_of::<usize>()].copy_from_slice(&(PATTERN + i).to_ne_bytes());
+    }
+    source_region.flush()?;
+
+    let uts = ut_buddy(local_ut);
+
+    smart_alloc!(|slots: local_slots, ut: uts| {
+        let (shared_source, local_source) = source_region.share(slots, root_cnode, CapRights::R)?;
+
+        let (child_asid, _asid_pool) = asid_pool.alloc();
+        let child_vspace_slots: LocalCNodeSlots<U1024> = slots;
+        let child_vspace_ut: LocalCap<Untyped<U15>> = ut;
+        let mut child_vspace = VSpace::new(
+            retype(ut, slots)?,
+            child_asid,
+            child_vspace_slots.weaken(),
+            child_vspace_ut.weaken(),
+            ProcessCodeImageConfig::ReadOnly,
+            user_image,
+            root_cnode,
+        )?;
+
+        let child_mapping = child_vspace.map_shared_region(
+            &shared_source,
+            rights::R,
+            arch::vm_attributes::PROGRAM_DATA,
+            slots,
+            root_cnode,
+        )?;
+        let data_vaddr = child_mapping.vaddr();
+        let params = ProcParams {
+            data_vaddr,
+            page_count,
+        };
+
+        let (child_cnode, child_slots) = retype_cnode::<U12>(ut, slots)?;
+        let setup = FaultSinkSetup::new(&root_cnode, ut, slots, slots)?;
+        let (child_slot_for_fault_source, _child_slots) = child_slots.alloc();
+        let fault_source =
+            setup.add_fault_source(&root_cnode, child_slot_for_fault_source, Badge::from(0))?;
+
+        let mut child_process = StandardProcess::new(
+            &mut child_vspace,
+            child_cnode,
+            stack_region,
+            root_cnode,
+            proc_main as extern "C" fn(_) -> (),
+            params,
+            ut,
+            ut,
+            slots,
+            tpa,
+            Some(fault_source),
+        )?;
+
+        let pager_slots: LocalCNodeSlots<U256> = slots;
+        let pager_ut: LocalCap<Untyped<U17>> = ut;
+    });
+
+    // Left writable, the parent could change pages the child hasn't
+    // copied yet under it.
+    let local_source = local_vspace
+        .remap_region(local_source, rights::R, arch::vm_attributes::DEFAULT)
+        .map_err(|(e, _)| e)?;
+
+    let mut pager = Pager::new(
+        child_vspace,
+        setup.sink(),
+        weak_ut_buddy(pager_ut.weaken()),
+        pager_slots.weaken(),
+    );
+    pager.add_copy_on_write_region(child_mapping, &local_source)?;
+    pager.set_scratch(local_vspace_scratch);
+
+    child_process.start()?;
+
+    // The child reports success by reading from an address outside
+    // every region, a fault the pager must hand back rather than serve
+    let child_succeeded = match pager.run()? {
+        Fault::VMFault(f) => f.address == SUCCESS_ADDRESS,
+        _ => false,
+    };
+    if !child_succeeded {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Child should have seen the shared data and been able to write its own copy",
+        ));
+    }
+
+    drop(pager);
+
+    // The child's writes went to private copies
+    let source_unchanged = local_source
+        .as_slice()
+        .chunks_exact(arch::PageBytes::USIZE)
+        .enumerate()
+        .all(|(i, page)| unsafe { *(page.as_ptr() as *const usize) } == PATTERN + i);
+    if !source_unchanged {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Shared source pages should not have been written to",
+        ));
+    }
+
+    // Hand the harness its memory back writable.
+    let _ = local_vspace
+        .remap_region(local_source, rights::RW, arch::vm_attributes::DEFAULT)
+        .map_err(|(e, _)| e)?;
+    Ok(())
+}
+
+pub struct ProcParams {
+    pub data_vaddr: usize,
+    pub page_count: usize,
+}
+
+impl RetypeForSetup for ProcParams {
+    type Output = ProcParams;
+}
+
+pub extern "C" fn proc_main(params: ProcParams) {
+    let page_size = 1 << 12;
+    let data = params.data_vaddr as *mut usize;
+    let word_stride = page_size / core::mem::size_of::<usize>();
+
+    let mut all_match = true;
+    unsafe {
+        for i in 0..params.page_count {
+            let word = data.add(i * word_stride);
+            all_match &= core::ptr::read_volatile(word) == PATTERN + i;
+            if i + 1 < params.page_count {
+                core::ptr::write_volatile(word, i);
+                all_match &= core::ptr::read_volatile(word) == i;
+            }
+        }
+    }
+
+    // Report the outcome through a deliberate fault
+    unsafe {
+        if all_match {
+            let _ = core::ptr::read_volatile(SUCCESS_ADDRESS as *const usize);
+        } else {
+            let _ = core::ptr::read_volatile(FAILURE_ADDRESS as *const usize);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use typenum::*;
+
+use ferros::alloc::ut_buddy::weak_ut_buddy;
+use ferros::alloc::{smart_alloc, ut_buddy};
+use ferros::arch::fault::Fault;
+use ferros::bootstrap::UserImage;
+use ferros::cap::{
+    retype, retype_cnode, role, ASIDPool, Badge, LocalCNode, LocalCNodeSlots, LocalCap,
+    ThreadPriorityAuthority, Untyped,
+};
+use ferros::userland::{FaultSinkSetup, Pager, RetypeForSetup, StandardProcess};
+use ferros::vspace::*;
+
+use super::TopLevelError;
+
+const FAILURE_ADDRESS: usize = 0x9999_9990;
+const SUCCESS_ADDRESS: usize = 0x8888_8880;
+
+#[ferros_test::ferros_test]
+pub fn demand_paging(
+    local_slots: LocalCNodeSlots<U32768>,
+    local_ut: LocalCap<Untyped<U20>>,
+    asid_pool: LocalCap<ASIDPool<U1>>,
+    local_mapped_region: MappedMemoryRegion<U17, shared_status::Exclusive>,
+    root_cnode: &LocalCap<LocalCNode>,
+    user_image: &UserImage<role::Local>,
+    tpa: &LocalCap<ThreadPriorityAuthority>,
+) -> Result<(), TopLevelError> {
+    let uts = ut_buddy(local_ut);
+
+    smart_alloc!(|slots: local_slots, ut: uts| {
+        let (child_asid, _asid_pool) = asid_pool.alloc();
+        let child_vspace_slots: LocalCNodeSlots<U1024> = slots;
+        let child_vspace_ut: LocalCap<Untyped<U15>> = ut;
+        let mut child_vspace = VSpace::new(
+            retype(ut, slots)?,
+            child_asid,
+            child_vspace_slots.weaken(),
+            child_vspace_ut.weaken(),
+            ProcessCodeImageConfig::ReadOnly,
+            user_image,
+            root_cnode,
+        )?;
+
+        // Nothing backs the heap until the child touches it
+        let heap = child_vspace.reserve_lazy::<U16>()?;
+        let params = ProcParams {
+            heap_vaddr: heap.vaddr(),
+            heap_size: heap.size_bytes(),
+        };
+
+        let (child_cnode, child_slots) = retype_cnode::<U12>(ut, slots)?;
+        let setup = FaultSinkSetup::new(&root_cnode, ut, slots, slots)?;
+        let (child_slot_for_fault_source, _child_slots) = child_slots.alloc();
+        let fault_source =
+            setup.add_fault_source(&root_cnode, child_slot_for_fault_source, Badge::from(0))?;
+
+        let mut child_process = StandardProcess::new(
+            &mut child_vspace,
+            child_cnode,
+            local_mapped_region,
+            root_cnode,
+            proc_main as extern "C" fn(_) -> (),
+            params,
+            ut,
+            ut,
+            slots,
+            tpa,
+            Some(fault_source),
+        )?;
+
+        let pager_slots: LocalCNodeSlots<U256> = slots;
+        let pager_ut: LocalCap<Untyped<U17>> = ut;
+    });
+
+    let mut pager = Pager::new(
+        child_vspace,
+        setup.sink(),
+        weak_ut_buddy(pager_ut.weaken()),
+        pager_slots.weaken(),
+    );
+    pager.add_region(heap)?;
+
+    child_process.start()?;
+
+    // The child reports success by reading from an address outside its
+    // heap, a fault the pager must hand back rather than try to back
+    match pager.run()? {
+        Fault::VMFault(f) if f.address == SUCCESS_ADDRESS => Ok(()),
+        _ => Err(TopLevelError::TestAssertionFailure(
+            "Child should have been able to use its lazily backed heap",
+        )),
+    }
+}
+
+pub struct ProcParams {
+    pub heap_vaddr: usize,
+    pub heap_size: usize,
+}
+
+impl RetypeForSetup for ProcParams {
+    type Output = ProcParams;
+}
+
+pub extern "C" fn proc_main(params: ProcParams) {
+    let page_size = 1 << 12;
+    let page_count = params.heap_size / page_size;
+    let heap = params.heap_vaddr as *mut usize;
+    let word_stride = page_size / core::mem::size_of::<usize>();
+
+    let mut all_match = true;
+    unsafe {
+        for i in 0..page_count {
+            core::ptr::write_volatile(heap.add(i * word_stride), i);
+        }
+        for i in 0..page_count {
+            all_match &= core::ptr::read_volatile(heap.add(i * word_stride)) == i;
+        }
+    }
+
+    // Report the outcome through a deliberate fault
+    unsafe {
+        if all_match {
+            let _ = core::ptr::read_volatile(SUCCESS_ADDRESS as *const usize);
+        } else {
+            let _ = core::ptr::read_volatile(FAILURE_ADDRESS as *const usize);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use typenum::*;
+
+use ferros::alloc::micro_alloc::{DeviceAllocator, PageAlignedAddressRange};
+use ferros::arch;
+use ferros::cap::{LocalCNodeSlots, LocalCap, Untyped};
+use ferros::userland::{rights, CapRights};
+use ferros::vspace::*;
+
+use super::TopLevelError;
+
+// The on-chip RAM is device memory as far as seL4 is concerned, but
+// unlike a peripheral's registers it can be written and read back.
+// C.f. i.MX 6Dual/6Quad Reference Manual Table 2-1.
+const OCRAM_PADDR: usize = 0x0090_0000;
+
+#[ferros_test::ferros_test]
+pub fn device_range_mapped(
+    local_slots: LocalCNodeSlots<U64>,
+    local_ut: LocalCap<Untyped<U14>>,
+    local_vspace: &mut VSpace,
+    device_allocator: &mut DeviceAllocator,
+) -> Result<(), TopLevelError> {
+    let (region_slots, local_slots) = local_slots.alloc();
+    let (few_slots, local_slots): (LocalCNodeSlots<U3>, _) = local_slots.alloc();
+    let mut slots = local_slots.weaken();
+    let page_bytes = arch::PageBytes::USIZE;
+
+    // Find somewhere free in the root task's address space to map the
+    // device range at.
+    let region: UnmappedMemoryRegion<U14, shared_status::Exclusive> =
+        UnmappedMemoryRegion::new(local_ut, region_slots)?;
+    let mapped = local_vspace.map_region(region, rights::RW, arch::vm_attributes::DEFAULT)?;
+    let vaddr = mapped.vaddr();
+    let _ = local_vspace.unmap_region(mapped)?;
+
+    // Taking out the second page and giving it back leaves the first
+    // four pages of the on-chip RAM split across three untypeds, of
+    // one, one and two pages.
+    let second_page = PageAlignedAddressRange::new_by_size(OCRAM_PADDR + page_bytes, page_bytes)
+        .expect("failed to specify the second on-chip RAM page");
+    let ut = device_allocator
+        .get_untyped_by_address_range(second_page, &mut slots)
+        .map_err(VSpaceError::from)?;
+    device_allocator
+        .give_back(ut)
+        .map_err(|_| TopLevelError::TestAssertionFailure("A device untyped can be given back"))?;
+
+    let range = PageAlignedAddressRange::new_by_size(OCRAM_PADDR, 4 * page_bytes)
+        .expect("failed to specify the on-chip RAM range");
+
+    // Without enough slots for its pages the range can't be taken, and
+    // its untypeds go back to the allocator.
+    let mut few_slots = few_slots.weaken();
+    match local_vspace.weak_map_device_region_at_addr(
+        device_allocator,
+        range,
+        &mut few_slots,
+        vaddr,
+        CapRights::RW,
+    ) {
+        Err((VSpaceError::InsufficientCNodeSlots, None)) => (),
+        _ => {
+            return Err(TopLevelError::TestAssertionFailure(
+                "Taking a device range with too few slots for its pages should fail",
+            ))
+        }
+    }
+
+    let mut mapped = local_vspace
+        .weak_map_device_region_at_addr(device_allocator, range, &mut slots, vaddr, CapRights::RW)
+        .map_err(|(e, _)| e)?;
+    if mapped.vaddr() != vaddr || mapped.size_bytes() != 4 * page_bytes {
+        return Err(TopLevelError::TestAssertionFailure(
+            "A device range should be mapped whole at the address asked for",
+        ));
+    }
+
+    // Every page, whichever untyped it came from, is backed by the
+    // device memory.
+    {
+        let data = mapped
+            .as_mut_slice()
+            .ok_or(TopLevelError::TestAssertionFailure(
+                "A device range mapped writable should have a mutable slice",
+            ))?;
+        for (index, page) in data.chunks_mut(page_bytes).enumerate() {
+            page[0] = index as u8;
+            page[page_bytes - 1] = !(index as u8);
+        }
+    }
+    let backed = mapped
+        .as_slice()
+        .chunks(page_bytes)
+        .enumerate()
+        .all(|(index, page)| page[0] == index as u8 && page[page_bytes - 1] == !(index as u8));
+    if !backed {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Every page of a device range should be backed by its memory",
+        ));
+    }
+    let _ = local_vspace.weak_unmap_region(mapped)?;
+
+    Ok(())
+}
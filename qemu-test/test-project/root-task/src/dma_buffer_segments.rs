@@ -0,0 +1,101 @@
+use typenum::*;
+
+use ferros::alloc::ut_buddy::weak_ut_buddy;
+use ferros::alloc::{smart_alloc, ut_buddy};
+use ferros::arch;
+use ferros::bootstrap::UserImage;
+use ferros::cap::{retype, role, ASIDPool, LocalCNode, LocalCNodeSlots, LocalCap, Untyped};
+use ferros::vspace::dma::{DmaBuffer, DmaCaching, DmaSegment};
+use ferros::vspace::*;
+
+use super::TopLevelError;
+
+#[ferros_test::ferros_test]
+pub fn dma_buffer_segments(
+    local_slots: LocalCNodeSlots<U1024>,
+    local_ut: LocalCap<Untyped<U20>>,
+    asid_pool: LocalCap<ASIDPool<U1>>,
+    root_cnode: &LocalCap<LocalCNode>,
+    user_image: &UserImage<role::Local>,
+) -> Result<(), TopLevelError> {
+    let uts = ut_buddy(local_ut);
+
+    smart_alloc!(|slots: local_slots, ut: uts| {
+        let (asid, _asid_pool) = asid_pool.alloc();
+        let vspace_slots: LocalCNodeSlots<U256> = slots;
+        let vspace_ut: LocalCap<Untyped<U15>> = ut;
+        let mut vspace = VSpace::new(
+            retype(ut, slots)?,
+            asid,
+            vspace_slots.weaken(),
+            vspace_ut.weaken(),
+            ProcessCodeImageConfig::ReadOnly,
+            user_image,
+            root_cnode,
+        )?;
+
+        let contiguous_ut: LocalCap<Untyped<U14>> = ut;
+        let contiguous_slots: LocalCNodeSlots<U4> = slots;
+        let scattered_ut: LocalCap<Untyped<U16>> = ut;
+        let scattered_slots: LocalCNodeSlots<U4> = slots;
+        let ut_slots: LocalCNodeSlots<U32> = slots;
+    });
+
+    // A buffer retyped from a single untyped is one segment.
+    let buffer: DmaBuffer<U14> = DmaBuffer::new_contiguous(
+        contiguous_ut,
+        contiguous_slots,
+        &mut vspace,
+        DmaCaching::Uncached,
+    )?;
+    let mut segments = buffer.segments();
+    match (segments.next(), segments.next()) {
+        (Some(Ok(DmaSegment { paddr, size_bytes })), None)
+            if paddr % arch::PageBytes::USIZE == 0 && size_bytes == buffer.size_bytes() => {}
+        _ => {
+            return Err(TopLevelError::TestAssertionFailure(
+                "A contiguous buffer should be a single segment covering all of it",
+            ))
+        }
+    }
+
+    // Handing the buffer to the device and back leaves it where it was.
+    let vaddr = buffer.vaddr();
+    let buffer = buffer.to_device().to_cpu();
+    if buffer.vaddr() != vaddr || buffer.caching() != DmaCaching::Uncached {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Changing a buffer's owner shouldn't change its mapping",
+        ));
+    }
+    let _ = vspace.unmap_region(buffer.into_region())?;
+
+    // A buffer gathered a page at a time is covered by its segments,
+    // however many there are.
+    let mut scattered_uts = weak_ut_buddy(scattered_ut.weaken());
+    let buffer: DmaBuffer<U14> = DmaBuffer::new_scattered(
+        &mut scattered_uts,
+        &mut ut_slots.weaken(),
+        scattered_slots,
+        &mut vspace,
+        DmaCaching::WriteCombined,
+    )
+    .map_err(|(e, _)| e)?;
+    let mut covered = 0;
+    for segment in buffer.segments() {
+        let segment = segment?;
+        if segment.paddr % arch::PageBytes::USIZE != 0 {
+            return Err(TopLevelError::TestAssertionFailure(
+                "Segments should start on a page boundary",
+            ));
+        }
+        covered += segment.size_bytes;
+    }
+    if covered != buffer.size_bytes() {
+        return Err(TopLevelError::TestAssertionFailure(
+            "The segments of a scattered buffer should cover all of it",
+        ));
+    }
+    let _ = vspace.unmap_region(buffer.into_region())?;
+
+    Ok(())
+}
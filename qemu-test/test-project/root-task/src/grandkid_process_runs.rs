@@ -5,7 +5,8 @@ use ferros::arch::{self, CodePageCount, CodePageTableCount, PageBytes};
 use ferros::bootstrap::UserImage;
 use ferros::cap::*;
 use ferros::userland::{
-    fault_or_message_channel, CapRights, FaultOrMessage, RetypeForSetup, Sender, StandardProcess,
+    fault_or_message_channel, rights, CapRights, FaultOrMessage, RetypeForSetup, Sender,
+    StandardProcess,
 };
 use ferros::vspace::{
     shared_status, MappedMemoryRegion, ProcessCodeImageConfig, UnmappedMemoryRegion, VSpace,
@@ -68,7 +69,7 @@ pub fn grandkid_process_runs(
                 UnmappedMemoryRegion::new(ut, slots)?;
             let child_mapped_region = child_vspace.map_region_and_move(
                 child_unmapped_region,
-                CapRights::RW,
+                rights::RW,
                 arch::vm_attributes::DEFAULT,
                 cnode,
                 slots_c,
@@ -0,0 +1,43 @@
+use super::TopLevelError;
+
+use typenum::*;
+
+use ferros::alloc::{smart_alloc, ut_buddy};
+use ferros::cap::*;
+use ferros::userland::CapRights;
+
+#[ferros_test::ferros_test]
+pub fn grant_revocable(
+    local_slots: LocalCNodeSlots<U32>,
+    local_ut: LocalCap<Untyped<U12>>,
+    root_cnode: &LocalCap<LocalCNode>,
+) -> Result<(), TopLevelError> {
+    let uts = ut_buddy(local_ut);
+
+    smart_alloc!(|slots: local_slots, ut: uts| {
+        let endpoint: LocalCap<Endpoint> = retype(ut, slots)?;
+        let (_child_cnode, child_slots) = retype_cnode::<U4>(ut, slots)?;
+        let copy_slot: LocalCNodeSlots<U1> = slots;
+        let check_slot: LocalCNodeSlots<U1> = slots;
+    });
+    let local_copy = endpoint.copy(root_cnode, copy_slot, CapRights::RW)?;
+
+    // A copy can't revoke anything itself, but granting from one can
+    // still be taken back.
+    let (slot, child_slots) = child_slots.alloc();
+    let (_granted, handle) = local_copy.grant_revocable(root_cnode, slot, CapRights::RW)?;
+    let slot = handle.revoke()?;
+
+    // The kernel won't copy into an occupied slot, so this only works
+    // if the grant is really gone.
+    let _regranted = local_copy.copy(root_cnode, slot, CapRights::RW)?;
+
+    // Taking back a grant from the original leaves its other copies be;
+    // copying from an emptied slot would fail.
+    let (slot, _child_slots) = child_slots.alloc();
+    let (_granted, handle) = endpoint.grant_revocable(root_cnode, slot, CapRights::RW)?;
+    let _ = handle.revoke()?;
+    let _ = local_copy.copy(root_cnode, check_slot, CapRights::RW)?;
+
+    Ok(())
+}
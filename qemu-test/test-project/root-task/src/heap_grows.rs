@@ -0,0 +1,161 @@
+use core::alloc::{GlobalAlloc, Layout};
+
+use typenum::*;
+
+use ferros::alloc::heap::{Heap, HeapGrower, HeapGrowth, HeapGrowthRequest};
+use ferros::alloc::{smart_alloc, ut_buddy};
+use ferros::arch;
+use ferros::bootstrap::UserImage;
+use ferros::cap::{
+    retype, retype_cnode, role, ASIDPool, CNodeRole, LocalCNode, LocalCNodeSlots, LocalCap,
+    ThreadPriorityAuthority, Untyped,
+};
+use ferros::userland::*;
+use ferros::vspace::*;
+
+use super::TopLevelError;
+
+#[ferros_test::ferros_test]
+pub fn heap_grows(
+    local_slots: LocalCNodeSlots<U32768>,
+    local_ut: LocalCap<Untyped<U20>>,
+    asid_pool: LocalCap<ASIDPool<U1>>,
+    local_mapped_region: MappedMemoryRegion<U17, shared_status::Exclusive>,
+    root_cnode: &LocalCap<LocalCNode>,
+    user_image: &UserImage<role::Local>,
+    tpa: &LocalCap<ThreadPriorityAuthority>,
+) -> Result<(), TopLevelError> {
+    let uts = ut_buddy(local_ut);
+
+    smart_alloc!(|slots: local_slots, ut: uts| {
+        let (child_asid, _asid_pool) = asid_pool.alloc();
+        let child_root = retype(ut, slots)?;
+        let child_vspace_slots: LocalCNodeSlots<U1024> = slots;
+        let child_vspace_ut: LocalCap<Untyped<U15>> = ut;
+
+        let mut child_vspace = VSpace::new(
+            child_root,
+            child_asid,
+            child_vspace_slots.weaken(),
+            child_vspace_ut.weaken(),
+            ProcessCodeImageConfig::ReadOnly,
+            user_image,
+            root_cnode,
+        )?;
+
+        let (child_cnode, child_slots) = retype_cnode::<U12>(ut, slots)?;
+
+        smart_alloc! {|slots_c: child_slots| {
+            let (ipc_setup, responder) = call_channel(ut, &root_cnode, slots, slots_c)?;
+            let grower = ipc_setup.create_caller(slots_c)?;
+            let (fault_source, outcome_sender, handler) =
+                fault_or_message_channel(&root_cnode, ut, slots, slots_c, slots)?;
+
+            let heap_region: UnmappedMemoryRegion<U12, shared_status::Exclusive> =
+                UnmappedMemoryRegion::new(ut, slots)?;
+            let heap_mem = child_vspace.map_region_and_move(
+                heap_region,
+                rights::RW,
+                arch::vm_attributes::DEFAULT,
+                root_cnode,
+                slots_c,
+            )?;
+        }}
+
+        let params = ProcParams {
+            heap_mem,
+            grower,
+            outcome_sender,
+        };
+
+        let mut child_process = StandardProcess::new(
+            &mut child_vspace,
+            child_cnode,
+            local_mapped_region,
+            root_cnode,
+            proc_main as extern "C" fn(_) -> (),
+            params,
+            ut,
+            ut,
+            slots,
+            tpa,
+            Some(fault_source),
+        )?;
+
+        let growth_region: UnmappedMemoryRegion<U14, shared_status::Exclusive> =
+            UnmappedMemoryRegion::new(ut, slots)?;
+    });
+
+    child_process.start()?;
+
+    // The child's first allocation fits in the region it started with;
+    // the second needs more.
+    let mut growth_region = Some(growth_region);
+    responder.recv_reply_once(|req: HeapGrowthRequest| {
+        let region = growth_region.take()?;
+        if req.min_bytes > region.size_bytes() {
+            return None;
+        }
+        child_vspace
+            .map_region(region, rights::RW, arch::vm_attributes::DEFAULT)
+            .ok()
+            .map(|mapped| HeapGrowth::of(&mapped))
+    })?;
+
+    match handler.await_message()? {
+        FaultOrMessage::Message(true) => Ok(()),
+        _ => Err(TopLevelError::TestAssertionFailure(
+            "Child process should have grown its heap",
+        )),
+    }
+}
+
+pub struct ProcParams<Role: CNodeRole> {
+    pub heap_mem: MappedMemoryRegion<U12, shared_status::Exclusive>,
+    pub grower: HeapGrower<Role>,
+    pub outcome_sender: Sender<bool, Role>,
+}
+
+impl RetypeForSetup for ProcParams<role::Local> {
+    type Output = ProcParams<role::Child>;
+}
+
+pub extern "C" fn proc_main(params: ProcParams<role::Local>) {
+    let heap = Heap::empty();
+    heap.add_region(params.heap_mem);
+    heap.set_grower(params.grower);
+
+    let small = Layout::from_size_align(64, 16).unwrap();
+    let large = Layout::from_size_align(2 * arch::PageBytes::USIZE, 64).unwrap();
+
+    let outcome = unsafe {
+        let a = heap.alloc(small);
+        let b = heap.alloc(large);
+        let ok = !a.is_null()
+            && !b.is_null()
+            && a as usize % small.align() == 0
+            && b as usize % large.align() == 0;
+        if ok {
+            core::ptr::write_bytes(a, 0xA5, small.size());
+            core::ptr::write_bytes(b, 0x5A, large.size());
+        }
+        let intact = ok
+            && core::slice::from_raw_parts(a, small.size())
+                .iter()
+                .all(|&byte| byte == 0xA5);
+
+        // Freed memory goes back to the heap, so the same allocation
+        // succeeds again without asking for more.
+        heap.dealloc(b, large);
+        let c = heap.alloc(large);
+        let reused = c == b;
+        heap.dealloc(c, large);
+        heap.dealloc(a, small);
+        intact && reused
+    };
+
+    params
+        .outcome_sender
+        .blocking_send(&outcome)
+        .expect("Failed to send test outcome");
+}
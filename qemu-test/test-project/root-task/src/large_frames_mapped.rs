@@ -0,0 +1,125 @@
+use typenum::*;
+
+use ferros::alloc::{smart_alloc, ut_buddy};
+use ferros::arch;
+use ferros::bootstrap::UserImage;
+use ferros::cap::{retype, role, ASIDPool, LocalCNode, LocalCNodeSlots, LocalCap, Untyped};
+use ferros::userland::{rights, CapRights};
+use ferros::vspace::*;
+
+use super::TopLevelError;
+
+#[ferros_test::ferros_test]
+pub fn large_frames_mapped(
+    local_slots: LocalCNodeSlots<U1024>,
+    local_ut: LocalCap<Untyped<U25>>,
+    asid_pool: LocalCap<ASIDPool<U1>>,
+    local_vspace: &mut VSpace,
+    root_cnode: &LocalCap<LocalCNode>,
+    user_image: &UserImage<role::Local>,
+) -> Result<(), TopLevelError> {
+    let uts = ut_buddy(local_ut);
+
+    smart_alloc!(|slots: local_slots, ut: uts| {
+        let (asid, _asid_pool) = asid_pool.alloc();
+        let vspace_slots: LocalCNodeSlots<U256> = slots;
+        let vspace_ut: LocalCap<Untyped<U15>> = ut;
+        let mut vspace = VSpace::new(
+            retype(ut, slots)?,
+            asid,
+            vspace_slots.weaken(),
+            vspace_ut.weaken(),
+            ProcessCodeImageConfig::ReadOnly,
+            user_image,
+            root_cnode,
+        )?;
+
+        let region_slots: LocalCNodeSlots<U16> = slots;
+        let region_ut: LocalCap<Untyped<U24>> = ut;
+        let share_slots: LocalCNodeSlots<U16> = slots;
+    });
+
+    // A 16 MiB region, the size of a modest framebuffer, takes only a
+    // handful of frames rather than 4096 pages.
+    let region: UnmappedMemoryRegion<U24, shared_status::Exclusive> =
+        UnmappedMemoryRegion::new_in_frames(region_ut, &mut region_slots.weaken())?;
+    let frame_bits = region.frame_bits();
+    if frame_bits <= arch::PageBits::U8 {
+        return Err(TopLevelError::TestAssertionFailure(
+            "A large region should be made of frames larger than a page",
+        ));
+    }
+
+    let mapped = vspace.map_region(region, rights::RW, arch::vm_attributes::DEFAULT)?;
+    if mapped.vaddr() % (1 << frame_bits) != 0 {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Frames should be mapped at a multiple of their size",
+        ));
+    }
+    let vaddr = mapped.vaddr();
+    let region = vspace.unmap_region(mapped)?;
+
+    // Frames can't be mapped somewhere that isn't aligned to their size
+    let region = match vspace.map_region_at_addr(
+        region,
+        vaddr + arch::PageBytes::USIZE,
+        rights::RW,
+        arch::vm_attributes::DEFAULT,
+    ) {
+        Err((VSpaceError::MappingError(MappingError::AddrNotPageAligned), Some(region))) => region,
+        _ => {
+            return Err(TopLevelError::TestAssertionFailure(
+                "Mapping frames at a misaligned address should fail",
+            ))
+        }
+    };
+
+    let mapped = vspace
+        .map_region_at_addr(region, vaddr, rights::RW, arch::vm_attributes::DEFAULT)
+        .map_err(|(e, _)| e)?;
+    let region = vspace.unmap_region(mapped)?;
+
+    // Every page of the region is backed by memory, which is checked
+    // through a mapping in the root task's own address space.
+    let page_bytes = arch::PageBytes::USIZE;
+    let mut local = local_vspace.map_region(region, rights::RW, arch::vm_attributes::DEFAULT)?;
+    for (index, page) in local.as_mut_slice().chunks_mut(page_bytes).enumerate() {
+        page[0] = index as u8;
+        page[page_bytes - 1] = !(index as u8);
+    }
+    let backed = |data: &[u8]| {
+        data.chunks(page_bytes)
+            .enumerate()
+            .all(|(index, page)| page[0] == index as u8 && page[page_bytes - 1] == !(index as u8))
+    };
+    if !backed(local.as_slice()) {
+        return Err(TopLevelError::TestAssertionFailure(
+            "The whole of a large region should be backed by memory",
+        ));
+    }
+    let local_vaddr = local.vaddr();
+    let region = local_vspace.unmap_region(local)?;
+
+    // Sharing takes a slot for each frame rather than for each page,
+    // and the copy is backed by the same memory.
+    let mut share_slots = share_slots.weaken();
+    let (copy, _region) = region
+        .weaken()
+        .share(&mut share_slots, root_cnode, CapRights::RW)?;
+    let copy = local_vspace
+        .weak_map_region_at_addr(
+            copy,
+            local_vaddr,
+            CapRights::R,
+            arch::vm_attributes::DEFAULT,
+        )
+        .map_err(|(e, _)| e)?;
+    if !backed(copy.as_slice()) {
+        return Err(TopLevelError::TestAssertionFailure(
+            "A shared copy of a large region should see the same memory",
+        ));
+    }
+    let _ = local_vspace.weak_unmap_region(copy)?;
+
+    Ok(())
+}
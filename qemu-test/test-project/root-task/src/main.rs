@@ -12,30 +12,70 @@ extern crate selfe_sys;
 #[macro_use]
 extern crate typenum;
 
-mod call_and_response_loop;
-mod child_process_cap_management;
-mod child_process_runs;
-mod child_thread_runs;
-mod dont_tread_on_me;
-mod double_door_backpressure;
-mod elf_process_runs;
-mod fault_or_message_handler;
-mod fault_pair;
-mod grandkid_process_runs;
-mod irq_control_manipulation;
-mod memory_read_protection;
-mod memory_write_protection;
-mod over_register_size_params;
-mod polling_consumer;
-mod reuse_slots;
-mod reuse_untyped;
-mod root_task_runs;
-mod self_hosted_mem_mgmt;
-mod shared_page_queue;
-mod stack_setup;
-mod uart;
-mod weak_elf;
-mod wutbuddy;
+// The unified suite is written against the classic kernel's fault and
+// reply handling, so an MCS kernel build only has the scheduling context
+// test.
+macro_rules! classic_kernel_test_modules {
+    ($($(#[$attr:meta])* mod $name:ident;)*) => {
+        $(
+            #[cfg(not(KernelIsMCS))]
+            $(#[$attr])*
+            mod $name;
+        )*
+    };
+}
+
+classic_kernel_test_modules! {
+    mod address_space_reuse;
+    mod cache_maintenance_ranges;
+    mod call_and_response_loop;
+    mod child_process_cap_management;
+    mod child_process_runs;
+    mod child_thread_runs;
+    mod copy_on_write;
+    mod demand_paging;
+    #[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
+    mod device_range_mapped;
+    mod dma_buffer_segments;
+    mod dont_tread_on_me;
+    mod double_door_backpressure;
+    mod elf_process_pie;
+    mod elf_process_runs;
+    mod elf_process_startup;
+    mod elf_process_tls;
+    mod fault_or_message_handler;
+    mod fault_pair;
+    mod grandkid_process_runs;
+    mod grant_revocable;
+    mod heap_grows;
+    #[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
+    mod irq_control_manipulation;
+    mod large_frames_mapped;
+    mod memory_read_protection;
+    mod memory_write_protection;
+    mod over_register_size_params;
+    mod paddr_constraint_alloc;
+    mod paging_layers_reclaimed;
+    mod polling_consumer;
+    mod process_destroy;
+    mod remap_region_rights;
+    mod reuse_slots;
+    mod reuse_untyped;
+    mod root_task_runs;
+    mod self_hosted_mem_mgmt;
+    mod shared_page_queue;
+    mod slot_recycling;
+    mod stack_setup;
+    mod supervisor_restarts;
+    mod two_level_cspace;
+    mod uart;
+    mod weak_elf;
+    mod wutbuddy;
+    mod wutbuddy_coalescing;
+}
+
+#[cfg(KernelIsMCS)]
+mod sched_context_throttling;
 
 mod resources {
     include! {concat!(env!("OUT_DIR"), "/resources.rs")}
@@ -48,52 +88,100 @@ extern "C" {
 
 use ferros::alloc::micro_alloc::Error as AllocError;
 use ferros::alloc::ut_buddy::UTBuddyError;
+use ferros::cap::CNodeSlotsError;
 use ferros::cap::IRQError;
 use ferros::cap::RetypeError;
 use ferros::error::SeL4Error;
 use ferros::userland::{
-    FaultManagementError, IPCError, MultiConsumerError, ProcessSetupError, ThreadSetupError,
+    FaultManagementError, IPCError, MultiConsumerError, PagerError, ProcessDestroyError,
+    ProcessSetupError, StartupBlockError, ThreadSetupError,
 };
+use ferros::vspace::dma::DmaError;
 use ferros::vspace::VSpaceError;
 
-#[cfg(not(test_case = "uart"))]
+#[cfg(not(any(test_case = "uart", KernelIsMCS)))]
 use ferros_test::ferros_test_main;
 
-#[cfg(not(test_case = "uart"))]
+// The device and IRQ tests are written against the arm platforms'
+// peripherals and interrupt controllers.
+#[cfg(not(any(test_case = "uart", KernelIsMCS)))]
 ferros_test_main!(&[
+    &address_space_reuse::address_space_reuse,
+    &cache_maintenance_ranges::cache_maintenance_ranges,
     &call_and_response_loop::call_and_response_loop,
     &child_process_cap_management::child_process_cap_management,
     &child_process_runs::child_process_runs,
     &child_thread_runs::child_thread_runs,
+    &copy_on_write::copy_on_write,
+    &demand_paging::demand_paging,
+    #[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
+    &device_range_mapped::device_range_mapped,
+    &dma_buffer_segments::dma_buffer_segments,
     &dont_tread_on_me::dont_tread_on_me,
     &double_door_backpressure::double_door_backpressure,
+    &elf_process_pie::elf_process_pie,
     &elf_process_runs::elf_process_runs,
+    &elf_process_startup::elf_process_startup,
+    &elf_process_tls::elf_process_tls,
     &fault_or_message_handler::fault_or_message_handler,
     &fault_pair::fault_pair,
     &grandkid_process_runs::grandkid_process_runs,
+    &grant_revocable::grant_revocable,
+    &heap_grows::heap_grows,
+    #[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
     &irq_control_manipulation::irq_control_manipulation,
+    &large_frames_mapped::large_frames_mapped,
     &memory_read_protection::memory_read_protection,
     &memory_write_protection::memory_write_protection,
     &over_register_size_params::over_register_size_params,
+    &paddr_constraint_alloc::paddr_constraint_alloc,
+    &paging_layers_reclaimed::paging_layers_reclaimed,
     &polling_consumer::polling_consumer,
+    &process_destroy::process_destroy,
+    &remap_region_rights::remap_region_rights,
     &reuse_slots::reuse_slots,
     &reuse_untyped::reuse_untyped,
     &root_task_runs::root_task_runs,
     &self_hosted_mem_mgmt::self_hosted_mem_mgmt,
     &shared_page_queue::shared_page_queue,
+    &slot_recycling::slot_recycling,
     &stack_setup::stack_setup,
+    &supervisor_restarts::supervisor_restarts,
+    &two_level_cspace::two_level_cspace,
     &wutbuddy::wutbuddy,
+    &wutbuddy_coalescing::wutbuddy_coalescing,
     &weak_elf::weak_elf_process_runs,
 ]);
 
-#[cfg(test_case = "uart")]
+#[cfg(KernelIsMCS)]
+fn main() {
+    let bootinfo = unsafe { &*selfe_start::BOOTINFO };
+    // Report in the same format as the unified suite
+    match sched_context_throttling::run(bootinfo) {
+        Ok(()) => {
+            debug_println!("test sched_context_throttling ... ok");
+            debug_println!("\ntest result: ok. 1 passed; 0 failed;");
+        }
+        Err(e) => {
+            debug_println!("test sched_context_throttling ... FAILED: {:?}", e);
+            debug_println!("\ntest result: FAILED. 0 passed; 1 failed;");
+        }
+    }
+    unsafe {
+        loop {
+            selfe_sys::seL4_Yield();
+        }
+    }
+}
+
+#[cfg(all(test_case = "uart", not(KernelIsMCS)))]
 fn main() {
     debug_println!("Starting the test!");
     let bootinfo = unsafe { &*selfe_start::BOOTINFO };
     run(bootinfo);
 }
 
-#[cfg(test_case = "uart")]
+#[cfg(all(test_case = "uart", not(KernelIsMCS)))]
 pub fn run(raw_boot_info: &'static selfe_sys::seL4_BootInfo) {
     uart::run(raw_boot_info).expect("run");
     unsafe {
@@ -111,11 +199,16 @@ pub enum TopLevelError {
     VSpaceError(VSpaceError),
     SeL4Error(SeL4Error),
     IRQError(IRQError),
+    DmaError(DmaError),
     FaultManagementError(FaultManagementError),
     ProcessSetupError(ProcessSetupError),
+    ProcessDestroyError(ProcessDestroyError),
+    PagerError(PagerError),
+    StartupBlockError(StartupBlockError),
     ThreadSetupError(ThreadSetupError),
     UTBuddyError(UTBuddyError),
     RetypeError(RetypeError),
+    CNodeSlotsError(CNodeSlotsError),
     TestAssertionFailure(&'static str),
 }
 
@@ -155,18 +248,42 @@ impl From<IRQError> for TopLevelError {
     }
 }
 
+impl From<DmaError> for TopLevelError {
+    fn from(e: DmaError) -> Self {
+        TopLevelError::DmaError(e)
+    }
+}
+
 impl From<FaultManagementError> for TopLevelError {
     fn from(e: FaultManagementError) -> Self {
         TopLevelError::FaultManagementError(e)
     }
 }
 
+impl From<ProcessDestroyError> for TopLevelError {
+    fn from(e: ProcessDestroyError) -> Self {
+        TopLevelError::ProcessDestroyError(e)
+    }
+}
+
 impl From<ProcessSetupError> for TopLevelError {
     fn from(e: ProcessSetupError) -> Self {
         TopLevelError::ProcessSetupError(e)
     }
 }
 
+impl From<PagerError> for TopLevelError {
+    fn from(e: PagerError) -> Self {
+        TopLevelError::PagerError(e)
+    }
+}
+
+impl From<StartupBlockError> for TopLevelError {
+    fn from(e: StartupBlockError) -> Self {
+        TopLevelError::StartupBlockError(e)
+    }
+}
+
 impl From<ThreadSetupError> for TopLevelError {
     fn from(e: ThreadSetupError) -> Self {
         TopLevelError::ThreadSetupError(e)
@@ -184,3 +301,9 @@ impl From<RetypeError> for TopLevelError {
         TopLevelError::RetypeError(e)
     }
 }
+
+impl From<CNodeSlotsError> for TopLevelError {
+    fn from(e: CNodeSlotsError) -> Self {
+        TopLevelError::CNodeSlotsError(e)
+    }
+}
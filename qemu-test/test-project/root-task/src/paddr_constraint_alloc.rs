@@ -0,0 +1,159 @@
+use typenum::*;
+
+use ferros::alloc::micro_alloc::{Allocator, PageAlignedAddressRange, PhysicalAddressConstraint};
+use ferros::arch;
+use ferros::cap::{LocalCNodeSlots, LocalCap, Untyped, WCNodeSlots};
+use ferros::vspace::*;
+
+use super::TopLevelError;
+
+#[ferros_test::ferros_test]
+pub fn paddr_constraint_alloc(
+    local_slots: LocalCNodeSlots<U256>,
+    allocator: &mut Allocator,
+) -> Result<(), TopLevelError> {
+    let (within_slot, local_slots) = local_slots.alloc();
+    let (spare_slot, local_slots) = local_slots.alloc();
+    let (below_slot, local_slots) = local_slots.alloc();
+    let (aligned_slot, local_slots) = local_slots.alloc();
+    let (no_slots, local_slots): (LocalCNodeSlots<U0>, _) = local_slots.alloc();
+    let mut no_slots = no_slots.weaken();
+    let mut slots = local_slots.weaken();
+    let page_bits = arch::PageBits::U8;
+    let page_bytes = arch::PageBytes::USIZE;
+
+    let (big_bits, big_paddr) = allocator
+        .untypeds()
+        .max_by_key(|&(size_bits, _)| size_bits)
+        .ok_or(TopLevelError::TestAssertionFailure(
+            "The harness should leave some untypeds in its allocator",
+        ))?;
+    if big_bits < page_bits + 2 {
+        return Err(TopLevelError::TestAssertionFailure(
+            "The harness should leave a few pages' worth of untyped in its allocator",
+        ));
+    }
+
+    // A page part way into the upper half of the biggest untyped can
+    // only come from that untyped, so it must be split all the way
+    // down, leaving a spare at every size in between.
+    let target = big_paddr + (1 << (big_bits - 1)) + 3 * page_bytes;
+    let range = PageAlignedAddressRange::new_by_size(target, page_bytes)
+        .expect("failed to specify the target page");
+    let before = allocator.untypeds().count();
+    let (ut, paddr) = take_page(
+        allocator,
+        PhysicalAddressConstraint::within(range),
+        &mut slots,
+    )?;
+    if paddr != target || kernel_paddr(ut, within_slot)? != target {
+        return Err(TopLevelError::TestAssertionFailure(
+            "An untyped taken within a range should be at the only place in it",
+        ));
+    }
+    let spares = check_spares(allocator, before, paddr)?;
+    if spares != usize::from(big_bits - page_bits)
+        || allocator
+            .untypeds()
+            .any(|untyped| untyped == (big_bits, big_paddr))
+    {
+        return Err(TopLevelError::TestAssertionFailure(
+            "The untyped split should be replaced by its spare pieces",
+        ));
+    }
+
+    // The page split off next to it needs no more splitting, so it can
+    // be had without any slots, and is where the allocator says.
+    let buddy = target ^ page_bytes;
+    let range = PageAlignedAddressRange::new_by_size(buddy, page_bytes)
+        .expect("failed to specify the spare page");
+    let (ut, paddr) = take_page(
+        allocator,
+        PhysicalAddressConstraint::within(range),
+        &mut no_slots,
+    )?;
+    if paddr != buddy || kernel_paddr(ut, spare_slot)? != buddy {
+        return Err(TopLevelError::TestAssertionFailure(
+            "A spare piece should be handed out at the address it was split off at",
+        ));
+    }
+
+    // The lower half of the biggest untyped is still spare, so there's
+    // certainly a page to be had below it.
+    let limit = big_paddr + (1 << (big_bits - 1));
+    let before = allocator.untypeds().count();
+    let (ut, paddr) = take_page(
+        allocator,
+        PhysicalAddressConstraint::below(limit),
+        &mut slots,
+    )?;
+    if paddr + page_bytes > limit || kernel_paddr(ut, below_slot)? != paddr {
+        return Err(TopLevelError::TestAssertionFailure(
+            "An untyped taken below a limit should end at or before it",
+        ));
+    }
+    let _ = check_spares(allocator, before, paddr)?;
+
+    let align_bits = 16;
+    let before = allocator.untypeds().count();
+    let (ut, paddr) = take_page(
+        allocator,
+        PhysicalAddressConstraint::any().aligned_to(align_bits),
+        &mut slots,
+    )?;
+    if paddr % (1 << align_bits) != 0 || kernel_paddr(ut, aligned_slot)? != paddr {
+        return Err(TopLevelError::TestAssertionFailure(
+            "An aligned untyped should start at a multiple of the alignment",
+        ));
+    }
+    let _ = check_spares(allocator, before, paddr)?;
+
+    Ok(())
+}
+
+fn take_page(
+    allocator: &mut Allocator,
+    constraint: PhysicalAddressConstraint,
+    slots: &mut WCNodeSlots,
+) -> Result<(LocalCap<Untyped<arch::PageBits>>, usize), TopLevelError> {
+    allocator
+        .get_untyped_by_paddr_constraint(constraint, slots)
+        .map_err(|_| {
+            TopLevelError::TestAssertionFailure("A page satisfying the constraint should be found")
+        })
+}
+
+/// Ask the kernel where the page made from `ut` really is.
+fn kernel_paddr(
+    ut: LocalCap<Untyped<arch::PageBits>>,
+    slot: LocalCNodeSlots<U1>,
+) -> Result<usize, TopLevelError> {
+    let region: UnmappedMemoryRegion<arch::PageBits, shared_status::Exclusive> =
+        UnmappedMemoryRegion::new(ut, slot)?;
+    Ok(region.paddr()?)
+}
+
+/// Check that whatever was split to get the page at `paddr` was put
+/// back as the page's buddy at each size on the way down, and nothing
+/// else, and return how many of those there were.
+fn check_spares(
+    allocator: &Allocator,
+    before: usize,
+    paddr: usize,
+) -> Result<usize, TopLevelError> {
+    let spares = (arch::PageBits::U8..)
+        .take_while(|&size_bits| {
+            let size = 1usize << size_bits;
+            let spare = (paddr & !(size - 1)) ^ size;
+            allocator
+                .untypeds()
+                .any(|untyped| untyped == (size_bits, spare))
+        })
+        .count();
+    if allocator.untypeds().count() + 1 != before + spares {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Only the spare pieces of a split untyped should go back in the allocator",
+        ));
+    }
+    Ok(spares)
+}
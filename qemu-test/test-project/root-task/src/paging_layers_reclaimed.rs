@@ -0,0 +1,88 @@
+use typenum::*;
+
+use ferros::alloc::{smart_alloc, ut_buddy};
+use ferros::arch;
+use ferros::bootstrap::UserImage;
+use ferros::cap::{retype, role, ASIDPool, LocalCNode, LocalCNodeSlots, LocalCap, Untyped};
+use ferros::userland::rights;
+use ferros::vspace::*;
+
+use super::TopLevelError;
+
+#[ferros_test::ferros_test]
+pub fn paging_layers_reclaimed(
+    local_slots: LocalCNodeSlots<U2048>,
+    local_ut: LocalCap<Untyped<U18>>,
+    asid_pool: LocalCap<ASIDPool<U1>>,
+    root_cnode: &LocalCap<LocalCNode>,
+    user_image: &UserImage<role::Local>,
+) -> Result<(), TopLevelError> {
+    let uts = ut_buddy(local_ut);
+
+    smart_alloc!(|slots: local_slots, ut: uts| {
+        let (asid, _asid_pool) = asid_pool.alloc();
+        let vspace_slots: LocalCNodeSlots<U1024> = slots;
+        let vspace_ut: LocalCap<Untyped<U15>> = ut;
+        let mut vspace = VSpace::new(
+            retype(ut, slots)?,
+            asid,
+            vspace_slots.weaken(),
+            vspace_ut.weaken(),
+            ProcessCodeImageConfig::ReadOnly,
+            user_image,
+            root_cnode,
+        )?;
+
+        let region: UnmappedMemoryRegion<U12, shared_status::Exclusive> =
+            UnmappedMemoryRegion::new(ut, slots)?;
+    });
+
+    // Each mapping lands in a page table of its own, and between them
+    // they need far more page tables than the VSpace's untyped could
+    // hold at once. This only works if each is given back on unmap.
+    let stride = 1 << 22;
+    let cycles = 64;
+    let base = vspace.allocate_address_range(cycles * stride, stride)?;
+
+    let mut region = region;
+    for i in 0..cycles {
+        let mapped = vspace
+            .map_region_at_addr(
+                region,
+                base + i * stride,
+                rights::RW,
+                arch::vm_attributes::DEFAULT,
+            )
+            .map_err(|(e, _)| e)?;
+        region = vspace.unmap_region(mapped)?;
+    }
+
+    // Mappings a huge page's span apart each need every layer but the
+    // topmost built out for them, which on RISC-V the kernel installs
+    // one page table at a time, from the highest missing level down.
+    // Each of those has to be found and given back as well.
+    #[cfg(target_pointer_width = "64")]
+    {
+        let stride = 1 << arch::HugePageBits::USIZE;
+        let base = vspace.allocate_address_range(cycles * stride, stride)?;
+        for i in 0..cycles {
+            let mapped = vspace
+                .map_region_at_addr(
+                    region,
+                    base + i * stride,
+                    rights::RW,
+                    arch::vm_attributes::DEFAULT,
+                )
+                .map_err(|(e, _)| e)?;
+            region = vspace.unmap_region(mapped)?;
+        }
+    }
+
+    if vspace.leaked_paging_layers() != 0 {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Every paging layer should have been given back",
+        ));
+    }
+
+    Ok(())
+}
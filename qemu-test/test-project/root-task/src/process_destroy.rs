@@ -0,0 +1,131 @@
+use super::TopLevelError;
+
+use ferros::alloc::{smart_alloc, ut_buddy};
+use typenum::*;
+
+use ferros::bootstrap::UserImage;
+use ferros::cap::*;
+use ferros::userland::{
+    fault_or_message_channel, FaultOrMessage, ProcessResources, RetypeForSetup, Sender,
+    StandardProcess,
+};
+use ferros::vspace::*;
+
+#[ferros_test::ferros_test]
+pub fn process_destroy(
+    local_slots: LocalCNodeSlots<U2048>,
+    local_ut: LocalCap<Untyped<U19>>,
+    asid_pool: LocalCap<ASIDPool<U1>>,
+    local_mapped_region: MappedMemoryRegion<U16, shared_status::Exclusive>,
+    root_cnode: &LocalCap<LocalCNode>,
+    user_image: &UserImage<role::Local>,
+    tpa: &LocalCap<ThreadPriorityAuthority>,
+) -> Result<(), TopLevelError> {
+    let stack_vaddr = local_mapped_region.vaddr();
+
+    let (resources, slots, ut, pool) = ProcessResources::lend(local_slots, local_ut, asid_pool);
+    let (process, vspace) = run_child(
+        1,
+        slots,
+        ut,
+        pool,
+        local_mapped_region,
+        root_cnode,
+        user_image,
+        tpa,
+    )?;
+    let (local_slots, local_ut, asid_pool, stack) =
+        process.destroy(vspace, resources).map_err(|(e, ..)| e)?;
+    if stack.vaddr() != stack_vaddr {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Destroying a process should hand back the stack it was built on",
+        ));
+    }
+
+    // Everything, including the sole ASID in the pool and the stack,
+    // is available again
+    let (resources, slots, ut, pool) = ProcessResources::lend(local_slots, local_ut, asid_pool);
+    let (process, vspace) = run_child(2, slots, ut, pool, stack, root_cnode, user_image, tpa)?;
+    let _ = process.destroy(vspace, resources).map_err(|(e, ..)| e)?;
+
+    Ok(())
+}
+
+fn run_child(
+    run: usize,
+    local_slots: LocalCNodeSlots<U2048>,
+    local_ut: LocalCap<Untyped<U19>>,
+    asid_pool: LocalCap<ASIDPool<U1>>,
+    stack_region: MappedMemoryRegion<U16, shared_status::Exclusive>,
+    root_cnode: &LocalCap<LocalCNode>,
+    user_image: &UserImage<role::Local>,
+    tpa: &LocalCap<ThreadPriorityAuthority>,
+) -> Result<(StandardProcess<U16>, VSpace), TopLevelError> {
+    let uts = ut_buddy(local_ut);
+
+    smart_alloc!(|slots: local_slots, ut: uts| {
+        let (child_cnode, child_slots) = retype_cnode::<U12>(ut, slots)?;
+        let (child_fault_source_slot, _child_slots) = child_slots.alloc();
+        let (fault_source, outcome_sender, handler) =
+            fault_or_message_channel(&root_cnode, ut, slots, child_fault_source_slot, slots)?;
+        let params = ProcParams {
+            run,
+            outcome_sender,
+        };
+
+        let (child_asid, _asid_pool) = asid_pool.alloc();
+
+        let child_root = retype(ut, slots)?;
+        let child_vspace_slots: LocalCNodeSlots<U1024> = slots;
+        let child_vspace_ut: LocalCap<Untyped<U15>> = ut;
+
+        let mut child_vspace = VSpace::new(
+            child_root,
+            child_asid,
+            child_vspace_slots.weaken(),
+            child_vspace_ut.weaken(),
+            ProcessCodeImageConfig::ReadOnly,
+            user_image,
+            root_cnode,
+        )?;
+
+        let mut child_process = StandardProcess::new(
+            &mut child_vspace,
+            child_cnode,
+            stack_region,
+            root_cnode,
+            proc_main as extern "C" fn(_) -> (),
+            params,
+            ut,
+            ut,
+            slots,
+            tpa,
+            None, // fault
+        )?;
+    });
+
+    child_process.start()?;
+
+    match handler.await_message()? {
+        FaultOrMessage::Message(reported) if reported == run => Ok((child_process, child_vspace)),
+        _ => Err(TopLevelError::TestAssertionFailure(
+            "Child process should have reported its run number",
+        )),
+    }
+}
+
+pub struct ProcParams<Role: CNodeRole> {
+    pub run: usize,
+    pub outcome_sender: Sender<usize, Role>,
+}
+
+impl RetypeForSetup for ProcParams<role::Local> {
+    type Output = ProcParams<role::Child>;
+}
+
+pub extern "C" fn proc_main(params: ProcParams<role::Local>) {
+    params
+        .outcome_sender
+        .blocking_send(&params.run)
+        .expect("Could not report run number")
+}
@@ -0,0 +1,100 @@
+use typenum::*;
+
+use ferros::arch;
+use ferros::cap::role;
+use ferros::userland::{rights, CapRights};
+use ferros::vspace::*;
+
+use super::TopLevelError;
+
+#[ferros_test::ferros_test]
+pub fn remap_region_rights(
+    local_mapped_region: MappedMemoryRegion<U14, shared_status::Exclusive>,
+    local_vspace: &mut VSpace,
+) -> Result<(), TopLevelError> {
+    let mut mapped = local_mapped_region;
+    let vaddr = mapped.vaddr();
+    let size_bytes = mapped.size_bytes();
+    {
+        let data = mapped.as_mut_slice();
+        data[0] = 0xaa;
+        data[size_bytes - 1] = 0x55;
+    }
+
+    // Seal it read-only, as a loader would its freshly written code.
+    // The sealed region has no `as_mut_slice` at all.
+    let sealed: MappedMemoryRegion<U14, shared_status::Exclusive, role::Local, rights::R> =
+        local_vspace
+            .remap_region(mapped, rights::R, arch::vm_attributes::DEFAULT)
+            .map_err(|(e, _)| e)?;
+    if sealed.rights() != CapRights::R || sealed.vaddr() != vaddr {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Remapping should change the rights and nothing else",
+        ));
+    }
+    let data = sealed.as_slice();
+    if data[0] != 0xaa || data[size_bytes - 1] != 0x55 {
+        return Err(TopLevelError::TestAssertionFailure(
+            "A region's contents should survive remapping it read-only",
+        ));
+    }
+
+    let mut mapped = local_vspace
+        .remap_region(sealed, rights::RW, arch::vm_attributes::DEFAULT)
+        .map_err(|(e, _)| e)?;
+    if mapped.rights() != CapRights::RW || mapped.vaddr() != vaddr {
+        return Err(TopLevelError::TestAssertionFailure(
+            "A region can be remapped writable again",
+        ));
+    }
+    {
+        let data = mapped.as_mut_slice();
+        data[0] = 0x5a;
+        data[size_bytes - 1] = 0xa5;
+    }
+    let data = mapped.as_slice();
+    if data[0] != 0x5a || data[size_bytes - 1] != 0xa5 {
+        return Err(TopLevelError::TestAssertionFailure(
+            "A region remapped writable should take writes",
+        ));
+    }
+
+    let mut weak = mapped.weaken();
+    local_vspace.weak_remap_region(&mut weak, CapRights::R, arch::vm_attributes::DEFAULT)?;
+    if weak.rights() != CapRights::R {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Remapping a weak region should change its rights",
+        ));
+    }
+    if weak.as_mut_slice().is_some() {
+        return Err(TopLevelError::TestAssertionFailure(
+            "A weak region mapped read-only should have no mutable slice",
+        ));
+    }
+    let data = weak.as_slice();
+    if data[0] != 0x5a || data[size_bytes - 1] != 0xa5 {
+        return Err(TopLevelError::TestAssertionFailure(
+            "A weak region's contents should survive remapping it read-only",
+        ));
+    }
+
+    // The test harness hands this region out again, so it's left
+    // mapped writable as it was found.
+    local_vspace.weak_remap_region(&mut weak, CapRights::RW, arch::vm_attributes::DEFAULT)?;
+    {
+        let data = weak
+            .as_mut_slice()
+            .ok_or(TopLevelError::TestAssertionFailure(
+                "A weak region remapped writable should have a mutable slice",
+            ))?;
+        data[0] = 0;
+        data[size_bytes - 1] = 0;
+    }
+    if weak.as_slice()[0] != 0 || weak.as_slice()[size_bytes - 1] != 0 {
+        return Err(TopLevelError::TestAssertionFailure(
+            "A weak region remapped writable should take writes",
+        ));
+    }
+
+    Ok(())
+}
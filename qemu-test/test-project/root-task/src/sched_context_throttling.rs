@@ -0,0 +1,126 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use selfe_sys::*;
+
+use typenum::*;
+
+use ferros::alloc::{self, micro_alloc, smart_alloc};
+use ferros::bootstrap::{root_cnode, BootInfo};
+use ferros::cap::{retype, retype_cnode, LocalCNodeSlots, SchedContext};
+use ferros::error::{ErrorExt, SeL4Error};
+use ferros::userland::{rights, RetypeForSetup, Thread};
+use ferros::vspace::*;
+
+use super::TopLevelError;
+
+// The child may run for a tenth of every period.
+const BUDGET_MICROS: u64 = 1_000;
+const PERIOD_MICROS: u64 = 10_000;
+
+const ROOT_PRIORITY: usize = 100;
+const ROOT_SPINS: usize = 20_000_000;
+
+static CHILD_SPINS: AtomicUsize = AtomicUsize::new(0);
+static ROOT_SPIN_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// A thread that never blocks, bound to a scheduling context with a small
+/// budget, leaves most of the processor to a lower priority thread.
+pub fn run(raw_boot_info: &'static seL4_BootInfo) -> Result<(), TopLevelError> {
+    let (mut allocator, _device_allocator) = micro_alloc::bootstrap_allocators(&raw_boot_info)?;
+    let (root_cnode, local_slots) = root_cnode(&raw_boot_info);
+    let (root_vspace_slots, local_slots): (LocalCNodeSlots<U100>, _) = local_slots.alloc();
+    let BootInfo {
+        mut root_vspace,
+        root_tcb,
+        sched_control,
+        ..
+    } = BootInfo::wrap(
+        &raw_boot_info,
+        allocator
+            .get_untyped::<U13>()
+            .expect("Initial untyped retrieval failure"),
+        root_vspace_slots,
+    );
+    let tpa = root_tcb.downgrade_to_thread_priority_authority();
+    let uts = alloc::ut_buddy(
+        allocator
+            .get_untyped::<U20>()
+            .expect("initial alloc failure"),
+    );
+
+    smart_alloc!(|slots: local_slots, ut: uts| {
+        let stack_region: UnmappedMemoryRegion<U16, shared_status::Exclusive> =
+            UnmappedMemoryRegion::new(ut, slots)?;
+        let stack_region = root_vspace.map_region(
+            stack_region,
+            rights::RW,
+            ferros::arch::vm_attributes::DEFAULT,
+        )?;
+        let ipc_buffer_region: UnmappedMemoryRegion<U12, shared_status::Exclusive> =
+            UnmappedMemoryRegion::new(ut, slots)?;
+        let ipc_buffer_region = root_vspace.map_region(
+            ipc_buffer_region,
+            rights::RW,
+            ferros::arch::vm_attributes::DEFAULT,
+        )?;
+
+        let (child_cnode, _child_slots) = retype_cnode::<U12>(ut, slots)?;
+        let sched_context: ferros::cap::LocalCap<SchedContext> = retype(ut, slots)?;
+
+        let mut child = Thread::new(
+            root_vspace.root(),
+            child_cnode,
+            stack_region,
+            spin,
+            SpinParams {
+                spins: &CHILD_SPINS,
+            },
+            ipc_buffer_region,
+            ut,
+            slots,
+            &tpa,
+            None, // fault
+        )?;
+    });
+
+    // The child runs at the highest priority, so whenever it has budget
+    // left it preempts the root task.
+    unsafe { seL4_TCB_SetPriority(seL4_CapInitThreadTCB as usize, tpa.cptr, ROOT_PRIORITY) }
+        .as_result()
+        .map_err(SeL4Error::TCBSetPriority)?;
+    child.bind_sched_context(&sched_control, sched_context, BUDGET_MICROS, PERIOD_MICROS)?;
+    child.start()?;
+
+    for _ in 0..ROOT_SPINS {
+        ROOT_SPIN_COUNT.fetch_add(1, Ordering::Relaxed);
+    }
+    let child_spins = CHILD_SPINS.load(Ordering::Relaxed);
+
+    // Unthrottled, the child would never have let the root task get this
+    // far. Throttled, it gets around a ninth as many spins as the root.
+    if child_spins == 0 {
+        return Err(TopLevelError::TestAssertionFailure(
+            "The child thread should have run",
+        ));
+    }
+    if child_spins >= ROOT_SPINS / 2 {
+        return Err(TopLevelError::TestAssertionFailure(
+            "The child thread should have been held to its budget",
+        ));
+    }
+    Ok(())
+}
+
+pub struct SpinParams {
+    pub spins: &'static AtomicUsize,
+}
+
+impl RetypeForSetup for SpinParams {
+    type Output = SpinParams;
+}
+
+pub extern "C" fn spin(params: SpinParams) {
+    loop {
+        params.spins.fetch_add(1, Ordering::Relaxed);
+    }
+}
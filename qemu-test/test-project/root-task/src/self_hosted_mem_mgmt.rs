@@ -7,7 +7,7 @@ use ferros::arch;
 use ferros::bootstrap::UserImage;
 use ferros::cap::*;
 use ferros::userland::{
-    fault_or_message_channel, CapRights, FaultOrMessage, RetypeForSetup, SelfHostedProcess, Sender,
+    fault_or_message_channel, rights, FaultOrMessage, RetypeForSetup, SelfHostedProcess, Sender,
 };
 use ferros::vspace::*;
 
@@ -117,7 +117,7 @@ pub extern "C" fn sh_main(mut vspace: VSpace, params: ProcParams<role::Local>) {
     let unmapped_region =
         UnmappedMemoryRegion::new(untyped, child_slots).expect("retyping memory failed");
     let mapped_region = vspace
-        .map_region(unmapped_region, CapRights::RW, arch::vm_attributes::DEFAULT)
+        .map_region(unmapped_region, rights::RW, arch::vm_attributes::DEFAULT)
         .expect("mapping region failed");
     let vaddr = mapped_region.vaddr() as *mut u8;
     let val_at_ptr = unsafe {
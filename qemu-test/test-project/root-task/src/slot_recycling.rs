@@ -0,0 +1,70 @@
+use super::TopLevelError;
+
+use typenum::*;
+
+use ferros::alloc::{smart_alloc, ut_buddy};
+use ferros::cap::*;
+use ferros::userland::CapRights;
+
+#[ferros_test::ferros_test]
+pub fn slot_recycling(
+    local_slots: LocalCNodeSlots<U16>,
+    local_ut: LocalCap<Untyped<U12>>,
+    root_cnode: &LocalCap<LocalCNode>,
+) -> Result<(), TopLevelError> {
+    let uts = ut_buddy(local_ut);
+
+    smart_alloc!(|slots: local_slots, ut: uts| {
+        let endpoint: LocalCap<Endpoint> = retype(ut, slots)?;
+        let one_slot: LocalCNodeSlots<U1> = slots;
+        let two_slots: LocalCNodeSlots<U2> = slots;
+        let other_slot: LocalCNodeSlots<U1> = slots;
+    });
+
+    // A single slot, handed back each time, holds any number of caps
+    // one after another.
+    let mut slots = RecyclingCNodeSlots::new(one_slot.weaken());
+    for _ in 0..64 {
+        let slot = slots.alloc_strong::<U1>()?;
+        let copy = endpoint.copy(root_cnode, slot, CapRights::RW)?;
+        let slot = copy.delete_into_slot(root_cnode)?;
+        slots.free(slot).map_err(|_| {
+            TopLevelError::TestAssertionFailure("A freed slot should be taken back")
+        })?;
+    }
+
+    // A slot of the same CNode that this owner never handed out isn't
+    // taken.
+    if slots.free(other_slot).is_ok() {
+        return Err(TopLevelError::TestAssertionFailure(
+            "A slot from elsewhere in the CNode should be refused",
+        ));
+    }
+
+    // Slots freed out of order are handed out again too.
+    let mut slots = RecyclingCNodeSlots::new(two_slots.weaken());
+    let first = endpoint.copy(root_cnode, slots.alloc_strong::<U1>()?, CapRights::RW)?;
+    let second = endpoint.copy(root_cnode, slots.alloc_strong::<U1>()?, CapRights::RW)?;
+    if slots.alloc_strong::<U1>().is_ok() {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Every slot should be in use",
+        ));
+    }
+    slots
+        .free(first.delete_into_slot(root_cnode)?)
+        .map_err(|_| TopLevelError::TestAssertionFailure("A freed slot should be taken back"))?;
+    slots
+        .free(second.delete_into_slot(root_cnode)?)
+        .map_err(|_| TopLevelError::TestAssertionFailure("A freed slot should be taken back"))?;
+
+    // The first slot freed waits in the free list until the second
+    // rejoins the range, and then follows it back in, so the two can
+    // be handed out together.
+    if slots.alloc(2).is_err() {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Slots freed back into the range should be handed out together",
+        ));
+    }
+
+    Ok(())
+}
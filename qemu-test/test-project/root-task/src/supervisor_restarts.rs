@@ -0,0 +1,192 @@
+use typenum::*;
+
+use ferros::alloc::{smart_alloc, ut_buddy};
+use ferros::bootstrap::UserImage;
+use ferros::cap::{
+    retype, retype_cnode, role, ASIDPool, Badge, LocalCNode, LocalCNodeSlots, LocalCap,
+    ThreadPriorityAuthority, Untyped,
+};
+use ferros::userland::{
+    FaultSinkSetup, RestartPolicy, RetypeForSetup, StandardProcess, Supervisable, SupervisedChild,
+    SupervisedFaultSource, Supervisor, SupervisorError, SupervisorTicker, Thread,
+};
+use ferros::vspace::*;
+
+use super::TopLevelError;
+
+const SPAWN_BUDGET: usize = 4;
+
+#[ferros_test::ferros_test]
+pub fn supervisor_restarts(
+    local_slots: LocalCNodeSlots<U2048>,
+    child_slots: LocalCNodeSlots<U2048>,
+    local_ut: LocalCap<Untyped<U18>>,
+    child_ut: LocalCap<Untyped<U19>>,
+    asid_pool: LocalCap<ASIDPool<U1>>,
+    local_mapped_region: MappedMemoryRegion<U17, shared_status::Exclusive>,
+    ticker_stack: MappedMemoryRegion<U17, shared_status::Exclusive>,
+    ticker_ipc_buffer: MappedMemoryRegion<U12, shared_status::Exclusive>,
+    root_cnode: &LocalCap<LocalCNode>,
+    user_image: &UserImage<role::Local>,
+    tpa: &LocalCap<ThreadPriorityAuthority>,
+    vspace_paging_root: &LocalCap<ferros::arch::PagingRoot>,
+) -> Result<(), TopLevelError> {
+    let uts = ut_buddy(local_ut);
+
+    smart_alloc!(|slots: local_slots, ut: uts| {
+        let setup = FaultSinkSetup::new(&root_cnode, ut, slots, slots)?;
+        let mut supervisor = Supervisor::new(setup, root_cnode);
+
+        // Ticks as fast as the supervisor takes them, which is all the
+        // delayed restarts need to go ahead.
+        let (ticker_cnode, ticker_slots) = retype_cnode::<U12>(ut, slots)?;
+        let (ticker_slot, _ticker_slots) = ticker_slots.alloc();
+        let ticker = supervisor.ticker(ticker_slot, Badge::from(2))?;
+        let ticker_thread = Thread::new(
+            vspace_paging_root,
+            ticker_cnode,
+            ticker_stack,
+            tick_forever,
+            TickerParams { ticker },
+            ticker_ipc_buffer,
+            ut,
+            slots,
+            tpa,
+            None, // fault
+        )?;
+    });
+    ticker_thread.start()?;
+
+    let spawner = CrashingChild {
+        spawns: 0,
+        root_cnode,
+        user_image,
+        tpa,
+    };
+    let mut child = SupervisedChild::new(
+        spawner,
+        Badge::from(1),
+        RestartPolicy::RestartWithBackoff {
+            initial_delay: 1,
+            max_delay: 4,
+        },
+        child_slots,
+        child_ut,
+        asid_pool,
+        local_mapped_region,
+    );
+
+    // Every instance of the child faults, and each restart rebuilds it from
+    // the same resources, until the spawner declines to build any more.
+    match supervisor.run::<TopLevelError>(&mut [&mut child]) {
+        Err(SupervisorError::Spawn(TopLevelError::TestAssertionFailure(_)))
+            if child.restarts() == SPAWN_BUDGET =>
+        {
+            Ok(())
+        }
+        _ => Err(TopLevelError::TestAssertionFailure(
+            "Child should have been restarted until its spawn budget ran out",
+        )),
+    }
+}
+
+struct CrashingChild<'a> {
+    spawns: usize,
+    root_cnode: &'a LocalCap<LocalCNode>,
+    user_image: &'a UserImage<role::Local>,
+    tpa: &'a LocalCap<ThreadPriorityAuthority>,
+}
+
+impl<'a> Supervisable for CrashingChild<'a> {
+    type SlotCount = U2048;
+    type UntypedBitSize = U19;
+    type ASIDPoolSlots = U1;
+    type StackBitSize = U17;
+    type Error = TopLevelError;
+
+    fn spawn(
+        &mut self,
+        local_slots: LocalCNodeSlots<U2048>,
+        local_ut: LocalCap<Untyped<U19>>,
+        asid_pool: LocalCap<ASIDPool<U1>>,
+        stack: MappedMemoryRegion<U17, shared_status::Exclusive>,
+        fault_source: &SupervisedFaultSource,
+    ) -> Result<(StandardProcess<U17>, VSpace), TopLevelError> {
+        if self.spawns == SPAWN_BUDGET {
+            return Err(TopLevelError::TestAssertionFailure(
+                "Spawn budget exhausted",
+            ));
+        }
+        self.spawns += 1;
+
+        let uts = ut_buddy(local_ut);
+
+        smart_alloc!(|slots: local_slots, ut: uts| {
+            let (child_asid, _asid_pool) = asid_pool.alloc();
+            let child_vspace_slots: LocalCNodeSlots<U1024> = slots;
+            let child_vspace_ut: LocalCap<Untyped<U15>> = ut;
+            let mut child_vspace = VSpace::new(
+                retype(ut, slots)?,
+                child_asid,
+                child_vspace_slots.weaken(),
+                child_vspace_ut.weaken(),
+                ProcessCodeImageConfig::ReadOnly,
+                self.user_image,
+                self.root_cnode,
+            )?;
+
+            let (child_cnode, child_slots) = retype_cnode::<U12>(ut, slots)?;
+            let (child_slot_for_fault_source, _child_slots) = child_slots.alloc();
+            let child_fault_source = fault_source.mint(child_slot_for_fault_source)?;
+
+            let params = ProcParams { run: self.spawns };
+
+            let child_process = StandardProcess::new(
+                &mut child_vspace,
+                child_cnode,
+                stack,
+                self.root_cnode,
+                proc_main as extern "C" fn(_) -> (),
+                params,
+                ut,
+                ut,
+                slots,
+                self.tpa,
+                Some(child_fault_source),
+            )?;
+        });
+
+        Ok((child_process, child_vspace))
+    }
+}
+
+pub struct ProcParams {
+    pub run: usize,
+}
+
+impl RetypeForSetup for ProcParams {
+    type Output = ProcParams;
+}
+
+pub extern "C" fn proc_main(params: ProcParams) {
+    debug_println!("Supervised child run {} is about to fault", params.run);
+    unsafe {
+        let x: *const usize = 0x88888888usize as _;
+        let y = *x;
+        debug_println!("Value from arbitrary memory is: {}", y);
+    }
+}
+
+pub struct TickerParams<Role: ferros::cap::CNodeRole> {
+    pub ticker: SupervisorTicker<Role>,
+}
+
+impl RetypeForSetup for TickerParams<role::Local> {
+    type Output = TickerParams<role::Child>;
+}
+
+pub extern "C" fn tick_forever(params: TickerParams<role::Local>) {
+    loop {
+        params.ticker.tick();
+    }
+}
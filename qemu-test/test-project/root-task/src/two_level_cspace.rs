@@ -0,0 +1,81 @@
+use super::TopLevelError;
+
+use typenum::*;
+
+use ferros::alloc::{smart_alloc, ut_buddy};
+use ferros::cap::*;
+use ferros::userland::{CapRights, RetypeForSetup, Thread};
+use ferros::vspace::*;
+
+#[ferros_test::ferros_test]
+pub fn two_level_cspace(
+    local_slots: LocalCNodeSlots<U32>,
+    local_ut: LocalCap<Untyped<U14>>,
+    stack_mapped_region: MappedMemoryRegion<U17, shared_status::Exclusive>,
+    ipc_buffer_region: MappedMemoryRegion<U12, shared_status::Exclusive>,
+    root_cnode: &LocalCap<LocalCNode>,
+    tpa: &LocalCap<ThreadPriorityAuthority>,
+    vspace_paging_root: &LocalCap<ferros::arch::PagingRoot>,
+) -> Result<(), TopLevelError> {
+    let uts = ut_buddy(local_ut);
+
+    smart_alloc!(|slots: local_slots, ut: uts| {
+        let notification: LocalCap<Notification> = retype(ut, slots)?;
+        let top = retype_two_level_cnode::<U2, U4>(ut, slots)?;
+        let (first_leaf, top) = top.add_leaf(ut, slots).map_err(|(e, _)| e)?;
+        let (second_leaf, top) = top.add_leaf(ut, slots).map_err(|(e, _)| e)?;
+
+        let (first_slot, _first_leaf) = first_leaf.alloc();
+        let (second_slot, _second_leaf) = second_leaf.alloc();
+        let first = notification.mint(root_cnode, first_slot, CapRights::RW, Badge::from(1))?;
+        let second = notification.mint(root_cnode, second_slot, CapRights::RW, Badge::from(2))?;
+        let first_cptr = first.cptr;
+        let second_cptr = second.cptr;
+
+        let child = Thread::new(
+            vspace_paging_root,
+            top.into_cspace_root(),
+            stack_mapped_region,
+            signal_both,
+            LeafParams { first, second },
+            ipc_buffer_region,
+            ut,
+            slots,
+            tpa,
+            None, // fault
+        )?;
+    });
+
+    // The same slot of each leaf is a different slot of the CSpace,
+    // so the second copy only lands if it reached the second leaf.
+    if first_cptr == second_cptr {
+        return Err(TopLevelError::TestAssertionFailure(
+            "Slots in different leaves should have different cptrs",
+        ));
+    }
+
+    child.start()?;
+
+    // Each badge only arrives if the child found its copy of the
+    // notification in that leaf.
+    let mut badges = 0;
+    while badges != 3 {
+        badges |= usize::from(notification.wait());
+    }
+
+    Ok(())
+}
+
+pub struct LeafParams<Role: CNodeRole> {
+    pub first: Cap<Notification, Role>,
+    pub second: Cap<Notification, Role>,
+}
+
+impl RetypeForSetup for LeafParams<role::Local> {
+    type Output = LeafParams<role::Child>;
+}
+
+pub extern "C" fn signal_both(params: LeafParams<role::Local>) {
+    params.first.signal();
+    params.second.signal();
+}
@@ -0,0 +1,33 @@
+use super::TopLevelError;
+
+use typenum::*;
+
+use ferros::alloc::ut_buddy::weak_ut_buddy;
+use ferros::cap::*;
+
+#[ferros_test::ferros_test]
+pub fn wutbuddy_coalescing(
+    local_slots: LocalCNodeSlots<U2>,
+    local_ut: LocalCap<Untyped<U13>>,
+    root_cnode: &LocalCap<LocalCNode>,
+) -> Result<(), TopLevelError> {
+    let mut wut = weak_ut_buddy(local_ut.weaken());
+    // Just enough slots for a single split, so that splitting again
+    // relies on the buddy keeping them when the halves are merged.
+    let mut weak_slots = local_slots.weaken();
+
+    for _ in 0..3 {
+        let first = wut.alloc(&mut weak_slots, 12)?;
+        let second = wut.alloc(&mut weak_slots, 12)?;
+        wut.free(first, root_cnode).map_err(|(e, _)| e)?;
+        wut.free(second, root_cnode).map_err(|(e, _)| e)?;
+    }
+
+    // The whole untyped is back in one piece.
+    let whole = wut.alloc(&mut weak_slots, 13)?;
+    assert_eq!(whole.size_bits(), 13);
+    assert_eq!(wut.untracked_splits(), 0);
+    wut.free(whole, root_cnode).map_err(|(e, _)| e)?;
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+use ferros::cap::*;
+extern crate selfe_runtime;
+
+use startup_process::ProcParams;
+
+#[no_mangle]
+pub extern "C" fn _start(params: ProcParams<role::Local>) -> ! {
+    // The startup block should carry what the root task put in it, and
+    // describe the stack we're running on
+    let local = 0usize;
+    let startup_ok = match ferros::userland::startup_info() {
+        Some(info) => {
+            let on_stack = &local as *const usize as usize;
+            info.image_name() == "startup-process"
+                && info.args().eq(["--check-startup"].iter().cloned())
+                && info.config("expected_value") == Some("42")
+                && info.page_size() == Some(4096)
+                && info
+                    .stack_bounds()
+                    .map_or(false, |(bottom, top)| on_stack >= bottom && on_stack < top)
+        }
+        None => false,
+    };
+
+    params
+        .outcome_sender
+        .blocking_send(&startup_ok)
+        .expect("Could not report the startup block check");
+
+    unsafe {
+        loop {
+            selfe_sys::seL4_Yield();
+        }
+    }
+}
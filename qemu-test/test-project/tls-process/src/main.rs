@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+#![feature(thread_local)]
+
+use ferros::cap::*;
+extern crate selfe_runtime;
+
+use tls_process::ProcParams;
+
+// One from .tdata and one from .tbss, to see that the TLS block was
+// initialized from the template
+#[thread_local]
+static mut TLS_DATA: usize = 0x7157;
+#[thread_local]
+static mut TLS_BSS: usize = 0;
+
+#[no_mangle]
+pub extern "C" fn _start(params: ProcParams<role::Local>) -> ! {
+    let tls_ok = unsafe {
+        let initialized = core::ptr::read_volatile(&TLS_DATA) == 0x7157
+            && core::ptr::read_volatile(&TLS_BSS) == 0;
+        core::ptr::write_volatile(&mut TLS_BSS, params.value);
+        initialized && core::ptr::read_volatile(&TLS_BSS) == params.value
+    };
+
+    params
+        .outcome_sender
+        .blocking_send(&tls_ok)
+        .expect("Could not report the TLS check");
+
+    unsafe {
+        loop {
+            selfe_sys::seL4_Yield();
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! A `GlobalAlloc` for processes to build their heap on, out of memory
+//! regions handed to them by their parent.
+//!
+//! The heap is a first-fit free list kept in address order, so that
+//! freed blocks can be merged back with their neighbours.
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static HEAP: Heap = Heap::empty();
+//!
+//! pub extern "C" fn _start(params: ProcParams<role::Local>) -> ! {
+//!     HEAP.add_region(params.heap_mem);
+//!     ...
+//! }
+//! ```
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+use core::ops::Sub;
+use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use typenum::*;
+
+use crate::arch::PageBits;
+use crate::cap::role;
+use crate::pow::{Pow, _Pow};
+use crate::userland::Caller;
+use crate::vspace::{shared_status, MappedMemoryRegion, WeakMappedMemoryRegion};
+
+/// What a heap asks its parent for when it runs out of memory.
+#[derive(Debug, Clone, Copy)]
+pub struct HeapGrowthRequest {
+    /// Enough to satisfy the allocation that didn't fit
+    pub min_bytes: usize,
+}
+
+/// The parent's answer to a `HeapGrowthRequest`: where it mapped fresh
+/// memory into the asking process's address space.
+#[derive(Debug, Clone, Copy)]
+pub struct HeapGrowth {
+    pub vaddr: usize,
+    pub size_bytes: usize,
+}
+
+impl HeapGrowth {
+    /// Describe `region`, which must be mapped into the asking
+    /// process's VSpace and no longer be used by anything else.
+    pub fn of<SizeBits: Unsigned>(
+        region: &MappedMemoryRegion<SizeBits, shared_status::Exclusive>,
+    ) -> Self
+    where
+        SizeBits: IsGreaterOrEqual<PageBits>,
+        SizeBits: Sub<PageBits>,
+        <SizeBits as Sub<PageBits>>::Output: Unsigned,
+        <SizeBits as Sub<PageBits>>::Output: _Pow,
+        Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+    {
+        HeapGrowth {
+            vaddr: region.vaddr(),
+            size_bytes: region.size_bytes(),
+        }
+    }
+}
+
+/// The channel through which a heap asks its parent for more memory. The
+/// parent answers `None` if it has none to spare.
+pub type HeapGrower<Role> = Caller<HeapGrowthRequest, Option<HeapGrowth>, Role>;
+
+struct FreeBlock {
+    size: usize,
+    next: Option<NonNull<FreeBlock>>,
+}
+
+/// The smallest block the heap hands out or keeps track of.
+const MIN_BLOCK_SIZE: usize = core::mem::size_of::<FreeBlock>();
+const BLOCK_ALIGN: usize = core::mem::align_of::<FreeBlock>();
+
+struct FreeList {
+    head: Option<NonNull<FreeBlock>>,
+}
+
+pub struct Heap {
+    locked: AtomicBool,
+    free: UnsafeCell<FreeList>,
+    grower: UnsafeCell<Option<HeapGrower<role::Local>>>,
+}
+
+// The free list and grower are only touched while holding `locked`.
+unsafe impl Sync for Heap {}
+
+impl Heap {
+    /// A heap with no memory, for use as a `#[global_allocator]` until
+    /// regions are added.
+    pub const fn empty() -> Self {
+        Heap {
+            locked: AtomicBool::new(false),
+            free: UnsafeCell::new(FreeList { head: None }),
+            grower: UnsafeCell::new(None),
+        }
+    }
+
+    /// Give the heap a region of memory, for good.
+    pub fn add_region<SizeBits: Unsigned>(
+        &self,
+        region: MappedMemoryRegion<SizeBits, shared_status::Exclusive>,
+    ) where
+        SizeBits: IsGreaterOrEqual<PageBits>,
+        SizeBits: Sub<PageBits>,
+        <SizeBits as Sub<PageBits>>::Output: Unsigned,
+        <SizeBits as Sub<PageBits>>::Output: _Pow,
+        Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+    {
+        self.with_lock(|free, _| unsafe { free.insert(region.vaddr(), region.size_bytes()) })
+    }
+
+    /// Give the heap a weak region of memory, for good.
+    pub fn add_weak_region(&self, region: WeakMappedMemoryRegion<shared_status::Exclusive>) {
+        self.with_lock(|free, _| unsafe { free.insert(region.vaddr(), region.size_bytes()) })
+    }
+
+    /// Ask the parent on the other end of `grower` for more memory
+    /// whenever an allocation doesn't fit.
+    pub fn set_grower(&self, grower: HeapGrower<role::Local>) {
+        self.with_lock(|_, g| *g = Some(grower))
+    }
+
+    fn with_lock<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut FreeList, &mut Option<HeapGrower<role::Local>>) -> T,
+    {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        let result = unsafe { f(&mut *self.free.get(), &mut *self.grower.get()) };
+        self.locked.store(false, Ordering::Release);
+        result
+    }
+}
+
+unsafe impl GlobalAlloc for Heap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let (size, align) = block_layout(layout);
+        self.with_lock(|free, grower| {
+            if let Some(ptr) = free.take(size, align) {
+                return ptr;
+            }
+            let growth = grower.as_ref().and_then(|g| {
+                g.blocking_call(&HeapGrowthRequest {
+                    min_bytes: size + align,
+                })
+                .ok()
+                .and_then(|rsp| rsp)
+            });
+            match growth {
+                Some(growth) => {
+                    free.insert(growth.vaddr, growth.size_bytes);
+                    free.take(size, align).unwrap_or(ptr::null_mut())
+                }
+                None => ptr::null_mut(),
+            }
+        })
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let (size, _) = block_layout(layout);
+        self.with_lock(|free, _| free.insert(ptr as usize, size))
+    }
+}
+
+/// The size and alignment of the block which holds an allocation,
+/// which must be able to hold a `FreeBlock` once it's freed.
+fn block_layout(layout: Layout) -> (usize, usize) {
+    let size = round_up(layout.size().max(MIN_BLOCK_SIZE), BLOCK_ALIGN);
+    (size, layout.align().max(BLOCK_ALIGN))
+}
+
+fn round_up(n: usize, align: usize) -> usize {
+    (n + align - 1) & !(align - 1)
+}
+
+impl FreeList {
+    /// Carve a block out of the first free block it fits in. Whatever's
+    /// left over on either side must be big enough to stay on the list.
+    unsafe fn take(&mut self, size: usize, align: usize) -> Option<*mut u8> {
+        let mut prev: Option<NonNull<FreeBlock>> = None;
+        let mut cursor = self.head;
+        while let Some(block) = cursor {
+            let block_start = block.as_ptr() as usize;
+            let block_end = block_start + block.as_ref().size;
+            let next = block.as_ref().next;
+
+            let mut start = round_up(block_start, align);
+            if start != block_start && start - block_start < MIN_BLOCK_SIZE {
+                start = round_up(block_start + MIN_BLOCK_SIZE, align);
+            }
+            let end = start + size;
+            if end <= block_end && (block_end - end == 0 || block_end - end >= MIN_BLOCK_SIZE) {
+                // Unlink the block, then put back what's left on each side
+                self.link(prev, next);
+                if end != block_end {
+                    let after = write_block(end, block_end - end, next);
+                    self.link(prev, Some(after));
+                }
+                if start != block_start {
+                    let after = match prev {
+                        Some(p) => p.as_ref().next,
+                        None => self.head,
+                    };
+                    let before = write_block(block_start, start - block_start, after);
+                    self.link(prev, Some(before));
+                }
+                return Some(start as *mut u8);
+            }
+
+            prev = cursor;
+            cursor = next;
+        }
+        None
+    }
+
+    /// Return a block to the list, merging it with any neighbours it
+    /// touches.
+    unsafe fn insert(&mut self, start: usize, size: usize) {
+        // Only whole, aligned blocks can go on the list
+        let aligned_start = round_up(start, BLOCK_ALIGN);
+        let end = start + size;
+        if end < aligned_start + MIN_BLOCK_SIZE {
+            return;
+        }
+        let start = aligned_start;
+        let size = (end - start) & !(BLOCK_ALIGN - 1);
+
+        let mut prev: Option<NonNull<FreeBlock>> = None;
+        let mut next = self.head;
+        while let Some(block) = next {
+            if block.as_ptr() as usize > start {
+                break;
+            }
+            prev = next;
+            next = block.as_ref().next;
+        }
+
+        let mut block = write_block(start, size, next);
+        if let Some(mut n) = next {
+            if start + size == n.as_ptr() as usize {
+                block.as_mut().size += n.as_ref().size;
+                block.as_mut().next = n.as_mut().next;
+            }
+        }
+        match prev {
+            Some(mut p) if p.as_ptr() as usize + p.as_ref().size == start => {
+                p.as_mut().size += block.as_ref().size;
+                p.as_mut().next = block.as_ref().next;
+            }
+            _ => self.link(prev, Some(block)),
+        }
+    }
+
+    unsafe fn link(&mut self, prev: Option<NonNull<FreeBlock>>, next: Option<NonNull<FreeBlock>>) {
+        match prev {
+            Some(mut p) => p.as_mut().next = next,
+            None => self.head = next,
+        }
+    }
+}
+
+unsafe fn write_block(
+    start: usize,
+    size: usize,
+    next: Option<NonNull<FreeBlock>>,
+) -> NonNull<FreeBlock> {
+    let block = start as *mut FreeBlock;
+    block.write(FreeBlock { size, next });
+    NonNull::new_unchecked(block)
+}
@@ -1,6 +1,7 @@
 //! A tiny first-chance allocator for the untyped capabilities sel4's BOOTINFO.
-//! This one doesn't split anything; it just hands out the smallest untyped item
-//! that's big enough for the request.
+//! This one doesn't split anything unless asked for memory at particular
+//! physical addresses; it just hands out the smallest untyped item that's big
+//! enough for the request.
 use core::fmt::{Debug, Error as FmtError, Formatter};
 use core::marker::PhantomData;
 
@@ -10,8 +11,8 @@ use crate::arch::MaxNaiveSplitCount;
 use crate::arch::MaxUntypedSize as MaxUntypedSizeBits;
 use crate::arch::MinUntypedSize as MinUntypedSizeBits;
 use crate::cap::{
-    memory_kind, role, Cap, LocalCNodeSlots, LocalCap, PhantomCap, Untyped, WCNodeSlotsData,
-    WUntyped, WUntypedSplitError,
+    memory_kind, role, Cap, LocalCNodeSlots, LocalCap, PhantomCap, Untyped, WCNodeSlots,
+    WCNodeSlotsData, WUntyped, WUntypedSplitError,
 };
 use crate::pow::Pow;
 use arrayvec::ArrayVec;
@@ -37,6 +38,7 @@ pub fn bootstrap_allocators(
     bootinfo: &'static seL4_BootInfo,
 ) -> Result<(Allocator, DeviceAllocator), Error> {
     let mut general_uts = ArrayVec::new();
+    let mut general_paddrs = ArrayVec::new();
     let mut device_uts: ArrayVec<[LocalCap<WUntyped<memory_kind::Device>>; MAX_DEVICE_UTS]> =
         ArrayVec::new();
 
@@ -64,7 +66,7 @@ pub fn bootstrap_allocators(
                 },
                 _role: PhantomData,
             }) {
-                Ok(()) => (),
+                Ok(()) => general_paddrs.push(ut.paddr),
                 Err(_) => return Err(Error::TooManyGeneralUntypeds),
             }
         }
@@ -73,7 +75,10 @@ pub fn bootstrap_allocators(
     // initial insertion
     pdqsort::sort_by_key(&mut device_uts, |wut| wut.cap_data.kind.paddr);
     Ok((
-        Allocator { items: general_uts },
+        Allocator {
+            items: general_uts,
+            paddrs: general_paddrs,
+        },
         DeviceAllocator {
             untypeds: device_uts,
         },
@@ -83,6 +88,8 @@ pub fn bootstrap_allocators(
 /// An allocator for general purpose memory.
 pub struct Allocator {
     pub(super) items: ArrayVec<[LocalCap<WUntyped<memory_kind::General>>; MAX_INIT_UNTYPED_ITEMS]>,
+    /// The physical address of each of `items`, in the same order.
+    paddrs: ArrayVec<[usize; MAX_INIT_UNTYPED_ITEMS]>,
 }
 
 impl Debug for Allocator {
@@ -101,6 +108,43 @@ impl Allocator {
         Ok(alloc)
     }
 
+    /// The size in bits and physical address of each untyped still in
+    /// the list, in no particular order.
+    pub fn untypeds(&self) -> impl Iterator<Item = (u8, usize)> + '_ {
+        self.items
+            .iter()
+            .zip(self.paddrs.iter())
+            .map(|(ut, &paddr)| (ut.size_bits(), paddr))
+    }
+
+    /// A copy of this allocator, holding aliases of every untyped still
+    /// in the list. Whatever is taken from the copy has to be revoked
+    /// before the originals are used again.
+    #[cfg(feature = "test_support")]
+    pub(crate) unsafe fn dangerous_internal_alias(&self) -> Self {
+        Allocator {
+            items: self
+                .items
+                .iter()
+                .map(|ut| Cap {
+                    cptr: ut.cptr,
+                    cap_data: WUntyped {
+                        size_bits: ut.size_bits(),
+                        kind: ut.cap_data.kind,
+                    },
+                    _role: PhantomData,
+                })
+                .collect(),
+            paddrs: self.paddrs.clone(),
+        }
+    }
+
+    /// The cptr of each untyped still in the list.
+    #[cfg(feature = "test_support")]
+    pub(crate) fn cptrs(&self) -> impl Iterator<Item = usize> + '_ {
+        self.items.iter().map(|ut| ut.cptr)
+    }
+
     /// Find an untyped of the given size. If one is found, remove
     /// from the list and return it.
     pub fn get_untyped<BitSize: Unsigned>(
@@ -117,8 +161,183 @@ impl Allocator {
             _role: PhantomData,
         };
         self.items.remove(position);
+        self.paddrs.remove(position);
         Some(ut)
     }
+
+    /// Find an untyped of the given size which satisfies `constraint`,
+    /// remove it from the list, and return it along with its physical
+    /// address, as for a device which can only reach some of memory.
+    ///
+    /// If no untyped of the given size will do, the smallest larger
+    /// one with room for it is split apart just enough to get it out,
+    /// consuming CNode slots as it does so. The rest of it goes back
+    /// in the list.
+    pub fn get_untyped_by_paddr_constraint<BitSize: Unsigned>(
+        &mut self,
+        constraint: PhysicalAddressConstraint,
+        slots: &mut WCNodeSlots,
+    ) -> Result<(LocalCap<Untyped<BitSize, memory_kind::General>>, usize), PaddrConstraintAllocError>
+    {
+        let size_bits = BitSize::U8;
+        let (position, target) = self
+            .items
+            .iter()
+            .zip(self.paddrs.iter())
+            .enumerate()
+            .filter(|(_, (ut, _))| ut.size_bits() >= size_bits)
+            .filter_map(|(position, (ut, &paddr))| {
+                constraint
+                    .place(paddr, ut.size_bits(), size_bits)
+                    .map(|target| (position, ut.size_bits(), target))
+            })
+            .min_by_key(|&(_, ut_size_bits, _)| ut_size_bits)
+            .map(|(position, _, target)| (position, target))
+            .ok_or(PaddrConstraintAllocError::NoUntypedSatisfiesConstraint)?;
+
+        let num_splits = usize::from(self.items[position].size_bits() - size_bits);
+        if num_splits > 0 && size_bits < crate::arch::PageBits::U8 {
+            return Err(PaddrConstraintAllocError::SplitError(
+                WUntypedSplitError::TooSmallToBeSplit,
+            ));
+        }
+        if 2 * num_splits > slots.size() {
+            return Err(PaddrConstraintAllocError::NotEnoughCNodeSlots);
+        }
+        if self.items.len() - 1 + num_splits > MAX_INIT_UNTYPED_ITEMS {
+            return Err(PaddrConstraintAllocError::TooManyGeneralUntypeds);
+        }
+
+        let mut ut = self.items.remove(position);
+        let mut paddr = self.paddrs.remove(position);
+        while ut.size_bits() > size_bits {
+            let slot_pair = match slots.alloc_strong::<U2>() {
+                Ok(slot_pair) => slot_pair,
+                Err(_) => {
+                    self.items.push(ut);
+                    self.paddrs.push(paddr);
+                    return Err(PaddrConstraintAllocError::NotEnoughCNodeSlots);
+                }
+            };
+            let (ut_cptr, ut_size_bits) = (ut.cptr, ut.size_bits());
+            let half_bytes = ut.size_bytes() / 2;
+            let (ut_left, ut_right) = match ut.split(slot_pair) {
+                Ok(halves) => halves,
+                Err(e) => {
+                    // A general untyped fails to split before anything
+                    // is made from it, so it's still whole and the two
+                    // slots just taken off the front are still empty.
+                    slots.cap_data.offset -= 2;
+                    slots.cap_data.size += 2;
+                    self.items.push(Cap {
+                        cptr: ut_cptr,
+                        cap_data: WUntyped {
+                            size_bits: ut_size_bits,
+                            kind: memory_kind::General,
+                        },
+                        _role: PhantomData,
+                    });
+                    self.paddrs.push(paddr);
+                    return Err(PaddrConstraintAllocError::SplitError(e));
+                }
+            };
+            // Keep the half the target sits in, and put back the other.
+            let (kept, kept_paddr, spare, spare_paddr) = if target < paddr + half_bytes {
+                (ut_left, paddr, ut_right, paddr + half_bytes)
+            } else {
+                (ut_right, paddr + half_bytes, ut_left, paddr)
+            };
+            self.items.push(spare);
+            self.paddrs.push(spare_paddr);
+            ut = kept;
+            paddr = kept_paddr;
+        }
+
+        debug_assert_eq!(
+            paddr, target,
+            "Splitting toward the target address should always end up there"
+        );
+        let ut = Cap {
+            cptr: ut.cptr,
+            cap_data: PhantomCap::phantom_instance(),
+            _role: PhantomData,
+        };
+        Ok((ut, paddr))
+    }
+}
+
+/// Where in physical memory an untyped may sit: entirely between
+/// two addresses, and starting at a multiple of some power of two.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalAddressConstraint {
+    start: usize,
+    /// The last address the untyped may cover, so that the whole of
+    /// the address space can be described.
+    last: usize,
+    align_bits: u8,
+}
+
+impl PhysicalAddressConstraint {
+    /// Anywhere at all.
+    pub fn any() -> Self {
+        PhysicalAddressConstraint {
+            start: 0,
+            last: usize::max_value(),
+            align_bits: 0,
+        }
+    }
+
+    /// Entirely below `limit`, e.g. below 4 GiB for a device that can
+    /// only address 32 bits.
+    pub fn below(limit: usize) -> Self {
+        PhysicalAddressConstraint {
+            last: limit.saturating_sub(1),
+            ..Self::any()
+        }
+    }
+
+    /// Entirely within `range`.
+    pub fn within(range: PageAlignedAddressRange) -> Self {
+        PhysicalAddressConstraint {
+            start: range.start(),
+            last: range.start() + (range.size_bytes() - 1),
+            ..Self::any()
+        }
+    }
+
+    /// Also starting at a multiple of `1 << align_bits` bytes. An
+    /// untyped is always aligned to its own size anyway.
+    pub fn aligned_to(self, align_bits: u8) -> Self {
+        PhysicalAddressConstraint { align_bits, ..self }
+    }
+
+    /// Where in an untyped of `1 << ut_size_bits` bytes at `ut_paddr`
+    /// the lowest `1 << size_bits` bytes block satisfying this
+    /// constraint starts, if there is one.
+    fn place(&self, ut_paddr: usize, ut_size_bits: u8, size_bits: u8) -> Option<usize> {
+        let align_bits = u32::from(core::cmp::max(self.align_bits, size_bits));
+        let align = 1usize.checked_shl(align_bits)?;
+        let lowest = core::cmp::max(ut_paddr, self.start);
+        let start = lowest.checked_add(align - 1)? & !(align - 1);
+        let last = start.checked_add((1usize << size_bits) - 1)?;
+        let ut_last = ut_paddr + ((1usize << ut_size_bits) - 1);
+        if last <= self.last && last <= ut_last {
+            Some(start)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PaddrConstraintAllocError {
+    /// No untyped has a block of the requested size anywhere the
+    /// constraint allows.
+    NoUntypedSatisfiesConstraint,
+    /// Not enough internal storage space for the pieces split off.
+    TooManyGeneralUntypeds,
+    NotEnoughCNodeSlots,
+    SplitError(WUntypedSplitError),
 }
 
 // TODO(dan@auxon.io): I have no idea what to put here.
@@ -201,6 +420,14 @@ impl PageAlignedAddressRange {
             .map_err(|_| PageAlignedAddressRangeError::SizeNotPageAligned)?;
         Ok(PageAlignedAddressRange { start, size_bytes })
     }
+
+    pub fn start(&self) -> usize {
+        self.start.0
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        self.size_bytes.0
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -334,6 +561,54 @@ impl DeviceAllocator {
         }
         Ok(ut)
     }
+
+    /// A copy of this allocator, holding aliases of every untyped still
+    /// in the list. Whatever is taken from the copy has to be revoked
+    /// before the originals are used again.
+    #[cfg(feature = "test_support")]
+    pub(crate) unsafe fn dangerous_internal_alias(&self) -> Self {
+        DeviceAllocator {
+            untypeds: self
+                .untypeds
+                .iter()
+                .map(|ut| Cap {
+                    cptr: ut.cptr,
+                    cap_data: WUntyped {
+                        size_bits: ut.size_bits(),
+                        kind: ut.cap_data.kind,
+                    },
+                    _role: PhantomData,
+                })
+                .collect(),
+        }
+    }
+
+    /// The cptr of each untyped still in the list.
+    #[cfg(feature = "test_support")]
+    pub(crate) fn cptrs(&self) -> impl Iterator<Item = usize> + '_ {
+        self.untypeds.iter().map(|ut| ut.cptr)
+    }
+
+    /// Take back a device untyped handed out by
+    /// `get_untyped_by_address_range`, once everything retyped from it
+    /// has been revoked. Fails, handing it back, if there's no room
+    /// left to keep track of it.
+    pub fn give_back(
+        &mut self,
+        ut: LocalCap<WUntyped<memory_kind::Device>>,
+    ) -> Result<(), LocalCap<WUntyped<memory_kind::Device>>> {
+        self.insert_sorted(ut).map_err(|e| e.element())
+    }
+
+    /// The physical address just past the end of the device untyped
+    /// containing `paddr`, if this allocator has it.
+    pub(crate) fn end_of_untyped_containing(&self, paddr: usize) -> Option<usize> {
+        self.untypeds
+            .iter()
+            .find(|ut| untyped_contains_paddr(ut, paddr))
+            .map(|ut| ut.paddr() + ut.size_bytes())
+    }
+
     /// Get the device untyped which contains the given physical
     /// address. If it's present in the list, remove it from the list
     /// and return it.
@@ -1,3 +1,4 @@
+pub mod heap;
 pub mod micro_alloc;
 pub mod ut_buddy;
 
@@ -1,4 +1,5 @@
 /// UTBuddy is a type-safe static buddy allocator for Untyped capabilites.
+use core::cmp;
 use core::marker::PhantomData;
 use core::mem;
 use core::ops::{Add, Mul, Sub};
@@ -18,6 +19,41 @@ use crate::error::{ErrorExt, SeL4Error};
 
 type UTPoolSlotsPerSize = U4;
 
+/// How many splits a `WUTBuddy` keeps track of, so as to merge their
+/// halves back together once both are freed. Splits made beyond that
+/// are never undone; `WUTBuddy::untracked_splits` counts them.
+pub type MaxTrackedUTSplits = U32;
+
+/// An untyped split in two by a `WUTBuddy`, whose halves sit in
+/// consecutive slots starting at `halves`.
+#[derive(Clone, Copy)]
+struct UTSplit {
+    parent: usize,
+    halves: usize,
+}
+
+/// The splits a `WUTBuddy` has made which are still standing.
+struct UTSplits {
+    tracked: ArrayVec<[UTSplit; MaxTrackedUTSplits::USIZE]>,
+    /// Splits made while `tracked` was full
+    untracked: usize,
+}
+
+impl UTSplits {
+    fn new() -> Self {
+        UTSplits {
+            tracked: ArrayVec::new(),
+            untracked: 0,
+        }
+    }
+
+    fn record(&mut self, split: UTSplit) {
+        if self.tracked.try_push(split).is_err() {
+            self.untracked += 1;
+        }
+    }
+}
+
 /// A type-level linked list of typenum::Unsigned.
 pub trait UList {
     type Length: Unsigned;
@@ -162,7 +198,13 @@ impl<PoolSizes: UList> UTBuddy<PoolSizes> {
         PoolSizes: _TakeUntyped<Diff<BitSize, MinUntypedSize>, NumSplits = NumSplits>,
         TakeUntyped_ResultPoolSizes<PoolSizes, Diff<BitSize, MinUntypedSize>>: UList,
     {
-        let weak_ut = alloc(&mut self.pool, slots.iter(), BitSize::U8, NumSplits::U8)?;
+        let weak_ut = alloc(
+            &mut self.pool,
+            None,
+            slots.iter(),
+            BitSize::U8,
+            NumSplits::U8,
+        )?;
         Ok((
             Cap::wrap_cptr(weak_ut.cptr),
             UTBuddy {
@@ -181,6 +223,8 @@ pub fn weak_ut_buddy<Role: CNodeRole>(
     pool[usize::from(ut.cap_data.size_bits) - MinUntypedSize::USIZE].push(ut.cptr);
     WUTBuddy {
         pool,
+        splits: UTSplits::new(),
+        spare_halves: ArrayVec::new(),
         _role: PhantomData,
     }
 }
@@ -198,8 +242,14 @@ pub enum UTBuddyError {
     /// The wrapped untyped lacks the sufficient size to do this
     /// allocation request.
     CannotAllocateRequestedSize(u8),
+    /// There is no room in the pool to take back an untyped of this
+    /// size.
+    PoolFull(u8),
+    /// The untyped at this cptr is already in the pool, or was merged
+    /// back into its parent, so it can't have been handed out.
+    DoubleFree(usize),
     /// We got an error from an seL4 syscall, namely the
-    /// `seL4_Untyped_Retype` call.
+    /// `seL4_Untyped_Retype` or `seL4_CNode_Revoke` call.
     SeL4Error(SeL4Error),
 }
 
@@ -215,6 +265,10 @@ impl From<SeL4Error> for UTBuddyError {
 /// Presently restricted to provide memory_kind::General untyped
 pub struct WUTBuddy<Role: CNodeRole = role::Local> {
     pool: [ArrayVec<[usize; UTPoolSlotsPerSize::USIZE]>; MaxUntypedSize::USIZE],
+    splits: UTSplits,
+    /// The first of each pair of slots emptied by merging halves back
+    /// together, to split into again before taking any fresh ones.
+    spare_halves: ArrayVec<[usize; MaxTrackedUTSplits::USIZE]>,
     _role: PhantomData<Role>,
 }
 
@@ -264,7 +318,10 @@ impl WUTBuddy<role::Local> {
             return Err(UTBuddyError::CannotAllocateRequestedSize(size));
         }
 
-        let slot_count = usize::from(split_count) * 2;
+        // Pairs of slots emptied by earlier merges are split into
+        // first, and only the rest come from `slots`.
+        let reused_count = cmp::min(usize::from(split_count), self.spare_halves.len());
+        let slot_count = (usize::from(split_count) - reused_count) * 2;
         // We also need to confirm that we have enough slots.
         if slot_count > slots.cap_data.size {
             return Err(UTBuddyError::NotEnoughSlots);
@@ -285,15 +342,145 @@ impl WUTBuddy<role::Local> {
         slots.cap_data.offset += slot_count;
         slots.cap_data.size -= slot_count;
 
+        let cptr = slots.cptr;
+        let reused_halves: ArrayVec<[usize; MaxTrackedUTSplits::USIZE]> = (0..reused_count)
+            .filter_map(|_| self.spare_halves.pop())
+            .collect();
+        let slots_iter = reused_halves
+            .into_iter()
+            .flat_map(move |halves| {
+                (halves..halves + 2)
+                    .map(move |offset| -> LocalCNodeSlot { Cap::internal_new(cptr, offset) })
+            })
+            .chain(slots_for_alloc_to_consume.into_strong_iter());
+
         let ut = alloc(
             &mut self.pool,
-            slots_for_alloc_to_consume.into_strong_iter(),
+            Some(&mut self.splits),
+            slots_iter,
             size,
             split_count,
         )?;
         Ok(ut)
     }
 
+    /// How many of the splits made to hand out untypeds came when
+    /// `MaxTrackedUTSplits` splits were already being tracked. Their
+    /// halves are never merged back together, so the untyped each was
+    /// split from is only ever handed out in pieces.
+    pub fn untracked_splits(&self) -> usize {
+        self.splits.untracked
+    }
+
+    /// Whether there's room in the pool to take back an untyped of
+    /// `size_bits`.
+    pub(crate) fn can_take_back(&self, size_bits: u8) -> bool {
+        size_bits >= MinUntypedSize::U8
+            && size_bits < MinUntypedSize::U8 + MaxUntypedSize::U8
+            && !self.pool[usize::from(size_bits - MinUntypedSize::U8)].is_full()
+    }
+
+    /// Put an untyped handed out by `alloc` back in the pool, once
+    /// everything retyped from it has been revoked.
+    ///
+    /// Whenever its buddy, the other half of the untyped it was split
+    /// from, is in the pool too, the two are merged back into that
+    /// untyped by revoking it, and so on up. The slots the halves
+    /// took are kept to be split into again, so `cnode` need only be
+    /// the CNode the untypeds are in. Only the last
+    /// `MaxTrackedUTSplits` splits still standing can be undone, and
+    /// an untyped isn't merged into one whose pool is full, nor when
+    /// there's no room left to keep the halves' slots.
+    ///
+    /// Fails if the untyped is already in the pool or was merged away,
+    /// if there's no room in the pool for it, or if revoking fails,
+    /// handing back the untyped as far as it got merged.
+    pub fn free(
+        &mut self,
+        ut: LocalCap<WUntyped<memory_kind::General>>,
+        cnode: &LocalCap<LocalCNode>,
+    ) -> Result<(), (UTBuddyError, LocalCap<WUntyped<memory_kind::General>>)> {
+        self.free_in(ut, cnode.cptr)
+    }
+
+    /// As `free`, for when all there is to hand is the cptr of the
+    /// CNode the untypeds are in.
+    pub(crate) fn free_in(
+        &mut self,
+        ut: LocalCap<WUntyped<memory_kind::General>>,
+        cnode_cptr: usize,
+    ) -> Result<(), (UTBuddyError, LocalCap<WUntyped<memory_kind::General>>)> {
+        if self.holds(ut.cptr) {
+            return Err((UTBuddyError::DoubleFree(ut.cptr), ut));
+        }
+        let mut ut = ut;
+        while let Some((split_index, buddy_index)) = self.find_free_buddy(&ut) {
+            let size_bits = ut.cap_data.size_bits;
+            if !self.can_take_back(size_bits + 1) || self.spare_halves.is_full() {
+                break;
+            }
+            let split = self.splits.tracked[split_index];
+            // Revoking the parent deletes both halves, and leaves it
+            // with nothing retyped from it.
+            let revoked =
+                unsafe { seL4_CNode_Revoke(cnode_cptr, split.parent, seL4_WordBits as u8) };
+            if let Err(e) = revoked.as_result() {
+                return Err((SeL4Error::CNodeRevoke(e).into(), ut));
+            }
+            self.pool[usize::from(size_bits - MinUntypedSize::U8)].swap_remove(buddy_index);
+            self.splits.tracked.swap_remove(split_index);
+            self.spare_halves.push(split.halves);
+            ut = Cap {
+                cptr: split.parent,
+                cap_data: WUntyped {
+                    size_bits: size_bits + 1,
+                    kind: memory_kind::General,
+                },
+                _role: PhantomData,
+            };
+        }
+
+        let size_bits = ut.cap_data.size_bits;
+        if !self.can_take_back(size_bits) {
+            return Err((UTBuddyError::PoolFull(size_bits), ut));
+        }
+        self.pool[usize::from(size_bits - MinUntypedSize::U8)].push(ut.cptr);
+        Ok(())
+    }
+
+    /// Whether `cptr` is an untyped in the pool, or one of the halves
+    /// of a split since undone.
+    fn holds(&self, cptr: usize) -> bool {
+        self.pool.iter().any(|sub_pool| sub_pool.contains(&cptr))
+            || self
+                .spare_halves
+                .iter()
+                .any(|&halves| cptr == halves || cptr == halves + 1)
+    }
+
+    /// Where to find the split `ut` came out of and its buddy in the
+    /// pool, if the buddy is there.
+    fn find_free_buddy(
+        &self,
+        ut: &LocalCap<WUntyped<memory_kind::General>>,
+    ) -> Option<(usize, usize)> {
+        let split_index = self
+            .splits
+            .tracked
+            .iter()
+            .position(|split| ut.cptr == split.halves || ut.cptr == split.halves + 1)?;
+        let halves = self.splits.tracked[split_index].halves;
+        let buddy = if ut.cptr == halves {
+            halves + 1
+        } else {
+            halves
+        };
+        let buddy_index = self.pool[usize::from(ut.cap_data.size_bits - MinUntypedSize::U8)]
+            .iter()
+            .position(|&cptr| cptr == buddy)?;
+        Some((split_index, buddy_index))
+    }
+
     fn total_occupied_slots(&self) -> usize {
         self.pool.iter().map(|sub_pool| sub_pool.len()).sum()
     }
@@ -331,8 +518,12 @@ impl WUTBuddy<role::Local> {
                 child_bucket.push(child_wut.cptr);
             }
         }
+        // The splits were of untypeds left behind in the local CSpace,
+        // so there's no merging the child's halves back into them.
         Ok(WUTBuddy {
             pool: child_pool,
+            splits: UTSplits::new(),
+            spare_halves: ArrayVec::new(),
             _role: PhantomData,
         })
     }
@@ -344,13 +535,19 @@ impl<Role: CNodeRole> WUTBuddy<Role> {
     pub(crate) fn empty() -> WUTBuddy<Role> {
         WUTBuddy {
             pool: make_pool(),
+            splits: UTSplits::new(),
+            spare_halves: ArrayVec::new(),
             _role: PhantomData,
         }
     }
 }
 
+/// Take an untyped of `1 << size_bits` bytes out of `pool`, splitting
+/// `split_count` larger ones along the way. Each split is recorded in
+/// `splits`, if given.
 fn alloc(
     pool: &mut [ArrayVec<[usize; UTPoolSlotsPerSize::USIZE]>; MaxUntypedSize::USIZE],
+    mut splits: Option<&mut UTSplits>,
     slots_iter: impl Iterator<Item = LocalCNodeSlot>,
     size_bits: u8,
     split_count: u8,
@@ -386,6 +583,12 @@ fn alloc(
 
             pool[usize::from(i) - 1].push(slot_offset);
             pool[usize::from(i) - 1].push(slot_offset + 1);
+            if let Some(splits) = splits.as_mut() {
+                splits.record(UTSplit {
+                    parent: cptr,
+                    halves: slot_offset,
+                });
+            }
         }
     }
 
@@ -412,6 +615,8 @@ impl From<super::micro_alloc::Allocator> for WUTBuddy<role::Local> {
 
         WUTBuddy {
             pool,
+            splits: UTSplits::new(),
+            spare_halves: ArrayVec::new(),
             _role: PhantomData,
         }
     }
@@ -10,6 +10,23 @@ pub struct VMFault {
     pub is_instruction_fault: bool,
     pub fault_status_register: usize,
 }
+
+impl VMFault {
+    /// Whether nothing was mapped at `address`, as opposed to the
+    /// mapping there not permitting the access.
+    pub fn is_translation_fault(&self) -> bool {
+        // The low six bits of the syndrome hold the fault status code,
+        // with translation faults at each level as 0b0001LL.
+        self.fault_status_register & 0x3c == 0b000100
+    }
+
+    /// Whether the faulting access was a write.
+    pub fn is_write(&self) -> bool {
+        // The WnR bit of a data abort's syndrome
+        !self.is_instruction_fault && self.fault_status_register & (1 << 6) != 0
+    }
+}
+
 #[derive(Debug)]
 pub struct UnknownSyscall {
     pub sender: Badge,
@@ -66,6 +66,31 @@ pub type PagingRoot = cap::PageGlobalDirectory;
 /// The level directly underneath the PagingRoot
 pub type PagingRootLowerLevel = cap::PageUpperDirectory;
 
+/// How many bits of address space one object at each intermediate layer
+/// of `AddressSpace` maps, from the layer just below the root down.
+pub(crate) const PAGING_LAYER_SPAN_BITS: [usize; 3] = [
+    HugePageBits::USIZE + PageUpperDirIndexBits::USIZE,
+    HugePageBits::USIZE,
+    LargePageBits::USIZE,
+];
+
+/// The size in bits and object type of each size of frame the paging
+/// structures can map, largest first and ending with `Page`.
+pub(crate) const FRAME_SIZES: [(u8, usize); 3] = [
+    (
+        HugePageBits::U8,
+        selfe_sys::_mode_object_seL4_ARM_HugePageObject as usize,
+    ),
+    (
+        LargePageBits::U8,
+        selfe_sys::_object_seL4_ARM_LargePageObject as usize,
+    ),
+    (
+        PageBits::U8,
+        selfe_sys::_object_seL4_ARM_SmallPageObject as usize,
+    ),
+];
+
 impl AddressSpace {
     pub fn new() -> Self {
         PagingRec {
@@ -147,12 +172,64 @@ pub mod vm_attributes {
     pub const PROGRAM_CODE: VMAttributes = DEFAULT;
 
     pub const PROGRAM_DATA: VMAttributes = PAGE_CACHEABLE | PARITY_ENABLED | EXECUTE_NEVER;
+
+    /// For memory a device reads or writes by DMA.
+    pub const UNCACHED: VMAttributes = DEFAULT & !PAGE_CACHEABLE;
+
+    /// seL4 has no write-combining attribute on ARM, so this is the
+    /// same as `UNCACHED`.
+    pub const WRITE_COMBINED: VMAttributes = UNCACHED;
+}
+
+/// Order every memory access before this against every one after, as
+/// observed by devices as well as other cores; `dmb ish` only covers the
+/// inner shareable domain, which devices sit outside of.
+pub(crate) fn dma_barrier() {
+    unsafe { asm!("dsb sy") };
 }
 
-pub(crate) unsafe fn flush_page(cptr: usize) -> Result<(), SeL4Error> {
-    selfe_sys::seL4_ARM_Page_CleanInvalidate_Data(cptr, 0x0000, PageBytes::USIZE)
+pub(crate) unsafe fn flush_frame(cptr: usize, size_bytes: usize) -> Result<(), SeL4Error> {
+    selfe_sys::seL4_ARM_Page_CleanInvalidate_Data(cptr, 0x0000, size_bytes)
         .as_result()
         .map_err(SeL4Error::PageCleanInvalidateData)?;
 
     Ok(())
 }
+
+/// Write back the data cache lines covering `start..end`, offsets into
+/// the frame, to memory.
+pub(crate) unsafe fn clean_frame(cptr: usize, start: usize, end: usize) -> Result<(), SeL4Error> {
+    selfe_sys::seL4_ARM_Page_Clean_Data(cptr, start, end)
+        .as_result()
+        .map_err(SeL4Error::PageCleanData)?;
+
+    Ok(())
+}
+
+/// Discard the data cache lines covering `start..end`, offsets into
+/// the frame, without writing them back.
+pub(crate) unsafe fn invalidate_frame(
+    cptr: usize,
+    start: usize,
+    end: usize,
+) -> Result<(), SeL4Error> {
+    selfe_sys::seL4_ARM_Page_Invalidate_Data(cptr, start, end)
+        .as_result()
+        .map_err(SeL4Error::PageInvalidateData)?;
+
+    Ok(())
+}
+
+/// Make the instruction cache coherent with the data cache over
+/// `start..end`, offsets into the frame.
+pub(crate) unsafe fn unify_instruction_frame(
+    cptr: usize,
+    start: usize,
+    end: usize,
+) -> Result<(), SeL4Error> {
+    selfe_sys::seL4_ARM_Page_Unify_Instruction(cptr, start, end)
+        .as_result()
+        .map_err(SeL4Error::PageUnifyInstruction)?;
+
+    Ok(())
+}
@@ -90,6 +90,14 @@ pub(crate) fn set_thread_link_register(
     registers.x30 = (post_return_fn as *const fn() -> !) as usize;
 }
 
+pub(crate) fn set_thread_stack_pointer(registers: &mut selfe_sys::seL4_UserContext, sp: usize) {
+    registers.sp = sp;
+}
+
+pub(crate) fn set_thread_program_counter(registers: &mut selfe_sys::seL4_UserContext, pc: usize) {
+    registers.pc = pc;
+}
+
 #[doc(hidden)]
 #[allow(dead_code)]
 #[cfg(feature = "test_support")]
@@ -1,6 +1,6 @@
 use selfe_sys::*;
 
-use crate::cap::{page_state, DirectRetype, LocalCap, Page, PageState, PhantomCap};
+use crate::cap::{page_state, DirectRetype, Frame, LocalCap, Page, PageState, PhantomCap};
 use crate::error::{ErrorExt, SeL4Error};
 use crate::typenum::Unsigned;
 use crate::userland::CapRights;
@@ -36,19 +36,46 @@ impl LocalCap<Page<page_state::Unmapped>> {
 }
 
 impl LocalCap<Page<page_state::Mapped>> {
+    /// Change the rights and attributes this page is mapped with, in
+    /// place.
+    pub(crate) fn remap(
+        &mut self,
+        root: &mut LocalCap<crate::arch::PagingRoot>,
+        rights: CapRights,
+        vm_attributes: seL4_ARM_VMAttributes,
+    ) -> Result<(), SeL4Error> {
+        unsafe {
+            seL4_ARM_Page_Map(
+                self.cptr,
+                root.cptr,
+                self.vaddr(),
+                seL4_CapRights_t::from(rights),
+                vm_attributes,
+            )
+        }
+        .as_result()
+        .map_err(SeL4Error::PageRemap)?;
+        self.cap_data.state.rights = rights;
+        self.cap_data.state.vm_attributes = vm_attributes;
+        Ok(())
+    }
+
     /// Keeping this non-public in order to restrict mapping operations to
     /// owners of a VSpace-related object
     pub(crate) fn unmap(self) -> Result<LocalCap<Page<page_state::Unmapped>>, SeL4Error> {
+        self.unmap_frame(super::super::PageBytes::USIZE)
+    }
+
+    /// Unmap a frame of `size_bytes`, cleaning all of it from the data
+    /// cache first if it was writable.
+    pub(crate) fn unmap_frame(
+        self,
+        size_bytes: usize,
+    ) -> Result<LocalCap<Page<page_state::Unmapped>>, SeL4Error> {
         if self.rights().is_writable() {
-            unsafe {
-                seL4_ARM_Page_CleanInvalidate_Data(
-                    self.cptr,
-                    0x0000,
-                    super::super::PageBytes::USIZE,
-                )
-            }
-            .as_result()
-            .map_err(SeL4Error::PageCleanInvalidateData)?;
+            unsafe { seL4_ARM_Page_CleanInvalidate_Data(self.cptr, 0x0000, size_bytes) }
+                .as_result()
+                .map_err(SeL4Error::PageCleanInvalidateData)?;
         }
 
         match unsafe { seL4_ARM_Page_Unmap(self.cptr) }.as_result() {
@@ -78,3 +105,43 @@ impl PhantomCap for Page<page_state::Unmapped> {
         }
     }
 }
+
+/// The sizes of `Frame` beyond a `Page` that the paging structures can
+/// map in one go.
+pub mod frame_size {
+    use selfe_sys::*;
+
+    use crate::arch::{LargePageBits, SectionBits, SuperSectionBits};
+    use crate::cap::FrameSize;
+
+    /// 64 KiB, mapped by 16 consecutive `PageTable` entries.
+    pub struct LargePage;
+    impl FrameSize for LargePage {
+        type SizeBits = LargePageBits;
+        fn sel4_type_id() -> usize {
+            _object_seL4_ARM_LargePageObject as usize
+        }
+    }
+
+    /// Mapped by a `PageDirectory` entry.
+    pub struct Section;
+    impl FrameSize for Section {
+        type SizeBits = SectionBits;
+        fn sel4_type_id() -> usize {
+            _mode_object_seL4_ARM_SectionObject as usize
+        }
+    }
+
+    /// Mapped by 16 consecutive `PageDirectory` entries.
+    pub struct SuperSection;
+    impl FrameSize for SuperSection {
+        type SizeBits = SuperSectionBits;
+        fn sel4_type_id() -> usize {
+            _mode_object_seL4_ARM_SuperSectionObject as usize
+        }
+    }
+}
+
+pub type LargePage<State = page_state::Unmapped> = Frame<frame_size::LargePage, State>;
+pub type Section<State = page_state::Unmapped> = Frame<frame_size::Section, State>;
+pub type SuperSection<State = page_state::Unmapped> = Frame<frame_size::SuperSection, State>;
@@ -10,6 +10,35 @@ pub struct VMFault {
     pub is_instruction_fault: bool,
     pub fault_status_register: usize,
 }
+
+impl VMFault {
+    /// Whether nothing was mapped at `address`, as opposed to the
+    /// mapping there not permitting the access.
+    pub fn is_translation_fault(&self) -> bool {
+        // The fault status, FS, is split across bits 10 and 3:0 of the
+        // short-descriptor format FSR, and section and page translation
+        // faults are 0b00101 and 0b00111.
+        #[cfg(not(KernelArmHypervisorSupport))]
+        {
+            let fsr = self.fault_status_register;
+            let status = (fsr & 0xf) | ((fsr >> 6) & 0x10);
+            status == 0b00101 || status == 0b00111
+        }
+        // The long-descriptor format has translation faults at each
+        // level as 0b0001LL in the low six bits.
+        #[cfg(KernelArmHypervisorSupport)]
+        {
+            self.fault_status_register & 0x3c == 0b000100
+        }
+    }
+
+    /// Whether the faulting access was a write.
+    pub fn is_write(&self) -> bool {
+        // The WnR bit of the DFSR
+        !self.is_instruction_fault && self.fault_status_register & (1 << 11) != 0
+    }
+}
+
 #[derive(Debug)]
 pub struct UnknownSyscall {
     pub sender: Badge,
@@ -62,6 +62,11 @@ mod hyp_dependent_constants {
     /// The level directly underneath the PagingRoot
     pub type PagingRootLowerLevel = PageTable;
 
+    /// How many bits of address space one object at each intermediate
+    /// layer of `AddressSpace` maps, from the layer just below the root
+    /// down.
+    pub(crate) const PAGING_LAYER_SPAN_BITS: [usize; 1] = [SectionBits::USIZE];
+
     impl AddressSpace {
         pub fn new() -> Self {
             PagingRec {
@@ -84,6 +89,27 @@ pub type PageIndexBits = U12;
 pub type PageBytes = op!(U1 << U12);
 pub type LargePageBits = U16;
 
+/// The size in bits and object type of each size of frame the paging
+/// structures can map, largest first and ending with `Page`.
+pub(crate) const FRAME_SIZES: [(u8, usize); 4] = [
+    (
+        SuperSectionBits::U8,
+        selfe_sys::_mode_object_seL4_ARM_SuperSectionObject as usize,
+    ),
+    (
+        SectionBits::U8,
+        selfe_sys::_mode_object_seL4_ARM_SectionObject as usize,
+    ),
+    (
+        LargePageBits::U8,
+        selfe_sys::_object_seL4_ARM_LargePageObject as usize,
+    ),
+    (
+        PageBits::U8,
+        selfe_sys::_object_seL4_ARM_SmallPageObject as usize,
+    ),
+];
+
 pub type BasePageDirFreeSlots = op!((U1 << PageDirIndexBits) - (U1 << U9));
 pub type BasePageTableFreeSlots = op!(U1 << PageTableIndexBits);
 
@@ -137,12 +163,76 @@ pub mod vm_attributes {
     pub const PROGRAM_CODE: VMAttributes = DEFAULT;
 
     pub const PROGRAM_DATA: VMAttributes = PAGE_CACHEABLE | PARITY_ENABLED | EXECUTE_NEVER;
+
+    /// For memory a device reads or writes by DMA.
+    pub const UNCACHED: VMAttributes = DEFAULT & !PAGE_CACHEABLE;
+
+    /// seL4 has no write-combining attribute on ARM, so this is the
+    /// same as `UNCACHED`.
+    pub const WRITE_COMBINED: VMAttributes = UNCACHED;
+}
+
+/// Order every memory access before this against every one after, as
+/// observed by devices as well as other cores; `dmb ish` only covers the
+/// inner shareable domain, which devices sit outside of.
+pub(crate) fn dma_barrier() {
+    unsafe { asm!("dsb sy") };
 }
 
-pub(crate) unsafe fn flush_page(cptr: usize) -> Result<(), SeL4Error> {
-    selfe_sys::seL4_ARM_Page_CleanInvalidate_Data(cptr, 0x0000, PageBytes::USIZE)
+pub(crate) unsafe fn flush_frame(cptr: usize, size_bytes: usize) -> Result<(), SeL4Error> {
+    selfe_sys::seL4_ARM_Page_CleanInvalidate_Data(cptr, 0x0000, size_bytes)
         .as_result()
         .map_err(SeL4Error::PageCleanInvalidateData)?;
 
     Ok(())
 }
+
+/// Write back the data cache lines covering `start..end`, offsets into
+/// the frame, to memory.
+pub(crate) unsafe fn clean_frame(cptr: usize, start: usize, end: usize) -> Result<(), SeL4Error> {
+    selfe_sys::seL4_ARM_Page_Clean_Data(cptr, start, end)
+        .as_result()
+        .map_err(SeL4Error::PageCleanData)?;
+
+    Ok(())
+}
+
+/// Discard the data cache lines covering `start..end`, offsets into
+/// the frame, without writing them back.
+pub(crate) unsafe fn invalidate_frame(
+    cptr: usize,
+    start: usize,
+    end: usize,
+) -> Result<(), SeL4Error> {
+    selfe_sys::seL4_ARM_Page_Invalidate_Data(cptr, start, end)
+        .as_result()
+        .map_err(SeL4Error::PageInvalidateData)?;
+
+    Ok(())
+}
+
+/// Make the instruction cache coherent with the data cache over
+/// `start..end`, offsets into the frame.
+pub(crate) unsafe fn unify_instruction_frame(
+    cptr: usize,
+    start: usize,
+    end: usize,
+) -> Result<(), SeL4Error> {
+    selfe_sys::seL4_ARM_Page_Unify_Instruction(cptr, start, end)
+        .as_result()
+        .map_err(SeL4Error::PageUnifyInstruction)?;
+
+    Ok(())
+}
+
+// The compiler reaches the thread pointer through this helper, which
+// must leave every register but r0 untouched. seL4 keeps a thread's TLS
+// base in TPIDRURW; TPIDRURO holds its IPC buffer address. It's weak so
+// that a libc linked in alongside can bring its own.
+global_asm!(
+    ".weak __aeabi_read_tp",
+    ".type __aeabi_read_tp, %function",
+    "__aeabi_read_tp:",
+    "mrc p15, 0, r0, c13, c0, 2",
+    "bx lr"
+);
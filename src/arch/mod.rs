@@ -8,6 +8,16 @@ mod aarch64;
 #[cfg(target_arch = "aarch64")]
 pub use aarch64::*;
 
+#[cfg(target_arch = "x86_64")]
+mod x86_64;
+#[cfg(target_arch = "x86_64")]
+pub use x86_64::*;
+
+#[cfg(target_arch = "riscv64")]
+mod riscv64;
+#[cfg(target_arch = "riscv64")]
+pub use riscv64::*;
+
 /// For use in places where code is generated from bitfield DSL files and
 /// hard-codes the output integer size rather than referring to seL4Word
 /// or equivalent.
@@ -34,3 +44,15 @@ pub(crate) unsafe fn to_sel4_word(n: usize) -> u32 {
 pub type CNodeSlotBits = typenum::U5;
 #[cfg(target_pointer_width = "32")]
 pub type CNodeSlotBits = typenum::U4;
+
+/// The size of a reply object on MCS kernels
+#[cfg(target_pointer_width = "64")]
+pub type ReplyBits = typenum::U5;
+/// The size of a reply object on MCS kernels
+#[cfg(target_pointer_width = "32")]
+pub type ReplyBits = typenum::U4;
+
+/// The size of the scheduling contexts created by ferros. seL4 requires at
+/// least `seL4_MinSchedContextBits`; anything larger buys room for extra
+/// refills.
+pub type SchedContextBits = typenum::U8;
@@ -0,0 +1,9 @@
+mod asid;
+mod asid_control;
+mod page;
+mod page_table;
+
+pub use asid::*;
+pub use asid_control::*;
+pub use page::*;
+pub use page_table::*;
@@ -0,0 +1,132 @@
+use selfe_sys::*;
+
+use crate::cap::{page_state, DirectRetype, Frame, LocalCap, Page, PageState, PhantomCap};
+use crate::error::{ErrorExt, SeL4Error};
+use crate::userland::CapRights;
+
+impl<T: PageState> LocalCap<Page<T>> {
+    pub(crate) fn paddr(&self) -> Result<usize, SeL4Error> {
+        let res = unsafe { seL4_RISCV_Page_GetAddress(self.cptr) };
+        match (res.error as seL4_Error).as_result() {
+            Ok(_) => Ok(res.paddr),
+            Err(e) => Err(SeL4Error::PageGetAddress(e)),
+        }
+    }
+}
+
+impl LocalCap<Page<page_state::Unmapped>> {
+    pub(crate) unsafe fn unchecked_page_map(
+        &self,
+        addr: usize,
+        root: &mut LocalCap<crate::arch::PagingRoot>,
+        rights: CapRights,
+        vm_attributes: seL4_RISCV_VMAttributes,
+    ) -> Result<(), SeL4Error> {
+        seL4_RISCV_Page_Map(
+            self.cptr,
+            root.cptr,
+            addr,
+            seL4_CapRights_t::from(rights),
+            vm_attributes,
+        )
+        .as_result()
+        .map_err(SeL4Error::PageMap)
+    }
+}
+
+impl LocalCap<Page<page_state::Mapped>> {
+    /// Change the rights and attributes this page is mapped with, in
+    /// place.
+    pub(crate) fn remap(
+        &mut self,
+        root: &mut LocalCap<crate::arch::PagingRoot>,
+        rights: CapRights,
+        vm_attributes: seL4_RISCV_VMAttributes,
+    ) -> Result<(), SeL4Error> {
+        unsafe {
+            seL4_RISCV_Page_Map(
+                self.cptr,
+                root.cptr,
+                self.vaddr(),
+                seL4_CapRights_t::from(rights),
+                vm_attributes,
+            )
+        }
+        .as_result()
+        .map_err(SeL4Error::PageRemap)?;
+        self.cap_data.state.rights = rights;
+        self.cap_data.state.vm_attributes = vm_attributes;
+        Ok(())
+    }
+
+    /// Keeping this non-public in order to restrict mapping operations to
+    /// owners of a VSpace-related object
+    pub(crate) fn unmap(self) -> Result<LocalCap<Page<page_state::Unmapped>>, SeL4Error> {
+        // Unlike on ARM, there is no cache maintenance to do before
+        // unmapping; seL4 exposes none on RISC-V.
+        match unsafe { seL4_RISCV_Page_Unmap(self.cptr) }.as_result() {
+            Ok(_) => Ok(crate::cap::Cap {
+                cptr: self.cptr,
+                cap_data: Page {
+                    state: page_state::Unmapped {},
+                },
+                _role: core::marker::PhantomData,
+            }),
+            Err(e) => Err(SeL4Error::PageUnmap(e)),
+        }
+    }
+
+    /// Unmap a frame of any size; the kernel knows how big it is.
+    pub(crate) fn unmap_frame(
+        self,
+        _size_bytes: usize,
+    ) -> Result<LocalCap<Page<page_state::Unmapped>>, SeL4Error> {
+        self.unmap()
+    }
+}
+
+impl DirectRetype for Page<page_state::Unmapped> {
+    type SizeBits = super::super::PageBits;
+    fn sel4_type_id() -> usize {
+        _object_seL4_RISCV_4K_Page as usize
+    }
+}
+
+impl PhantomCap for Page<page_state::Unmapped> {
+    fn phantom_instance() -> Self {
+        Page {
+            state: page_state::Unmapped {},
+        }
+    }
+}
+
+/// The sizes of `Frame` beyond a `Page` that a single page table entry
+/// can map.
+pub mod frame_size {
+    use selfe_sys::*;
+
+    use crate::arch::{HugePageBits, LargePageBits};
+    use crate::cap::FrameSize;
+
+    /// 2 MiB, a "megapage", mapped by an entry one level above the
+    /// leaf page tables.
+    pub struct LargePage;
+    impl FrameSize for LargePage {
+        type SizeBits = LargePageBits;
+        fn sel4_type_id() -> usize {
+            _object_seL4_RISCV_Mega_Page as usize
+        }
+    }
+
+    /// 1 GiB, a "gigapage", mapped by an entry in the root page table.
+    pub struct HugePage;
+    impl FrameSize for HugePage {
+        type SizeBits = HugePageBits;
+        fn sel4_type_id() -> usize {
+            _mode_object_seL4_RISCV_Giga_Page as usize
+        }
+    }
+}
+
+pub type LargePage<State = page_state::Unmapped> = Frame<frame_size::LargePage, State>;
+pub type HugePage<State = page_state::Unmapped> = Frame<frame_size::HugePage, State>;
@@ -0,0 +1,43 @@
+use selfe_sys::*;
+
+use crate::cap::{DirectRetype, LocalCap, Movable, PageTable};
+use crate::error::{ErrorExt, KernelError, SeL4Error};
+use crate::userland::CapRights;
+use crate::vspace::{MappingError, Maps};
+
+use super::super::PagingRoot;
+
+/// On RISC-V, every level of the address space is a page table, and the
+/// kernel installs a newly mapped table at the highest level which is
+/// missing for the given address. Consequently a single implementation
+/// serves every intermediate layer as well as the root.
+impl Maps<PageTable> for PageTable {
+    fn map_granule(
+        &mut self,
+        table: &LocalCap<PageTable>,
+        addr: usize,
+        root: &mut LocalCap<PagingRoot>,
+        _rights: CapRights,
+        vm_attributes: seL4_RISCV_VMAttributes,
+    ) -> Result<(), MappingError> {
+        match unsafe { seL4_RISCV_PageTable_Map(table.cptr, root.cptr, addr, vm_attributes) }
+            .as_result()
+        {
+            Ok(_) => Ok(()),
+            Err(KernelError::FailedLookup) => Err(MappingError::Overflow),
+            Err(e) => Err(MappingError::IntermediateLayerFailure(
+                SeL4Error::PageTableMap(e),
+            )),
+        }
+    }
+}
+
+// The VSpace root is itself a page table.
+impl Movable for PageTable {}
+
+impl DirectRetype for PageTable {
+    type SizeBits = super::super::PageTableBits;
+    fn sel4_type_id() -> usize {
+        _object_seL4_RISCV_PageTableObject as usize
+    }
+}
@@ -0,0 +1,149 @@
+use crate::cap::Badge;
+use crate::userland::MessageInfo;
+use selfe_sys::*;
+
+#[derive(Debug)]
+pub struct VMFault {
+    pub sender: Badge,
+    pub program_counter: usize,
+    pub address: usize,
+    pub is_instruction_fault: bool,
+    pub fault_status_register: usize,
+}
+
+impl VMFault {
+    /// Whether nothing was mapped at `address`, as opposed to the
+    /// mapping there not permitting the access.
+    ///
+    /// N.B. RISC-V raises the same page fault for a missing mapping as
+    /// for one with insufficient permissions, so this is only false
+    /// for access faults, which come of physical memory protection
+    /// rather than of the page tables. Callers that need to tell the
+    /// two apart must track what they have mapped themselves.
+    pub fn is_translation_fault(&self) -> bool {
+        // Instruction, load and store page faults, as opposed to the
+        // corresponding access faults at 1, 5 and 7
+        self.fault_status_register == 12
+            || self.fault_status_register == 13
+            || self.fault_status_register == 15
+    }
+
+    /// Whether the faulting access was a write.
+    pub fn is_write(&self) -> bool {
+        // Store access and store page faults
+        self.fault_status_register == 7 || self.fault_status_register == 15
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownSyscall {
+    pub sender: Badge,
+    pub a0: usize,
+    pub a1: usize,
+    pub a2: usize,
+    pub a3: usize,
+    pub a4: usize,
+    pub a5: usize,
+    pub a6: usize,
+    pub program_counter: usize,
+    pub stack_pointer: usize,
+    pub return_address: usize,
+    pub syscall: usize,
+}
+#[derive(Debug)]
+pub struct UserException {
+    pub sender: Badge,
+    pub program_counter: usize,
+    pub stack_pointer: usize,
+    pub flags: usize,
+    pub number: usize,
+    pub code: usize,
+}
+#[derive(Debug)]
+pub struct NullFault {
+    pub sender: Badge,
+}
+#[derive(Debug)]
+pub struct CapFault {
+    pub sender: Badge,
+    pub in_receive_phase: bool,
+    pub cap_address: usize,
+}
+/// Grab bag for faults that don't fit the regular classification
+#[derive(Debug)]
+pub struct UnidentifiedFault {
+    pub sender: Badge,
+}
+
+#[derive(Debug)]
+pub enum Fault {
+    VMFault(VMFault),
+    UnknownSyscall(UnknownSyscall),
+    UserException(UserException),
+    NullFault(NullFault),
+    CapFault(CapFault),
+    UnidentifiedFault(UnidentifiedFault),
+}
+
+impl Fault {
+    pub fn sender(&self) -> Badge {
+        match self {
+            Fault::VMFault(f) => f.sender,
+            Fault::UnknownSyscall(f) => f.sender,
+            Fault::UserException(f) => f.sender,
+            Fault::NullFault(f) => f.sender,
+            Fault::CapFault(f) => f.sender,
+            Fault::UnidentifiedFault(f) => f.sender,
+        }
+    }
+}
+
+impl From<(MessageInfo, Badge)> for Fault {
+    fn from(info_and_sender: (MessageInfo, Badge)) -> Self {
+        let (info, sender) = info_and_sender;
+        let buffer: &mut seL4_IPCBuffer = unsafe { &mut *seL4_GetIPCBuffer() };
+        const VM_FAULT: usize = seL4_Fault_tag_seL4_Fault_VMFault as usize;
+        const UNKNOWN_SYSCALL: usize = seL4_Fault_tag_seL4_Fault_UnknownSyscall as usize;
+        const USER_EXCEPTION: usize = seL4_Fault_tag_seL4_Fault_UserException as usize;
+        const NULL_FAULT: usize = seL4_Fault_tag_seL4_Fault_NullFault as usize;
+        const CAP_FAULT: usize = seL4_Fault_tag_seL4_Fault_CapFault as usize;
+        match info.label() {
+            NULL_FAULT => Fault::NullFault(NullFault { sender }),
+            VM_FAULT => Fault::VMFault(VMFault {
+                sender,
+                program_counter: buffer.msg[seL4_VMFault_IP as usize],
+                address: buffer.msg[seL4_VMFault_Addr as usize],
+                is_instruction_fault: 1 == buffer.msg[seL4_VMFault_PrefetchFault as usize],
+                fault_status_register: buffer.msg[seL4_VMFault_FSR as usize],
+            }),
+            UNKNOWN_SYSCALL => Fault::UnknownSyscall(UnknownSyscall {
+                sender,
+                a0: buffer.msg[seL4_UnknownSyscall_A0 as usize],
+                a1: buffer.msg[seL4_UnknownSyscall_A1 as usize],
+                a2: buffer.msg[seL4_UnknownSyscall_A2 as usize],
+                a3: buffer.msg[seL4_UnknownSyscall_A3 as usize],
+                a4: buffer.msg[seL4_UnknownSyscall_A4 as usize],
+                a5: buffer.msg[seL4_UnknownSyscall_A5 as usize],
+                a6: buffer.msg[seL4_UnknownSyscall_A6 as usize],
+                program_counter: buffer.msg[seL4_UnknownSyscall_FaultIP as usize],
+                stack_pointer: buffer.msg[seL4_UnknownSyscall_SP as usize],
+                return_address: buffer.msg[seL4_UnknownSyscall_RA as usize],
+                syscall: buffer.msg[seL4_UnknownSyscall_Syscall as usize],
+            }),
+            USER_EXCEPTION => Fault::UserException(UserException {
+                sender,
+                program_counter: buffer.msg[seL4_UserException_FaultIP as usize],
+                stack_pointer: buffer.msg[seL4_UserException_SP as usize],
+                flags: buffer.msg[seL4_UserException_FLAGS as usize],
+                number: buffer.msg[seL4_UserException_Number as usize],
+                code: buffer.msg[seL4_UserException_Code as usize],
+            }),
+            CAP_FAULT => Fault::CapFault(CapFault {
+                sender,
+                cap_address: buffer.msg[seL4_CapFault_Addr as usize],
+                in_receive_phase: 1 == buffer.msg[seL4_CapFault_InRecvPhase as usize],
+            }),
+            _ => Fault::UnidentifiedFault(UnidentifiedFault { sender }),
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use core::marker::PhantomData;
+
+use typenum::*;
+
+use crate::cap::{page_state, Page, PageTable, PhantomCap};
+use crate::error::SeL4Error;
+use crate::vspace::{PagingRec, PagingTop};
+
+pub mod cap;
+pub mod fault;
+pub mod userland;
+
+pub type WordSize = U64;
+pub type MinUntypedSize = U4;
+// MaxUntypedSize is bounded by the 39-bit (Sv39) virtual address space.
+pub type MaxUntypedSize = U38;
+/// The number of splits it would take to extract an untyped of the minimum
+/// size starting from an untyped of the maximum size
+pub type MaxNaiveSplitCount = op!(MaxUntypedSize - MinUntypedSize);
+
+/// The ASID address space is a total of 16 bits. It is bifurcated
+/// into high bits and low bits where the high bits determine the
+/// number of pools while the low bits identify the ASID /in/ its
+/// pool.
+pub type ASIDHighBits = U7;
+pub type ASIDLowBits = U9;
+/// The total number of available pools is 2 ^ ASIDHighBits, however,
+/// there is an initial pool given to the root thread.
+pub type ASIDPoolCount = op!(U1 << ASIDHighBits);
+pub type ASIDPoolSize = op!(U1 << ASIDLowBits);
+pub type TCBBits = U10;
+pub type NotificationBits = U5;
+
+// The paging structures (Sv39) are layed out as follows:
+// L0: PageTable (the VSpace root)
+// L1: |_PageTable      *L2 | HugePage
+// L2:   |_PageTable        | LargePage
+//         |_Page
+pub type PageTableBits = U12; // How big is the kernel object for a PageTable
+pub type PageTableIndexBits = U9; // How many slots are there, in addressable bit space?
+pub type PageBits = U12;
+pub type PageIndexBits = U12;
+
+pub type PageBytes = op!(U1 << U12);
+pub type LargePageBits = U21;
+pub type HugePageBits = U30;
+
+pub type AddressSpace =
+    PagingRec<Page<page_state::Unmapped>, PageTable, PagingRec<PageTable, PageTable, PagingTop>>;
+
+pub type PagingRoot = PageTable;
+/// The level directly underneath the PagingRoot
+pub type PagingRootLowerLevel = PageTable;
+
+/// How many bits of address space one object at each intermediate layer
+/// of `AddressSpace` maps, from the layer just below the root down.
+pub(crate) const PAGING_LAYER_SPAN_BITS: [usize; 2] = [HugePageBits::USIZE, LargePageBits::USIZE];
+
+/// The size in bits and object type of each size of frame the paging
+/// structures can map, largest first and ending with `Page`.
+pub(crate) const FRAME_SIZES: [(u8, usize); 3] = [
+    (
+        HugePageBits::U8,
+        selfe_sys::_mode_object_seL4_RISCV_Giga_Page as usize,
+    ),
+    (
+        LargePageBits::U8,
+        selfe_sys::_object_seL4_RISCV_Mega_Page as usize,
+    ),
+    (PageBits::U8, selfe_sys::_object_seL4_RISCV_4K_Page as usize),
+];
+
+impl AddressSpace {
+    pub fn new() -> Self {
+        PagingRec {
+            layer: PageTable::phantom_instance(),
+            next: PagingRec {
+                layer: PageTable::phantom_instance(),
+                next: PagingTop {
+                    layer: PageTable::phantom_instance(),
+                    _item: PhantomData,
+                },
+                _item: PhantomData,
+            },
+            _item: PhantomData,
+        }
+    }
+}
+
+// There are no page directories on RISC-V; the "directory" level used to
+// size the root task's reserved paging structures is the second-to-last
+// level of page tables.
+pub type BasePageDirFreeSlots = op!(U1 << PageTableIndexBits);
+pub type BasePageTableFreeSlots = op!(U1 << PageTableIndexBits);
+
+// TODO remove these when elf stuff lands.
+// this is the default link address for riscv64 executables.
+/// 0x00010000
+pub type ProgramStart = op!(U1 << U16);
+pub type CodePageTableBits = U5;
+pub type CodePageTableCount = op!(U1 << CodePageTableBits); // 32 page tables, but larger == 64 mb
+pub type CodePageCount = op!(CodePageTableCount * BasePageTableFreeSlots); // 2^14
+pub type TotalCodeSizeBits = op!(CodePageTableBits + PageBits + PageTableIndexBits);
+pub type TotalCodeSizeBytes = crate::pow::Pow<TotalCodeSizeBits>;
+// The root task has a stack size configurable by the sel4.toml
+// in the `root-task-stack-bytes` metadata property.
+// This configuration is turned into a generated Rust type named
+// `RootTaskStackPageTableCount` that implements `typenum::Unsigned` in the
+// `build.rs` file.
+include!(concat!(
+    env!("OUT_DIR"),
+    "/ROOT_TASK_STACK_PAGE_TABLE_COUNT"
+));
+// The first N page tables are already mapped for the user image in the root
+// task. Add in the stack-reserved page tables (minimum of 1 more)
+pub type RootTaskReservedPageDirSlots = op!(CodePageTableCount + RootTaskStackPageTableCount);
+pub type RootTaskPageDirFreeSlots = op!(BasePageDirFreeSlots - RootTaskReservedPageDirSlots);
+
+/* Sv39 user space covers the lower half of the 39-bit address space,
+ * everything above belongs to the kernel.
+ * 0x0000004000000000 */
+// Cf. https://github.com/seL4/seL4/blob/master/include/arch/riscv/arch/64/mode/hardware.h
+pub type KernelReservedStart = op!(U1 << U38);
+
+pub const WORDS_PER_PAGE: usize = PageBytes::USIZE / core::mem::size_of::<usize>();
+
+/// Type type alias allows us to treat vm_attributes in a cross-architecture
+/// way, abstractly
+pub type VMAttributes = selfe_sys::seL4_RISCV_VMAttributes;
+
+/// A convenience module
+///
+/// RISC-V has no cacheability or parity mapping attributes, so those are
+/// provided as no-op values in order to keep the cross-architecture
+/// combinations (e.g. `PAGE_CACHEABLE | EXECUTE_NEVER`) meaningful.
+pub mod vm_attributes {
+    use super::*;
+
+    pub const DEFAULT: VMAttributes =
+        selfe_sys::seL4_RISCV_VMAttributes_seL4_RISCV_Default_VMAttributes;
+
+    pub const PAGE_CACHEABLE: VMAttributes = DEFAULT;
+
+    pub const PARITY_ENABLED: VMAttributes = DEFAULT;
+
+    pub const EXECUTE_NEVER: VMAttributes =
+        selfe_sys::seL4_RISCV_VMAttributes_seL4_RISCV_ExecuteNever;
+
+    pub const PROGRAM_CODE: VMAttributes = DEFAULT;
+
+    pub const PROGRAM_DATA: VMAttributes = PAGE_CACHEABLE | PARITY_ENABLED | EXECUTE_NEVER;
+
+    /// seL4 has no cacheability attributes on RISC-V, so this is the
+    /// same as `DEFAULT`.
+    pub const UNCACHED: VMAttributes = DEFAULT;
+
+    pub const WRITE_COMBINED: VMAttributes = DEFAULT;
+}
+
+/// Order every memory and device I/O access before this against every
+/// one after.
+pub(crate) fn dma_barrier() {
+    unsafe { asm!("fence iorw, iorw") };
+}
+
+/// seL4 exposes no cache maintenance operations on RISC-V, so there is
+/// nothing to do here.
+pub(crate) unsafe fn flush_frame(_cptr: usize, _size_bytes: usize) -> Result<(), SeL4Error> {
+    Ok(())
+}
+
+/// As for `flush_frame`, there is nothing to do here.
+pub(crate) unsafe fn clean_frame(
+    _cptr: usize,
+    _start: usize,
+    _end: usize,
+) -> Result<(), SeL4Error> {
+    Ok(())
+}
+
+/// As for `flush_frame`, there is nothing to do here.
+pub(crate) unsafe fn invalidate_frame(
+    _cptr: usize,
+    _start: usize,
+    _end: usize,
+) -> Result<(), SeL4Error> {
+    Ok(())
+}
+
+/// seL4 has no call for this on RISC-V either, but user code can issue
+/// `fence.i`, which makes instruction fetches on this hart see all of
+/// its prior stores, across the whole address space. Other harts aren't
+/// covered, so this is only enough on a single core kernel
+/// (`KernelMaxNumNodes = 1`).
+pub(crate) unsafe fn unify_instruction_frame(
+    _cptr: usize,
+    _start: usize,
+    _end: usize,
+) -> Result<(), SeL4Error> {
+    asm!("fence.i");
+    Ok(())
+}
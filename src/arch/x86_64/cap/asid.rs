@@ -0,0 +1,19 @@
+use core::mem;
+
+use selfe_sys::*;
+
+use crate::cap::{AssignedASID, LocalCap, UnassignedASID};
+use crate::error::{ErrorExt, SeL4Error};
+
+impl LocalCap<UnassignedASID> {
+    pub fn assign(
+        self,
+        pml4: &mut LocalCap<crate::arch::PagingRoot>,
+    ) -> Result<LocalCap<AssignedASID>, SeL4Error> {
+        unsafe { seL4_X86_ASIDPool_Assign(self.cptr, pml4.cptr) }
+            .as_result()
+            .map_err(SeL4Error::ASIDPoolAssign)?;
+
+        Ok(unsafe { mem::transmute(self) })
+    }
+}
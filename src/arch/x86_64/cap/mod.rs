@@ -0,0 +1,15 @@
+mod asid;
+mod asid_control;
+mod page;
+mod page_directory;
+mod page_table;
+mod pdpt;
+mod pml4;
+
+pub use asid::*;
+pub use asid_control::*;
+pub use page::*;
+pub use page_directory::*;
+pub use page_table::*;
+pub use pdpt::*;
+pub use pml4::*;
@@ -0,0 +1,56 @@
+use selfe_sys::*;
+
+use typenum::Unsigned;
+
+use crate::cap::{CapType, DirectRetype, LocalCap, PhantomCap};
+use crate::error::{ErrorExt, KernelError, SeL4Error};
+use crate::userland::CapRights;
+use crate::vspace::{MappingError, Maps};
+
+use super::super::{PageDirIndexBits, PageIndexBits, PageTableIndexBits, PagingRoot};
+use super::PageDirectory;
+
+const PDPT_MASK: usize =
+    !((1 << (PageIndexBits::USIZE + PageTableIndexBits::USIZE + PageDirIndexBits::USIZE)) - 1);
+
+/// Page Directory Pointer Table, the level directly beneath the PML4.
+#[derive(Debug)]
+pub struct PDPT {}
+
+impl Maps<PageDirectory> for PDPT {
+    fn map_granule(
+        &mut self,
+        dir: &LocalCap<PageDirectory>,
+        addr: usize,
+        root: &mut LocalCap<PagingRoot>,
+        _rights: CapRights,
+        vm_attributes: seL4_X86_VMAttributes,
+    ) -> Result<(), MappingError> {
+        match unsafe {
+            seL4_X86_PageDirectory_Map(dir.cptr, root.cptr, addr & PDPT_MASK, vm_attributes)
+        }
+        .as_result()
+        {
+            Ok(_) => Ok(()),
+            Err(KernelError::FailedLookup) => Err(MappingError::Overflow),
+            Err(e) => Err(MappingError::IntermediateLayerFailure(
+                SeL4Error::PageDirectoryMap(e),
+            )),
+        }
+    }
+}
+
+impl CapType for PDPT {}
+
+impl PhantomCap for PDPT {
+    fn phantom_instance() -> Self {
+        PDPT {}
+    }
+}
+
+impl DirectRetype for PDPT {
+    type SizeBits = super::super::PDPTBits;
+    fn sel4_type_id() -> usize {
+        _mode_object_seL4_X86_PDPTObject as usize
+    }
+}
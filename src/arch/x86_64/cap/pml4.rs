@@ -0,0 +1,54 @@
+use selfe_sys::*;
+
+use typenum::Unsigned;
+
+use crate::cap::{CapType, DirectRetype, LocalCap, Movable, PhantomCap};
+use crate::error::{ErrorExt, SeL4Error};
+use crate::userland::CapRights;
+use crate::vspace::{MappingError, Maps};
+
+use super::super::{
+    PDPTIndexBits, PageDirIndexBits, PageIndexBits, PageTableIndexBits, PagingRoot,
+};
+use super::PDPT;
+
+const PML4_MASK: usize = !((1
+    << (PageIndexBits::USIZE
+        + PageTableIndexBits::USIZE
+        + PageDirIndexBits::USIZE
+        + PDPTIndexBits::USIZE))
+    - 1);
+
+/// Page Map Level 4, the root of an x86_64 address space.
+#[derive(Debug)]
+pub struct PML4 {}
+
+impl Maps<PDPT> for PML4 {
+    fn map_granule(
+        &mut self,
+        pdpt: &LocalCap<PDPT>,
+        addr: usize,
+        root: &mut LocalCap<PagingRoot>,
+        _rights: CapRights,
+        vm_attributes: seL4_X86_VMAttributes,
+    ) -> Result<(), MappingError> {
+        unsafe { seL4_X86_PDPT_Map(pdpt.cptr, root.cptr, addr & PML4_MASK, vm_attributes) }
+            .as_result()
+            .map_err(|e| MappingError::IntermediateLayerFailure(SeL4Error::PDPTMap(e)))
+    }
+}
+
+impl CapType for PML4 {}
+impl Movable for PML4 {}
+impl PhantomCap for PML4 {
+    fn phantom_instance() -> Self {
+        PML4 {}
+    }
+}
+
+impl DirectRetype for PML4 {
+    type SizeBits = super::super::PML4Bits;
+    fn sel4_type_id() -> usize {
+        _mode_object_seL4_X64_PML4Object as usize
+    }
+}
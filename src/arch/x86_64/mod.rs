@@ -0,0 +1,229 @@
+use core::marker::PhantomData;
+
+use typenum::*;
+
+use crate::cap::{page_state, Page, PageTable, PhantomCap};
+use crate::error::SeL4Error;
+use crate::vspace::{PagingRec, PagingTop};
+
+pub mod cap;
+pub mod fault;
+pub mod userland;
+
+pub type WordSize = U64;
+pub type MinUntypedSize = U4;
+// MaxUntypedSize is half the address space and/or word size.
+pub type MaxUntypedSize = U47;
+/// The number of splits it would take to extract an untyped of the minimum
+/// size starting from an untyped of the maximum size
+pub type MaxNaiveSplitCount = op!(MaxUntypedSize - MinUntypedSize);
+
+/// The ASID address space is a total of 12 bits. It is bifurcated
+/// into high bits and low bits where the high bits determine the
+/// number of pools while the low bits identify the ASID /in/ its
+/// pool.
+pub type ASIDHighBits = U3;
+pub type ASIDLowBits = U9;
+/// The total number of available pools is 2 ^ ASIDHighBits, however,
+/// there is an initial pool given to the root thread.
+pub type ASIDPoolCount = op!(U1 << ASIDHighBits);
+pub type ASIDPoolSize = op!(U1 << ASIDLowBits);
+pub type TCBBits = U11;
+pub type NotificationBits = U5;
+
+// The paging structures are layed out as follows:
+// L0: PML4
+// L1: |_PDPT             *L2 | HugePage
+// L2:   |_PageDirectory  *L3 | LargePage
+// L3:    |_PageTable
+//          |_Page
+pub type PML4Bits = U12;
+pub type PML4IndexBits = U9;
+pub type PDPTBits = U12;
+pub type PDPTIndexBits = U9;
+pub type PageDirectoryBits = U12;
+pub type PageDirIndexBits = U9;
+pub type PageTableBits = U12; // How big is the kernel object for a PageTable
+pub type PageTableIndexBits = U9; // How many slots are there, in addressable bit space?
+pub type PageBits = U12;
+pub type PageIndexBits = U12;
+
+pub type PageBytes = op!(U1 << U12);
+pub type LargePageBits = U21;
+pub type HugePageBits = U30;
+
+pub type AddressSpace = PagingRec<
+    Page<page_state::Unmapped>,
+    PageTable,
+    PagingRec<PageTable, cap::PageDirectory, PagingRec<cap::PageDirectory, cap::PDPT, PagingTop>>,
+>;
+
+pub type PagingRoot = cap::PML4;
+/// The level directly underneath the PagingRoot
+pub type PagingRootLowerLevel = cap::PDPT;
+
+/// How many bits of address space one object at each intermediate layer
+/// of `AddressSpace` maps, from the layer just below the root down.
+pub(crate) const PAGING_LAYER_SPAN_BITS: [usize; 3] = [
+    HugePageBits::USIZE + PDPTIndexBits::USIZE,
+    HugePageBits::USIZE,
+    LargePageBits::USIZE,
+];
+
+/// The size in bits and object type of each size of frame the paging
+/// structures can map, largest first and ending with `Page`.
+#[cfg(KernelHugePage)]
+pub(crate) const FRAME_SIZES: [(u8, usize); 3] = [
+    (
+        HugePageBits::U8,
+        selfe_sys::_mode_object_seL4_X64_HugePageObject as usize,
+    ),
+    (
+        LargePageBits::U8,
+        selfe_sys::_object_seL4_X86_LargePageObject as usize,
+    ),
+    (PageBits::U8, selfe_sys::_object_seL4_X86_4K as usize),
+];
+/// The size in bits and object type of each size of frame the paging
+/// structures can map, largest first and ending with `Page`.
+#[cfg(not(KernelHugePage))]
+pub(crate) const FRAME_SIZES: [(u8, usize); 2] = [
+    (
+        LargePageBits::U8,
+        selfe_sys::_object_seL4_X86_LargePageObject as usize,
+    ),
+    (PageBits::U8, selfe_sys::_object_seL4_X86_4K as usize),
+];
+
+impl AddressSpace {
+    pub fn new() -> Self {
+        PagingRec {
+            layer: PageTable::phantom_instance(),
+            next: PagingRec {
+                layer: cap::PageDirectory::phantom_instance(),
+                next: PagingRec {
+                    layer: cap::PDPT::phantom_instance(),
+                    next: PagingTop {
+                        layer: cap::PML4::phantom_instance(),
+                        _item: PhantomData,
+                    },
+                    _item: PhantomData,
+                },
+                _item: PhantomData,
+            },
+            _item: PhantomData,
+        }
+    }
+}
+
+pub type BasePageDirFreeSlots = op!(U1 << PageDirIndexBits);
+pub type BasePageTableFreeSlots = op!(U1 << PageTableIndexBits);
+
+// TODO remove these when elf stuff lands.
+// this is the default link address for x86_64 executables.
+/// 0x00400000
+pub type ProgramStart = op!(U1 << U22);
+pub type CodePageTableBits = U5;
+pub type CodePageTableCount = op!(U1 << CodePageTableBits); // 32 page tables, but larger == 64 mb
+pub type CodePageCount = op!(CodePageTableCount * BasePageTableFreeSlots); // 2^14
+pub type TotalCodeSizeBits = op!(CodePageTableBits + PageBits + PageTableIndexBits);
+pub type TotalCodeSizeBytes = crate::pow::Pow<TotalCodeSizeBits>;
+// The root task has a stack size configurable by the sel4.toml
+// in the `root-task-stack-bytes` metadata property.
+// This configuration is turned into a generated Rust type named
+// `RootTaskStackPageTableCount` that implements `typenum::Unsigned` in the
+// `build.rs` file.
+include!(concat!(
+    env!("OUT_DIR"),
+    "/ROOT_TASK_STACK_PAGE_TABLE_COUNT"
+));
+// The first N page tables are already mapped for the user image in the root
+// task. Add in the stack-reserved page tables (minimum of 1 more)
+pub type RootTaskReservedPageDirSlots = op!(CodePageTableCount + RootTaskStackPageTableCount);
+pub type RootTaskPageDirFreeSlots = op!(BasePageDirFreeSlots - RootTaskReservedPageDirSlots);
+
+/* User space ends at the top of the lower half of the canonical
+ * 48-bit address space, everything above belongs to the kernel.
+ * 0x0000800000000000 */
+// Cf. https://github.com/seL4/seL4/blob/master/include/arch/x86/arch/64/mode/hardware.h
+pub type KernelReservedStart = op!(U1 << U47);
+
+pub const WORDS_PER_PAGE: usize = PageBytes::USIZE / core::mem::size_of::<usize>();
+
+/// Type type alias allows us to treat vm_attributes in a cross-architecture
+/// way, abstractly
+pub type VMAttributes = selfe_sys::seL4_X86_VMAttributes;
+
+/// A convenience module
+///
+/// x86 has no parity or execute-never mapping attributes, so those are
+/// provided as no-op values in order to keep the cross-architecture
+/// combinations (e.g. `DEFAULT | EXECUTE_NEVER`) meaningful.
+pub mod vm_attributes {
+    use super::*;
+
+    pub const DEFAULT: VMAttributes =
+        selfe_sys::seL4_X86_VMAttributes_seL4_X86_Default_VMAttributes;
+
+    pub const PAGE_CACHEABLE: VMAttributes = selfe_sys::seL4_X86_VMAttributes_seL4_X86_WriteBack;
+
+    pub const PARITY_ENABLED: VMAttributes = DEFAULT;
+
+    pub const EXECUTE_NEVER: VMAttributes = DEFAULT;
+
+    pub const WRITE_THROUGH: VMAttributes = selfe_sys::seL4_X86_VMAttributes_seL4_X86_WriteThrough;
+
+    pub const CACHE_DISABLED: VMAttributes =
+        selfe_sys::seL4_X86_VMAttributes_seL4_X86_CacheDisabled;
+
+    pub const PROGRAM_CODE: VMAttributes = DEFAULT;
+
+    pub const PROGRAM_DATA: VMAttributes = PAGE_CACHEABLE | PARITY_ENABLED | EXECUTE_NEVER;
+
+    /// For memory a device reads or writes by DMA.
+    pub const UNCACHED: VMAttributes = CACHE_DISABLED;
+
+    pub const WRITE_COMBINED: VMAttributes =
+        selfe_sys::seL4_X86_VMAttributes_seL4_X86_WriteCombining;
+}
+
+/// Order every memory access before this against every one after, as
+/// observed by devices as well as other cores, including stores still
+/// sitting in write-combining buffers.
+pub(crate) fn dma_barrier() {
+    unsafe { asm!("mfence") };
+}
+
+/// x86 data caches are coherent with respect to other cores and to DMA, so
+/// there is no maintenance to perform here.
+pub(crate) unsafe fn flush_frame(_cptr: usize, _size_bytes: usize) -> Result<(), SeL4Error> {
+    Ok(())
+}
+
+/// As for `flush_frame`, there is nothing to clean.
+pub(crate) unsafe fn clean_frame(
+    _cptr: usize,
+    _start: usize,
+    _end: usize,
+) -> Result<(), SeL4Error> {
+    Ok(())
+}
+
+/// As for `flush_frame`, there is nothing to invalidate.
+pub(crate) unsafe fn invalidate_frame(
+    _cptr: usize,
+    _start: usize,
+    _end: usize,
+) -> Result<(), SeL4Error> {
+    Ok(())
+}
+
+/// The x86 instruction cache snoops stores to memory, so it never
+/// needs to be unified with the data cache.
+pub(crate) unsafe fn unify_instruction_frame(
+    _cptr: usize,
+    _start: usize,
+    _end: usize,
+) -> Result<(), SeL4Error> {
+    Ok(())
+}
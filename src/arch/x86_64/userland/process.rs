@@ -0,0 +1,235 @@
+use core::mem;
+use core::ptr;
+
+use selfe_sys::*;
+
+use crate::userland::process::yield_forever;
+
+/// Set up the target registers and stack to pass the parameter.
+/// https://en.wikipedia.org/wiki/X86_calling_conventions#System_V_AMD64_ABI
+///
+/// x86_64 has no link register, so a return address slot is always pushed
+/// beneath the parameter and pre-populated with `yield_forever`. The stack
+/// is laid out such that `rsp + 8` is 16-byte aligned on entry, as the ABI
+/// expects immediately after a `call`.
+///
+/// Returns a tuple of (regs, stack_extent), where regs only has rdi and rsi
+/// set.
+pub(crate) unsafe fn setup_initial_stack_and_regs(
+    param: *const usize,
+    param_size: usize,
+    stack_top: *mut usize,
+    child_stack_top: usize,
+) -> (seL4_UserContext, usize) {
+    let word_size = mem::size_of::<usize>();
+    let tail_size = param_size % word_size;
+    let padding_size = if tail_size == 0 {
+        0
+    } else {
+        word_size - tail_size
+    };
+    let padded_param_size = param_size + padding_size;
+
+    let mut regs: seL4_UserContext = mem::zeroed();
+
+    let params_extent = if padded_param_size <= 16 {
+        let mut p = param;
+        let tail = (param as *const u8).add(param_size).sub(tail_size);
+
+        let mut tail_word = 0_usize;
+        for i in 0..tail_size {
+            tail_word |= (*tail.add(i) as usize) << (8 * i);
+        }
+
+        if p < tail as *const usize {
+            // If we've got a whole word worth of data, put the whole thing in
+            // the register.
+            regs.rdi = *p;
+            p = p.add(1);
+            if p < tail as *const usize {
+                regs.rsi = *p;
+            } else {
+                regs.rsi = tail_word;
+            }
+        } else {
+            // If not, store the pre-computed tail word here and be done.
+            regs.rdi = tail_word;
+        }
+        0
+    } else {
+        // Keep the parameter itself 16-byte aligned.
+        let params_extent = (param_size + 15) & !15;
+        let sp = (stack_top as *mut u8).sub(params_extent);
+        ptr::copy_nonoverlapping(param as *const u8, sp, param_size);
+        regs.rdi = child_stack_top - params_extent;
+        params_extent
+    };
+
+    let stack_extent = params_extent + word_size;
+    let return_slot = (stack_top as *mut u8).sub(stack_extent) as *mut usize;
+    *return_slot = yield_forever as usize;
+
+    (regs, stack_extent)
+}
+
+/// The return address on x86_64 lives on the stack rather than in a
+/// register, and has already been written by `setup_initial_stack_and_regs`.
+pub(crate) fn set_thread_link_register(
+    _registers: &mut selfe_sys::seL4_UserContext,
+    _post_return_fn: fn() -> !,
+) {
+}
+
+pub(crate) fn set_thread_stack_pointer(registers: &mut selfe_sys::seL4_UserContext, sp: usize) {
+    registers.rsp = sp;
+}
+
+pub(crate) fn set_thread_program_counter(registers: &mut selfe_sys::seL4_UserContext, pc: usize) {
+    registers.rip = pc;
+}
+
+#[doc(hidden)]
+#[allow(dead_code)]
+#[cfg(feature = "test_support")]
+pub mod test {
+    use super::*;
+
+    #[doc(hidden)]
+    #[derive(Debug, Clone)]
+    pub struct ComparisonError {
+        name: &'static str,
+        expected: usize,
+        actual: usize,
+    }
+
+    fn smaller_than_16() -> Result<(), ComparisonError> {
+        let smaller_than_16: [usize; 1] = [42; 1];
+        let mut stack: [usize; 256] = [0; 256];
+        let stack_top = unsafe { (&mut stack as *mut [usize; 256] as *mut usize).add(256) };
+        let child_stack_top = 2048;
+        let (regs, param_size) = unsafe {
+            setup_initial_stack_and_regs(
+                &smaller_than_16 as *const usize,
+                mem::size_of::<[usize; 1]>(),
+                stack_top,
+                child_stack_top,
+            )
+        };
+        if param_size != 8 {
+            return Err(ComparisonError {
+                name: "smaller_than_16: param size was incorrect",
+                expected: 8,
+                actual: param_size,
+            });
+        }
+
+        if regs.rdi != 42 {
+            return Err(ComparisonError {
+                name: "smaller_than_16: rdi was incorrect",
+                expected: 42,
+                actual: regs.rdi,
+            });
+        }
+        if stack[255] != yield_forever as usize {
+            return Err(ComparisonError {
+                name: "smaller_than_16: return address was incorrect",
+                expected: yield_forever as usize,
+                actual: stack[255],
+            });
+        }
+        Ok(())
+    }
+
+    fn is_16() -> Result<(), ComparisonError> {
+        let is_16: [usize; 2] = [42; 2];
+        let mut stack: [usize; 256] = [0; 256];
+        let stack_top = unsafe { (&mut stack as *mut [usize; 256] as *mut usize).add(256) };
+        let child_stack_top = 2048;
+        let (regs, param_size) = unsafe {
+            setup_initial_stack_and_regs(
+                &is_16 as *const usize,
+                mem::size_of::<[usize; 2]>(),
+                stack_top,
+                child_stack_top,
+            )
+        };
+        if param_size != 8 {
+            return Err(ComparisonError {
+                name: "is_16: param size was incorrect",
+                expected: 8,
+                actual: param_size,
+            });
+        }
+
+        if regs.rdi != 42 {
+            return Err(ComparisonError {
+                name: "is_16: rdi was incorrect",
+                expected: 42,
+                actual: regs.rdi,
+            });
+        }
+        if regs.rsi != 42 {
+            return Err(ComparisonError {
+                name: "is_16: rsi was incorrect",
+                expected: 42,
+                actual: regs.rsi,
+            });
+        }
+        Ok(())
+    }
+
+    fn larger_than_16() -> Result<(), ComparisonError> {
+        let larger_than_16: [usize; 10] = [42; 10];
+        let mut stack: [usize; 256] = [0; 256];
+        let stack_top = unsafe { (&mut stack as *mut [usize; 256] as *mut usize).add(256) };
+        let child_stack_top = 2048;
+        let (regs, param_size) = unsafe {
+            setup_initial_stack_and_regs(
+                &larger_than_16 as *const usize,
+                mem::size_of::<[usize; 10]>(),
+                stack_top,
+                child_stack_top,
+            )
+        };
+        if param_size != 88 {
+            return Err(ComparisonError {
+                name: "larger_than_16: param size was incorrect",
+                expected: 88,
+                actual: param_size,
+            });
+        }
+
+        if regs.rdi != child_stack_top - mem::size_of::<[usize; 10]>() {
+            return Err(ComparisonError {
+                name: "larger_than_16: rdi was incorrect",
+                expected: child_stack_top - mem::size_of::<[usize; 10]>(),
+                actual: regs.rdi,
+            });
+        }
+        for idx in 0..10 {
+            // we should copy into the last ten slots of the stack.
+            if stack[idx + 246] != 42 {
+                return Err(ComparisonError {
+                    name: "larger_than_16: stack was incorrect",
+                    expected: 42,
+                    actual: stack[idx + 246],
+                });
+            }
+        }
+        if stack[245] != yield_forever as usize {
+            return Err(ComparisonError {
+                name: "larger_than_16: return address was incorrect",
+                expected: yield_forever as usize,
+                actual: stack[245],
+            });
+        }
+        Ok(())
+    }
+
+    pub fn test_stack_setup() -> Result<(), ComparisonError> {
+        smaller_than_16()?;
+        is_16()?;
+        larger_than_16()?;
+        Ok(())
+    }
+}
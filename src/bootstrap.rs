@@ -6,6 +6,8 @@ use typenum::operator_aliases::Diff;
 use typenum::*;
 
 use crate::arch::*;
+#[cfg(KernelIsMCS)]
+use crate::cap::SchedControl;
 use crate::cap::{
     page_state, role, ASIDControl, AssignedASID, CNode, CNodeRole, CNodeSlots, Cap, IRQControl,
     InternalASID, LocalCNode, LocalCNodeSlots, LocalCap, MaxIRQCount, Page, ThreadControlBlock,
@@ -70,6 +72,9 @@ pub struct BootInfo<ASIDControlFreePools: Unsigned> {
     pub asid_control: LocalCap<ASIDControl<ASIDControlFreePools>>,
     pub irq_control: LocalCap<IRQControl>,
     pub user_image: UserImage<role::Local>,
+    /// The scheduling control capability for the boot core.
+    #[cfg(KernelIsMCS)]
+    pub sched_control: LocalCap<SchedControl>,
 
     #[allow(dead_code)]
     neither_send_nor_sync: NeitherSendNorSync,
@@ -132,6 +137,8 @@ impl BootInfo<op!(ASIDPoolCount - U1)> {
                 _role: PhantomData,
             },
             user_image,
+            #[cfg(KernelIsMCS)]
+            sched_control: Cap::wrap_cptr(bootinfo.schedcontrol.start),
             neither_send_nor_sync: Default::default(),
         }
     }
@@ -166,6 +173,7 @@ impl UserImage<role::Local> {
 
                         // We don't actually know how each page is mapped; assume the worst.
                         rights: CapRights::RW,
+                        vm_attributes: crate::arch::vm_attributes::DEFAULT,
                     },
                 },
                 _role: PhantomData,
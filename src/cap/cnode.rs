@@ -1,6 +1,8 @@
 use core::marker::PhantomData;
 use core::ops::{Add, Sub};
 
+use arrayvec::ArrayVec;
+
 use selfe_sys::*;
 
 use typenum::operator_aliases::Diff;
@@ -235,6 +237,7 @@ impl<Role: CNodeRole> LocalCap<WCNodeSlotsData<Role>> {
             _role: PhantomData,
         })
     }
+
     /// Allocate `Count` and return them as strengthened cnode slots.
     pub fn alloc_strong<Count: Unsigned>(
         &mut self,
@@ -291,3 +294,120 @@ impl<Role: CNodeRole> LocalCap<WCNodeSlotsData<Role>> {
         })
     }
 }
+
+/// The most slots handed back to a `RecyclingCNodeSlots` by `free`
+/// that it keeps track of, beyond those it can fold back into its
+/// range.
+pub type MaxFreeCNodeSlots = U32;
+
+/// Weak cnode slots which take back slots emptied by
+/// `Cap::delete_into_slot`, to be handed out again, so that a
+/// long-running component can allocate and free caps indefinitely.
+///
+/// Only this owner keeps track of the slots freed; the slots `alloc`
+/// hands out from it are plain `WCNodeSlots` with no such list.
+///
+/// The free list lives here rather than in `WCNodeSlots` itself
+/// because weak slots are split apart by `alloc` into values with no
+/// link back to one another. A list carried by each would be copied or
+/// dropped with every split, and a slot could be freed into a range
+/// that never held it. With a single owner, `WCNodeSlots` stays a
+/// plain range and `free` can tell which slots it handed out. Callers
+/// wanting to recycle slots wrap their `WCNodeSlots` with `new`.
+#[derive(Debug)]
+pub struct RecyclingCNodeSlots<Role: CNodeRole = role::Local> {
+    slots: LocalCap<WCNodeSlotsData<Role>>,
+    /// Where the range started out; every slot this owner has handed
+    /// out lies between here and the start of `slots`
+    origin: usize,
+    /// Single slots handed back by `free`, outside of the range above
+    free: ArrayVec<[usize; MaxFreeCNodeSlots::USIZE]>,
+}
+
+impl<Role: CNodeRole> RecyclingCNodeSlots<Role> {
+    pub fn new(slots: LocalCap<WCNodeSlotsData<Role>>) -> Self {
+        RecyclingCNodeSlots {
+            origin: slots.cap_data.offset,
+            slots,
+            free: ArrayVec::new(),
+        }
+    }
+
+    pub(crate) fn cptr(&self) -> usize {
+        self.slots.cptr
+    }
+
+    /// The range of slots not yet handed out, for those uses which
+    /// take `WCNodeSlots` directly. Slots taken from it this way can
+    /// be freed back here all the same.
+    pub fn slots_mut(&mut self) -> &mut LocalCap<WCNodeSlotsData<Role>> {
+        &mut self.slots
+    }
+
+    /// Allocate `count` and return them as weak cnode slots. A single
+    /// slot comes from those handed back by `free` first, if there are
+    /// any.
+    pub fn alloc(
+        &mut self,
+        count: usize,
+    ) -> Result<LocalCap<WCNodeSlotsData<Role>>, CNodeSlotsError> {
+        if count == 1 {
+            if let Some(offset) = self.free.pop() {
+                return Ok(Cap {
+                    cptr: self.slots.cptr,
+                    cap_data: WCNodeSlotsData {
+                        offset,
+                        size: 1,
+                        _role: PhantomData,
+                    },
+                    _role: PhantomData,
+                });
+            }
+        }
+        self.slots.alloc(count)
+    }
+
+    /// Allocate `Count` and return them as strengthened cnode slots.
+    pub fn alloc_strong<Count: Unsigned>(
+        &mut self,
+    ) -> Result<LocalCap<CNodeSlotsData<Count, Role>>, CNodeSlotsError> {
+        let cap = self.alloc(Count::USIZE)?;
+        Ok(Cap::internal_new(cap.cptr, cap.cap_data.offset))
+    }
+
+    /// Take back a slot emptied by `Cap::delete_into_slot`, to be
+    /// handed out again by `alloc`. Fails, handing the slot back, if
+    /// it wasn't handed out by this owner in the first place (slots of
+    /// the same CNode from elsewhere included), if it's already been
+    /// freed, or if there's no room left to keep track of it.
+    pub fn free(&mut self, slot: CNodeSlot<Role>) -> Result<(), CNodeSlot<Role>> {
+        if slot.cptr != self.slots.cptr {
+            return Err(slot);
+        }
+        let offset = slot.cap_data.offset;
+        let range = &self.slots.cap_data;
+        // Slots are handed out from the front of the range, so those
+        // handed out so far lie between its origin and its start.
+        if offset < self.origin || offset >= range.offset || self.free.contains(&offset) {
+            return Err(slot);
+        }
+        if offset + 1 != range.offset {
+            return self.free.try_push(offset).map_err(|_| slot);
+        }
+        // The slot just below the range can simply rejoin it, as can
+        // any freed earlier that it leaves just below the range in
+        // turn.
+        self.slots.cap_data.offset = offset;
+        self.slots.cap_data.size += 1;
+        while let Some(index) = self
+            .free
+            .iter()
+            .position(|&freed| freed + 1 == self.slots.cap_data.offset)
+        {
+            self.free.swap_remove(index);
+            self.slots.cap_data.offset -= 1;
+            self.slots.cap_data.size += 1;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+//! A CSpace for a child process laid out over two levels of CNodes,
+//! for processes that need more slots than one CNode can hold.
+//!
+//! The top CNode holds nothing but leaf CNodes, and every other
+//! capability lives in a leaf. A cptr in the child is the leaf's index
+//! in the top CNode followed by the slot's index in the leaf:
+//!
+//! | guard | top index (`TopRadix`) | leaf index (`LeafRadix`) |
+//!
+//! The parent reaches each leaf through a copy of its capability whose
+//! guard is that leaf's top index, so the very same cptr picks out a
+//! slot from either side, and the child's slots can be handled like
+//! those of a CNode made by `retype_cnode`.
+use core::marker::PhantomData;
+use core::ops::{Add, Sub};
+
+use selfe_sys::*;
+
+use typenum::operator_aliases::{Diff, Sum};
+use typenum::*;
+
+use crate::arch::CNodeSlotBits;
+use crate::cap::{
+    role, CNode, CNodeSlots, Cap, CapType, ChildCNode, ChildCNodeSlots, LocalCNodeSlot,
+    LocalCNodeSlots, LocalCap, Untyped,
+};
+use crate::error::{ErrorExt, SeL4Error};
+use crate::pow::{Pow, _Pow};
+use crate::userland::CapRights;
+
+use super::untyped::retype_guarded_cnode;
+
+/// The top CNode of a two-level CSpace, with room for `FreeLeaves`
+/// more leaf CNodes of `1 << LeafRadix` slots each.
+#[derive(Debug)]
+pub struct TwoLevelCNode<TopRadix: Unsigned, LeafRadix: Unsigned, FreeLeaves: Unsigned> {
+    next_leaf: usize,
+    _top_radix: PhantomData<TopRadix>,
+    _leaf_radix: PhantomData<LeafRadix>,
+    _free_leaves: PhantomData<FreeLeaves>,
+}
+
+impl<TopRadix: Unsigned, LeafRadix: Unsigned, FreeLeaves: Unsigned> CapType
+    for TwoLevelCNode<TopRadix, LeafRadix, FreeLeaves>
+{
+}
+
+/// Retype the top CNode of a two-level CSpace, with no leaves yet.
+pub fn retype_two_level_cnode<TopRadix: Unsigned, LeafRadix: Unsigned>(
+    untyped: LocalCap<Untyped<Sum<TopRadix, CNodeSlotBits>>>,
+    local_slots: LocalCNodeSlots<U2>,
+) -> Result<LocalCap<TwoLevelCNode<TopRadix, LeafRadix, Pow<TopRadix>>>, SeL4Error>
+where
+    TopRadix: _Pow,
+    Pow<TopRadix>: Unsigned,
+
+    TopRadix: Add<CNodeSlotBits>,
+    Sum<TopRadix, CNodeSlotBits>: Unsigned,
+
+    TopRadix: Add<LeafRadix>,
+    Sum<TopRadix, LeafRadix>: Unsigned,
+{
+    // The guard takes whatever of a cptr the two levels don't.
+    let cptr = retype_guarded_cnode(
+        untyped.cptr,
+        TopRadix::U8,
+        seL4_WordBits as u8 - Sum::<TopRadix, LeafRadix>::U8,
+        local_slots,
+    )?;
+    Ok(Cap {
+        cptr,
+        cap_data: TwoLevelCNode {
+            next_leaf: 0,
+            _top_radix: PhantomData,
+            _leaf_radix: PhantomData,
+            _free_leaves: PhantomData,
+        },
+        _role: PhantomData,
+    })
+}
+
+impl<TopRadix: Unsigned, LeafRadix: Unsigned, FreeLeaves: Unsigned>
+    LocalCap<TwoLevelCNode<TopRadix, LeafRadix, FreeLeaves>>
+{
+    /// Retype a leaf CNode into the next free slot of the top CNode,
+    /// keeping a copy of its capability in `local_slot` to reach it
+    /// by, and return its slots. Slot 0 of each leaf is left out, so
+    /// that the first leaf's doesn't stand in for the null cptr.
+    ///
+    /// Fails if the kernel won't make or copy the leaf, handing back
+    /// the top CNode with the leaf taken out of it again, unless
+    /// deleting it failed too.
+    pub fn add_leaf(
+        self,
+        untyped: LocalCap<Untyped<Sum<LeafRadix, CNodeSlotBits>>>,
+        local_slot: LocalCNodeSlot,
+    ) -> Result<
+        (
+            ChildCNodeSlots<Diff<Pow<LeafRadix>, U1>>,
+            LocalCap<TwoLevelCNode<TopRadix, LeafRadix, Diff<FreeLeaves, U1>>>,
+        ),
+        (SeL4Error, Self),
+    >
+    where
+        FreeLeaves: Sub<U1>,
+        Diff<FreeLeaves, U1>: Unsigned,
+
+        LeafRadix: _Pow,
+        Pow<LeafRadix>: Unsigned,
+        Pow<LeafRadix>: Sub<U1>,
+        Diff<Pow<LeafRadix>, U1>: Unsigned,
+
+        LeafRadix: Add<CNodeSlotBits>,
+        Sum<LeafRadix, CNodeSlotBits>: Unsigned,
+    {
+        let leaf = self.cap_data.next_leaf;
+        let (local_cptr, local_offset, _) = local_slot.elim();
+
+        // With no depth given, the top CNode itself is where the leaf
+        // lands. The leaf's capability there has no guard, as the top
+        // CNode's covers the bits above both levels.
+        let retyped = unsafe {
+            seL4_Untyped_Retype(
+                untyped.cptr,                            // _service
+                api_object_seL4_CapTableObject as usize, // type
+                LeafRadix::USIZE,                        // size_bits
+                self.cptr,                               // root
+                0,                                       // index
+                0,                                       // depth
+                leaf,                                    // offset
+                1,                                       // num_objects
+            )
+        };
+        if let Err(e) = retyped.as_result() {
+            return Err((SeL4Error::UntypedRetype(e), self));
+        }
+
+        // Looking up the leaf's slot in the top CNode resolves the top
+        // CNode's guard and radix, but none of the leaf's.
+        let minted = unsafe {
+            let guard_data = seL4_CNode_CapData_new(
+                leaf as _,                                        // guard
+                (seL4_WordBits as usize - LeafRadix::USIZE) as _, // guard size in bits
+            )
+            .words[0];
+            seL4_CNode_Mint(
+                local_cptr,                          // _service
+                local_offset,                        // dest index
+                seL4_WordBits as u8,                 // dest depth
+                self.cptr,                           // src_root
+                leaf,                                // src_index
+                seL4_WordBits as u8 - LeafRadix::U8, // src_depth
+                CapRights::RW.into(),                // rights
+                guard_data as usize,                 // badge or guard
+            )
+        };
+        if let Err(e) = minted.as_result() {
+            // Free the leaf's slot in the top CNode for another try.
+            let _ =
+                unsafe { seL4_CNode_Delete(self.cptr, leaf, seL4_WordBits as u8 - LeafRadix::U8) };
+            return Err((SeL4Error::CNodeMint(e), self));
+        }
+
+        Ok((
+            CNodeSlots::internal_new(local_offset, (leaf << LeafRadix::USIZE) + 1),
+            Cap {
+                cptr: self.cptr,
+                cap_data: TwoLevelCNode {
+                    next_leaf: leaf + 1,
+                    _top_radix: PhantomData,
+                    _leaf_radix: PhantomData,
+                    _free_leaves: PhantomData,
+                },
+                _role: PhantomData,
+            },
+        ))
+    }
+
+    /// The CSpace root to hand to the child's thread, for instance
+    /// through `StandardProcess::new`. The leaves added so far are
+    /// all it will have.
+    pub fn into_cspace_root(self) -> LocalCap<ChildCNode>
+    where
+        TopRadix: Add<LeafRadix>,
+        Sum<TopRadix, LeafRadix>: Unsigned,
+    {
+        Cap {
+            cptr: self.cptr,
+            cap_data: CNode {
+                radix: Sum::<TopRadix, LeafRadix>::U8,
+                _role: PhantomData::<role::Child>,
+            },
+            _role: PhantomData,
+        }
+    }
+}
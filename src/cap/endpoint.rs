@@ -2,7 +2,7 @@ use typenum::*;
 
 use selfe_sys::*;
 
-use crate::cap::{CapType, CopyAliasable, DirectRetype, Mintable, PhantomCap};
+use crate::cap::{CapType, CopyAliasable, Delible, DirectRetype, Mintable, PhantomCap};
 
 #[derive(Debug)]
 pub struct Endpoint {}
@@ -26,6 +26,8 @@ impl<'a> From<&'a Endpoint> for Endpoint {
 
 impl Mintable for Endpoint {}
 
+impl Delible for Endpoint {}
+
 impl DirectRetype for Endpoint {
     type SizeBits = U4;
     fn sel4_type_id() -> usize {
@@ -1,8 +1,15 @@
+#[cfg(not(KernelIsMCS))]
 use crate::arch;
-use crate::cap::{CNodeSlotsData, Cap, CapType, LocalCNodeSlot, LocalCap};
+#[cfg(KernelIsMCS)]
+use crate::cap::Reply;
+#[cfg(not(KernelIsMCS))]
+use crate::cap::{CNodeSlotsData, LocalCNodeSlot};
+use crate::cap::{Cap, CapType, LocalCap};
+#[cfg(not(KernelIsMCS))]
 use crate::error::{ErrorExt, SeL4Error};
 use core::marker::PhantomData;
 use selfe_sys::*;
+#[cfg(not(KernelIsMCS))]
 use typenum::*;
 
 #[derive(Debug)]
@@ -11,12 +18,19 @@ use typenum::*;
 /// source's execution. After that, it destroys itself, and gives you back the
 /// cnode slot where it was living. Or you can just destroy it, to get the cnode
 /// slot back.
+///
+/// On MCS kernels there is no reply capability held by the TCB to save;
+/// instead the reply lands in the `Reply` object the fault was received
+/// with (see `FaultSink::wait_for_fault_with_reply`), and resuming the
+/// faulted thread hands that `Reply` back for the next receive.
 pub struct FaultReplyEndpoint {
+    #[cfg(not(KernelIsMCS))]
     original_slot_cptr: usize,
 }
 
 impl CapType for FaultReplyEndpoint {}
 
+#[cfg(not(KernelIsMCS))]
 impl LocalCap<FaultReplyEndpoint> {
     /// Save the TCB reply capability into the given CNode slot. This expects to
     /// be Used only in response to a Fault.
@@ -68,3 +82,29 @@ impl LocalCap<FaultReplyEndpoint> {
         self.to_slot()
     }
 }
+
+#[cfg(KernelIsMCS)]
+impl LocalCap<FaultReplyEndpoint> {
+    /// Wrap a reply object that a fault has just been received into.
+    pub(crate) fn from_reply(reply: LocalCap<Reply>) -> LocalCap<FaultReplyEndpoint> {
+        Cap {
+            cptr: reply.cptr,
+            _role: PhantomData,
+            cap_data: FaultReplyEndpoint {},
+        }
+    }
+
+    /// Resume the thread that that caused the fault, consume the cap, and
+    /// return the reply object, ready to receive the next fault with.
+    pub fn resume_faulted_thread(self) -> LocalCap<Reply> {
+        let empty_msg = unsafe { seL4_MessageInfo_new(0, 0, 0, 0) };
+
+        unsafe { seL4_Send(self.cptr, empty_msg) };
+
+        Cap {
+            cptr: self.cptr,
+            _role: PhantomData,
+            cap_data: Reply {},
+        }
+    }
+}
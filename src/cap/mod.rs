@@ -11,6 +11,7 @@ mod asid_control;
 mod asid_pool;
 mod badge;
 mod cnode;
+mod cspace;
 mod endpoint;
 mod fault_reply_endpoint;
 mod irq_control;
@@ -18,6 +19,10 @@ pub mod irq_handler;
 mod notification;
 mod page;
 mod page_table;
+#[cfg(KernelIsMCS)]
+mod reply;
+#[cfg(KernelIsMCS)]
+mod sched_context;
 mod tcb;
 mod untyped;
 
@@ -26,6 +31,7 @@ pub use asid_control::*;
 pub use asid_pool::*;
 pub use badge::*;
 pub use cnode::*;
+pub use cspace::*;
 pub use endpoint::*;
 pub use fault_reply_endpoint::*;
 pub use irq_control::*;
@@ -33,6 +39,10 @@ pub use irq_handler::*;
 pub use notification::*;
 pub use page::*;
 pub use page_table::*;
+#[cfg(KernelIsMCS)]
+pub use reply::*;
+#[cfg(KernelIsMCS)]
+pub use sched_context::*;
 pub use tcb::*;
 pub use untyped::*;
 
@@ -58,6 +68,11 @@ pub mod role {
 pub trait DirectRetype {
     type SizeBits: Unsigned;
     fn sel4_type_id() -> usize;
+    /// The `size_bits` argument handed to `seL4_Untyped_Retype`, which
+    /// the kernel ignores for all but its variable-sized objects.
+    fn sel4_size_bits() -> usize {
+        0
+    }
 }
 
 /// Marker trait for CapType implementing structs to indicate that
@@ -511,6 +526,90 @@ impl<Role: CNodeRole, CT: CapType> Cap<CT, Role> {
         .as_result()
         .map_err(SeL4Error::CNodeDelete)
     }
+
+    /// Delete a capability and return the slot it occupied in `cnode`,
+    /// to be reused or handed back with `RecyclingCNodeSlots::free`.
+    pub fn delete_into_slot(
+        self,
+        cnode: &LocalCap<CNode<Role>>,
+    ) -> Result<CNodeSlot<Role>, SeL4Error>
+    where
+        CT: Delible,
+    {
+        unsafe {
+            seL4_CNode_Delete(
+                cnode.cptr,          // _service
+                self.cptr,           // index
+                seL4_WordBits as u8, // depth
+            )
+        }
+        .as_result()
+        .map_err(SeL4Error::CNodeDelete)?;
+        Ok(Cap::internal_new(cnode.cptr, self.cptr))
+    }
+}
+
+impl<CT: CapType> LocalCap<CT> {
+    /// Copy a capability into another CNode such that it can be taken
+    /// back later with the returned `RevocationHandle`, as for lending
+    /// a driver a device or endpoint for the length of a session.
+    /// Taking it back only touches the granted copy, so it may be
+    /// granted from an original or a copy alike.
+    pub fn grant_revocable<DestRole: CNodeRole>(
+        &self,
+        src_cnode: &LocalCap<LocalCNode>,
+        dest_slot: CNodeSlot<DestRole>,
+        rights: CapRights,
+    ) -> Result<(Cap<CT::CopyOutput, DestRole>, RevocationHandle<DestRole>), SeL4Error>
+    where
+        CT: CopyAliasable,
+    {
+        let dest_cnode_cptr = dest_slot.cptr;
+        let granted = self.copy(src_cnode, dest_slot, rights)?;
+        let handle = RevocationHandle {
+            dest_cnode_cptr,
+            dest_offset: granted.cptr,
+            _role: PhantomData,
+        };
+        Ok((granted, handle))
+    }
+}
+
+/// The means of taking back a capability handed out by
+/// `grant_revocable`.
+#[derive(Debug)]
+pub struct RevocationHandle<DestRole: CNodeRole> {
+    dest_cnode_cptr: usize,
+    dest_offset: usize,
+    _role: PhantomData<DestRole>,
+}
+
+impl<DestRole: CNodeRole> RevocationHandle<DestRole> {
+    /// Delete the granted capability, along with whatever the kernel
+    /// counts as derived from it, and return the slot it was granted
+    /// into. The capability it was granted from, and any other copies
+    /// of that, are left alone.
+    pub fn revoke(self) -> Result<CNodeSlot<DestRole>, SeL4Error> {
+        unsafe {
+            seL4_CNode_Revoke(
+                self.dest_cnode_cptr, // _service
+                self.dest_offset,     // index
+                seL4_WordBits as u8,  // depth
+            )
+        }
+        .as_result()
+        .map_err(SeL4Error::CNodeRevoke)?;
+        unsafe {
+            seL4_CNode_Delete(
+                self.dest_cnode_cptr, // _service
+                self.dest_offset,     // index
+                seL4_WordBits as u8,  // depth
+            )
+        }
+        .as_result()
+        .map_err(SeL4Error::CNodeDelete)?;
+        Ok(Cap::internal_new(self.dest_cnode_cptr, self.dest_offset))
+    }
 }
 
 mod private {
@@ -528,6 +627,12 @@ mod private {
     impl SealedCapType for ThreadPriorityAuthority {}
     impl SealedCapType for Endpoint {}
     impl SealedCapType for FaultReplyEndpoint {}
+    #[cfg(KernelIsMCS)]
+    impl SealedCapType for Reply {}
+    #[cfg(KernelIsMCS)]
+    impl SealedCapType for SchedContext {}
+    #[cfg(KernelIsMCS)]
+    impl SealedCapType for SchedControl {}
     impl SealedCapType for Notification {}
     impl<FreeSlots: Unsigned> SealedCapType for ASIDPool<FreeSlots> {}
     impl SealedCapType for IRQControl {}
@@ -548,8 +653,8 @@ mod private {
     |      x64      |    x86_64     |
     |               |    powerpc    |
     |               |   powerpc64   |
-    | RISC-V 32-bit |               |
-    | RISC-V 64-bit |               |
+    | RISC-V 32-bit |    riscv32    |
+    | RISC-V 64-bit |    riscv64    |
 
     Cf.
       - seL4 Manual 7.1.1
@@ -566,14 +671,13 @@ mod private {
        Manual 7.1.3)
 
     2. RISC-V's address space structure consists of only page
-       tables. If there someday exists first-class support for
-       RISC-V in Rust, we will need to pluck page directories out
-       from the `arch` mod and move them into the architecture
-       mods which use them. (Cf. seL4 Manual 7.1.1)
+       tables, so page directories are only sealed for the
+       architectures which have them. (Cf. seL4 Manual 7.1.1)
     */
     mod arch {
         use super::super::*;
         use crate::arch::cap::*;
+        #[cfg(not(target_arch = "riscv64"))]
         impl super::SealedCapType for PageDirectory {}
         impl super::SealedCapType for PageTable {}
 
@@ -1,6 +1,8 @@
 use selfe_sys::*;
 
-use crate::cap::{Badge, CapType, CopyAliasable, DirectRetype, LocalCap, Mintable, PhantomCap};
+use crate::cap::{
+    Badge, CapType, CopyAliasable, Delible, DirectRetype, LocalCap, Mintable, PhantomCap,
+};
 
 #[derive(Debug)]
 pub struct Notification {}
@@ -24,6 +26,8 @@ impl<'a> From<&'a Notification> for Notification {
 
 impl Mintable for Notification {}
 
+impl Delible for Notification {}
+
 impl DirectRetype for Notification {
     type SizeBits = crate::arch::NotificationBits;
     fn sel4_type_id() -> usize {
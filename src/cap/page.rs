@@ -1,6 +1,9 @@
+use core::marker::PhantomData;
+
 use crate::arch::PageBytes;
 use crate::cap::{
-    CNodeRole, Cap, CapRangeDataReconstruction, CapType, CopyAliasable, InternalASID, Movable,
+    CNodeRole, Cap, CapRangeDataReconstruction, CapType, CopyAliasable, DirectRetype, InternalASID,
+    Movable, PhantomCap,
 };
 use crate::userland::CapRights;
 use typenum::Unsigned;
@@ -24,6 +27,7 @@ pub mod page_state {
         pub(crate) vaddr: usize,
         pub(crate) asid: InternalASID,
         pub(crate) rights: CapRights,
+        pub(crate) vm_attributes: crate::arch::VMAttributes,
     }
     impl super::PageState for Mapped {
         fn offset_by(&self, bytes: usize) -> Option<Self> {
@@ -31,6 +35,7 @@ pub mod page_state {
                 vaddr: b,
                 asid: self.asid,
                 rights: self.rights,
+                vm_attributes: self.vm_attributes,
             })
         }
     }
@@ -80,6 +85,41 @@ impl<CapRole: CNodeRole> Cap<Page<page_state::Mapped>, CapRole> {
     }
 }
 
+/// A frame of memory larger than a `Page`, which the paging structures
+/// map with a single entry further up the tree. The sizes on offer
+/// depend on the architecture; see the `frame_size` module in
+/// `arch::cap`.
+#[derive(Clone, Debug)]
+pub struct Frame<Size: FrameSize, State: PageState = page_state::Unmapped> {
+    pub(crate) state: State,
+    pub(crate) _size: PhantomData<Size>,
+}
+
+pub trait FrameSize {
+    type SizeBits: Unsigned;
+    fn sel4_type_id() -> usize;
+}
+
+impl<Size: FrameSize, State: PageState> CapType for Frame<Size, State> {}
+
+impl<Size: FrameSize, State: PageState> Movable for Frame<Size, State> {}
+
+impl<Size: FrameSize> DirectRetype for Frame<Size, page_state::Unmapped> {
+    type SizeBits = Size::SizeBits;
+    fn sel4_type_id() -> usize {
+        Size::sel4_type_id()
+    }
+}
+
+impl<Size: FrameSize> PhantomCap for Frame<Size, page_state::Unmapped> {
+    fn phantom_instance() -> Self {
+        Frame {
+            state: page_state::Unmapped {},
+            _size: PhantomData,
+        }
+    }
+}
+
 mod private {
     pub trait SealedPageState {}
     impl SealedPageState for super::page_state::Unmapped {}
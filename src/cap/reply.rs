@@ -0,0 +1,23 @@
+use selfe_sys::*;
+
+use crate::cap::{CapType, DirectRetype, PhantomCap};
+
+/// A reply object, which on MCS kernels stands in for the implicit
+/// reply capability held by the TCB of a receiving thread.
+#[derive(Debug)]
+pub struct Reply {}
+
+impl CapType for Reply {}
+
+impl PhantomCap for Reply {
+    fn phantom_instance() -> Self {
+        Self {}
+    }
+}
+
+impl DirectRetype for Reply {
+    type SizeBits = crate::arch::ReplyBits;
+    fn sel4_type_id() -> usize {
+        api_object_seL4_ReplyObject as usize
+    }
+}
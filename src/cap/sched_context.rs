@@ -0,0 +1,67 @@
+use selfe_sys::*;
+
+use crate::cap::{CapType, DirectRetype, LocalCap, PhantomCap};
+use crate::error::{ErrorExt, SeL4Error};
+
+/// A scheduling context, which holds the budget and period that bound how
+/// much processor time a thread bound to it may consume.
+#[derive(Debug)]
+pub struct SchedContext {}
+
+impl CapType for SchedContext {}
+
+impl PhantomCap for SchedContext {
+    fn phantom_instance() -> Self {
+        Self {}
+    }
+}
+
+impl DirectRetype for SchedContext {
+    type SizeBits = crate::arch::SchedContextBits;
+    fn sel4_type_id() -> usize {
+        api_object_seL4_SchedContextObject as usize
+    }
+    fn sel4_size_bits() -> usize {
+        use typenum::Unsigned;
+        Self::SizeBits::USIZE
+    }
+}
+
+/// The authority to configure scheduling contexts for a single core.
+/// The kernel hands one of these to the root task per core; they
+/// cannot be created by retyping.
+#[derive(Debug)]
+pub struct SchedControl {}
+
+impl CapType for SchedControl {}
+
+impl PhantomCap for SchedControl {
+    fn phantom_instance() -> Self {
+        Self {}
+    }
+}
+
+impl LocalCap<SchedControl> {
+    /// Set the budget and period, both in microseconds, of a scheduling
+    /// context. A thread bound to it may run for at most `budget` out of
+    /// every `period`.
+    pub fn configure(
+        &self,
+        sched_context: &mut LocalCap<SchedContext>,
+        budget: u64,
+        period: u64,
+    ) -> Result<(), SeL4Error> {
+        unsafe {
+            seL4_SchedControl_Configure(
+                self.cptr,          // _service
+                sched_context.cptr, // schedcontext
+                budget,             // budget
+                period,             // period
+                0,                  // extra_refills
+                0,                  // badge
+            )
+        }
+        .as_result()
+        .map_err(SeL4Error::SchedControlConfigure)
+    }
+}
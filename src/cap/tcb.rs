@@ -1,10 +1,12 @@
 use selfe_sys::*;
 
 use crate::cap::{
-    page_state, role, CapType, ChildCNode, CopyAliasable, DirectRetype, LocalCap, Page, PhantomCap,
+    page_state, CapType, ChildCNode, CopyAliasable, DirectRetype, LocalCap, Page, PhantomCap,
 };
+#[cfg(KernelIsMCS)]
+use crate::cap::{SchedContext, SchedControl};
 use crate::error::{ErrorExt, SeL4Error};
-use crate::userland::FaultSource;
+use crate::userland::{FaultSource, FaultSourceRole};
 
 #[derive(Debug)]
 pub struct ThreadControlBlock {}
@@ -67,10 +69,13 @@ impl LocalCap<ThreadControlBlock> {
         unsafe { core::mem::transmute(self) }
     }
 
+    /// On an MCS kernel the fault source is copied into the TCB, and so
+    /// is one in the local CSpace rather than the child's; see
+    /// `FaultSourceRole`.
     pub fn configure(
         &mut self,
         cspace_root: LocalCap<ChildCNode>,
-        fault_source: Option<FaultSource<role::Child>>,
+        fault_source: Option<FaultSource<FaultSourceRole>>,
         virtual_address_space_root: &LocalCap<crate::arch::PagingRoot>, // vspace_root,
         ipc_buffer: Option<LocalCap<Page<page_state::Mapped>>>,
     ) -> Result<(), SeL4Error> {
@@ -90,6 +95,7 @@ impl LocalCap<ThreadControlBlock> {
             (seL4_CapNull as usize, 0)
         };
 
+        #[cfg(not(KernelIsMCS))]
         unsafe {
             seL4_TCB_Configure(
                 self.cptr,
@@ -103,7 +109,90 @@ impl LocalCap<ThreadControlBlock> {
             )
         }
         .as_result()
-        .map_err(SeL4Error::TCBConfigure)
+        .map_err(SeL4Error::TCBConfigure)?;
+
+        // MCS kernels take the fault endpoint alongside the address spaces
+        // rather than in `seL4_TCB_Configure`.
+        #[cfg(KernelIsMCS)]
+        unsafe {
+            seL4_TCB_SetSpace(
+                self.cptr,
+                fault_source.map_or(seL4_CapNull as usize, |source| source.endpoint.cptr), // fault_ep.cptr,
+                cspace_root.cptr,
+                cspace_root_data,
+                virtual_address_space_root.cptr,
+                seL4_NilData as usize, // vspace_root_data, always 0, reserved by kernel?
+            )
+            .as_result()
+            .map_err(SeL4Error::TCBConfigure)?;
+
+            seL4_TCB_SetIPCBuffer(
+                self.cptr,
+                buffer_vaddr, // buffer address
+                buffer_cap,   // bufferFrame capability
+            )
+            .as_result()
+            .map_err(SeL4Error::TCBConfigure)?;
+        }
+
+        Ok(())
+    }
+
+    /// Configure this TCB as with `configure`, and additionally bind it to
+    /// a scheduling context which `sched_control` grants `budget`
+    /// microseconds of execution in every `period` microseconds.
+    ///
+    /// The scheduling context is consumed, as it now belongs to this TCB.
+    #[cfg(KernelIsMCS)]
+    pub fn configure_with_sched_context(
+        &mut self,
+        cspace_root: LocalCap<ChildCNode>,
+        fault_source: Option<FaultSource<FaultSourceRole>>,
+        virtual_address_space_root: &LocalCap<crate::arch::PagingRoot>, // vspace_root,
+        ipc_buffer: Option<LocalCap<Page<page_state::Mapped>>>,
+        sched_control: &LocalCap<SchedControl>,
+        sched_context: LocalCap<SchedContext>,
+        budget: u64,
+        period: u64,
+        tpa: &LocalCap<ThreadPriorityAuthority>,
+        priority: usize,
+    ) -> Result<(), SeL4Error> {
+        self.configure(
+            cspace_root,
+            fault_source,
+            virtual_address_space_root,
+            ipc_buffer,
+        )?;
+
+        // `seL4_TCB_SetSchedParams` would set the fault endpoint all over
+        // again, so set the rest piecemeal.
+        unsafe {
+            seL4_TCB_SetMCPriority(self.cptr, tpa.cptr, priority)
+                .as_result()
+                .map_err(SeL4Error::TCBSetMCPriority)?;
+        }
+        self.set_priority(tpa, priority)?;
+        self.bind_sched_context(sched_control, sched_context, budget, period)
+    }
+
+    /// Bind this TCB to a scheduling context which `sched_control` grants
+    /// `budget` microseconds of execution in every `period` microseconds.
+    /// On an MCS kernel, a thread only runs while bound to one.
+    ///
+    /// The scheduling context is consumed, as it now belongs to this TCB.
+    #[cfg(KernelIsMCS)]
+    pub fn bind_sched_context(
+        &mut self,
+        sched_control: &LocalCap<SchedControl>,
+        mut sched_context: LocalCap<SchedContext>,
+        budget: u64,
+        period: u64,
+    ) -> Result<(), SeL4Error> {
+        sched_control.configure(&mut sched_context, budget, period)?;
+
+        unsafe { seL4_SchedContext_Bind(sched_context.cptr, self.cptr) }
+            .as_result()
+            .map_err(SeL4Error::SchedContextBind)
     }
 
     /// Set this TCB's priority.
@@ -116,4 +205,12 @@ impl LocalCap<ThreadControlBlock> {
             .as_result()
             .map_err(SeL4Error::TCBSetPriority)
     }
+
+    /// Set the value this TCB's thread finds in its thread pointer
+    /// register.
+    pub fn set_tls_base(&mut self, tls_base: usize) -> Result<(), SeL4Error> {
+        unsafe { seL4_TCB_SetTLSBase(self.cptr, tls_base) }
+            .as_result()
+            .map_err(SeL4Error::TCBSetTLSBase)
+    }
 }
@@ -7,7 +7,7 @@ use typenum::operator_aliases::{Diff, Prod, Sum};
 
 use typenum::*;
 
-use crate::arch::{CNodeSlotBits, PageBits};
+use crate::arch::{CNodeSlotBits, PageBits, FRAME_SIZES};
 use crate::cap::{
     page_state, role, CNode, CNodeRole, CNodeSlot, CNodeSlots, CNodeSlotsError, Cap, CapRange,
     CapType, ChildCNode, ChildCNodeSlots, Delible, DirectRetype, LocalCNode, LocalCNodeSlot,
@@ -118,27 +118,52 @@ impl<Kind: MemoryKind> LocalCap<WUntyped<Kind>> {
         self,
         slots: &mut Cap<WCNodeSlotsData<CRole>, role::Local>,
     ) -> Result<WeakCapRange<Page<page_state::Unmapped>, CRole>, RetypeError> {
-        if self.cap_data.size_bits < PageBits::U8 {
+        self.retype_frames_of(slots, PageBits::U8, Page::sel4_type_id())
+    }
+
+    /// Retype into as few frames as possible, all of the largest size
+    /// the untyped can hold. Returns them along with their size in
+    /// bits.
+    pub(crate) fn retype_largest_frames<CRole: CNodeRole>(
+        self,
+        slots: &mut Cap<WCNodeSlotsData<CRole>, role::Local>,
+    ) -> Result<(WeakCapRange<Page<page_state::Unmapped>, CRole>, u8), RetypeError> {
+        let (frame_bits, type_id) = FRAME_SIZES
+            .iter()
+            .cloned()
+            .find(|(frame_bits, _)| *frame_bits <= self.cap_data.size_bits)
+            .ok_or(RetypeError::NotBigEnough)?;
+        self.retype_frames_of(slots, frame_bits, type_id)
+            .map(|caps| (caps, frame_bits))
+    }
+
+    fn retype_frames_of<CRole: CNodeRole>(
+        self,
+        slots: &mut Cap<WCNodeSlotsData<CRole>, role::Local>,
+        frame_bits: u8,
+        type_id: usize,
+    ) -> Result<WeakCapRange<Page<page_state::Unmapped>, CRole>, RetypeError> {
+        if self.cap_data.size_bits < frame_bits {
             return Err(RetypeError::NotBigEnough);
         }
-        let num_pages = 1 << usize::from(self.cap_data.size_bits - PageBits::U8);
-        if num_pages > KernelRetypeFanOutLimit::USIZE {
+        let num_frames = 1 << usize::from(self.cap_data.size_bits - frame_bits);
+        if num_frames > KernelRetypeFanOutLimit::USIZE {
             return Err(RetypeError::KernelRetypeFanOutLimit);
         }
-        // TODO - REVIEW - Do we need more constraints on num_pages?
+        // TODO - REVIEW - Do we need more constraints on num_frames?
         let dest_slots = slots
-            .alloc(num_pages)
+            .alloc(num_frames)
             .map_err(RetypeError::CNodeSlotsError)?;
         unsafe {
             seL4_Untyped_Retype(
                 self.cptr,                  // _service
-                Page::sel4_type_id(),       // type
+                type_id,                    // type
                 0,                          // size_bits
                 dest_slots.cptr,            // root
                 0,                          // index
                 0,                          // depth
                 dest_slots.cap_data.offset, // offset
-                num_pages,                  // num_objects
+                num_frames,                 // num_objects
             )
             .as_result()
             .map_err(SeL4Error::UntypedRetype)?;
@@ -151,7 +176,7 @@ impl<Kind: MemoryKind> LocalCap<WUntyped<Kind>> {
                 /* TODO - kind piping
                  *memory_kind: self.cap_data.kind, */
             },
-            num_pages,
+            num_frames,
         ))
     }
 }
@@ -170,7 +195,7 @@ impl LocalCap<WUntyped<memory_kind::General>> {
             seL4_Untyped_Retype(
                 self.cptr,            // _service
                 D::sel4_type_id(),    // type
-                0,                    // size_bits
+                D::sel4_size_bits(),  // size_bits
                 slots.cptr,           // root
                 0,                    // index
                 0,                    // depth
@@ -587,14 +612,14 @@ impl<BitSize: Unsigned> LocalCap<Untyped<BitSize, memory_kind::General>> {
 
         unsafe {
             seL4_Untyped_Retype(
-                self.cptr,                     // _service
-                TargetCapType::sel4_type_id(), // type
-                0,                             // size_bits
-                dest_cptr,                     // root
-                0,                             // index
-                0,                             // depth
-                dest_offset,                   // offset
-                1,                             // num_objects
+                self.cptr,                       // _service
+                TargetCapType::sel4_type_id(),   // type
+                TargetCapType::sel4_size_bits(), // size_bits
+                dest_cptr,                       // root
+                0,                               // index
+                0,                               // depth
+                dest_offset,                     // offset
+                1,                               // num_objects
             )
         }
         .as_result()
@@ -635,6 +660,7 @@ impl<BitSize: Unsigned> LocalCap<Untyped<BitSize, memory_kind::General>> {
                 self.cptr,
                 Count::USIZE,
                 TargetCapType::sel4_type_id(),
+                TargetCapType::sel4_size_bits(),
                 dest_cptr,
                 dest_offset,
             )?;
@@ -646,13 +672,14 @@ impl<BitSize: Unsigned> LocalCap<Untyped<BitSize, memory_kind::General>> {
         self_cptr: usize,
         count: usize,
         type_id: usize,
+        size_bits: usize,
         dest_cptr: usize,
         dest_offset: usize,
     ) -> Result<(), SeL4Error> {
         seL4_Untyped_Retype(
             self_cptr,   // _service
             type_id,     // type
-            0,           // size_bits
+            size_bits,   // size_bits
             dest_cptr,   // root
             0,           // index
             0,           // depth
@@ -684,54 +711,12 @@ impl<BitSize: Unsigned> LocalCap<Untyped<BitSize, memory_kind::General>> {
         Sum<ChildRadix, CNodeSlotBits>: Unsigned,
         BitSize: IsGreaterOrEqual<Sum<ChildRadix, CNodeSlotBits>>,
     {
-        let (scratch_slot, local_slots) = local_slots.alloc::<U1>();
-        let (dest_slot, _) = local_slots.alloc::<U1>();
-
-        let (scratch_cptr, scratch_offset, _) = scratch_slot.elim();
-        let (dest_cptr, dest_offset, _) = dest_slot.elim();
-
-        unsafe {
-            // Retype to fill the scratch slot with a fresh CNode
-            seL4_Untyped_Retype(
-                self.cptr,                               // _service
-                api_object_seL4_CapTableObject as usize, // type
-                ChildRadix::to_usize(),                  // size_bits
-                scratch_cptr,                            // root
-                0,                                       // index
-                0,                                       // depth
-                scratch_offset,                          // offset
-                1,                                       // num_objects
-            )
-            .as_result()
-            .map_err(SeL4Error::UntypedRetype)?;
-
-            // In order to set the guard (for the sake of our C-pointer simplification
-            // scheme), mutate the CNode in the scratch slot, which copies the
-            // CNode into a second slot
-            let guard_data = seL4_CNode_CapData_new(
-                0,                                                      // guard
-                (seL4_WordBits - ChildRadix::to_usize() as usize) as _, // guard size in bits
-            )
-            .words[0];
-
-            seL4_CNode_Mutate(
-                dest_cptr,           // _service: seL4_CNode,
-                dest_offset,         // dest_index: seL4_Word,
-                seL4_WordBits as u8, // dest_depth: seL4_Uint8,
-                scratch_cptr,        // src_root: seL4_CNode,
-                scratch_offset,      // src_index: seL4_Word,
-                seL4_WordBits as u8, // src_depth: seL4_Uint8,
-                guard_data as usize, // badge or guard: seL4_Word,
-            )
-            .as_result()
-            .map_err(SeL4Error::CNodeMutate)?;
-
-            // TODO - If we wanted to make more efficient use of our available
-            // slots at the cost of complexity, we could swap the
-            // two created CNodes, then delete the one with
-            // the incorrect guard (the one originally occupying the scratch
-            // slot).
-        }
+        let dest_offset = retype_guarded_cnode(
+            self.cptr,
+            ChildRadix::U8,
+            seL4_WordBits as u8 - ChildRadix::U8,
+            local_slots,
+        )?;
 
         Ok((
             Cap {
@@ -749,6 +734,67 @@ impl<BitSize: Unsigned> LocalCap<Untyped<BitSize, memory_kind::General>> {
     }
 }
 
+/// Retype a CNode of `1 << radix` slots whose capability skips over
+/// `guard_bits` of each cptr, leaving it in the second of `local_slots`
+/// and returning that slot's offset.
+pub(crate) fn retype_guarded_cnode(
+    untyped_cptr: usize,
+    radix: u8,
+    guard_bits: u8,
+    local_slots: LocalCNodeSlots<U2>,
+) -> Result<usize, SeL4Error> {
+    let (scratch_slot, local_slots) = local_slots.alloc::<U1>();
+    let (dest_slot, _) = local_slots.alloc::<U1>();
+
+    let (scratch_cptr, scratch_offset, _) = scratch_slot.elim();
+    let (dest_cptr, dest_offset, _) = dest_slot.elim();
+
+    unsafe {
+        // Retype to fill the scratch slot with a fresh CNode
+        seL4_Untyped_Retype(
+            untyped_cptr,                            // _service
+            api_object_seL4_CapTableObject as usize, // type
+            usize::from(radix),                      // size_bits
+            scratch_cptr,                            // root
+            0,                                       // index
+            0,                                       // depth
+            scratch_offset,                          // offset
+            1,                                       // num_objects
+        )
+        .as_result()
+        .map_err(SeL4Error::UntypedRetype)?;
+
+        // In order to set the guard (for the sake of our C-pointer simplification
+        // scheme), mutate the CNode in the scratch slot, which copies the
+        // CNode into a second slot
+        let guard_data = seL4_CNode_CapData_new(
+            0,               // guard
+            guard_bits as _, // guard size in bits
+        )
+        .words[0];
+
+        seL4_CNode_Mutate(
+            dest_cptr,           // _service: seL4_CNode,
+            dest_offset,         // dest_index: seL4_Word,
+            seL4_WordBits as u8, // dest_depth: seL4_Uint8,
+            scratch_cptr,        // src_root: seL4_CNode,
+            scratch_offset,      // src_index: seL4_Word,
+            seL4_WordBits as u8, // src_depth: seL4_Uint8,
+            guard_data as usize, // badge or guard: seL4_Word,
+        )
+        .as_result()
+        .map_err(SeL4Error::CNodeMutate)?;
+
+        // TODO - If we wanted to make more efficient use of our available
+        // slots at the cost of complexity, we could swap the
+        // two created CNodes, then delete the one with
+        // the incorrect guard (the one originally occupying the scratch
+        // slot).
+    }
+
+    Ok(dest_offset)
+}
+
 impl LocalCap<Untyped<PageBits, memory_kind::Device>> {
     /// The only thing memory_kind::Device memory can be used to make
     /// is a page/frame.
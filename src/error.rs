@@ -4,12 +4,14 @@ pub enum SeL4Error {
     TCBConfigure(KernelError),
     PageTableMap(KernelError),
     PageUpperDirectoryMap(KernelError),
+    PDPTMap(KernelError),
     PageDirectoryMap(KernelError),
     ASIDControlMakePool(KernelError),
     ASIDPoolAssign(KernelError),
     PageGetAddress(KernelError),
     PageMap(KernelError),
     PageUnmap(KernelError),
+    PageRemap(KernelError),
     CNodeCopy(KernelError),
     CNodeMint(KernelError),
     CNodeSaveCaller(KernelError),
@@ -17,6 +19,8 @@ pub enum SeL4Error {
     TCBReadRegisters(KernelError),
     TCBSetPriority(KernelError),
     TCBResume(KernelError),
+    TCBSuspend(KernelError),
+    TCBSetTLSBase(KernelError),
     CNodeMutate(KernelError),
     CNodeMove(KernelError),
     CNodeDelete(KernelError),
@@ -25,12 +29,18 @@ pub enum SeL4Error {
     IRQHandlerAck(KernelError),
     GetPageAddr(KernelError),
     PageCleanInvalidateData(KernelError),
+    PageCleanData(KernelError),
+    PageInvalidateData(KernelError),
+    PageUnifyInstruction(KernelError),
     CNodeRevoke(KernelError),
     VCPUInjectIRQ(KernelError),
     VCPUReadRegisters(KernelError),
     VCPUWriteRegisters(KernelError),
     VCPUBindTcb(KernelError),
     TCBBindNotification(KernelError),
+    SchedControlConfigure(KernelError),
+    TCBSetMCPriority(KernelError),
+    SchedContextBind(KernelError),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
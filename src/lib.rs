@@ -1,6 +1,8 @@
 #![no_std]
 #![recursion_limit = "256"]
 #![feature(proc_macro_hygiene)]
+#![feature(asm)]
+#![feature(global_asm)]
 #![allow(
     clippy::too_many_arguments,
     clippy::type_complexity,
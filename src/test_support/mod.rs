@@ -61,6 +61,9 @@ pub fn execute_tests<'t, R: types::TestReporter>(
         untyped,
         asid_pool,
         mut scratch,
+        vspace,
+        device_allocator,
+        allocator,
         mapped_memory_region,
         cnode,
         thread_authority,
@@ -83,11 +86,17 @@ pub fn execute_tests<'t, R: types::TestReporter>(
              inner_mapped_memory_region,
              inner_irq_control|
              -> Result<(), SeL4Error> {
+                let mut test_vspace = vspace.for_test();
+                let mut test_device_allocator = device_allocator.for_test();
+                let mut test_allocator = allocator.for_test();
                 let (name, outcome) = t(
                     inner_slots,
                     inner_untyped,
                     inner_asid_pool,
                     &mut scratch,
+                    &mut test_vspace,
+                    &mut test_device_allocator,
+                    &mut test_allocator,
                     inner_mapped_memory_region,
                     cnode,
                     thread_authority,
@@ -95,6 +104,9 @@ pub fn execute_tests<'t, R: types::TestReporter>(
                     user_image,
                     inner_irq_control,
                 );
+                vspace.reclaim(test_vspace)?;
+                device_allocator.reclaim(test_device_allocator)?;
+                allocator.reclaim(test_allocator)?;
                 reporter.report(name, outcome);
                 if outcome == types::TestOutcome::Success {
                     successes += 1;
@@ -1,9 +1,11 @@
-use selfe_sys::seL4_BootInfo;
+use selfe_sys::*;
 use typenum::*;
 
+use crate::alloc::micro_alloc::{Allocator, DeviceAllocator};
 use crate::arch;
 use crate::bootstrap::*;
 use crate::cap::*;
+use crate::error::{ErrorExt, SeL4Error};
 use crate::test_support::MaxMappedMemoryRegionBitSize;
 use crate::vspace::*;
 
@@ -11,8 +13,9 @@ pub struct Resources {
     pub(super) slots: LocalCNodeSlots<super::types::MaxTestCNodeSlots>,
     pub(super) untyped: LocalCap<Untyped<super::types::MaxTestUntypedSize>>,
     pub(super) asid_pool: LocalCap<ASIDPool<super::types::MaxTestASIDPoolSize>>,
-    #[allow(dead_code)]
-    pub(super) vspace: VSpace<vspace_state::Imaged, role::Local>,
+    pub(super) vspace: TestVSpace,
+    pub(super) device_allocator: DeviceAllocator,
+    pub(super) allocator: Allocator,
     pub(super) scratch: ScratchRegion,
     pub(super) mapped_memory_region: MappedMemoryRegion<
         super::types::MaxMappedMemoryRegionBitSize,
@@ -30,6 +33,9 @@ pub struct TestResourceRefs<'t> {
     pub(super) untyped: &'t mut LocalCap<Untyped<super::types::MaxTestUntypedSize>>,
     pub(super) asid_pool: &'t mut LocalCap<ASIDPool<super::types::MaxTestASIDPoolSize>>,
     pub(super) scratch: &'t mut ScratchRegion<crate::userland::process::DefaultStackPageCount>,
+    pub(super) vspace: &'t mut TestVSpace,
+    pub(super) device_allocator: &'t mut DeviceAllocator,
+    pub(super) allocator: &'t mut Allocator,
     pub(super) mapped_memory_region: &'t mut MappedMemoryRegion<
         super::types::MaxMappedMemoryRegionBitSize,
         crate::vspace::shared_status::Exclusive,
@@ -41,13 +47,105 @@ pub struct TestResourceRefs<'t> {
     pub(super) irq_control: &'t mut LocalCap<IRQControl>,
 }
 
+/// Harness state a test borrows through a `&mut` parameter. Rather
+/// than the harness' own, each test is handed a copy of its own, and
+/// whatever it did with that copy is undone once it returns, so no test
+/// sees what an earlier one left behind.
+pub(super) trait TestResource {
+    type ForTest;
+
+    /// A copy for a single test to use as its own.
+    fn for_test(&mut self) -> Self::ForTest;
+
+    /// Undo whatever a test did with its copy.
+    fn reclaim(&mut self, used: Self::ForTest) -> Result<(), SeL4Error>;
+}
+
+impl TestResource for Allocator {
+    type ForTest = Allocator;
+
+    fn for_test(&mut self) -> Allocator {
+        unsafe { self.dangerous_internal_alias() }
+    }
+
+    fn reclaim(&mut self, _used: Allocator) -> Result<(), SeL4Error> {
+        self.cptrs().try_for_each(revoke_root_cnode_cap)
+    }
+}
+
+impl TestResource for DeviceAllocator {
+    type ForTest = DeviceAllocator;
+
+    fn for_test(&mut self) -> DeviceAllocator {
+        unsafe { self.dangerous_internal_alias() }
+    }
+
+    fn reclaim(&mut self, _used: DeviceAllocator) -> Result<(), SeL4Error> {
+        self.cptrs().try_for_each(revoke_root_cnode_cap)
+    }
+}
+
+type TestVSpacePagingBits = U16;
+type TestVSpacePagingSlots = U1024;
+type TestVSpacePagingFallbackNextSize = Sum<U1, TestVSpacePagingBits>;
+
+/// The harness' own address space, along with what a test's copy of it
+/// builds paging layers out of.
+pub(super) struct TestVSpace {
+    vspace: VSpace<vspace_state::Imaged, role::Local>,
+    paging_untyped: LocalCap<Untyped<TestVSpacePagingBits>>,
+    paging_slots: LocalCNodeSlots<TestVSpacePagingSlots>,
+}
+
+impl TestResource for TestVSpace {
+    type ForTest = VSpace<vspace_state::Imaged, role::Local>;
+
+    fn for_test(&mut self) -> Self::ForTest {
+        unsafe {
+            self.vspace.dangerous_internal_alias(
+                Cap {
+                    cptr: self.paging_untyped.cptr,
+                    cap_data: WUntyped {
+                        size_bits: TestVSpacePagingBits::U8,
+                        kind: memory_kind::General {},
+                    },
+                    _role: core::marker::PhantomData,
+                },
+                LocalCNodeSlots::<TestVSpacePagingSlots>::internal_new(
+                    self.paging_slots.cptr,
+                    self.paging_slots.cap_data.offset,
+                )
+                .weaken(),
+            )
+        }
+    }
+
+    /// Revoking the paging untyped deletes every layer the test built,
+    /// taking down whatever it mapped through them. Everything else it
+    /// mapped goes when the memory backing it is revoked.
+    fn reclaim(&mut self, _used: Self::ForTest) -> Result<(), SeL4Error> {
+        revoke_root_cnode_cap(self.paging_untyped.cptr)
+    }
+}
+
+fn revoke_root_cnode_cap(cptr: usize) -> Result<(), SeL4Error> {
+    unsafe { seL4_CNode_Revoke(seL4_CapInitThreadCNode as usize, cptr, seL4_WordBits as u8) }
+        .as_result()
+        .map_err(SeL4Error::CNodeRevoke)
+}
+
 type PageFallbackNextSize = Sum<U1, <Page<page_state::Unmapped> as DirectRetype>::SizeBits>;
 type MappedMemoryRegionFallbackNextSize = Sum<U1, MaxMappedMemoryRegionBitSize>;
 
 impl Resources {
+    /// Set up the harness' resources out of `allocator`, handing tests
+    /// device memory from `device_allocator`. Both should come from the
+    /// same `bootstrap_allocators` call, so that none of the untypeds
+    /// they hold are aliased.
     pub fn with_debug_reporting(
         raw_boot_info: &'static seL4_BootInfo,
-        mut allocator: crate::alloc::micro_alloc::Allocator,
+        mut allocator: Allocator,
+        device_allocator: DeviceAllocator,
     ) -> Result<(Self, impl super::TestReporter), super::TestSetupError> {
         let (cnode, local_slots) = root_cnode(&raw_boot_info);
         // TODO - Refine sizes of VSpace untyped and slots
@@ -127,20 +225,41 @@ impl Resources {
         > = UnmappedMemoryRegion::new(memory_region_ut, memory_region_slots)?;
         let mapped_memory_region = root_vspace.map_region(
             unmapped_region,
-            crate::userland::CapRights::RW,
+            crate::userland::rights::RW,
             arch::vm_attributes::DEFAULT,
         )?;
+        let (extra_paging_slots, local_slots) = local_slots.alloc();
+        let paging_untyped = match allocator.get_untyped::<TestVSpacePagingBits>() {
+            Some(v) => v,
+            None => {
+                let ut_fallback = allocator
+                    .get_untyped::<TestVSpacePagingFallbackNextSize>()
+                    .ok_or_else(|| super::TestSetupError::InitialUntypedNotFound {
+                        bit_size: TestVSpacePagingFallbackNextSize::USIZE,
+                    })?;
+                let (ut_target, _) = ut_fallback.split(extra_paging_slots)?;
+                ut_target
+            }
+        };
+        let (paging_slots, local_slots) = local_slots.alloc();
         let (slots, _local_slots) = local_slots.alloc();
+        let untyped = allocator
+            .get_untyped::<super::types::MaxTestUntypedSize>()
+            .ok_or_else(|| super::TestSetupError::InitialUntypedNotFound {
+                bit_size: super::types::MaxTestUntypedSize::USIZE,
+            })?;
         Ok((
             Resources {
                 slots,
-                untyped: allocator
-                    .get_untyped::<super::types::MaxTestUntypedSize>()
-                    .ok_or_else(|| super::TestSetupError::InitialUntypedNotFound {
-                        bit_size: super::types::MaxTestUntypedSize::USIZE,
-                    })?,
+                untyped,
                 asid_pool,
-                vspace: root_vspace,
+                vspace: TestVSpace {
+                    vspace: root_vspace,
+                    paging_untyped,
+                    paging_slots,
+                },
+                device_allocator,
+                allocator,
                 scratch,
                 mapped_memory_region,
                 cnode,
@@ -163,6 +282,9 @@ impl Resources {
             untyped: &mut self.untyped,
             asid_pool: &mut self.asid_pool,
             scratch: &mut self.scratch,
+            vspace: &mut self.vspace,
+            device_allocator: &mut self.device_allocator,
+            allocator: &mut self.allocator,
             mapped_memory_region: &mut self.mapped_memory_region,
             cnode: &self.cnode,
             thread_authority: &self.thread_authority,
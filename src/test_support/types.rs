@@ -22,6 +22,9 @@ pub type RunTest = dyn Fn(
     LocalCap<Untyped<MaxTestUntypedSize>>,
     LocalCap<ASIDPool<MaxTestASIDPoolSize>>,
     &mut ScratchRegion<crate::userland::process::DefaultStackPageCount>,
+    &mut VSpace<vspace_state::Imaged, role::Local>,
+    &mut crate::alloc::micro_alloc::DeviceAllocator,
+    &mut crate::alloc::micro_alloc::Allocator,
     crate::vspace::MappedMemoryRegion<
         MaxMappedMemoryRegionBitSize,
         crate::vspace::shared_status::Exclusive,
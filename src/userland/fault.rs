@@ -7,7 +7,10 @@ use crate::cap::{
     role, Badge, CNodeRole, CNodeSlot, Cap, ChildCNodeSlot, DirectRetype, Endpoint, LocalCNode,
     LocalCNodeSlot, LocalCap, Untyped,
 };
+#[cfg(KernelIsMCS)]
+use crate::cap::{FaultReplyEndpoint, Reply};
 use crate::error::SeL4Error;
+use crate::userland::ipc::recv;
 use crate::userland::{type_length_in_words, CapRights, IPCBuffer, IPCError, MessageInfo, Sender};
 
 #[derive(Debug)]
@@ -57,12 +60,12 @@ impl<SinkRole: CNodeRole> FaultSinkSetup<SinkRole> {
         })
     }
 
-    pub fn add_fault_source(
+    pub fn add_fault_source<SourceRole: CNodeRole>(
         &self,
         local_cnode: &LocalCap<LocalCNode>,
-        fault_source_slot: ChildCNodeSlot,
+        fault_source_slot: CNodeSlot<SourceRole>,
         badge: Badge,
-    ) -> Result<FaultSource<role::Child>, FaultManagementError> {
+    ) -> Result<FaultSource<SourceRole>, FaultManagementError> {
         if fault_source_slot.cptr == self.sink_cspace_local_cptr {
             return Err(FaultManagementError::SelfFaultHandlingForbidden);
         }
@@ -92,14 +95,24 @@ pub fn setup_fault_endpoint_pair(
     local_cnode: &LocalCap<LocalCNode>,
     untyped: LocalCap<Untyped<<Endpoint as DirectRetype>::SizeBits>>,
     endpoint_slot: LocalCNodeSlot,
-    fault_source_slot: ChildCNodeSlot,
+    fault_source_slot: CNodeSlot<FaultSourceRole>,
     fault_sink_slot: ChildCNodeSlot,
-) -> Result<(FaultSource<role::Child>, FaultSink<role::Child>), FaultManagementError> {
+) -> Result<(FaultSource<FaultSourceRole>, FaultSink<role::Child>), FaultManagementError> {
     let setup = FaultSinkSetup::new(local_cnode, untyped, endpoint_slot, fault_sink_slot)?;
     let fault_source = setup.add_fault_source(local_cnode, fault_source_slot, Badge::from(0))?;
     Ok((fault_source, setup.sink()))
 }
 
+/// The CSpace a TCB's fault source is looked up in when the TCB is
+/// configured. On a classic kernel that's the thread's own, where the
+/// kernel goes looking for it at fault time. An MCS kernel instead
+/// copies the endpoint into the TCB, looking it up in the CSpace of
+/// the thread doing the configuring.
+#[cfg(not(KernelIsMCS))]
+pub type FaultSourceRole = role::Child;
+#[cfg(KernelIsMCS)]
+pub type FaultSourceRole = role::Local;
+
 /// The side of a fault endpoint that sends fault messages
 #[derive(Debug)]
 pub struct FaultSource<Role: CNodeRole> {
@@ -114,12 +127,36 @@ pub struct FaultSink<Role: CNodeRole> {
 
 impl FaultSink<role::Local> {
     pub fn wait_for_fault(&self) -> Fault {
-        let mut sender: usize = 0;
-        let info = unsafe { seL4_Recv(self.endpoint.cptr, &mut sender as *mut usize) }.into();
-        (info, Badge::from(sender)).into()
+        wait_for_fault(self.endpoint.cptr, seL4_CapNull as usize)
+    }
+
+    /// Wait for a fault, receiving the means to reply to it into `reply`.
+    /// This stands in for `FaultReplyEndpoint::save_caller_and_create` on
+    /// MCS kernels, where there is no implicit reply capability to save.
+    #[cfg(KernelIsMCS)]
+    pub fn wait_for_fault_with_reply(
+        &self,
+        reply: LocalCap<Reply>,
+    ) -> (Fault, LocalCap<FaultReplyEndpoint>) {
+        let fault = wait_for_fault(self.endpoint.cptr, reply.cptr);
+        (fault, LocalCap::<FaultReplyEndpoint>::from_reply(reply))
     }
 }
 
+impl FaultSinkSetup<role::Local> {
+    /// Wait for a fault from any of the sources added so far, while
+    /// retaining the ability to add more.
+    pub fn wait_for_fault(&self) -> Fault {
+        wait_for_fault(self.local_endpoint.cptr, seL4_CapNull as usize)
+    }
+}
+
+pub(crate) fn wait_for_fault(endpoint_cptr: usize, reply_cptr: usize) -> Fault {
+    let mut sender: usize = 0;
+    let info = unsafe { recv(endpoint_cptr, &mut sender, reply_cptr) }.into();
+    (info, Badge::from(sender)).into()
+}
+
 pub fn fault_or_message_channel<Msg: Sized, HandlerRole: CNodeRole>(
     local_cnode: &LocalCap<LocalCNode>,
     untyped: LocalCap<Untyped<<Endpoint as DirectRetype>::SizeBits>>,
@@ -128,7 +165,7 @@ pub fn fault_or_message_channel<Msg: Sized, HandlerRole: CNodeRole>(
     handler_slot: CNodeSlot<HandlerRole>,
 ) -> Result<
     (
-        FaultSource<role::Child>,
+        FaultSource<FaultSourceRole>,
         Sender<Msg, role::Child>,
         FaultOrMessageHandler<Msg, HandlerRole>,
     ),
@@ -152,13 +189,20 @@ pub fn fault_or_message_channel<Msg: Sized, HandlerRole: CNodeRole>(
         Badge::from(0),
     )?;
 
+    // An MCS kernel looks the fault source up locally, and the local
+    // endpoint is as good as the child's unbadged copy of it.
+    #[cfg(not(KernelIsMCS))]
+    let fault_source_cptr = child_endpoint_fault_source.cptr;
+    #[cfg(KernelIsMCS)]
+    let fault_source_cptr = local_endpoint.cptr;
+
     Ok((
         FaultSource {
             // Alias the endpoint harmlessly because FaultSource exposes no public methods
             // and is intended only to be used to tell the kernel where to route faults
             // for the child thread's TCB
             endpoint: Cap {
-                cptr: child_endpoint_fault_source.cptr,
+                cptr: fault_source_cptr,
                 _role: PhantomData,
                 cap_data: Endpoint {},
             },
@@ -193,7 +237,7 @@ impl<Msg: Sized> FaultOrMessageHandler<Msg, role::Local> {
         let mut sender: usize = 0;
         // Do a regular receive to seed our initial value
         let msg_info: MessageInfo =
-            unsafe { seL4_Recv(self.endpoint.cptr, &mut sender as *mut usize) }.into();
+            unsafe { recv(self.endpoint.cptr, &mut sender, seL4_CapNull as usize) }.into();
 
         let badge = Badge::from(sender);
         if msg_info.has_null_fault_label() {
@@ -3,6 +3,8 @@ use core::marker::PhantomData;
 use selfe_sys::*;
 
 use crate::arch;
+#[cfg(KernelIsMCS)]
+use crate::cap::Reply;
 use crate::cap::{
     role, Badge, CNode, CNodeRole, CNodeSlot, Cap, DirectRetype, Endpoint, LocalCNode,
     LocalCNodeSlot, LocalCNodeSlots, LocalCap, Notification, Untyped,
@@ -36,6 +38,52 @@ impl From<VSpaceError> for IPCError {
     }
 }
 
+/// Receive on `src`. On MCS kernels the reply capability for the received
+/// message, if any, is deposited in the `reply` object; elsewhere it lands
+/// in the receiving TCB and `reply` is ignored.
+#[cfg(not(KernelIsMCS))]
+pub(crate) unsafe fn recv(src: usize, sender: &mut usize, _reply: usize) -> seL4_MessageInfo_t {
+    seL4_Recv(src, sender as *mut usize)
+}
+
+/// Receive on `src`. On MCS kernels the reply capability for the received
+/// message, if any, is deposited in the `reply` object; elsewhere it lands
+/// in the receiving TCB and `reply` is ignored.
+#[cfg(KernelIsMCS)]
+pub(crate) unsafe fn recv(src: usize, sender: &mut usize, reply: usize) -> seL4_MessageInfo_t {
+    seL4_Recv(src, sender as *mut usize, reply)
+}
+
+#[cfg(not(KernelIsMCS))]
+unsafe fn reply_recv(
+    src: usize,
+    info: seL4_MessageInfo_t,
+    sender: &mut usize,
+    _reply: usize,
+) -> seL4_MessageInfo_t {
+    seL4_ReplyRecv(src, info, sender as *mut usize)
+}
+
+#[cfg(KernelIsMCS)]
+unsafe fn reply_recv(
+    src: usize,
+    info: seL4_MessageInfo_t,
+    sender: &mut usize,
+    reply: usize,
+) -> seL4_MessageInfo_t {
+    seL4_ReplyRecv(src, info, sender as *mut usize, reply)
+}
+
+#[cfg(not(KernelIsMCS))]
+pub(crate) unsafe fn reply(info: seL4_MessageInfo_t, _reply: usize) {
+    seL4_Reply(info)
+}
+
+#[cfg(KernelIsMCS)]
+pub(crate) unsafe fn reply(info: seL4_MessageInfo_t, reply: usize) {
+    seL4_Send(reply, info)
+}
+
 pub struct IpcSetup<'a, Req, Rsp> {
     endpoint: LocalCap<Endpoint>,
     endpoint_cnode: &'a LocalCap<LocalCNode>,
@@ -352,6 +400,7 @@ impl<Req, Rsp> Responder<Req, Rsp, role::Local> {
         }
     }
 
+    #[cfg(not(KernelIsMCS))]
     pub fn reply_recv<F>(self, mut f: F) -> Result<Rsp, IPCError>
     where
         F: FnMut(Req) -> Rsp,
@@ -359,6 +408,7 @@ impl<Req, Rsp> Responder<Req, Rsp, role::Local> {
         self.reply_recv_with_state((), move |req, state| (f(req), state))
     }
 
+    #[cfg(not(KernelIsMCS))]
     pub fn reply_recv_with_state<F, State>(
         self,
         initial_state: State,
@@ -370,9 +420,80 @@ impl<Req, Rsp> Responder<Req, Rsp, role::Local> {
         self.reply_recv_with_notification(initial_state, f, move |_sender_badge, state| state)
     }
 
+    #[cfg(not(KernelIsMCS))]
     pub fn reply_recv_with_notification<F, G, State>(
         self,
         initial_state: State,
+        f: F,
+        g: G,
+    ) -> Result<Rsp, IPCError>
+    where
+        F: FnMut(Req, State) -> (Rsp, State),
+        G: FnMut(usize, State) -> State,
+    {
+        self.reply_recv_internal(seL4_CapNull as usize, initial_state, f, g)
+    }
+
+    #[cfg(not(KernelIsMCS))]
+    pub fn recv_reply_once<F>(&self, f: F) -> Result<(), IPCError>
+    where
+        F: FnMut(Req) -> Rsp,
+    {
+        self.recv_reply_once_internal(seL4_CapNull as usize, f)
+    }
+
+    /// The MCS form of `reply_recv`, where replies to callers are
+    /// routed through the given reply object.
+    #[cfg(KernelIsMCS)]
+    pub fn reply_recv<F>(self, reply: LocalCap<Reply>, mut f: F) -> Result<Rsp, IPCError>
+    where
+        F: FnMut(Req) -> Rsp,
+    {
+        self.reply_recv_with_state(reply, (), move |req, state| (f(req), state))
+    }
+
+    #[cfg(KernelIsMCS)]
+    pub fn reply_recv_with_state<F, State>(
+        self,
+        reply: LocalCap<Reply>,
+        initial_state: State,
+        f: F,
+    ) -> Result<Rsp, IPCError>
+    where
+        F: FnMut(Req, State) -> (Rsp, State),
+    {
+        self.reply_recv_with_notification(reply, initial_state, f, move |_sender_badge, state| {
+            state
+        })
+    }
+
+    #[cfg(KernelIsMCS)]
+    pub fn reply_recv_with_notification<F, G, State>(
+        self,
+        reply: LocalCap<Reply>,
+        initial_state: State,
+        f: F,
+        g: G,
+    ) -> Result<Rsp, IPCError>
+    where
+        F: FnMut(Req, State) -> (Rsp, State),
+        G: FnMut(usize, State) -> State,
+    {
+        self.reply_recv_internal(reply.cptr, initial_state, f, g)
+    }
+
+    #[cfg(KernelIsMCS)]
+    pub fn recv_reply_once<F>(&self, reply: &LocalCap<Reply>, f: F) -> Result<(), IPCError>
+    where
+        F: FnMut(Req) -> Rsp,
+    {
+        self.recv_reply_once_internal(reply.cptr, f)
+    }
+
+    fn reply_recv_internal<F, G, State>(
+        self,
+        reply_cptr: usize,
+        initial_state: State,
         mut f: F,
         mut g: G,
     ) -> Result<Rsp, IPCError>
@@ -386,7 +507,7 @@ impl<Req, Rsp> Responder<Req, Rsp, role::Local> {
         let mut sender_badge: usize = 0;
         // Do a regular receive to seed our initial value
         let mut msg_info: MessageInfo =
-            unsafe { seL4_Recv(self.endpoint.cptr, &mut sender_badge as *mut usize) }.into();
+            unsafe { recv(self.endpoint.cptr, &mut sender_badge, reply_cptr) }.into();
 
         let request_length_in_words = type_length_in_words::<Req>();
         let mut response;
@@ -414,10 +535,11 @@ impl<Req, Rsp> Responder<Req, Rsp, role::Local> {
 
                 ipc_buffer.copy_rsp_into_buffer(&response);
                 msg_info = unsafe {
-                    seL4_ReplyRecv(
+                    reply_recv(
                         self.endpoint.cptr,
                         type_length_message_info::<Rsp>(),
-                        &mut sender_badge as *mut usize,
+                        &mut sender_badge,
+                        reply_cptr,
                     )
                 }
                 .into();
@@ -426,13 +548,12 @@ impl<Req, Rsp> Responder<Req, Rsp, role::Local> {
                 state = g(sender_badge, state);
 
                 msg_info =
-                    unsafe { seL4_Recv(self.endpoint.cptr, &mut sender_badge as *mut usize) }
-                        .into();
+                    unsafe { recv(self.endpoint.cptr, &mut sender_badge, reply_cptr) }.into();
             }
         }
     }
 
-    pub fn recv_reply_once<F>(&self, mut f: F) -> Result<(), IPCError>
+    fn recv_reply_once_internal<F>(&self, reply_cptr: usize, mut f: F) -> Result<(), IPCError>
     where
         F: FnMut(Req) -> Rsp,
     {
@@ -442,7 +563,7 @@ impl<Req, Rsp> Responder<Req, Rsp, role::Local> {
         let mut sender_badge: usize = 0;
         // Do a regular receive to seed our initial value
         let msg_info: MessageInfo =
-            unsafe { seL4_Recv(self.endpoint.cptr, &mut sender_badge as *mut usize) }.into();
+            unsafe { recv(self.endpoint.cptr, &mut sender_badge, reply_cptr) }.into();
 
         let request_length_in_words = type_length_in_words::<Req>();
         if msg_info.length_words() != request_length_in_words {
@@ -464,7 +585,7 @@ impl<Req, Rsp> Responder<Req, Rsp, role::Local> {
         ipc_buffer.copy_rsp_into_buffer(&response);
 
         unsafe {
-            seL4_Reply(type_length_message_info::<Rsp>());
+            reply(type_length_message_info::<Rsp>(), reply_cptr);
         }
 
         Ok(())
@@ -2,14 +2,20 @@ mod fault;
 mod ipc;
 mod irq;
 mod multi_consumer;
+mod pager;
 pub(crate) mod process;
-mod rights;
+pub mod rights;
 mod shared_memory_ipc;
+mod startup;
+mod supervisor;
 
 pub use crate::userland::fault::*;
 pub use crate::userland::ipc::*;
 pub use crate::userland::irq::*;
 pub use crate::userland::multi_consumer::*;
+pub use crate::userland::pager::*;
 pub use crate::userland::process::*;
 pub use crate::userland::rights::*;
 pub use crate::userland::shared_memory_ipc::*;
+pub use crate::userland::startup::*;
+pub use crate::userland::supervisor::*;
@@ -52,7 +52,7 @@ use crate::cap::{
 };
 use crate::error::SeL4Error;
 use crate::pow::{Pow, _Pow};
-use crate::userland::CapRights;
+use crate::userland::{rights, CapRights};
 use crate::vspace::{
     shared_status, KernelRetypeFanOutLimit, MappedMemoryRegion, NumPages, ScratchRegion,
     UnmappedMemoryRegion, VSpace, VSpaceError,
@@ -890,7 +890,7 @@ where
     consumer_vspace.skip_pages(1)?;
     let consumer_shared_region = consumer_vspace.map_shared_region(
         &shared_region,
-        CapRights::RW,
+        rights::RW,
         arch::vm_attributes::DEFAULT,
         shared_slots,
         local_cnode,
@@ -1387,7 +1387,7 @@ impl<T: Sized + Sync + Send, Role: CNodeRole> Producer<Role, T> {
         }
         let producer_shared_region = dest_vspace.map_shared_region(
             &setup.shared_region,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT,
             local_slots,
             local_cnode,
@@ -0,0 +1,462 @@
+use core::marker::PhantomData;
+use core::ops::Sub;
+
+use arrayvec::ArrayVec;
+
+use selfe_sys::*;
+
+use typenum::*;
+
+use crate::alloc::ut_buddy::{UTBuddyError, WUTBuddy};
+use crate::arch::fault::Fault;
+use crate::arch::{self, PageBits, PageBytes};
+#[cfg(KernelIsMCS)]
+use crate::cap::Reply;
+use crate::cap::{
+    memory_kind, page_state, role, Cap, LocalCap, Page, RecyclingCNodeSlots, WCNodeSlots, WUntyped,
+};
+use crate::error::{ErrorExt, SeL4Error};
+use crate::pow::{Pow, _Pow};
+use crate::userland::fault::wait_for_fault;
+use crate::userland::ipc::reply;
+use crate::userland::{rights, CapRights, FaultSink};
+use crate::vspace::{
+    shared_status, LazyRegion, MappedMemoryRegion, ScratchRegion, VSpace, VSpaceError,
+};
+
+/// The number of distinct lazy regions, and separately the number of
+/// copy-on-write regions, a single `Pager` can serve.
+pub type MaxPagerRegions = U8;
+
+/// The largest lazy region a `Pager` can serve, in pages.
+pub type MaxLazyPages = U4096;
+
+/// The largest copy-on-write region a `Pager` can serve, in pages.
+pub type MaxCopyOnWritePages = U512;
+
+const BACKED_WORDS: usize = MaxLazyPages::USIZE / 64;
+const COPIED_WORDS: usize = MaxCopyOnWritePages::USIZE / 64;
+
+#[derive(Debug)]
+pub enum PagerError {
+    /// The pager is already serving `MaxPagerRegions` regions.
+    TooManyRegions,
+    /// The region was reserved in a different VSpace than the one
+    /// the pager maps into.
+    ASIDMismatch,
+    /// The lazy region has more than `MaxLazyPages` pages, or the
+    /// copy-on-write region more than `MaxCopyOnWritePages`.
+    RegionTooLarge,
+    /// A copy-on-write page was written to before a scratch region was
+    /// provided through which to fill its copy.
+    NoScratchRegion,
+    /// There are no more slots in which to place freshly retyped pages.
+    NotEnoughCNodeSlots,
+    UTBuddyError(UTBuddyError),
+    VSpaceError(VSpaceError),
+    SeL4Error(SeL4Error),
+}
+
+impl From<UTBuddyError> for PagerError {
+    fn from(e: UTBuddyError) -> Self {
+        PagerError::UTBuddyError(e)
+    }
+}
+
+impl From<VSpaceError> for PagerError {
+    fn from(e: VSpaceError) -> Self {
+        PagerError::VSpaceError(e)
+    }
+}
+
+impl From<SeL4Error> for PagerError {
+    fn from(e: SeL4Error) -> Self {
+        PagerError::SeL4Error(e)
+    }
+}
+
+/// Commits the pages of a child VSpace's lazy and copy-on-write
+/// regions on demand.
+///
+/// The pager is the fault handler for the threads running in the
+/// VSpace. When one of them touches an unbacked page inside a region
+/// handed to `add_region`, the pager retypes a fresh page from its
+/// untyped pool, maps it at the faulting address and replies to the
+/// fault so the thread carries on where it left off. Pages of a region
+/// handed to `add_copy_on_write_region` start out shared read-only;
+/// the first write to one swaps in a private, writable copy.
+pub struct Pager<'a> {
+    vspace: VSpace,
+    fault_sink: FaultSink<role::Local>,
+    untyped: WUTBuddy,
+    slots: RecyclingCNodeSlots,
+    regions: ArrayVec<[LazyPages; MaxPagerRegions::USIZE]>,
+    copy_on_write_regions: ArrayVec<[CopyOnWriteRegion; MaxPagerRegions::USIZE]>,
+    scratch: Option<&'a mut ScratchRegion>,
+    #[cfg(KernelIsMCS)]
+    reply: LocalCap<Reply>,
+    _sources: PhantomData<&'a ()>,
+}
+
+struct LazyPages {
+    vaddr: usize,
+    page_count: usize,
+    /// Which pages the pager has backed, and so are mapped in the
+    /// pager's VSpace
+    backed: [u64; BACKED_WORDS],
+}
+
+impl LazyPages {
+    fn unbacked_page_index(&self, address: usize) -> Option<usize> {
+        if address < self.vaddr || address >= self.vaddr + self.page_count * PageBytes::USIZE {
+            return None;
+        }
+        let index = (address - self.vaddr) / PageBytes::USIZE;
+        if self.backed[index / 64] & (1 << (index % 64)) == 0 {
+            Some(index)
+        } else {
+            None
+        }
+    }
+}
+
+struct CopyOnWriteRegion {
+    vaddr: usize,
+    page_count: usize,
+    rights: CapRights,
+    vm_attributes: arch::VMAttributes,
+    /// The cptr of the first of the shared pages mapped into the
+    /// pager's VSpace
+    start_cptr: usize,
+    /// Where the shared pages are mapped in the local VSpace
+    source_vaddr: usize,
+    copied: [u64; COPIED_WORDS],
+}
+
+impl CopyOnWriteRegion {
+    fn uncopied_page_index(&self, address: usize) -> Option<usize> {
+        if address < self.vaddr || address >= self.vaddr + self.page_count * PageBytes::USIZE {
+            return None;
+        }
+        let index = (address - self.vaddr) / PageBytes::USIZE;
+        if self.copied[index / 64] & (1 << (index % 64)) == 0 {
+            Some(index)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> Pager<'a> {
+    #[cfg(not(KernelIsMCS))]
+    pub fn new(
+        vspace: VSpace,
+        fault_sink: FaultSink<role::Local>,
+        untyped: WUTBuddy,
+        slots: WCNodeSlots,
+    ) -> Self {
+        Pager {
+            vspace,
+            fault_sink,
+            untyped,
+            slots: RecyclingCNodeSlots::new(slots),
+            regions: ArrayVec::new(),
+            copy_on_write_regions: ArrayVec::new(),
+            scratch: None,
+            _sources: PhantomData,
+        }
+    }
+
+    /// On MCS kernels, faults are replied to through `reply`.
+    #[cfg(KernelIsMCS)]
+    pub fn new(
+        vspace: VSpace,
+        fault_sink: FaultSink<role::Local>,
+        untyped: WUTBuddy,
+        slots: WCNodeSlots,
+        reply: LocalCap<Reply>,
+    ) -> Self {
+        Pager {
+            vspace,
+            fault_sink,
+            untyped,
+            slots: RecyclingCNodeSlots::new(slots),
+            regions: ArrayVec::new(),
+            copy_on_write_regions: ArrayVec::new(),
+            scratch: None,
+            reply,
+            _sources: PhantomData,
+        }
+    }
+
+    /// Serve faults within `region`, which must have been reserved in
+    /// this pager's VSpace.
+    pub fn add_region<SizeBits: Unsigned>(
+        &mut self,
+        region: LazyRegion<SizeBits>,
+    ) -> Result<(), PagerError> {
+        if region.asid() != self.vspace.asid() {
+            return Err(PagerError::ASIDMismatch);
+        }
+        let page_count = region.size_bytes() / PageBytes::USIZE;
+        if page_count > MaxLazyPages::USIZE {
+            return Err(PagerError::RegionTooLarge);
+        }
+        self.regions
+            .try_push(LazyPages {
+                vaddr: region.vaddr(),
+                page_count,
+                backed: [0; BACKED_WORDS],
+            })
+            .map_err(|_| PagerError::TooManyRegions)
+    }
+
+    /// Serve writes to `mapping`, a read-only mapping of `source` into
+    /// this pager's VSpace, by giving the writer its own copy of each
+    /// page as it is first written.
+    ///
+    /// `source` is the local mapping the copies are filled from. It must
+    /// be mapped read-only, e.g. with `VSpace::remap_region`, since a
+    /// write through it would show up in every page not yet copied. Pages
+    /// are copied one at a time, so `mapping` must be made of page-sized
+    /// frames; one of larger frames is rejected with `FrameTooLarge`.
+    pub fn add_copy_on_write_region<SizeBits: Unsigned>(
+        &mut self,
+        mapping: MappedMemoryRegion<SizeBits, shared_status::Shared, role::Local, rights::R>,
+        source: &'a MappedMemoryRegion<SizeBits, shared_status::Shared, role::Local, rights::R>,
+    ) -> Result<(), PagerError>
+    where
+        SizeBits: IsGreaterOrEqual<PageBits>,
+        SizeBits: Sub<PageBits>,
+        <SizeBits as Sub<PageBits>>::Output: Unsigned,
+        <SizeBits as Sub<PageBits>>::Output: _Pow,
+        Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+    {
+        if mapping.asid() != self.vspace.asid() {
+            return Err(PagerError::ASIDMismatch);
+        }
+        if mapping.frame_bits() != PageBits::U8 {
+            return Err(VSpaceError::FrameTooLarge.into());
+        }
+        let page_count = mapping.size_bytes() / PageBytes::USIZE;
+        if page_count > MaxCopyOnWritePages::USIZE {
+            return Err(PagerError::RegionTooLarge);
+        }
+        self.copy_on_write_regions
+            .try_push(CopyOnWriteRegion {
+                vaddr: mapping.vaddr(),
+                page_count,
+                rights: mapping.rights(),
+                vm_attributes: mapping.vm_attributes(),
+                start_cptr: mapping.start_cptr(),
+                source_vaddr: source.vaddr(),
+                copied: [0; COPIED_WORDS],
+            })
+            .map_err(|_| PagerError::TooManyRegions)
+    }
+
+    /// Provide the local scratch region through which copy-on-write
+    /// pages are filled.
+    pub fn set_scratch(&mut self, scratch: &'a mut ScratchRegion) {
+        self.scratch = Some(scratch);
+    }
+
+    /// Back pages on demand until a fault arrives that none of the
+    /// pager's regions account for, and return that fault. The thread
+    /// that raised it is left blocked.
+    ///
+    /// Only faults on pages not yet backed are served within regions
+    /// handed to `add_region`; any other fault there, such as an
+    /// attempt to execute from an already backed page, is returned. Likewise only
+    /// writes to copy-on-write pages not yet copied are served. Should
+    /// backing or copying a page fail, the untyped and slot taken for
+    /// it are handed back to the pager's pool before the error is
+    /// returned.
+    pub fn run(&mut self) -> Result<Fault, PagerError> {
+        loop {
+            let fault = wait_for_fault(self.fault_sink.endpoint.cptr, self.reply_cptr());
+            let (address, missing, write) = match fault {
+                Fault::VMFault(ref f) => (f.address, f.is_translation_fault(), f.is_write()),
+                _ => return Ok(fault),
+            };
+            let vaddr = address & !PAGE_MASK;
+            if let Some((region, page)) = self.lazy_page(address).filter(|_| missing) {
+                self.back_page(region, page, vaddr)?;
+            } else if let Some((region, page)) = self.copy_on_write_page(address).filter(|_| write)
+            {
+                self.copy_page(region, page, vaddr)?;
+            } else {
+                return Ok(fault);
+            }
+            unsafe { reply(seL4_MessageInfo_new(0, 0, 0, 0), self.reply_cptr()) };
+        }
+    }
+
+    fn lazy_page(&self, address: usize) -> Option<(usize, usize)> {
+        self.regions
+            .iter()
+            .enumerate()
+            .find_map(|(i, r)| r.unbacked_page_index(address).map(|page| (i, page)))
+    }
+
+    fn back_page(&mut self, region: usize, page: usize, vaddr: usize) -> Result<(), PagerError> {
+        let (fresh_page, ut_cptr) = self.fresh_page()?;
+        let page_cptr = fresh_page.cptr;
+        if let Err(e) = self.vspace.map_page_at_addr_without_claiming(
+            fresh_page,
+            vaddr,
+            CapRights::RW,
+            arch::vm_attributes::DEFAULT | arch::vm_attributes::EXECUTE_NEVER,
+        ) {
+            self.give_back(ut_cptr, Some(page_cptr))?;
+            return Err(e.into());
+        }
+        let region = &mut self.regions[region];
+        region.backed[page / 64] |= 1 << (page % 64);
+        Ok(())
+    }
+
+    /// Retype a page from the pager's pool, returning it along with
+    /// the cptr of the untyped it was retyped from.
+    fn fresh_page(&mut self) -> Result<(LocalCap<Page<page_state::Unmapped>>, usize), PagerError> {
+        let ut = self
+            .untyped
+            .alloc_strong::<PageBits>(self.slots.slots_mut())?;
+        let ut_cptr = ut.cptr;
+        let slot = match self.slots.alloc_strong::<U1>() {
+            Ok(slot) => slot,
+            Err(_) => {
+                self.give_back(ut_cptr, None)?;
+                return Err(PagerError::NotEnoughCNodeSlots);
+            }
+        };
+        let slot_offset = slot.cap_data.offset;
+        match ut.retype(slot) {
+            Ok(page) => Ok((page, ut_cptr)),
+            Err(e) => {
+                self.give_back(ut_cptr, Some(slot_offset))?;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Return a page-sized untyped taken by `fresh_page` to the pool,
+    /// along with the slot at `slot_offset` that a page may have been
+    /// retyped into from it.
+    fn give_back(&mut self, ut_cptr: usize, slot_offset: Option<usize>) -> Result<(), PagerError> {
+        // Revoking the untyped deletes any page retyped from it
+        unsafe { seL4_CNode_Revoke(self.slots.cptr(), ut_cptr, seL4_WordBits as u8) }
+            .as_result()
+            .map_err(SeL4Error::CNodeRevoke)?;
+        if let Some(offset) = slot_offset {
+            // Should there be no room to track the slot, it's lost
+            let _ = self
+                .slots
+                .free(Cap::internal_new(self.slots.cptr(), offset));
+        }
+        let ut = Cap {
+            cptr: ut_cptr,
+            _role: PhantomData,
+            cap_data: WUntyped {
+                size_bits: PageBits::U8,
+                kind: memory_kind::General,
+            },
+        };
+        self.untyped
+            .free_in(ut, self.slots.cptr())
+            .map_err(|(e, _)| PagerError::UTBuddyError(e))
+    }
+
+    fn copy_on_write_page(&self, address: usize) -> Option<(usize, usize)> {
+        self.copy_on_write_regions
+            .iter()
+            .enumerate()
+            .find_map(|(i, r)| r.uncopied_page_index(address).map(|page| (i, page)))
+    }
+
+    fn copy_page(&mut self, region: usize, page: usize, vaddr: usize) -> Result<(), PagerError> {
+        if self.scratch.is_none() {
+            return Err(PagerError::NoScratchRegion);
+        }
+        let (fresh_page, ut_cptr) = self.fresh_page()?;
+        let fresh_cptr = fresh_page.cptr;
+        if let Err(e) = self.swap_in_copy(region, page, vaddr, fresh_page) {
+            self.give_back(ut_cptr, Some(fresh_cptr))?;
+            return Err(e);
+        }
+        let region = &mut self.copy_on_write_regions[region];
+        region.copied[page / 64] |= 1 << (page % 64);
+        Ok(())
+    }
+
+    /// Fill `fresh_page` from the source of the copy-on-write page and
+    /// map it in place of the shared page at `vaddr`. Should mapping the
+    /// copy fail, the shared page is mapped back at `vaddr`.
+    fn swap_in_copy(
+        &mut self,
+        region_index: usize,
+        page: usize,
+        vaddr: usize,
+        fresh_page: LocalCap<Page<page_state::Unmapped>>,
+    ) -> Result<(), PagerError> {
+        let scratch = self.scratch.as_mut().ok_or(PagerError::NoScratchRegion)?;
+        let region = &self.copy_on_write_regions[region_index];
+        let mut fresh = fresh_page.to_region();
+
+        let source = (region.source_vaddr + page * PageBytes::USIZE) as *const u8;
+        scratch.temporarily_map_region(&mut fresh, |mapped| {
+            unsafe {
+                core::ptr::copy_nonoverlapping(source, mapped.vaddr() as *mut u8, PageBytes::USIZE)
+            };
+            mapped.flush()
+        })??;
+
+        let shared: LocalCap<Page<page_state::Mapped>> = Cap {
+            cptr: region.start_cptr + page,
+            _role: PhantomData,
+            cap_data: Page {
+                state: page_state::Mapped {
+                    vaddr,
+                    asid: self.vspace.asid(),
+                    rights: region.rights,
+                    vm_attributes: region.vm_attributes,
+                },
+            },
+        };
+        let (rights, vm_attributes) = (region.rights, region.vm_attributes);
+        let shared = self.vspace.unmap_page(shared)?;
+
+        if let Err(e) = self.vspace.map_page_at_addr_without_claiming(
+            fresh.to_page(),
+            vaddr,
+            CapRights::RW,
+            arch::vm_attributes::PROGRAM_DATA,
+        ) {
+            // Put the shared page back, so that the next write there is
+            // served as before. Failing that, nothing is left at `vaddr`
+            // to swap out, so the page is no longer served at all.
+            if self
+                .vspace
+                .map_page_at_addr_without_claiming(shared, vaddr, rights, vm_attributes)
+                .is_err()
+            {
+                self.copy_on_write_regions[region_index].copied[page / 64] |= 1 << (page % 64);
+            }
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(KernelIsMCS))]
+    fn reply_cptr(&self) -> usize {
+        seL4_CapNull as usize
+    }
+
+    #[cfg(KernelIsMCS)]
+    fn reply_cptr(&self) -> usize {
+        self.reply.cptr
+    }
+}
+
+// 0xfff, for 4k pages
+const PAGE_MASK: usize = (1 << PageBits::USIZE) - 1;
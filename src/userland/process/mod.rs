@@ -5,6 +5,7 @@ use selfe_sys::seL4_Yield;
 use typenum::*;
 
 use crate::error::*;
+use crate::userland::StartupBlockError;
 use crate::vspace::VSpaceError;
 
 pub(crate) use crate::arch::userland::process::*;
@@ -12,6 +13,9 @@ pub(crate) use crate::arch::userland::process::*;
 mod thread;
 pub use thread::{Thread, ThreadSetupError};
 
+mod resources;
+pub use resources::ProcessResources;
+
 mod standard;
 pub use standard::StandardProcess;
 
@@ -61,6 +65,28 @@ pub enum ProcessSetupError {
     VSpaceError(VSpaceError),
     SeL4Error(SeL4Error),
     ElfParseError(&'static str),
+    /// The image doesn't link in ferros' startup block slot, or its
+    /// startup block has already been written.
+    NoStartupBlockSlot,
+    StartupBlockError(StartupBlockError),
+}
+
+/// Why a process couldn't be destroyed.
+#[derive(Debug)]
+pub enum ProcessDestroyError {
+    /// The process or its VSpace wasn't built out of the resources
+    /// given, so nothing was torn down.
+    NotBuiltFromResources,
+    /// The process's stack isn't the size its type says, so nothing
+    /// was torn down.
+    StackSizeMismatch,
+    SeL4Error(SeL4Error),
+}
+
+impl From<SeL4Error> for ProcessDestroyError {
+    fn from(e: SeL4Error) -> Self {
+        ProcessDestroyError::SeL4Error(e)
+    }
 }
 
 impl From<VSpaceError> for ProcessSetupError {
@@ -74,3 +100,9 @@ impl From<SeL4Error> for ProcessSetupError {
         ProcessSetupError::SeL4Error(e)
     }
 }
+
+impl From<StartupBlockError> for ProcessSetupError {
+    fn from(e: StartupBlockError) -> Self {
+        ProcessSetupError::StartupBlockError(e)
+    }
+}
@@ -0,0 +1,110 @@
+use core::marker::PhantomData;
+
+use selfe_sys::*;
+use typenum::*;
+
+use crate::cap::{ASIDPool, Cap, LocalCNodeSlots, LocalCap, Untyped};
+use crate::error::{ErrorExt, SeL4Error};
+
+/// The resources set aside for building a single process.
+///
+/// Rather than handing its slots, untyped memory and ASID pool
+/// directly to the process setup machinery, a parent may `lend` them,
+/// keeping hold of the originals while the process is built out of
+/// aliases. When the process is destroyed, everything derived from
+/// those aliases is revoked and the originals are handed back with
+/// their full capacity, much as `with_temporary` does for a function
+/// scope.
+pub struct ProcessResources<SlotCount: Unsigned, UntypedBitSize: Unsigned, ASIDPoolSlots: Unsigned>
+{
+    slots: LocalCNodeSlots<SlotCount>,
+    untyped: LocalCap<Untyped<UntypedBitSize>>,
+    asid_pool: LocalCap<ASIDPool<ASIDPoolSlots>>,
+}
+
+impl<SlotCount: Unsigned, UntypedBitSize: Unsigned, ASIDPoolSlots: Unsigned>
+    ProcessResources<SlotCount, UntypedBitSize, ASIDPoolSlots>
+{
+    /// Retain the given resources, returning aliases of them from which
+    /// a process may be constructed.
+    pub fn lend(
+        slots: LocalCNodeSlots<SlotCount>,
+        untyped: LocalCap<Untyped<UntypedBitSize>>,
+        asid_pool: LocalCap<ASIDPool<ASIDPoolSlots>>,
+    ) -> (
+        Self,
+        LocalCNodeSlots<SlotCount>,
+        LocalCap<Untyped<UntypedBitSize>>,
+        LocalCap<ASIDPool<ASIDPoolSlots>>,
+    ) {
+        let slots_alias = Cap::internal_new(slots.cptr, slots.cap_data.offset);
+        let untyped_alias = Cap {
+            cptr: untyped.cptr,
+            _role: PhantomData,
+            cap_data: Untyped {
+                _bit_size: PhantomData,
+                kind: untyped.cap_data.kind.clone(),
+            },
+        };
+        let asid_pool_alias = Cap {
+            cptr: asid_pool.cptr,
+            _role: PhantomData,
+            cap_data: ASIDPool {
+                id: asid_pool.cap_data.id,
+                next_free_slot: asid_pool.cap_data.next_free_slot,
+                _free_slots: PhantomData,
+            },
+        };
+        (
+            ProcessResources {
+                slots,
+                untyped,
+                asid_pool,
+            },
+            slots_alias,
+            untyped_alias,
+            asid_pool_alias,
+        )
+    }
+
+    /// Whether `cptr` is one of the lent slots.
+    pub(crate) fn lent_slot(&self, cptr: usize) -> bool {
+        let offset = self.slots.cap_data.offset;
+        cptr >= offset && cptr < offset + SlotCount::USIZE
+    }
+
+    /// Revoke and delete every capability derived from the lent aliases,
+    /// then hand back the original resources.
+    ///
+    /// Deleting the last capability to the process's paging root also
+    /// releases the ASID it was assigned, so the pool is returned with
+    /// its capacity intact. If revoking the untyped fails, the resources
+    /// are handed back still lent, so that reclaiming can be tried again.
+    pub(crate) fn reclaim(
+        self,
+    ) -> Result<
+        (
+            LocalCNodeSlots<SlotCount>,
+            LocalCap<Untyped<UntypedBitSize>>,
+            LocalCap<ASIDPool<ASIDPoolSlots>>,
+        ),
+        (SeL4Error, Self),
+    > {
+        unsafe { self.slots.revoke_in_reverse() }
+
+        // Because the slots and the untyped are both Local, the slots' parent CNode
+        // capability pointer must be the same as the untyped's parent CNode
+        let revoked = unsafe {
+            seL4_CNode_Revoke(
+                self.slots.cptr,     // _service
+                self.untyped.cptr,   // index
+                seL4_WordBits as u8, // depth
+            )
+        };
+        if let Err(e) = revoked.as_result() {
+            return Err((SeL4Error::CNodeRevoke(e), self));
+        }
+
+        Ok((self.slots, self.untyped, self.asid_pool))
+    }
+}
@@ -9,7 +9,7 @@ use crate::cap::{
     role, CNodeRole, CNodeSlotsError, Cap, ChildCNode, DirectRetype, LocalCNode, LocalCNodeSlots,
     LocalCap, ThreadControlBlock, ThreadPriorityAuthority, Untyped, WCNodeSlotsData,
 };
-use crate::userland::CapRights;
+use crate::userland::{rights, CapRights};
 use crate::vspace::*;
 
 use super::*;
@@ -49,7 +49,7 @@ impl<StackBitSize: Unsigned> SelfHostedProcess<StackBitSize> {
         mut cap_transfer_slots: LocalCap<WCNodeSlotsData<role::Child>>,
         child_paging_slots: Cap<WCNodeSlotsData<role::Child>, role::Child>,
         priority_authority: &LocalCap<ThreadPriorityAuthority>,
-        fault_source: Option<crate::userland::FaultSource<role::Child>>,
+        fault_source: Option<crate::userland::FaultSource<crate::userland::FaultSourceRole>>,
     ) -> Result<SelfHostedProcess<StackBitSize>, ProcessSetupError>
     where
         NumPages<StackBitSize>: Add<U2>,
@@ -90,7 +90,7 @@ impl<StackBitSize: Unsigned> SelfHostedProcess<StackBitSize> {
         let ipc_buffer = ipc_buffer_ut.retype(ipc_slots)?;
         let ipc_buffer = vspace.map_region(
             ipc_buffer.to_region(),
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT | arch::vm_attributes::EXECUTE_NEVER,
         )?;
 
@@ -114,7 +114,7 @@ impl<StackBitSize: Unsigned> SelfHostedProcess<StackBitSize> {
             parent_mapped_region.share(stack_slots, parent_cnode, CapRights::RW)?;
         let mapped_stack_pages = vspace.map_shared_region_and_consume(
             unmapped_stack_pages,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT | arch::vm_attributes::EXECUTE_NEVER,
         )?;
 
@@ -153,8 +153,8 @@ impl<StackBitSize: Unsigned> SelfHostedProcess<StackBitSize> {
 
         local_stack_pages.flush()?;
 
-        registers.sp = stack_pointer;
-        registers.pc = self_hosted_run::<T> as usize;
+        set_thread_stack_pointer(&mut registers, stack_pointer);
+        set_thread_program_counter(&mut registers, self_hosted_run::<T> as usize);
 
         // TODO - Probably ought to suspend or destroy the thread
         // instead of endlessly yielding
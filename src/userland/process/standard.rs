@@ -1,7 +1,8 @@
 use crate::arch::{self, *};
 use crate::cap::*;
 use crate::pow::{Pow, _Pow};
-use crate::userland::rights::CapRights;
+use crate::userland::rights::{self, CapRights};
+use crate::userland::{AuxKey, StartupBlock};
 use crate::vspace::*;
 use core::ops::{Add, Sub};
 
@@ -22,6 +23,10 @@ use super::*;
 ///  * An IPC buffer and CSpace and fault handler associated with that TCB.
 pub struct StandardProcess<StackBitSize: Unsigned = DefaultStackBitSize> {
     tcb: LocalCap<ThreadControlBlock>,
+    /// Where the stack is mapped in the process's VSpace
+    stack_vaddr: usize,
+    /// The parent's own mapping of the stack, handed back on `destroy`
+    local_stack: WeakMappedMemoryRegion<shared_status::Shared>,
     _stack_bit_size: PhantomData<StackBitSize>,
 }
 
@@ -59,7 +64,7 @@ impl<StackBitSize: Unsigned> StandardProcess<StackBitSize> {
         tcb_ut: LocalCap<Untyped<<ThreadControlBlock as DirectRetype>::SizeBits>>,
         slots: LocalCNodeSlots<Sum<NumPages<StackBitSize>, U2>>,
         priority_authority: &LocalCap<ThreadPriorityAuthority>,
-        fault_source: Option<crate::userland::FaultSource<role::Child>>,
+        fault_source: Option<crate::userland::FaultSource<crate::userland::FaultSourceRole>>,
     ) -> Result<StandardProcess<StackBitSize>, ProcessSetupError>
     where
         NumPages<StackBitSize>: Add<U2>,
@@ -103,7 +108,7 @@ impl<StackBitSize: Unsigned> StandardProcess<StackBitSize> {
             parent_mapped_region.share(stack_slots, parent_cnode, CapRights::RW)?;
         let mapped_stack_pages = vspace.map_shared_region_and_consume(
             unmapped_stack_pages,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT | arch::vm_attributes::EXECUTE_NEVER,
         )?;
 
@@ -123,16 +128,17 @@ impl<StackBitSize: Unsigned> StandardProcess<StackBitSize> {
         let stack_pointer =
             mapped_stack_pages.vaddr() + mapped_stack_pages.size_bytes() - param_size_on_stack;
 
-        registers.sp = stack_pointer;
+        set_thread_stack_pointer(&mut registers, stack_pointer);
 
-        registers.pc = match entry_point {
+        let program_counter = match entry_point {
             EntryPoint::Fork(f) => f as usize,
             EntryPoint::Elf(elf_data) => {
                 let elf =
                     xmas_elf::ElfFile::new(elf_data).map_err(ProcessSetupError::ElfParseError)?;
-                elf.header.pt2.entry_point() as usize
+                elf.header.pt2.entry_point() as usize + vspace.load_bias()
             }
         };
+        set_thread_program_counter(&mut registers, program_counter);
 
         // TODO - Probably ought to suspend or destroy the thread instead of endlessly
         // yielding
@@ -149,7 +155,7 @@ impl<StackBitSize: Unsigned> StandardProcess<StackBitSize> {
         let ipc_buffer = ipc_buffer_ut.retype(ipc_slots)?;
         let ipc_buffer = vspace.map_region(
             ipc_buffer.to_region(),
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT | arch::vm_attributes::EXECUTE_NEVER,
         )?;
 
@@ -163,6 +169,9 @@ impl<StackBitSize: Unsigned> StandardProcess<StackBitSize> {
             vspace.root(),
             Some(ipc_buffer.to_page()),
         )?;
+        if let Some(tls_base) = vspace.take_tls_base() {
+            tcb.set_tls_base(tls_base)?;
+        }
         unsafe {
             seL4_TCB_WriteRegisters(
                 tcb.cptr,
@@ -181,6 +190,8 @@ impl<StackBitSize: Unsigned> StandardProcess<StackBitSize> {
         }
         Ok(StandardProcess {
             tcb,
+            stack_vaddr: mapped_stack_pages.vaddr(),
+            local_stack: local_stack_pages.weaken(),
             _stack_bit_size: PhantomData,
         })
     }
@@ -196,6 +207,53 @@ impl<StackBitSize: Unsigned> StandardProcess<StackBitSize> {
         }
     }
 
+    /// Lay out `block` in a page retyped from `page_ut` and map it into
+    /// `vspace`, which must be the one this process was built in, where
+    /// the process will find it. The block's auxiliary entries describe
+    /// the page size and this process's stack.
+    ///
+    /// The process's image must have been loaded by
+    /// `VSpace::new_from_elf` and link in ferros' startup block slot,
+    /// as any elf process that reads its block with `startup_info` does.
+    pub fn write_startup_block(
+        &mut self,
+        vspace: &mut VSpace,
+        block: &StartupBlock,
+        page_ut: LocalCap<Untyped<PageBits>>,
+        slots: LocalCNodeSlots<U1>,
+        local_vspace_scratch: &mut ScratchRegion,
+    ) -> Result<(), ProcessSetupError> {
+        let block_vaddr = vspace
+            .take_startup_block_vaddr()
+            .ok_or(ProcessSetupError::NoStartupBlockSlot)?;
+        let aux = [
+            (AuxKey::PageSize, PageBytes::USIZE),
+            (AuxKey::StackBottom, self.stack_vaddr),
+            (
+                AuxKey::StackTop,
+                self.stack_vaddr + (1 << StackBitSize::USIZE),
+            ),
+        ];
+
+        let mut page_region = page_ut.retype(slots)?.to_region();
+        local_vspace_scratch.temporarily_map_region::<PageBits, _, _>(
+            &mut page_region,
+            |temp_mapped_region| {
+                let written = block.write(temp_mapped_region.as_mut_slice(), &aux);
+                temp_mapped_region.flush()?;
+                written.map_err(ProcessSetupError::StartupBlockError)
+            },
+        )??;
+
+        vspace.map_page_at_addr_without_claiming(
+            page_region.to_page(),
+            block_vaddr,
+            CapRights::R,
+            arch::vm_attributes::DEFAULT | arch::vm_attributes::EXECUTE_NEVER,
+        )?;
+        Ok(())
+    }
+
     pub fn bind_notification(
         &mut self,
         notification: &LocalCap<Notification>,
@@ -211,6 +269,111 @@ impl<StackBitSize: Unsigned> StandardProcess<StackBitSize> {
             .map_err(SeL4Error::TCBResume)
     }
 
+    pub fn suspend(&mut self) -> Result<(), SeL4Error> {
+        unsafe { seL4_TCB_Suspend(self.tcb.cptr) }
+            .as_result()
+            .map_err(SeL4Error::TCBSuspend)
+    }
+
+    /// Stop this process and tear down everything that was built for it
+    /// out of the aliases lent by `resources`: its TCB, CSpace, `vspace`
+    /// with its paging structures, and its stack and IPC buffer
+    /// mappings. The original slots, untyped and ASID pool are handed
+    /// back for reuse, along with the stack region given to `new`,
+    /// still mapped in the parent and exclusively its own again, so
+    /// that another process may be built on it.
+    ///
+    /// `vspace` must be the one this process runs in. Unless both its
+    /// root and this process's TCB sit in slots `resources` lent, nothing
+    /// is torn down. Anything else derived from `resources` is destroyed
+    /// along with the process.
+    ///
+    /// On failure the process, `vspace` and `resources` are handed back
+    /// with the error. If it was revoking the lent untyped that failed,
+    /// the process has already been torn down and only the resources
+    /// are left to reclaim.
+    pub fn destroy<SlotCount: Unsigned, UntypedBitSize: Unsigned, ASIDPoolSlots: Unsigned>(
+        mut self,
+        vspace: VSpace,
+        resources: ProcessResources<SlotCount, UntypedBitSize, ASIDPoolSlots>,
+    ) -> Result<
+        (
+            LocalCNodeSlots<SlotCount>,
+            LocalCap<Untyped<UntypedBitSize>>,
+            LocalCap<ASIDPool<ASIDPoolSlots>>,
+            MappedMemoryRegion<StackBitSize, shared_status::Exclusive>,
+        ),
+        (
+            ProcessDestroyError,
+            Self,
+            VSpace,
+            ProcessResources<SlotCount, UntypedBitSize, ASIDPoolSlots>,
+        ),
+    >
+    where
+        StackBitSize: IsGreaterOrEqual<PageBits>,
+        StackBitSize: Sub<PageBits>,
+        <StackBitSize as Sub<PageBits>>::Output: Unsigned,
+        <StackBitSize as Sub<PageBits>>::Output: _Pow,
+        Pow<<StackBitSize as Sub<PageBits>>::Output>: Unsigned,
+    {
+        if !resources.lent_slot(self.tcb.cptr) || !resources.lent_slot(vspace.root().cptr) {
+            return Err((
+                ProcessDestroyError::NotBuiltFromResources,
+                self,
+                vspace,
+                resources,
+            ));
+        }
+        if let Err(e) = self.suspend() {
+            return Err((e.into(), self, vspace, resources));
+        }
+
+        let StandardProcess {
+            tcb,
+            stack_vaddr,
+            local_stack,
+            _stack_bit_size,
+        } = self;
+        let local_stack: MappedMemoryRegion<StackBitSize, shared_status::Shared> =
+            match local_stack.try_as_strong() {
+                Ok(local_stack) => local_stack,
+                Err(local_stack) => {
+                    return Err((
+                        ProcessDestroyError::StackSizeMismatch,
+                        StandardProcess {
+                            tcb,
+                            stack_vaddr,
+                            local_stack,
+                            _stack_bit_size,
+                        },
+                        vspace,
+                        resources,
+                    ))
+                }
+            };
+
+        // The child's copies of the stack pages were made in the same
+        // lent slots as its TCB, so reclaiming deletes them.
+        match resources.reclaim() {
+            Ok((slots, untyped, asid_pool)) => {
+                let stack = unsafe { local_stack.to_exclusive() };
+                Ok((slots, untyped, asid_pool, stack))
+            }
+            Err((e, resources)) => Err((
+                e.into(),
+                StandardProcess {
+                    tcb,
+                    stack_vaddr,
+                    local_stack: local_stack.weaken(),
+                    _stack_bit_size,
+                },
+                vspace,
+                resources,
+            )),
+        }
+    }
+
     pub fn elim(self) -> usize {
         self.tcb.cptr
     }
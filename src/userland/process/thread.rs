@@ -35,7 +35,7 @@ impl<StackBitSize: Unsigned> Thread<StackBitSize> {
         tcb_ut: LocalCap<Untyped<<ThreadControlBlock as DirectRetype>::SizeBits>>,
         slots: LocalCNodeSlots<U1>,
         priority_authority: &LocalCap<ThreadPriorityAuthority>,
-        fault_source: Option<crate::userland::FaultSource<role::Child>>,
+        fault_source: Option<crate::userland::FaultSource<crate::userland::FaultSourceRole>>,
     ) -> Result<Thread<StackBitSize>, ThreadSetupError>
     where
         StackBitSize: IsGreaterOrEqual<PageBits>,
@@ -75,8 +75,8 @@ impl<StackBitSize: Unsigned> Thread<StackBitSize> {
         let stack_pointer =
             mapped_stack_pages.vaddr() + mapped_stack_pages.size_bytes() - param_size_on_stack;
 
-        registers.sp = stack_pointer;
-        registers.pc = function_descriptor as usize;
+        set_thread_stack_pointer(&mut registers, stack_pointer);
+        set_thread_program_counter(&mut registers, function_descriptor as usize);
 
         // TODO - Probably ought to suspend or destroy the thread instead of endlessly
         // yielding
@@ -114,6 +114,28 @@ impl<StackBitSize: Unsigned> Thread<StackBitSize> {
         })
     }
 
+    /// Point this thread at its TLS block, as set up by
+    /// `TLSImage::initialize_region`.
+    pub fn set_tls_base(&mut self, tls_base: usize) -> Result<(), SeL4Error> {
+        self.tcb.set_tls_base(tls_base)
+    }
+
+    /// Bind this thread to a scheduling context which `sched_control`
+    /// grants `budget` microseconds of execution in every `period`
+    /// microseconds. On an MCS kernel, the thread won't run until it has
+    /// been bound to one.
+    #[cfg(KernelIsMCS)]
+    pub fn bind_sched_context(
+        &mut self,
+        sched_control: &LocalCap<SchedControl>,
+        sched_context: LocalCap<SchedContext>,
+        budget: u64,
+        period: u64,
+    ) -> Result<(), SeL4Error> {
+        self.tcb
+            .bind_sched_context(sched_control, sched_context, budget, period)
+    }
+
     pub fn start(self) -> Result<(), SeL4Error> {
         unsafe { seL4_TCB_Resume(self.tcb.cptr) }
             .as_result()
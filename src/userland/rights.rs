@@ -31,56 +31,62 @@ impl CapRights {
     }
 }
 
+/// A set of rights known at compile time, for tracking in a type
+/// what a capability or mapping allows.
 pub trait Rights: private::SealedRights {
     fn as_caprights() -> CapRights;
 }
 
-#[allow(unused, clippy::module_inception)]
-pub mod rights {
-    use super::*;
+/// Rights which allow both reading and writing, and so allow handing
+/// out a `&mut [u8]` over a mapping. Write-only mappings (`W`, `WG`)
+/// fault on reads, which safe code could perform through such a slice.
+pub trait Writable: Rights {}
 
-    pub struct R {}
-    pub struct W {}
-    pub struct RW {}
-    pub struct RWG {}
-    pub struct WG {}
+pub struct R;
+pub struct W;
+pub struct RW;
+pub struct RWG;
+pub struct WG;
 
-    impl Rights for R {
-        fn as_caprights() -> CapRights {
-            CapRights::R
-        }
+impl Rights for R {
+    fn as_caprights() -> CapRights {
+        CapRights::R
     }
+}
 
-    impl Rights for W {
-        fn as_caprights() -> CapRights {
-            CapRights::W
-        }
+impl Rights for W {
+    fn as_caprights() -> CapRights {
+        CapRights::W
     }
+}
 
-    impl Rights for RW {
-        fn as_caprights() -> CapRights {
-            CapRights::RW
-        }
+impl Rights for RW {
+    fn as_caprights() -> CapRights {
+        CapRights::RW
     }
+}
 
-    impl Rights for RWG {
-        fn as_caprights() -> CapRights {
-            CapRights::RWG
-        }
+impl Rights for RWG {
+    fn as_caprights() -> CapRights {
+        CapRights::RWG
     }
+}
 
-    impl Rights for WG {
-        fn as_caprights() -> CapRights {
-            CapRights::WG
-        }
+impl Rights for WG {
+    fn as_caprights() -> CapRights {
+        CapRights::WG
     }
 }
+
+impl Writable for RW {}
+impl Writable for RWG {}
+
 mod private {
     use super::*;
     pub trait SealedRights {}
-    impl SealedRights for rights::R {}
-    impl SealedRights for rights::W {}
-    impl SealedRights for rights::RW {}
-    impl SealedRights for rights::RWG {}
-    impl SealedRights for rights::WG {}
+    impl SealedRights for R {}
+    impl SealedRights for W {}
+    impl SealedRights for RW {}
+    impl SealedRights for RWG {}
+    impl SealedRights for WG {}
 }
@@ -9,7 +9,7 @@ use crate::cap::{
     Notification, Untyped,
 };
 use crate::userland::multi_consumer::WakerSetup;
-use crate::userland::{CapRights, IPCError};
+use crate::userland::{rights, CapRights, IPCError};
 use crate::vspace::{UnmappedMemoryRegion, VSpace};
 
 pub(crate) const WAKER_BADGE: usize = 2;
@@ -52,7 +52,7 @@ pub mod sync {
         let (slot, local_slots) = local_slots.alloc();
         let caller_shared_region = caller_vspace.map_shared_region(
             &shared_region,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT,
             slot,
             local_cnode,
@@ -60,7 +60,7 @@ pub mod sync {
 
         let responder_shared_region = responder_vspace.map_shared_region_and_consume(
             shared_region,
-            CapRights::RW,
+            rights::RW,
             arch::vm_attributes::DEFAULT,
         )?;
 
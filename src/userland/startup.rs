@@ -0,0 +1,266 @@
+use core::convert::TryInto;
+
+use arrayvec::ArrayVec;
+
+use typenum::*;
+
+/// The most arguments a `StartupBlock` can carry.
+pub type MaxStartupArgs = U16;
+
+/// The most configuration entries a `StartupBlock` can carry.
+pub type MaxStartupConfigEntries = U16;
+
+/// The owner name of the note announcing where an elf process keeps
+/// the word through which it finds its startup block.
+pub(crate) const STARTUP_NOTE_NAME: &[u8] = b"ferros\0";
+
+/// The type of that note, whose descriptor is the 32-bit offset of the
+/// word from the descriptor itself.
+pub(crate) const STARTUP_NOTE_TYPE: u32 = 1;
+
+/// The elf loader fills this in while copying the image.
+#[no_mangle]
+static mut FERROS_STARTUP_BLOCK_VADDR: usize = 0;
+
+// The word is found through a note rather than by section or symbol
+// name, since a `PT_NOTE` program header survives stripping. Notes are
+// kept by `--gc-sections`, and keep the word alive with them.
+global_asm!(
+    ".pushsection .note.ferros.startup, \"a\", %note",
+    ".balign 4",
+    ".4byte 7",
+    ".4byte 4",
+    ".4byte 1",
+    ".asciz \"ferros\"",
+    ".balign 4",
+    ".Lferros_startup_note_desc:",
+    ".4byte FERROS_STARTUP_BLOCK_VADDR - .Lferros_startup_note_desc",
+    ".popsection"
+);
+
+const MAGIC: usize = 0x5354_5254;
+const WORD_BYTES: usize = core::mem::size_of::<usize>();
+const HEADER_WORDS: usize = 6;
+
+/// The auxiliary entries of a startup block. Where the ELF auxiliary
+/// vector has an equivalent, the key matches its `AT_*` number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuxKey {
+    PageSize = 6,
+    /// The lowest address of the initial thread's stack
+    StackBottom = 0x100,
+    /// The address just past the initial thread's stack
+    StackTop = 0x101,
+}
+
+#[derive(Debug)]
+pub enum StartupBlockError {
+    /// The block already holds `MaxStartupArgs` arguments.
+    TooManyArgs,
+    /// The block already holds `MaxStartupConfigEntries` entries.
+    TooManyConfigEntries,
+    /// The block doesn't fit in a page.
+    TooLarge,
+}
+
+/// The arguments and configuration to hand one instance of an elf
+/// process, laid out in a page mapped read-only into the child by
+/// `StandardProcess::write_startup_block`. The child reads it back
+/// with `startup_info`.
+pub struct StartupBlock<'a> {
+    image_name: &'a str,
+    args: ArrayVec<[&'a str; MaxStartupArgs::USIZE]>,
+    config: ArrayVec<[(&'a str, &'a str); MaxStartupConfigEntries::USIZE]>,
+}
+
+impl<'a> StartupBlock<'a> {
+    pub fn new(image_name: &'a str) -> Self {
+        StartupBlock {
+            image_name,
+            args: ArrayVec::new(),
+            config: ArrayVec::new(),
+        }
+    }
+
+    pub fn push_arg(&mut self, arg: &'a str) -> Result<(), StartupBlockError> {
+        self.args
+            .try_push(arg)
+            .map_err(|_| StartupBlockError::TooManyArgs)
+    }
+
+    pub fn push_config(&mut self, key: &'a str, value: &'a str) -> Result<(), StartupBlockError> {
+        self.config
+            .try_push((key, value))
+            .map_err(|_| StartupBlockError::TooManyConfigEntries)
+    }
+
+    /// Lay the block out at the start of `dest`.
+    ///
+    /// The layout is a table of words followed by the bytes of the
+    /// strings, which the table refers to by offset and length:
+    ///
+    /// ```text
+    /// magic, image name, arg count, config count, aux count
+    /// args:   (offset, length) per arg
+    /// config: (key offset, key length, value offset, value length) per entry
+    /// aux:    (key, value) per entry
+    /// string bytes
+    /// ```
+    pub(crate) fn write(
+        &self,
+        dest: &mut [u8],
+        aux: &[(AuxKey, usize)],
+    ) -> Result<(), StartupBlockError> {
+        let table_words =
+            HEADER_WORDS + 2 * self.args.len() + 4 * self.config.len() + 2 * aux.len();
+        let mut writer = Writer {
+            dest,
+            next_word: 0,
+            next_byte: table_words * WORD_BYTES,
+        };
+
+        writer.word(MAGIC)?;
+        writer.string(self.image_name)?;
+        writer.word(self.args.len())?;
+        writer.word(self.config.len())?;
+        writer.word(aux.len())?;
+        for arg in self.args.iter() {
+            writer.string(arg)?;
+        }
+        for (key, value) in self.config.iter() {
+            writer.string(key)?;
+            writer.string(value)?;
+        }
+        for (key, value) in aux.iter() {
+            writer.word(*key as usize)?;
+            writer.word(*value)?;
+        }
+        Ok(())
+    }
+}
+
+struct Writer<'d> {
+    dest: &'d mut [u8],
+    next_word: usize,
+    next_byte: usize,
+}
+
+impl<'d> Writer<'d> {
+    fn word(&mut self, word: usize) -> Result<(), StartupBlockError> {
+        let at = self.next_word * WORD_BYTES;
+        self.dest
+            .get_mut(at..at + WORD_BYTES)
+            .ok_or(StartupBlockError::TooLarge)?
+            .copy_from_slice(&word.to_ne_bytes());
+        self.next_word += 1;
+        Ok(())
+    }
+
+    fn string(&mut self, s: &str) -> Result<(), StartupBlockError> {
+        let at = self.next_byte;
+        self.dest
+            .get_mut(at..at + s.len())
+            .ok_or(StartupBlockError::TooLarge)?
+            .copy_from_slice(s.as_bytes());
+        self.next_byte += s.len();
+        self.word(at)?;
+        self.word(s.len())
+    }
+}
+
+/// The startup block of the running elf process, or `None` if it
+/// wasn't given one.
+pub fn startup_info() -> Option<StartupInfo<'static>> {
+    let vaddr = unsafe { core::ptr::read_volatile(&FERROS_STARTUP_BLOCK_VADDR) };
+    if vaddr == 0 {
+        return None;
+    }
+    let page =
+        unsafe { core::slice::from_raw_parts(vaddr as *const u8, crate::arch::PageBytes::USIZE) };
+    StartupInfo::parse(page)
+}
+
+/// A read-only view of a startup block.
+#[derive(Clone, Copy)]
+pub struct StartupInfo<'a> {
+    block: &'a [u8],
+    arg_count: usize,
+    config_count: usize,
+    aux_count: usize,
+}
+
+impl<'a> StartupInfo<'a> {
+    fn parse(block: &'a [u8]) -> Option<Self> {
+        let info = StartupInfo {
+            block,
+            arg_count: 0,
+            config_count: 0,
+            aux_count: 0,
+        };
+        if info.word(0)? != MAGIC {
+            return None;
+        }
+        Some(StartupInfo {
+            arg_count: info.word(3)?,
+            config_count: info.word(4)?,
+            aux_count: info.word(5)?,
+            ..info
+        })
+    }
+
+    pub fn image_name(&self) -> &'a str {
+        self.string(1).unwrap_or("")
+    }
+
+    pub fn args(&self) -> impl Iterator<Item = &'a str> + 'a {
+        let info = *self;
+        (0..self.arg_count).filter_map(move |i| info.string(HEADER_WORDS + 2 * i))
+    }
+
+    /// The value of the first configuration entry with the given key.
+    pub fn config(&self, key: &str) -> Option<&'a str> {
+        self.config_entries()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn config_entries(&self) -> impl Iterator<Item = (&'a str, &'a str)> + 'a {
+        let info = *self;
+        let start = HEADER_WORDS + 2 * self.arg_count;
+        (0..self.config_count).filter_map(move |i| {
+            let index = start + 4 * i;
+            Some((info.string(index)?, info.string(index + 2)?))
+        })
+    }
+
+    pub fn aux(&self, key: AuxKey) -> Option<usize> {
+        let start = HEADER_WORDS + 2 * self.arg_count + 4 * self.config_count;
+        (0..self.aux_count)
+            .map(|i| start + 2 * i)
+            .find(|&index| self.word(index) == Some(key as usize))
+            .and_then(|index| self.word(index + 1))
+    }
+
+    pub fn page_size(&self) -> Option<usize> {
+        self.aux(AuxKey::PageSize)
+    }
+
+    /// The lowest address of the initial thread's stack, and the
+    /// address just past it.
+    pub fn stack_bounds(&self) -> Option<(usize, usize)> {
+        Some((self.aux(AuxKey::StackBottom)?, self.aux(AuxKey::StackTop)?))
+    }
+
+    fn word(&self, index: usize) -> Option<usize> {
+        let at = index.checked_mul(WORD_BYTES)?;
+        let bytes = self.block.get(at..at.checked_add(WORD_BYTES)?)?;
+        Some(usize::from_ne_bytes(bytes.try_into().ok()?))
+    }
+
+    fn string(&self, index: usize) -> Option<&'a str> {
+        let at = self.word(index)?;
+        let len = self.word(index + 1)?;
+        let bytes = self.block.get(at..at.checked_add(len)?)?;
+        core::str::from_utf8(bytes).ok()
+    }
+}
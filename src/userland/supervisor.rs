@@ -0,0 +1,502 @@
+use core::ops::Sub;
+
+use selfe_sys::{seL4_MessageInfo_new, seL4_Send};
+
+use typenum::*;
+
+use crate::arch::fault::Fault;
+use crate::arch::PageBits;
+use crate::cap::{
+    role, ASIDPool, Badge, CNodeRole, CNodeSlot, Cap, Endpoint, LocalCNode, LocalCNodeSlots,
+    LocalCap, Untyped,
+};
+use crate::error::SeL4Error;
+use crate::pow::{Pow, _Pow};
+use crate::userland::{
+    FaultManagementError, FaultSinkSetup, FaultSource, FaultSourceRole, ProcessDestroyError,
+    ProcessResources, RetypeForSetup, StandardProcess,
+};
+use crate::vspace::{shared_status, MappedMemoryRegion, VSpace};
+
+/// What a `Supervisor` does when one of its children faults.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestartPolicy {
+    /// Tear the child down and immediately build it anew.
+    Restart,
+    /// Tear the child down and build it anew after a delay, counted in
+    /// ticks of the supervisor's `SupervisorTicker`, which starts at
+    /// `initial_delay` and doubles with each restart up to `max_delay`.
+    /// Other children are supervised as usual in the meantime.
+    RestartWithBackoff {
+        initial_delay: usize,
+        max_delay: usize,
+    },
+    /// Leave the child as it is and hand the fault back to the caller
+    /// of `Supervisor::run`.
+    Escalate,
+    /// Tear the child down and leave it that way.
+    LeaveDead,
+}
+
+#[derive(Debug)]
+pub enum SupervisorError<E> {
+    /// A child could not be (re)built.
+    Spawn(E),
+    /// A fault arrived bearing a badge that matches none of the children.
+    UnknownFaultSource(Badge),
+    /// A child could not be torn down. It is left running, so that
+    /// tearing it down can be tried again.
+    Destroy(ProcessDestroyError),
+    /// A child's restart was put off until some number of ticks had
+    /// passed, but the supervisor has no `SupervisorTicker`.
+    NoTicker,
+    SeL4Error(SeL4Error),
+}
+
+impl<E> From<SeL4Error> for SupervisorError<E> {
+    fn from(e: SeL4Error) -> Self {
+        SupervisorError::SeL4Error(e)
+    }
+}
+
+impl<E> From<ProcessDestroyError> for SupervisorError<E> {
+    fn from(e: ProcessDestroyError) -> Self {
+        SupervisorError::Destroy(e)
+    }
+}
+
+/// Why `Supervisor::run` stopped supervising.
+#[derive(Debug)]
+pub enum SupervisorExit {
+    /// A child whose policy is `Escalate` faulted.
+    Escalated(Fault),
+    /// No children remain running.
+    AllChildrenDead,
+}
+
+/// Produces fault sources carrying the badge of a single supervised
+/// child, for wiring into that child's TCB.
+pub struct SupervisedFaultSource<'a> {
+    setup: &'a FaultSinkSetup<role::Local>,
+    local_cnode: &'a LocalCap<LocalCNode>,
+    badge: Badge,
+}
+
+impl<'a> SupervisedFaultSource<'a> {
+    pub fn mint(
+        &self,
+        fault_source_slot: CNodeSlot<FaultSourceRole>,
+    ) -> Result<FaultSource<FaultSourceRole>, FaultManagementError> {
+        self.setup
+            .add_fault_source(self.local_cnode, fault_source_slot, self.badge)
+    }
+}
+
+/// Delivers the ticks of some timer to a `Supervisor`, by which delayed
+/// restarts are measured; typically driven from a timer's interrupt
+/// handler.
+#[derive(Debug)]
+pub struct SupervisorTicker<Role: CNodeRole> {
+    endpoint: Cap<Endpoint, Role>,
+}
+
+impl SupervisorTicker<role::Local> {
+    /// Blocks until the supervisor receives the tick.
+    pub fn tick(&self) {
+        unsafe { seL4_Send(self.endpoint.cptr, seL4_MessageInfo_new(0, 0, 0, 0)) };
+    }
+}
+
+impl RetypeForSetup for SupervisorTicker<role::Local> {
+    type Output = SupervisorTicker<role::Child>;
+}
+
+/// A recipe for building a supervised child process, followed afresh
+/// every time the child is (re)started.
+pub trait Supervisable {
+    type SlotCount: Unsigned;
+    type UntypedBitSize: Unsigned;
+    type ASIDPoolSlots: Unsigned;
+    type StackBitSize: Unsigned;
+    type Error;
+
+    /// Build a new instance of the child with a fresh parameter set,
+    /// typically from its image via `VSpace::new_from_elf` and
+    /// `StandardProcess::new`. Every kernel object belonging to the
+    /// child must be made from the given resources so that it can be
+    /// reclaimed when the child is torn down, and the child's fault
+    /// source must be minted from `fault_source`.
+    ///
+    /// The supervisor starts the returned process, and keeps the VSpace
+    /// it runs in until tearing it down.
+    fn spawn(
+        &mut self,
+        slots: LocalCNodeSlots<Self::SlotCount>,
+        untyped: LocalCap<Untyped<Self::UntypedBitSize>>,
+        asid_pool: LocalCap<ASIDPool<Self::ASIDPoolSlots>>,
+        stack: MappedMemoryRegion<Self::StackBitSize, shared_status::Exclusive>,
+        fault_source: &SupervisedFaultSource,
+    ) -> Result<(StandardProcess<Self::StackBitSize>, VSpace), Self::Error>;
+}
+
+/// The type-erased view of a `SupervisedChild` that a `Supervisor`
+/// works with, allowing children of different shapes to be supervised
+/// together.
+pub trait Supervise<E> {
+    fn badge(&self) -> Badge;
+
+    fn is_running(&self) -> bool;
+
+    /// Whether the child is torn down, waiting out a delay before
+    /// being restarted.
+    fn is_restart_pending(&self) -> bool;
+
+    /// Build and start the child, unless it is already running or has
+    /// been left dead.
+    fn start(
+        &mut self,
+        setup: &FaultSinkSetup<role::Local>,
+        local_cnode: &LocalCap<LocalCNode>,
+    ) -> Result<(), SupervisorError<E>>;
+
+    /// Apply the child's policy to a fault it raised, returning the
+    /// fault if it is to be escalated.
+    fn handle_fault(
+        &mut self,
+        fault: Fault,
+        setup: &FaultSinkSetup<role::Local>,
+        local_cnode: &LocalCap<LocalCNode>,
+    ) -> Result<Option<Fault>, SupervisorError<E>>;
+
+    /// Count down a pending restart by one tick, restarting the child
+    /// once the delay has passed.
+    fn tick(
+        &mut self,
+        setup: &FaultSinkSetup<role::Local>,
+        local_cnode: &LocalCap<LocalCNode>,
+    ) -> Result<(), SupervisorError<E>>;
+}
+
+enum ChildState<S: Supervisable> {
+    Stopped(
+        LocalCNodeSlots<S::SlotCount>,
+        LocalCap<Untyped<S::UntypedBitSize>>,
+        LocalCap<ASIDPool<S::ASIDPoolSlots>>,
+    ),
+    Running(
+        StandardProcess<S::StackBitSize>,
+        VSpace,
+        ProcessResources<S::SlotCount, S::UntypedBitSize, S::ASIDPoolSlots>,
+    ),
+    /// Left behind when building the child failed and what had been
+    /// built couldn't be cleared away; clearing it is tried again on
+    /// the next start.
+    Reclaiming(ProcessResources<S::SlotCount, S::UntypedBitSize, S::ASIDPoolSlots>),
+    /// Only held while moving between the other states
+    Vacant,
+}
+
+/// A child process under supervision, along with the resources from
+/// which it is built and rebuilt.
+pub struct SupervisedChild<S: Supervisable>
+where
+    S::StackBitSize: IsGreaterOrEqual<PageBits>,
+    S::StackBitSize: Sub<PageBits>,
+    <S::StackBitSize as Sub<PageBits>>::Output: Unsigned,
+    <S::StackBitSize as Sub<PageBits>>::Output: _Pow,
+    Pow<<S::StackBitSize as Sub<PageBits>>::Output>: Unsigned,
+{
+    spawner: S,
+    badge: Badge,
+    policy: RestartPolicy,
+    stack: MappedMemoryRegion<S::StackBitSize, shared_status::Exclusive>,
+    state: ChildState<S>,
+    left_dead: bool,
+    restart_in: Option<usize>,
+    restarts: usize,
+}
+
+impl<S: Supervisable> SupervisedChild<S>
+where
+    S::StackBitSize: IsGreaterOrEqual<PageBits>,
+    S::StackBitSize: Sub<PageBits>,
+    <S::StackBitSize as Sub<PageBits>>::Output: Unsigned,
+    <S::StackBitSize as Sub<PageBits>>::Output: _Pow,
+    Pow<<S::StackBitSize as Sub<PageBits>>::Output>: Unsigned,
+{
+    /// The stack region stays mapped in the supervisor's own address
+    /// space and is shared anew with each instance of the child.
+    pub fn new(
+        spawner: S,
+        badge: Badge,
+        policy: RestartPolicy,
+        slots: LocalCNodeSlots<S::SlotCount>,
+        untyped: LocalCap<Untyped<S::UntypedBitSize>>,
+        asid_pool: LocalCap<ASIDPool<S::ASIDPoolSlots>>,
+        stack: MappedMemoryRegion<S::StackBitSize, shared_status::Exclusive>,
+    ) -> Self {
+        SupervisedChild {
+            spawner,
+            badge,
+            policy,
+            stack,
+            state: ChildState::Stopped(slots, untyped, asid_pool),
+            left_dead: false,
+            restart_in: None,
+            restarts: 0,
+        }
+    }
+
+    /// How many times the child has been restarted after a fault.
+    pub fn restarts(&self) -> usize {
+        self.restarts
+    }
+
+    fn stop(&mut self) -> Result<(), ProcessDestroyError> {
+        match core::mem::replace(&mut self.state, ChildState::Vacant) {
+            ChildState::Running(process, vspace, resources) => {
+                match process.destroy(vspace, resources) {
+                    // The stack handed back is an alias of the one kept here
+                    Ok((slots, untyped, asid_pool, _stack)) => {
+                        self.state = ChildState::Stopped(slots, untyped, asid_pool);
+                        Ok(())
+                    }
+                    Err((e, process, vspace, resources)) => {
+                        self.state = ChildState::Running(process, vspace, resources);
+                        Err(e)
+                    }
+                }
+            }
+            other => {
+                self.state = other;
+                Ok(())
+            }
+        }
+    }
+
+    fn restart(
+        &mut self,
+        setup: &FaultSinkSetup<role::Local>,
+        local_cnode: &LocalCap<LocalCNode>,
+    ) -> Result<(), SupervisorError<S::Error>> {
+        self.restarts += 1;
+        self.start(setup, local_cnode)
+    }
+
+    fn backoff_delay(&self, initial_delay: usize, max_delay: usize) -> usize {
+        let mut delay = initial_delay.min(max_delay);
+        for _ in 0..self.restarts {
+            if delay >= max_delay {
+                break;
+            }
+            delay = delay.saturating_mul(2).min(max_delay);
+        }
+        delay
+    }
+}
+
+impl<S: Supervisable> Supervise<S::Error> for SupervisedChild<S>
+where
+    S::StackBitSize: IsGreaterOrEqual<PageBits>,
+    S::StackBitSize: Sub<PageBits>,
+    <S::StackBitSize as Sub<PageBits>>::Output: Unsigned,
+    <S::StackBitSize as Sub<PageBits>>::Output: _Pow,
+    Pow<<S::StackBitSize as Sub<PageBits>>::Output>: Unsigned,
+{
+    fn badge(&self) -> Badge {
+        self.badge
+    }
+
+    fn is_running(&self) -> bool {
+        matches!(self.state, ChildState::Running(..))
+    }
+
+    fn is_restart_pending(&self) -> bool {
+        self.restart_in.is_some()
+    }
+
+    fn start(
+        &mut self,
+        setup: &FaultSinkSetup<role::Local>,
+        local_cnode: &LocalCap<LocalCNode>,
+    ) -> Result<(), SupervisorError<S::Error>> {
+        if self.left_dead {
+            return Ok(());
+        }
+        let (slots, untyped, asid_pool) =
+            match core::mem::replace(&mut self.state, ChildState::Vacant) {
+                ChildState::Stopped(slots, untyped, asid_pool) => (slots, untyped, asid_pool),
+                ChildState::Reclaiming(resources) => match resources.reclaim() {
+                    Ok(reclaimed) => reclaimed,
+                    Err((e, resources)) => {
+                        self.state = ChildState::Reclaiming(resources);
+                        return Err(e.into());
+                    }
+                },
+                other => {
+                    self.state = other;
+                    return Ok(());
+                }
+            };
+
+        let (resources, slots, untyped, asid_pool) =
+            ProcessResources::lend(slots, untyped, asid_pool);
+        let fault_source = SupervisedFaultSource {
+            setup,
+            local_cnode,
+            badge: self.badge,
+        };
+        let stack = unsafe { self.stack.dangerous_internal_alias() };
+        let (mut process, vspace) =
+            match self
+                .spawner
+                .spawn(slots, untyped, asid_pool, stack, &fault_source)
+            {
+                Ok(spawned) => spawned,
+                Err(e) => {
+                    // Clear away whatever was built before the failure
+                    self.state = match resources.reclaim() {
+                        Ok((slots, untyped, asid_pool)) => {
+                            ChildState::Stopped(slots, untyped, asid_pool)
+                        }
+                        Err((_, resources)) => ChildState::Reclaiming(resources),
+                    };
+                    return Err(SupervisorError::Spawn(e));
+                }
+            };
+        let started = process.start();
+        self.state = ChildState::Running(process, vspace, resources);
+        if let Err(e) = started {
+            // Tear the child back down; if that fails too, it stays
+            // Running so that a later teardown can have another go.
+            let _ = self.stop();
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    fn handle_fault(
+        &mut self,
+        fault: Fault,
+        setup: &FaultSinkSetup<role::Local>,
+        local_cnode: &LocalCap<LocalCNode>,
+    ) -> Result<Option<Fault>, SupervisorError<S::Error>> {
+        match self.policy {
+            RestartPolicy::Escalate => return Ok(Some(fault)),
+            RestartPolicy::LeaveDead => {
+                self.stop()?;
+                self.left_dead = true;
+                return Ok(None);
+            }
+            RestartPolicy::Restart => self.stop()?,
+            RestartPolicy::RestartWithBackoff {
+                initial_delay,
+                max_delay,
+            } => {
+                self.stop()?;
+                let delay = self.backoff_delay(initial_delay, max_delay);
+                if delay > 0 {
+                    self.restart_in = Some(delay);
+                    return Ok(None);
+                }
+            }
+        }
+        self.restart(setup, local_cnode)?;
+        Ok(None)
+    }
+
+    fn tick(
+        &mut self,
+        setup: &FaultSinkSetup<role::Local>,
+        local_cnode: &LocalCap<LocalCNode>,
+    ) -> Result<(), SupervisorError<S::Error>> {
+        match self.restart_in {
+            Some(ticks) if ticks > 1 => self.restart_in = Some(ticks - 1),
+            Some(_) => {
+                self.restart_in = None;
+                self.restart(setup, local_cnode)?;
+            }
+            None => (),
+        }
+        Ok(())
+    }
+}
+
+/// Watches the fault sources of a set of children and applies each
+/// child's `RestartPolicy` when it faults.
+pub struct Supervisor<'a> {
+    fault_sink: FaultSinkSetup<role::Local>,
+    local_cnode: &'a LocalCap<LocalCNode>,
+    tick_badge: Option<Badge>,
+}
+
+impl<'a> Supervisor<'a> {
+    pub fn new(
+        fault_sink: FaultSinkSetup<role::Local>,
+        local_cnode: &'a LocalCap<LocalCNode>,
+    ) -> Self {
+        Supervisor {
+            fault_sink,
+            local_cnode,
+            tick_badge: None,
+        }
+    }
+
+    /// Make the ticker for this supervisor's delayed restarts, carrying
+    /// a badge distinct from all of the children's.
+    pub fn ticker<Role: CNodeRole>(
+        &mut self,
+        slot: CNodeSlot<Role>,
+        badge: Badge,
+    ) -> Result<SupervisorTicker<Role>, FaultManagementError> {
+        let source = self
+            .fault_sink
+            .add_fault_source(self.local_cnode, slot, badge)?;
+        self.tick_badge = Some(badge);
+        Ok(SupervisorTicker {
+            endpoint: source.endpoint,
+        })
+    }
+
+    /// Start any children that are not yet running, then handle their
+    /// faults until one is escalated or none remain running or waiting
+    /// to be restarted.
+    ///
+    /// Each child must have been given a distinct badge.
+    pub fn run<E>(
+        &self,
+        children: &mut [&mut dyn Supervise<E>],
+    ) -> Result<SupervisorExit, SupervisorError<E>> {
+        for child in children.iter_mut() {
+            child.start(&self.fault_sink, self.local_cnode)?;
+        }
+
+        loop {
+            if !children
+                .iter()
+                .any(|c| c.is_running() || c.is_restart_pending())
+            {
+                return Ok(SupervisorExit::AllChildrenDead);
+            }
+            if self.tick_badge.is_none() && children.iter().any(|c| c.is_restart_pending()) {
+                return Err(SupervisorError::NoTicker);
+            }
+
+            let fault = self.fault_sink.wait_for_fault();
+            let badge = fault.sender();
+            if Some(badge) == self.tick_badge {
+                for child in children.iter_mut() {
+                    child.tick(&self.fault_sink, self.local_cnode)?;
+                }
+                continue;
+            }
+            let child = children
+                .iter_mut()
+                .find(|c| c.badge() == badge)
+                .ok_or(SupervisorError::UnknownFaultSource(badge))?;
+            if let Some(fault) = child.handle_fault(fault, &self.fault_sink, self.local_cnode)? {
+                return Ok(SupervisorExit::Escalated(fault));
+            }
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! Tracking of which parts of a VSpace's address space are free.
+use arrayvec::ArrayVec;
+
+use typenum::*;
+
+use crate::vspace::VSpaceError;
+
+/// The most separate free ranges an address space can be split into.
+/// Freeing addresses that would split it further fails with
+/// `VSpaceError::TooManyFreeAddressRanges`.
+pub type MaxFreeAddressRanges = U32;
+
+/// The most ranges of address space that can be reserved at once.
+/// Reserving more fails with `VSpaceError::TooManyReservedAddressRanges`.
+pub type MaxReservedAddressRanges = U32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Range {
+    start: usize,
+    /// The address just past the range
+    end: usize,
+}
+
+/// An interval allocator for address space. Free ranges are kept
+/// sorted and merged with their neighbours, and allocations are made
+/// first-fit from the bottom of the address space.
+///
+/// Ranges reserved ahead of the mappings made into them are recorded
+/// separately, so that unmapping a region within one leaves its
+/// address space reserved.
+#[derive(Debug, Clone)]
+pub(crate) struct AddressAllocator {
+    free: ArrayVec<[Range; MaxFreeAddressRanges::USIZE]>,
+    reserved: ArrayVec<[Range; MaxReservedAddressRanges::USIZE]>,
+}
+
+impl AddressAllocator {
+    /// An allocator with everything from `start` up free.
+    pub(crate) fn new(start: usize) -> Self {
+        let mut free = ArrayVec::new();
+        free.push(Range {
+            start,
+            end: core::usize::MAX,
+        });
+        AddressAllocator {
+            free,
+            reserved: ArrayVec::new(),
+        }
+    }
+
+    /// The lowest address at which `size` bytes aligned to `align` are
+    /// free, without claiming them.
+    pub(crate) fn lowest_fit(&self, size: usize, align: usize) -> Option<usize> {
+        self.free.iter().find_map(|range| {
+            let start = align_up(range.start, align)?;
+            let end = start.checked_add(size)?;
+            if end <= range.end {
+                Some(start)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Claim the lowest `size` free bytes aligned to `align`.
+    pub(crate) fn allocate(&mut self, size: usize, align: usize) -> Result<usize, VSpaceError> {
+        let start = self
+            .lowest_fit(size, align)
+            .ok_or(VSpaceError::InsufficientAddressSpaceAvailableToMapRegion)?;
+        self.mark_used(start, size)?;
+        Ok(start)
+    }
+
+    /// Reserve `size` bytes at `start`, which must all be free.
+    pub(crate) fn reserve(&mut self, start: usize, size: usize) -> Result<(), VSpaceError> {
+        if !self.is_free(start, size) {
+            return Err(VSpaceError::AddressRangeUnavailable);
+        }
+        self.record_reservation(start, size)?;
+        self.mark_used(start, size).map_err(|e| {
+            self.reserved.pop();
+            e
+        })
+    }
+
+    /// Reserve the lowest `size` free bytes aligned to `align`.
+    pub(crate) fn allocate_reserved(
+        &mut self,
+        size: usize,
+        align: usize,
+    ) -> Result<usize, VSpaceError> {
+        let start = self
+            .lowest_fit(size, align)
+            .ok_or(VSpaceError::InsufficientAddressSpaceAvailableToMapRegion)?;
+        self.reserve(start, size)?;
+        Ok(start)
+    }
+
+    /// Give back the reservation of exactly the `size` bytes at `start`.
+    pub(crate) fn release_reserved(
+        &mut self,
+        start: usize,
+        size: usize,
+    ) -> Result<(), VSpaceError> {
+        let range = Range {
+            start,
+            end: start
+                .checked_add(size)
+                .ok_or(VSpaceError::ExceededAddressableSpace)?,
+        };
+        let index = self
+            .reserved
+            .iter()
+            .position(|r| *r == range)
+            .ok_or(VSpaceError::AddressRangeNotReserved)?;
+        self.release(start, size)?;
+        self.reserved.swap_remove(index);
+        Ok(())
+    }
+
+    /// Make whichever of the `size` bytes at `start` aren't reserved
+    /// free again, as for a region that's been unmapped.
+    pub(crate) fn release_unreserved(
+        &mut self,
+        start: usize,
+        size: usize,
+    ) -> Result<(), VSpaceError> {
+        let end = start
+            .checked_add(size)
+            .ok_or(VSpaceError::ExceededAddressableSpace)?;
+        let mut next = start;
+        while next < end {
+            // Reservations never overlap, so the lowest one left in the
+            // range bounds the next stretch to free
+            match self
+                .reserved
+                .iter()
+                .filter(|r| r.end > next && r.start < end)
+                .min_by_key(|r| r.start)
+                .copied()
+            {
+                Some(r) => {
+                    if r.start > next {
+                        self.release(next, r.start - next)?;
+                    }
+                    next = r.end;
+                }
+                None => {
+                    self.release(next, end - next)?;
+                    next = end;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn record_reservation(&mut self, start: usize, size: usize) -> Result<(), VSpaceError> {
+        self.reserved
+            .try_push(Range {
+                start,
+                end: start + size,
+            })
+            .map_err(|_| VSpaceError::TooManyReservedAddressRanges)
+    }
+
+    /// Claim whichever of the `size` bytes at `start` are still free.
+    /// Used to record mappings made at addresses chosen by the caller,
+    /// which may fall within ranges claimed for them in advance.
+    pub(crate) fn mark_used(&mut self, start: usize, size: usize) -> Result<(), VSpaceError> {
+        let end = start
+            .checked_add(size)
+            .ok_or(VSpaceError::ExceededAddressableSpace)?;
+        let mut i = 0;
+        while i < self.free.len() {
+            let range = self.free[i];
+            if range.end <= start || range.start >= end {
+                i += 1;
+                continue;
+            }
+            match (range.start < start, range.end > end) {
+                // The used range is in the middle; split the free one,
+                // only cutting it short once there's room for its tail
+                (true, true) => {
+                    self.insert_at(
+                        i + 1,
+                        Range {
+                            start: end,
+                            end: range.end,
+                        },
+                    )?;
+                    self.free[i].end = start;
+                    return Ok(());
+                }
+                (true, false) => {
+                    self.free[i].end = start;
+                    i += 1;
+                }
+                (false, true) => {
+                    self.free[i].start = end;
+                    return Ok(());
+                }
+                (false, false) => {
+                    self.free.remove(i);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Make the `size` bytes at `start` free again, merging them with
+    /// any free space they touch. None of them may be free already.
+    pub(crate) fn release(&mut self, start: usize, size: usize) -> Result<(), VSpaceError> {
+        let mut new = Range {
+            start,
+            end: start
+                .checked_add(size)
+                .ok_or(VSpaceError::ExceededAddressableSpace)?,
+        };
+        if size == 0 {
+            return Ok(());
+        }
+        if self
+            .free
+            .iter()
+            .any(|range| range.start < new.end && new.start < range.end)
+        {
+            return Err(VSpaceError::AddressRangeAlreadyFree);
+        }
+
+        // Swallow every free range the new one overlaps or touches
+        let first = self
+            .free
+            .iter()
+            .position(|range| range.end >= new.start)
+            .unwrap_or_else(|| self.free.len());
+        while first < self.free.len() && self.free[first].start <= new.end {
+            let range = self.free.remove(first);
+            new.start = core::cmp::min(new.start, range.start);
+            new.end = core::cmp::max(new.end, range.end);
+        }
+        self.insert_at(first, new)
+    }
+
+    /// Whether all `size` bytes at `start` are free.
+    pub(crate) fn is_free(&self, start: usize, size: usize) -> bool {
+        match start.checked_add(size) {
+            Some(end) => self
+                .free
+                .iter()
+                .any(|range| range.start <= start && end <= range.end),
+            None => false,
+        }
+    }
+
+    /// The total number of free bytes.
+    pub(crate) fn free_bytes(&self) -> usize {
+        self.free.iter().map(|range| range.end - range.start).sum()
+    }
+
+    /// The size of the largest free range.
+    pub(crate) fn largest_free_range(&self) -> usize {
+        self.free
+            .iter()
+            .map(|range| range.end - range.start)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn insert_at(&mut self, index: usize, range: Range) -> Result<(), VSpaceError> {
+        self.free
+            .try_insert(index, range)
+            .map_err(|_| VSpaceError::TooManyFreeAddressRanges)
+    }
+}
+
+fn align_up(n: usize, align: usize) -> Option<usize> {
+    Some(n.checked_add(align - 1)? & !(align - 1))
+}
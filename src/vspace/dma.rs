@@ -0,0 +1,331 @@
+//! Buffers of memory for devices to read and write by DMA.
+//!
+//! A `DmaBuffer` is mapped uncached or write-combined, so the CPU and
+//! the device see the same contents without cache maintenance, and
+//! it's tracked in its type as owned by either the CPU or the device.
+//! Only the CPU-owned buffer can be read or written through; handing
+//! it to the device gives it up until it's handed back.
+//!
+//! A buffer retyped out of a single untyped is physically contiguous.
+//! One gathered a page at a time from an allocator may be scattered
+//! about physical memory, so a device is given it as the list of
+//! physically contiguous segments from `segments`.
+use core::iter::Peekable;
+use core::marker::PhantomData;
+use core::ops::Sub;
+
+use arrayvec::ArrayVec;
+
+use selfe_sys::{seL4_CNode_Revoke, seL4_WordBits};
+
+use typenum::*;
+
+use super::{
+    shared_status, KernelRetypeFanOutLimit, MappedMemoryRegion, MemoryRegion, NumPages,
+    UnmappedMemoryRegion, VSpace, VSpaceError,
+};
+use crate::alloc::ut_buddy::{UTBuddyError, WUTBuddy};
+use crate::arch::{self, PageBits};
+use crate::cap::{
+    memory_kind, page_state, Cap, LocalCNodeSlots, LocalCap, Page, Untyped, WCNodeSlots, WUntyped,
+    WeakMemoryKind,
+};
+use crate::error::{ErrorExt, SeL4Error};
+use crate::pow::{Pow, _Pow};
+use crate::userland::rights;
+
+pub trait DmaOwner: private::SealedDmaOwner {}
+
+pub mod dma_owner {
+    use super::DmaOwner;
+
+    /// The CPU may read and write the buffer; the device must not
+    /// touch it.
+    pub struct Cpu;
+    impl DmaOwner for Cpu {}
+
+    /// The device may read and write the buffer; the CPU must not
+    /// touch it.
+    pub struct Device;
+    impl DmaOwner for Device {}
+}
+
+mod private {
+    use super::dma_owner::{Cpu, Device};
+    pub trait SealedDmaOwner {}
+    impl SealedDmaOwner for Cpu {}
+    impl SealedDmaOwner for Device {}
+}
+
+#[derive(Debug)]
+pub enum DmaError {
+    UTBuddyError(UTBuddyError),
+    VSpaceError(VSpaceError),
+    SeL4Error(SeL4Error),
+}
+
+impl From<UTBuddyError> for DmaError {
+    fn from(e: UTBuddyError) -> Self {
+        DmaError::UTBuddyError(e)
+    }
+}
+
+impl From<VSpaceError> for DmaError {
+    fn from(e: VSpaceError) -> Self {
+        DmaError::VSpaceError(e)
+    }
+}
+
+impl From<SeL4Error> for DmaError {
+    fn from(e: SeL4Error) -> Self {
+        DmaError::SeL4Error(e)
+    }
+}
+
+/// How the CPU's view of a buffer is cached.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DmaCaching {
+    /// Every access goes straight to memory.
+    Uncached,
+    /// Reads go straight to memory, while writes may be buffered and
+    /// combined, which suits buffers the CPU fills for the device.
+    WriteCombined,
+}
+
+impl DmaCaching {
+    fn vm_attributes(self) -> arch::VMAttributes {
+        let attributes = match self {
+            DmaCaching::Uncached => arch::vm_attributes::UNCACHED,
+            DmaCaching::WriteCombined => arch::vm_attributes::WRITE_COMBINED,
+        };
+        attributes | arch::vm_attributes::EXECUTE_NEVER
+    }
+}
+
+/// A physically contiguous run of a buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DmaSegment {
+    pub paddr: usize,
+    pub size_bytes: usize,
+}
+
+/// A `1 << SizeBits` bytes buffer for DMA, owned by `Owner`.
+pub struct DmaBuffer<SizeBits: Unsigned, Owner: DmaOwner = dma_owner::Cpu>
+where
+    SizeBits: IsGreaterOrEqual<PageBits>,
+    SizeBits: Sub<PageBits>,
+    <SizeBits as Sub<PageBits>>::Output: Unsigned,
+    <SizeBits as Sub<PageBits>>::Output: _Pow,
+    Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+{
+    region: MappedMemoryRegion<SizeBits, shared_status::Exclusive>,
+    caching: DmaCaching,
+    _owner: PhantomData<Owner>,
+}
+
+impl<SizeBits: Unsigned> DmaBuffer<SizeBits, dma_owner::Cpu>
+where
+    SizeBits: IsGreaterOrEqual<PageBits>,
+    SizeBits: Sub<PageBits>,
+    <SizeBits as Sub<PageBits>>::Output: Unsigned,
+    <SizeBits as Sub<PageBits>>::Output: _Pow,
+    Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+{
+    /// Retype `ut` into a physically contiguous buffer and map it
+    /// into `vspace`.
+    pub fn new_contiguous(
+        ut: LocalCap<Untyped<SizeBits>>,
+        slots: LocalCNodeSlots<NumPages<SizeBits>>,
+        vspace: &mut VSpace,
+        caching: DmaCaching,
+    ) -> Result<Self, DmaError>
+    where
+        Pow<<SizeBits as Sub<PageBits>>::Output>:
+            IsLessOrEqual<KernelRetypeFanOutLimit, Output = True>,
+    {
+        let region = UnmappedMemoryRegion::new(ut, slots)?;
+        Self::map(region, vspace, caching)
+    }
+
+    /// Gather a buffer a page at a time from `untyped`, which may
+    /// leave it scattered about physical memory, and map it into
+    /// `vspace`. Any untyped split off along the way takes its slots
+    /// from `ut_slots`.
+    ///
+    /// Should it fail, the pages gathered so far are deleted and their
+    /// untypeds go back to `untyped`, and `slots` are handed back
+    /// along with the error.
+    pub fn new_scattered(
+        untyped: &mut WUTBuddy,
+        ut_slots: &mut WCNodeSlots,
+        slots: LocalCNodeSlots<NumPages<SizeBits>>,
+        vspace: &mut VSpace,
+        caching: DmaCaching,
+    ) -> Result<Self, (DmaError, LocalCNodeSlots<NumPages<SizeBits>>)>
+    where
+        Pow<<SizeBits as Sub<PageBits>>::Output>:
+            IsLessOrEqual<KernelRetypeFanOutLimit, Output = True>,
+    {
+        let (slots_cptr, start_cptr) = (slots.cptr, slots.cap_data.offset);
+        let mut page_uts = ArrayVec::<[usize; KernelRetypeFanOutLimit::USIZE]>::new();
+        let gathered = slots.iter().try_for_each(|slot| -> Result<(), DmaError> {
+            let ut = untyped.alloc_strong::<PageBits>(ut_slots)?;
+            page_uts.push(ut.cptr);
+            let _page: LocalCap<Page<page_state::Unmapped>> = ut.retype(slot)?;
+            Ok(())
+        });
+        let mapped = gathered.and_then(|()| {
+            let region = MemoryRegion::unchecked_new(
+                start_cptr,
+                page_state::Unmapped,
+                WeakMemoryKind::General,
+                PageBits::U8,
+            );
+            Self::map(region, vspace, caching)
+        });
+        mapped.map_err(|e| {
+            give_back_pages(untyped, ut_slots, &page_uts);
+            (e, Cap::internal_new(slots_cptr, start_cptr))
+        })
+    }
+
+    fn map(
+        region: UnmappedMemoryRegion<SizeBits, shared_status::Exclusive>,
+        vspace: &mut VSpace,
+        caching: DmaCaching,
+    ) -> Result<Self, DmaError> {
+        let region = vspace.map_region(region, rights::RW, caching.vm_attributes())?;
+        Ok(DmaBuffer {
+            region,
+            caching,
+            _owner: PhantomData,
+        })
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        self.region.as_slice()
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.region.as_mut_slice()
+    }
+
+    /// Hand the buffer to the device. Everything the CPU wrote to it
+    /// beforehand is visible to the device.
+    pub fn to_device(self) -> DmaBuffer<SizeBits, dma_owner::Device> {
+        arch::dma_barrier();
+        DmaBuffer {
+            region: self.region,
+            caching: self.caching,
+            _owner: PhantomData,
+        }
+    }
+
+    /// Give up the buffer, as for unmapping it from its VSpace.
+    pub fn into_region(self) -> MappedMemoryRegion<SizeBits, shared_status::Exclusive> {
+        self.region
+    }
+}
+
+impl<SizeBits: Unsigned> DmaBuffer<SizeBits, dma_owner::Device>
+where
+    SizeBits: IsGreaterOrEqual<PageBits>,
+    SizeBits: Sub<PageBits>,
+    <SizeBits as Sub<PageBits>>::Output: Unsigned,
+    <SizeBits as Sub<PageBits>>::Output: _Pow,
+    Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+{
+    /// Take the buffer back from the device, once it's signalled it's
+    /// done with it. Everything the device wrote to it is then visible
+    /// to the CPU.
+    pub fn to_cpu(self) -> DmaBuffer<SizeBits, dma_owner::Cpu> {
+        arch::dma_barrier();
+        DmaBuffer {
+            region: self.region,
+            caching: self.caching,
+            _owner: PhantomData,
+        }
+    }
+}
+
+impl<SizeBits: Unsigned, Owner: DmaOwner> DmaBuffer<SizeBits, Owner>
+where
+    SizeBits: IsGreaterOrEqual<PageBits>,
+    SizeBits: Sub<PageBits>,
+    <SizeBits as Sub<PageBits>>::Output: Unsigned,
+    <SizeBits as Sub<PageBits>>::Output: _Pow,
+    Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+{
+    pub fn vaddr(&self) -> usize {
+        self.region.vaddr()
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        self.region.size_bytes()
+    }
+
+    pub fn caching(&self) -> DmaCaching {
+        self.caching
+    }
+
+    /// The physically contiguous segments making up this buffer, in
+    /// address order, to hand to a device as a scatter/gather list. A
+    /// contiguous buffer is a single segment.
+    pub fn segments(&self) -> impl Iterator<Item = Result<DmaSegment, SeL4Error>> {
+        Segments {
+            paddrs: self.region.frame_paddrs().peekable(),
+            frame_bytes: 1 << self.region.frame_bits(),
+        }
+    }
+}
+
+/// Return the page-sized untypeds in `page_uts` to `untyped`,
+/// deleting the pages retyped from them. Any that can't be revoked or
+/// taken back are lost.
+fn give_back_pages(untyped: &mut WUTBuddy, ut_slots: &mut WCNodeSlots, page_uts: &[usize]) {
+    // The latest first, so that split halves can merge back as they go.
+    for &ut_cptr in page_uts.iter().rev() {
+        // Revoking the untyped deletes the page retyped from it, and
+        // with it the page's mapping.
+        let revoked = unsafe { seL4_CNode_Revoke(ut_slots.cptr, ut_cptr, seL4_WordBits as u8) };
+        if revoked.as_result().is_err() {
+            continue;
+        }
+        let ut = Cap {
+            cptr: ut_cptr,
+            _role: PhantomData,
+            cap_data: WUntyped {
+                size_bits: PageBits::U8,
+                kind: memory_kind::General,
+            },
+        };
+        let _ = untyped.free_in(ut, ut_slots.cptr);
+    }
+}
+
+/// Coalesces the physical addresses of consecutive frames into
+/// segments wherever they're adjacent.
+struct Segments<I: Iterator<Item = Result<usize, SeL4Error>>> {
+    paddrs: Peekable<I>,
+    frame_bytes: usize,
+}
+
+impl<I: Iterator<Item = Result<usize, SeL4Error>>> Iterator for Segments<I> {
+    type Item = Result<DmaSegment, SeL4Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let paddr = match self.paddrs.next()? {
+            Ok(paddr) => paddr,
+            Err(e) => return Some(Err(e)),
+        };
+        let mut size_bytes = self.frame_bytes;
+        while let Some(Ok(next)) = self.paddrs.peek() {
+            if *next != paddr + size_bytes {
+                break;
+            }
+            size_bytes += self.frame_bytes;
+            let _ = self.paddrs.next();
+        }
+        Some(Ok(DmaSegment { paddr, size_bytes }))
+    }
+}
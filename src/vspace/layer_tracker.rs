@@ -0,0 +1,239 @@
+//! Occupancy tracking for the intermediate paging objects a VSpace
+//! creates, so that each can be torn down once nothing is mapped
+//! through it.
+use core::marker::PhantomData;
+
+use arrayvec::ArrayVec;
+
+use selfe_sys::*;
+
+use typenum::*;
+
+use crate::alloc::ut_buddy::{UTBuddyError, WUTBuddy};
+use crate::arch::PAGING_LAYER_SPAN_BITS;
+use crate::cap::{memory_kind, Cap, WCNodeSlots, WCNodeSlotsData, WUntyped};
+use crate::error::{ErrorExt, SeL4Error};
+
+/// The most intermediate paging objects a VSpace keeps track of. Objects
+/// created beyond that are never reclaimed, and neither is the object
+/// they're mapped into; `VSpace::leaked_paging_layers` counts them.
+pub type MaxTrackedPagingLayers = U32;
+
+/// How many layers of intermediate paging objects sit below the root.
+pub(crate) const PAGING_DEPTH: usize = PAGING_LAYER_SPAN_BITS.len();
+
+/// An intermediate paging object freshly retyped and mapped in, whose
+/// place in the paging structure isn't settled yet.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NewLayer {
+    pub(crate) cptr: usize,
+    pub(crate) untyped: usize,
+    pub(crate) untyped_size_bits: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TrackedLayer {
+    /// How far below the root the object sits, starting at 1
+    depth: usize,
+    /// The lowest address mapped through the object
+    base: usize,
+    object: NewLayer,
+    /// How many frames, or objects one layer down, are mapped into it
+    occupancy: usize,
+}
+
+/// Counts what is mapped into each intermediate paging object the
+/// VSpace created. When the count drops to zero the object is deleted,
+/// which unmaps it, and its untyped goes back to the VSpace's buddy.
+#[derive(Debug, Clone)]
+pub struct LayerTracker {
+    layers: ArrayVec<[TrackedLayer; MaxTrackedPagingLayers::USIZE]>,
+    /// Slots emptied by deleting objects, to retype the next ones into
+    free_slots: ArrayVec<[usize; MaxTrackedPagingLayers::USIZE]>,
+    /// Objects which will never be deleted, either for want of room to
+    /// track them or for want of room in the buddy to take them back
+    untracked: usize,
+}
+
+impl LayerTracker {
+    pub(crate) fn new() -> Self {
+        LayerTracker {
+            layers: ArrayVec::new(),
+            free_slots: ArrayVec::new(),
+            untracked: 0,
+        }
+    }
+
+    /// How many objects hold on to their memory with nothing mapped
+    /// into them: those that will never be deleted, and those left
+    /// empty while the buddy had no room to take them back, which are
+    /// tried again whenever something mapped into them is unmapped.
+    pub(crate) fn leaked(&self) -> usize {
+        self.untracked
+            + self
+                .layers
+                .iter()
+                .filter(|layer| layer.occupancy == 0)
+                .count()
+    }
+
+    /// A slot once used by a deleted object, in the same CNode as
+    /// `slots`.
+    pub(crate) fn take_free_slot(&mut self, slots: &WCNodeSlots) -> Option<WCNodeSlots> {
+        self.free_slots.pop().map(|offset| Cap {
+            cptr: slots.cptr,
+            cap_data: WCNodeSlotsData {
+                offset,
+                size: 1,
+                _role: PhantomData,
+            },
+            _role: PhantomData,
+        })
+    }
+
+    /// Start tracking an object mapped in to cover `addr` at `depth`,
+    /// with nothing mapped into it yet.
+    pub(crate) fn record(&mut self, depth: usize, addr: usize, object: NewLayer) {
+        // The object above holds this one whether or not there's room
+        // to track it; if there isn't, the object above is never
+        // reclaimed.
+        if let Some(parent) = self.find(depth - 1, addr) {
+            self.layers[parent].occupancy += 1;
+        }
+        let tracked = self.layers.try_push(TrackedLayer {
+            depth,
+            base: addr & !span_mask(depth),
+            object,
+            occupancy: 0,
+        });
+        if tracked.is_err() {
+            self.untracked += 1;
+        }
+    }
+
+    /// Count a frame mapped at `addr` by the object at `depth`.
+    pub(crate) fn frame_mapped(&mut self, depth: usize, addr: usize) {
+        if let Some(index) = self.find(depth, addr) {
+            self.layers[index].occupancy += 1;
+        }
+    }
+
+    /// Reclaim the object at `depth` the frame at `addr` was mapped
+    /// into if it was the last thing there, and so on up the paging
+    /// structure.
+    pub(crate) fn frame_unmapped(
+        &mut self,
+        depth: usize,
+        addr: usize,
+        utb: &mut WUTBuddy,
+        slots: &mut WCNodeSlots,
+    ) -> Result<(), SeL4Error> {
+        match self.find(depth, addr) {
+            Some(index) => {
+                let layer = &mut self.layers[index];
+                layer.occupancy = layer.occupancy.saturating_sub(1);
+                self.reclaim_if_empty(index, utb, slots)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Reclaim the deepest object covering `addr` if nothing is mapped
+    /// into it, as after backing out of a failed mapping.
+    pub(crate) fn reclaim_empty(
+        &mut self,
+        addr: usize,
+        utb: &mut WUTBuddy,
+        slots: &mut WCNodeSlots,
+    ) -> Result<(), SeL4Error> {
+        match (1..=PAGING_DEPTH)
+            .rev()
+            .find_map(|depth| self.find(depth, addr))
+        {
+            Some(index) => self.reclaim_if_empty(index, utb, slots),
+            None => Ok(()),
+        }
+    }
+
+    /// Delete an object, which unmaps it, and give back its untyped,
+    /// merging it with its buddy where it can. If there's no room in
+    /// `utb` to take the untyped, the object is left where it is and
+    /// counted as leaked.
+    pub(crate) fn discard(
+        &mut self,
+        object: NewLayer,
+        utb: &mut WUTBuddy,
+        slots: &mut WCNodeSlots,
+    ) -> Result<(), SeL4Error> {
+        if !utb.can_take_back(object.untyped_size_bits) {
+            self.untracked += 1;
+            return Ok(());
+        }
+        unsafe { seL4_CNode_Delete(slots.cptr, object.cptr, seL4_WordBits as u8) }
+            .as_result()
+            .map_err(SeL4Error::CNodeDelete)?;
+        let freed = utb.free_in(
+            Cap {
+                cptr: object.untyped,
+                cap_data: WUntyped {
+                    size_bits: object.untyped_size_bits,
+                    kind: memory_kind::General,
+                },
+                _role: PhantomData,
+            },
+            slots.cptr,
+        );
+        if let Err((UTBuddyError::SeL4Error(e), _)) = freed {
+            return Err(e);
+        }
+        let _ = self.free_slots.try_push(object.cptr);
+        Ok(())
+    }
+
+    fn reclaim_if_empty(
+        &mut self,
+        mut index: usize,
+        utb: &mut WUTBuddy,
+        slots: &mut WCNodeSlots,
+    ) -> Result<(), SeL4Error> {
+        loop {
+            let layer = self.layers[index];
+            if layer.occupancy != 0 || !utb.can_take_back(layer.object.untyped_size_bits) {
+                return Ok(());
+            }
+            self.discard(layer.object, utb, slots)?;
+            self.layers.swap_remove(index);
+            match self.find(layer.depth - 1, layer.base) {
+                Some(parent) => {
+                    self.layers[parent].occupancy -= 1;
+                    index = parent;
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+
+    fn find(&self, depth: usize, addr: usize) -> Option<usize> {
+        if depth == 0 {
+            return None;
+        }
+        let base = addr & !span_mask(depth);
+        self.layers
+            .iter()
+            .position(|layer| layer.depth == depth && layer.base == base)
+    }
+}
+
+/// How far below the root the object sits that maps a frame of
+/// `frame_bits` with a single entry: the highest whose entries are no
+/// bigger than the frame.
+pub(crate) fn frame_depth(frame_bits: u8) -> usize {
+    PAGING_LAYER_SPAN_BITS
+        .iter()
+        .position(|span_bits| *span_bits <= usize::from(frame_bits))
+        .unwrap_or(PAGING_DEPTH)
+}
+
+fn span_mask(depth: usize) -> usize {
+    (1 << PAGING_LAYER_SPAN_BITS[depth - 1]) - 1
+}
@@ -5,10 +5,13 @@
 //! memory _regions_ rather than expose the granules that each layer
 //! in the addressing structures is responsible for mapping.
 use core::marker::PhantomData;
-use core::ops::Sub;
+use core::ops::{Add, Sub};
+
+use arrayvec::ArrayVec;
 
 use typenum::*;
 
+use crate::alloc::micro_alloc::{DeviceAllocator, DeviceRangeAllocError, PageAlignedAddressRange};
 use crate::alloc::ut_buddy::{self, UTBuddyError, WUTBuddy};
 use crate::arch::{self, AddressSpace, PageBits, PageBytes, PagingRoot, PagingRootLowerLevel};
 use crate::bootstrap::UserImage;
@@ -16,13 +19,23 @@ use crate::cap::{
     memory_kind, page_state, role, AssignedASID, CNodeRole, CNodeSlots, Cap, CapRange, CapType,
     ChildCNodeSlot, DirectRetype, InternalASID, LocalCNode, LocalCNodeSlots, LocalCap, Page,
     PhantomCap, RetypeError, UnassignedASID, Untyped, WCNodeSlots, WCNodeSlotsData, WUntyped,
-    WeakCapRange, WeakCopyError,
+    WeakCopyError,
 };
-use crate::error::SeL4Error;
+use crate::error::{KernelError, SeL4Error};
 use crate::pow::{Pow, _Pow};
-use crate::userland::CapRights;
+use crate::userland::{CapRights, Rights, STARTUP_NOTE_NAME, STARTUP_NOTE_TYPE};
+mod address_allocator;
+pub mod dma;
+mod layer_tracker;
 mod region;
+mod reloc;
+mod tls;
+use address_allocator::AddressAllocator;
+pub use address_allocator::{MaxFreeAddressRanges, MaxReservedAddressRanges};
+pub use layer_tracker::MaxTrackedPagingLayers;
+use layer_tracker::{frame_depth, LayerTracker, NewLayer, PAGING_DEPTH};
 pub use region::*;
+pub use tls::*;
 
 include!(concat!(env!("OUT_DIR"), "/KERNEL_RETYPE_FAN_OUT_LIMIT"));
 
@@ -31,13 +44,28 @@ pub trait ElfProc: Sized {
     const IMAGE_NAME: &'static str;
 
     /// The total number of pages which need to be mapped when starting the
-    /// process
+    /// process; the sum of `SharedPages` and `InstancePages`.
     type RequiredPages: Unsigned;
 
+    /// The number of read-only pages. These are mapped straight from the
+    /// `UserImage`, so every instance shares them and each costs only a
+    /// CNode slot per instance.
+    type SharedPages: Unsigned;
+
+    /// The number of pages retyped out of each instance's
+    /// `RequiredMemoryBits` of memory to hold its own copy of the
+    /// writable pages and its initial TLS block.
+    type InstancePages: Unsigned;
+
     /// The number of pages which need to be mapped as writeable (data and BSS
     /// sections)
     type WritablePages: Unsigned;
 
+    /// The number of pages in the initial thread's TLS block, zero if
+    /// the image has no `PT_TLS` segment. These are retyped along with
+    /// the writable pages, and so are counted in `InstancePages`.
+    type TLSPages: Unsigned;
+
     /// How much memory is required to set up this process (for its writable
     /// pages), as a bitsize.
     type RequiredMemoryBits: Unsigned;
@@ -143,9 +171,23 @@ pub enum VSpaceError {
     /// An attempted mapping would have overflowed the maximum addressable range
     /// (core::usize::MAX)
     ExceededAddressableSpace,
-    /// Internal watermarking has determined that the desired region mapping
-    /// would not fit in available unclaimed address space.
+    /// There is no free range of address space large enough for the
+    /// desired region mapping.
     InsufficientAddressSpaceAvailableToMapRegion,
+    /// Part of the range of address space asked for is already mapped
+    /// or reserved.
+    AddressRangeUnavailable,
+    /// Freeing the range of address space would leave the free space
+    /// split into more than `MaxFreeAddressRanges` ranges.
+    TooManyFreeAddressRanges,
+    /// Reserving the range of address space would leave more than
+    /// `MaxReservedAddressRanges` ranges reserved.
+    TooManyReservedAddressRanges,
+    /// The range of address space to release doesn't match any one
+    /// range that was reserved.
+    AddressRangeNotReserved,
+    /// Part of the range of address space to free is free already.
+    AddressRangeAlreadyFree,
     ASIDMismatch,
 
     /// This error is returned by `map_region_at_addr` its rollback
@@ -154,8 +196,25 @@ pub enum VSpaceError {
     /// special-sized granules.
     TriedToMapTooManyPagesAtOnce,
     InvalidRegionSize,
+    /// The region is made of frames too large for what was asked of
+    /// it, such as splitting a region of one frame.
+    FrameTooLarge,
     ElfParseError(&'static str),
     InsufficientResourcesForElf,
+    /// The region given for a thread's TLS block is smaller than the
+    /// block laid out from the image's TLS template.
+    TLSBlockTooSmall,
+    /// A position-independent elf image has a relocation of a kind the
+    /// loader can't apply.
+    UnsupportedRelocation(u32),
+    /// A position-independent elf image has a relocation which doesn't
+    /// land in any of its writable segments.
+    RelocationOutsideWritableSegment(usize),
+    /// The elf image's startup block slot doesn't lie wholly within one
+    /// of its writable segments, the only ones the loader writes to.
+    StartupBlockSlotNotWritable,
+    /// Device memory couldn't be taken from the `DeviceAllocator`.
+    DeviceRangeAllocError(DeviceRangeAllocError),
 }
 
 impl From<RetypeError> for VSpaceError {
@@ -170,17 +229,31 @@ impl From<SeL4Error> for VSpaceError {
     }
 }
 
+impl From<DeviceRangeAllocError> for VSpaceError {
+    fn from(e: DeviceRangeAllocError) -> VSpaceError {
+        VSpaceError::DeviceRangeAllocError(e)
+    }
+}
+
+/// Device registers must be neither cached nor executed from.
+const DEVICE_VM_ATTRIBUTES: arch::VMAttributes =
+    arch::vm_attributes::UNCACHED | arch::vm_attributes::EXECUTE_NEVER;
+
 /// A `PagingLayer` is a mapping-layer in an architecture's address
 /// space structure.
 pub trait PagingLayer {
     /// The `Item` is the granule which this layer maps.
     type Item: CapType + DirectRetype + PhantomCap;
 
+    /// How far below the root this layer sits; the root is 0.
+    const DEPTH: usize;
+
     /// A function which attempts to map this layer's granule at the
     /// given address. If the error is a seL4 lookup error, then the
     /// implementor ought to return `MappingError::Overflow` to signal
     /// that mapping is needed at the layer above, otherwise the error
-    /// is just bubbled up to the caller.
+    /// is just bubbled up to the caller. Any intermediate objects
+    /// created along the way are recorded in `tracker`.
     fn map_layer(
         &mut self,
         item: &LocalCap<Self::Item>,
@@ -190,9 +263,42 @@ pub trait PagingLayer {
         vm_attributes: arch::VMAttributes,
         utb: &mut WUTBuddy,
         slots: &mut WCNodeSlots,
+        tracker: &mut LayerTracker,
+    ) -> Result<(), MappingError>;
+
+    /// Map `frame` with a single entry in the object at `depth` that
+    /// covers `addr`, where `depth` is this layer's or one above it.
+    /// Missing layers are built out just as for `map_layer`.
+    fn map_frame(
+        &mut self,
+        frame: &LocalCap<Page<page_state::Unmapped>>,
+        depth: usize,
+        addr: usize,
+        root: &mut LocalCap<PagingRoot>,
+        rights: CapRights,
+        vm_attributes: arch::VMAttributes,
+        utb: &mut WUTBuddy,
+        slots: &mut WCNodeSlots,
+        tracker: &mut LayerTracker,
     ) -> Result<(), MappingError>;
 }
 
+/// Map a frame of any size into whichever object the kernel finds for
+/// it, signalling `MappingError::Overflow` if that object is missing.
+fn map_frame_entry(
+    frame: &LocalCap<Page<page_state::Unmapped>>,
+    addr: usize,
+    root: &mut LocalCap<PagingRoot>,
+    rights: CapRights,
+    vm_attributes: arch::VMAttributes,
+) -> Result<(), MappingError> {
+    match unsafe { frame.unchecked_page_map(addr, root, rights, vm_attributes) } {
+        Ok(_) => Ok(()),
+        Err(SeL4Error::PageMap(KernelError::FailedLookup)) => Err(MappingError::Overflow),
+        Err(e) => Err(MappingError::PageMapFailure(e)),
+    }
+}
+
 /// `PagingTop` represents the root of an address space structure.
 pub struct PagingTop
 where
@@ -206,6 +312,7 @@ where
 
 impl PagingLayer for PagingTop {
     type Item = PagingRootLowerLevel;
+    const DEPTH: usize = 0;
     fn map_layer(
         &mut self,
         item: &LocalCap<Self::Item>,
@@ -215,10 +322,26 @@ impl PagingLayer for PagingTop {
         vm_attributes: arch::VMAttributes,
         _utb: &mut WUTBuddy,
         _slots: &mut WCNodeSlots,
+        _tracker: &mut LayerTracker,
     ) -> Result<(), MappingError> {
         self.layer
             .map_granule(item, addr, root, rights, vm_attributes)
     }
+
+    fn map_frame(
+        &mut self,
+        frame: &LocalCap<Page<page_state::Unmapped>>,
+        _depth: usize,
+        addr: usize,
+        root: &mut LocalCap<PagingRoot>,
+        rights: CapRights,
+        vm_attributes: arch::VMAttributes,
+        _utb: &mut WUTBuddy,
+        _slots: &mut WCNodeSlots,
+        _tracker: &mut LayerTracker,
+    ) -> Result<(), MappingError> {
+        map_frame_entry(frame, addr, root, rights, vm_attributes)
+    }
 }
 
 /// `PagingRec` represents an intermediate layer. It is of type `CurrentLevel`,
@@ -236,6 +359,7 @@ where
     LowerLevel: CapType + DirectRetype + PhantomCap,
 {
     type Item = LowerLevel;
+    const DEPTH: usize = UpperLevel::DEPTH + 1;
     fn map_layer(
         &mut self,
         item: &LocalCap<LowerLevel>,
@@ -244,27 +368,173 @@ where
         rights: CapRights,
         vm_attributes: arch::VMAttributes,
         utb: &mut WUTBuddy,
-        mut slots: &mut WCNodeSlots,
+        slots: &mut WCNodeSlots,
+        tracker: &mut LayerTracker,
     ) -> Result<(), MappingError> {
-        // Attempt to map this layer's granule.
-        match self
-            .layer
-            .map_granule(item, addr, root, rights, vm_attributes)
-        {
-            // if it fails with a lookup error, ask the next layer up
-            // to map a new instance at this layer.
-            Err(MappingError::Overflow) => {
-                let ut = utb.alloc(slots, <UpperLevel::Item as DirectRetype>::SizeBits::U8)?;
-                let next_item = ut.retype::<UpperLevel::Item>(&mut slots)?;
-                self.next
-                    .map_layer(&next_item, addr, root, rights, vm_attributes, utb, slots)?;
-                // Then try again to map this layer.
-                self.layer
-                    .map_granule(item, addr, root, rights, vm_attributes)
+        self.map_through(
+            addr,
+            root,
+            rights,
+            vm_attributes,
+            utb,
+            slots,
+            tracker,
+            |layer, root| layer.map_granule(item, addr, root, rights, vm_attributes),
+        )
+    }
+
+    fn map_frame(
+        &mut self,
+        frame: &LocalCap<Page<page_state::Unmapped>>,
+        depth: usize,
+        addr: usize,
+        root: &mut LocalCap<PagingRoot>,
+        rights: CapRights,
+        vm_attributes: arch::VMAttributes,
+        utb: &mut WUTBuddy,
+        slots: &mut WCNodeSlots,
+        tracker: &mut LayerTracker,
+    ) -> Result<(), MappingError> {
+        if depth < Self::DEPTH {
+            return self.next.map_frame(
+                frame,
+                depth,
+                addr,
+                root,
+                rights,
+                vm_attributes,
+                utb,
+                slots,
+                tracker,
+            );
+        }
+        self.map_through(
+            addr,
+            root,
+            rights,
+            vm_attributes,
+            utb,
+            slots,
+            tracker,
+            |_, root| map_frame_entry(frame, addr, root, rights, vm_attributes),
+        )
+    }
+}
+
+impl<LowerLevel, CurrentLevel: Maps<LowerLevel>, UpperLevel: PagingLayer>
+    PagingRec<LowerLevel, CurrentLevel, UpperLevel>
+where
+    CurrentLevel: CapType,
+    LowerLevel: CapType + DirectRetype + PhantomCap,
+{
+    /// Make the mapping `map` does into this layer's object covering
+    /// `addr`, creating that object, and any missing above it, first
+    /// if need be.
+    fn map_through<F>(
+        &mut self,
+        addr: usize,
+        root: &mut LocalCap<PagingRoot>,
+        rights: CapRights,
+        vm_attributes: arch::VMAttributes,
+        utb: &mut WUTBuddy,
+        slots: &mut WCNodeSlots,
+        tracker: &mut LayerTracker,
+        mut map: F,
+    ) -> Result<(), MappingError>
+    where
+        F: FnMut(&mut CurrentLevel, &mut LocalCap<PagingRoot>) -> Result<(), MappingError>,
+    {
+        // The objects created at this layer to cover `addr`, from the
+        // top down. On architectures whose paging structures are all
+        // the same kind of object (e.g. RISC-V page tables), the kernel
+        // installs each new one at the highest missing level, so more
+        // than one round may be needed before this layer's granule
+        // fits, and only the last one made sits at this layer.
+        let mut created: ArrayVec<[NewLayer; PAGING_DEPTH]> = ArrayVec::new();
+        let res = loop {
+            // Attempt the mapping.
+            match map(&mut self.layer, root) {
+                // if it fails with a lookup error, ask the next layer up
+                // to map a new instance at this layer, then try again.
+                Err(MappingError::Overflow) => {
+                    match self.add_layer(addr, root, rights, vm_attributes, utb, slots, tracker) {
+                        Ok(new) => {
+                            if created.try_push(new).is_err() {
+                                break Err(MappingError::Overflow);
+                            }
+                        }
+                        Err(e) => break Err(e),
+                    }
+                }
+                // Any other result (success \/ other failure cases) can
+                // be returned as is.
+                res => break res,
+            }
+        };
+
+        match res {
+            Ok(()) => {
+                let top_depth = Self::DEPTH + 1 - created.len();
+                for (i, new) in created.into_iter().enumerate() {
+                    tracker.record(top_depth + i, addr, new);
+                }
+                Ok(())
+            }
+            Err(e) => {
+                // Back out whatever was created on the way
+                for new in created.into_iter().rev() {
+                    tracker
+                        .discard(new, utb, slots)
+                        .map_err(MappingError::IntermediateLayerFailure)?;
+                }
+                tracker
+                    .reclaim_empty(addr, utb, slots)
+                    .map_err(MappingError::IntermediateLayerFailure)?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Create an object at this layer and have the layer above map it
+    /// in to cover `addr`.
+    fn add_layer(
+        &mut self,
+        addr: usize,
+        root: &mut LocalCap<PagingRoot>,
+        rights: CapRights,
+        vm_attributes: arch::VMAttributes,
+        utb: &mut WUTBuddy,
+        slots: &mut WCNodeSlots,
+        tracker: &mut LayerTracker,
+    ) -> Result<NewLayer, MappingError> {
+        let ut = utb.alloc(slots, <UpperLevel::Item as DirectRetype>::SizeBits::U8)?;
+        let (untyped, untyped_size_bits) = (ut.cptr, ut.cap_data.size_bits);
+        let next_item = match tracker.take_free_slot(slots) {
+            Some(mut slot) => ut.retype::<UpperLevel::Item>(&mut slot)?,
+            None => ut.retype::<UpperLevel::Item>(slots)?,
+        };
+        let new = NewLayer {
+            cptr: next_item.cptr,
+            untyped,
+            untyped_size_bits,
+        };
+        match self.next.map_layer(
+            &next_item,
+            addr,
+            root,
+            rights,
+            vm_attributes,
+            utb,
+            slots,
+            tracker,
+        ) {
+            Ok(()) => Ok(new),
+            Err(e) => {
+                tracker
+                    .discard(new, utb, slots)
+                    .map_err(MappingError::IntermediateLayerFailure)?;
+                Err(e)
             }
-            // Any other result (success \/ other failure cases) can
-            // be returned as is.
-            res => res,
         }
     }
 }
@@ -300,7 +570,20 @@ pub struct VSpace<State: VSpaceState = vspace_state::Imaged, CapRole: CNodeRole
     /// when building out intermediate layers.
     untyped: WUTBuddy<CapRole>,
     slots: Cap<WCNodeSlotsData<CapRole>, CapRole>,
-    available_address_range: AvailableAddressRange,
+    /// Which parts of the address space are free to map regions into.
+    address_allocator: AddressAllocator,
+    /// What's mapped into each intermediate paging object built out of
+    /// `untyped`, so that each can be given back once it's empty.
+    layer_tracker: LayerTracker,
+    /// The TLS base of the initial thread of an elf process, whose
+    /// TLS block was built along with the address space.
+    tls_base: Option<usize>,
+    /// How far the elf image in this address space was moved from its
+    /// link addresses, zero unless it is position-independent.
+    load_bias: usize,
+    /// Where the elf process in this address space expects to find its
+    /// startup block, if it links in a place to be told.
+    startup_block_vaddr: Option<usize>,
     _state: PhantomData<State>,
 }
 
@@ -318,43 +601,102 @@ impl VSpace<vspace_state::Empty, role::Local> {
             layers: AddressSpace::new(),
             untyped: ut_buddy::weak_ut_buddy(untyped),
             slots,
-            available_address_range: AvailableAddressRange::default(),
+            // Leave the first page unmapped so that null pointers fault
+            address_allocator: AddressAllocator::new(PageBytes::USIZE),
+            layer_tracker: LayerTracker::new(),
+            tls_base: None,
+            load_bias: 0,
+            startup_block_vaddr: None,
             _state: PhantomData,
         })
     }
 }
 
+#[cfg(feature = "test_support")]
+impl VSpace<vspace_state::Imaged, role::Local> {
+    /// A copy of this address space which builds any paging layers it
+    /// needs out of `untyped` and `slots` instead. Nothing done through
+    /// the copy is recorded here, and only layers the copy built itself
+    /// are given back when it unmaps things, so revoking `untyped` once
+    /// the copy is done with takes the address space back to where it
+    /// was.
+    pub(crate) unsafe fn dangerous_internal_alias(
+        &self,
+        untyped: LocalCap<WUntyped<memory_kind::General>>,
+        slots: LocalCap<WCNodeSlotsData<role::Local>>,
+    ) -> Self {
+        VSpace {
+            root: Cap {
+                cptr: self.root.cptr,
+                cap_data: PagingRoot {},
+                _role: PhantomData,
+            },
+            asid: self.asid,
+            layers: AddressSpace::new(),
+            untyped: ut_buddy::weak_ut_buddy(untyped),
+            slots,
+            address_allocator: self.address_allocator.clone(),
+            layer_tracker: LayerTracker::new(),
+            tls_base: None,
+            load_bias: self.load_bias,
+            startup_block_vaddr: None,
+            _state: PhantomData,
+        }
+    }
+}
+
 impl<State: VSpaceState, CapRole: CNodeRole> VSpace<State, CapRole> {
     /// This address space's id.
     pub(crate) fn asid(&self) -> InternalASID {
         self.asid
     }
 
-    pub(crate) fn root(&self) -> &Cap<PagingRoot, CapRole> {
+    /// The root paging structure of this address space, e.g. for
+    /// running a `Thread` in it.
+    pub fn root(&self) -> &Cap<PagingRoot, CapRole> {
         &self.root
     }
+
+    /// Hand over the TLS base of the initial thread's TLS block, if
+    /// there is one that hasn't been handed over already.
+    pub(crate) fn take_tls_base(&mut self) -> Option<usize> {
+        self.tls_base.take()
+    }
+
+    /// How many intermediate paging objects this VSpace built hold on
+    /// to their memory with nothing mapped through them. Those built
+    /// past the first `MaxTrackedPagingLayers` still standing, and any
+    /// whose untyped its buddy had no room to take back, are never
+    /// deleted, nor are the objects above them.
+    pub fn leaked_paging_layers(&self) -> usize {
+        self.layer_tracker.leaked()
+    }
+
+    /// How far the elf image in this address space was moved from its
+    /// link addresses. Zero for images linked at fixed addresses.
+    pub fn load_bias(&self) -> usize {
+        self.load_bias
+    }
+
+    /// Hand over the address reserved for the startup block, if there
+    /// is one that hasn't been handed over already.
+    pub(crate) fn take_startup_block_vaddr(&mut self) -> Option<usize> {
+        self.startup_block_vaddr.take()
+    }
 }
 
 impl<State: VSpaceState> VSpace<State, role::Local> {
     /// A thin wrapper around self.layers.map_layer that reduces the amount
-    /// of repetitive, visible self-reference
-    fn map_page_at_addr_without_watermarking(
+    /// of repetitive, visible self-reference. The page's address is not
+    /// claimed from the address allocator; that's up to the caller.
+    pub(crate) fn map_page_at_addr_without_claiming(
         &mut self,
         page: LocalCap<Page<page_state::Unmapped>>,
         address: usize,
         rights: CapRights,
         vm_attributes: arch::VMAttributes,
     ) -> Result<LocalCap<Page<page_state::Mapped>>, VSpaceError> {
-        self.layers
-            .map_layer(
-                &page,
-                address,
-                &mut self.root,
-                rights,
-                vm_attributes,
-                &mut self.untyped,
-                &mut self.slots,
-            )
+        self.map_page_through_layers(&page, address, rights, vm_attributes)
             .map(|_| Cap {
                 cptr: page.cptr,
                 _role: PhantomData,
@@ -363,6 +705,7 @@ impl<State: VSpaceState> VSpace<State, role::Local> {
                         asid: self.asid,
                         vaddr: address,
                         rights,
+                        vm_attributes,
                     },
                 },
             })
@@ -373,11 +716,229 @@ impl<State: VSpaceState> VSpace<State, role::Local> {
                 e => VSpaceError::MappingError(e),
             })
     }
+
+    /// Map `page` at `address`, building out any missing paging
+    /// layers, and count it against the layer it's mapped into.
+    fn map_page_through_layers(
+        &mut self,
+        page: &LocalCap<Page<page_state::Unmapped>>,
+        address: usize,
+        rights: CapRights,
+        vm_attributes: arch::VMAttributes,
+    ) -> Result<(), MappingError> {
+        self.layers.map_layer(
+            page,
+            address,
+            &mut self.root,
+            rights,
+            vm_attributes,
+            &mut self.untyped,
+            &mut self.slots,
+            &mut self.layer_tracker,
+        )?;
+        self.layer_tracker.frame_mapped(PAGING_DEPTH, address);
+        Ok(())
+    }
+
+    /// Map `frame`, `1 << frame_bits` bytes big, at `address` with a
+    /// single entry in whichever paging layer maps frames of its size,
+    /// building out any missing layers above that.
+    fn map_frame_through_layers(
+        &mut self,
+        frame: &LocalCap<Page<page_state::Unmapped>>,
+        frame_bits: u8,
+        address: usize,
+        rights: CapRights,
+        vm_attributes: arch::VMAttributes,
+    ) -> Result<(), MappingError> {
+        if frame_bits == PageBits::U8 {
+            return self.map_page_through_layers(frame, address, rights, vm_attributes);
+        }
+        let depth = frame_depth(frame_bits);
+        self.layers.map_frame(
+            frame,
+            depth,
+            address,
+            &mut self.root,
+            rights,
+            vm_attributes,
+            &mut self.untyped,
+            &mut self.slots,
+            &mut self.layer_tracker,
+        )?;
+        self.layer_tracker.frame_mapped(depth, address);
+        Ok(())
+    }
+
+    /// Unmap a page mapped into this VSpace. If it was the last thing
+    /// mapped through any of the paging layers above it, they are
+    /// deleted and their memory goes back to the VSpace's untyped.
+    pub(crate) fn unmap_page(
+        &mut self,
+        page: LocalCap<Page<page_state::Mapped>>,
+    ) -> Result<LocalCap<Page<page_state::Unmapped>>, SeL4Error> {
+        self.unmap_frame(page, PageBits::U8)
+    }
+
+    /// Unmap a frame of `1 << frame_bits` bytes mapped into this
+    /// VSpace, reclaiming any paging layers it leaves empty as
+    /// `unmap_page` does.
+    fn unmap_frame(
+        &mut self,
+        frame: LocalCap<Page<page_state::Mapped>>,
+        frame_bits: u8,
+    ) -> Result<LocalCap<Page<page_state::Unmapped>>, SeL4Error> {
+        let vaddr = frame.vaddr();
+        let frame = frame.unmap_frame(1 << frame_bits)?;
+        self.layer_tracker.frame_unmapped(
+            frame_depth(frame_bits),
+            vaddr,
+            &mut self.untyped,
+            &mut self.slots,
+        )?;
+        Ok(frame)
+    }
+
+    /// Leave the lowest `count` free pages unmapped, as a guard
+    /// between whatever was mapped last and whatever is mapped next.
+    pub(crate) fn skip_pages(&mut self, count: usize) -> Result<(), VSpaceError> {
+        self.address_allocator
+            .allocate(count * PageBytes::USIZE, PageBytes::USIZE)
+            .map(|_| ())
+    }
+
+    /// Claim `size_bytes` of address space at `vaddr` without mapping
+    /// anything there, so that no region is mapped into it unless
+    /// asked for with `map_region_at_addr`. Fails if any of it is
+    /// already mapped or reserved. It stays reserved as regions are
+    /// mapped into it and unmapped again, until released with
+    /// `release_address_range`.
+    pub fn reserve_address_range(
+        &mut self,
+        vaddr: usize,
+        size_bytes: usize,
+    ) -> Result<(), VSpaceError> {
+        check_page_granular(vaddr, size_bytes)?;
+        self.address_allocator.reserve(vaddr, size_bytes)
+    }
+
+    /// Claim `size_bytes` of address space aligned to `align` bytes
+    /// wherever there's room, without mapping anything there. Returns
+    /// where it starts.
+    pub fn allocate_address_range(
+        &mut self,
+        size_bytes: usize,
+        align: usize,
+    ) -> Result<usize, VSpaceError> {
+        if !align.is_power_of_two() {
+            return Err(VSpaceError::InvalidRegionSize);
+        }
+        check_page_granular(0, size_bytes)?;
+        self.address_allocator
+            .allocate_reserved(size_bytes, core::cmp::max(align, PageBytes::USIZE))
+    }
+
+    /// Give back address space claimed with `reserve_address_range` or
+    /// `allocate_address_range`, as exactly the range that was claimed.
+    /// Nothing must be mapped in it.
+    pub fn release_address_range(
+        &mut self,
+        vaddr: usize,
+        size_bytes: usize,
+    ) -> Result<(), VSpaceError> {
+        check_page_granular(vaddr, size_bytes)?;
+        self.address_allocator.release_reserved(vaddr, size_bytes)
+    }
+
+    /// Whether nothing is mapped or reserved in the `size_bytes` of
+    /// address space at `vaddr`.
+    pub fn is_address_range_free(&self, vaddr: usize, size_bytes: usize) -> bool {
+        self.address_allocator.is_free(vaddr, size_bytes)
+    }
+
+    /// The total amount of address space, in bytes, that is neither
+    /// mapped nor reserved.
+    pub fn free_address_space(&self) -> usize {
+        self.address_allocator.free_bytes()
+    }
+
+    /// The size in bytes of the largest free range of address space,
+    /// and so of the largest region that could be mapped.
+    pub fn largest_free_address_range(&self) -> usize {
+        self.address_allocator.largest_free_range()
+    }
+}
+
+fn check_page_granular(vaddr: usize, size_bytes: usize) -> Result<(), VSpaceError> {
+    if vaddr & PAGE_MASK != 0 || size_bytes & PAGE_MASK != 0 {
+        Err(VSpaceError::InvalidRegionSize)
+    } else {
+        Ok(())
+    }
 }
 
 // 0xfff, for 4k pages
 const PAGE_MASK: usize = (1 << arch::PageBits::USIZE) - 1;
 
+/// Where the image keeps the word through which it finds its startup
+/// block, going by the note ferros links into it. Only program headers
+/// are consulted, so stripped images are found too.
+fn find_startup_slot(
+    elf: &xmas_elf::ElfFile,
+    elf_data: &[u8],
+) -> Result<Option<usize>, VSpaceError> {
+    fn word(data: &[u8], at: usize) -> Result<u32, VSpaceError> {
+        data.get(at..at + 4)
+            .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or(VSpaceError::ElfParseError("truncated note"))
+    }
+    fn align4(n: usize) -> usize {
+        (n + 3) & !3
+    }
+
+    for note_header in elf
+        .program_iter()
+        .filter(|h| h.get_type() == Ok(xmas_elf::program::Type::Note))
+    {
+        let start = note_header.offset() as usize;
+        let notes = elf_data
+            .get(start..start + note_header.file_size() as usize)
+            .ok_or(VSpaceError::ElfParseError("note segment out of bounds"))?;
+        let mut at = 0;
+        while at + 12 <= notes.len() {
+            let name_size = word(notes, at)? as usize;
+            let desc_size = word(notes, at + 4)? as usize;
+            let note_type = word(notes, at + 8)?;
+            let name_at = at + 12;
+            let desc_at = name_at + align4(name_size);
+            if notes.get(name_at..name_at + name_size) == Some(STARTUP_NOTE_NAME)
+                && note_type == STARTUP_NOTE_TYPE
+                && desc_size == 4
+            {
+                let desc_vaddr = note_header.virtual_addr() as usize + desc_at;
+                let offset = word(notes, desc_at)? as i32;
+                return Ok(Some(desc_vaddr.wrapping_add(offset as isize as usize)));
+            }
+            at = desc_at + align4(desc_size);
+        }
+    }
+    Ok(None)
+}
+
+/// Write `value` to the word at `vaddr`, given `dest` holding the
+/// memory at `dest_vaddr`. Only the bytes of the word that fall within
+/// `dest` are written, so a word straddling two pages can be written a
+/// page at a time.
+fn patch_word(dest: &mut [u8], dest_vaddr: usize, vaddr: usize, value: usize) {
+    let dest_end = dest_vaddr + dest.len();
+    for (i, byte) in value.to_ne_bytes().iter().enumerate() {
+        let at = vaddr + i;
+        if at >= dest_vaddr && at < dest_end {
+            dest[at - dest_vaddr] = *byte;
+        }
+    }
+}
+
 struct ByPageIterator {
     next: usize,
     end: usize,
@@ -412,10 +973,17 @@ fn iterate_by_page(start: usize, end: usize) -> impl Iterator<Item = (usize, usi
 }
 
 impl VSpace<vspace_state::Imaged, role::Local> {
-    /// Unmap a region.
-    pub fn unmap_region<SizeBits: Unsigned, SS: SharedStatus>(
+    /// Unmap a region. Its address space is free to be reused, unless
+    /// it was reserved with `reserve_address_range` or
+    /// `allocate_address_range`.
+    ///
+    /// Paging layers the region leaves empty are deleted and their
+    /// memory given back, but only the first `MaxTrackedPagingLayers`
+    /// layers still standing are tracked well enough for that. Anything
+    /// kept past that limit shows up in `leaked_paging_layers`.
+    pub fn unmap_region<SizeBits: Unsigned, SS: SharedStatus, Rt: Rights>(
         &mut self,
-        region: MappedMemoryRegion<SizeBits, SS>,
+        region: MappedMemoryRegion<SizeBits, SS, role::Local, Rt>,
     ) -> Result<UnmappedMemoryRegion<SizeBits, SS>, VSpaceError>
     where
         SizeBits: IsGreaterOrEqual<PageBits>,
@@ -425,9 +993,12 @@ impl VSpace<vspace_state::Imaged, role::Local> {
         Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
     {
         self.weak_unmap_region(region.weaken())
-            .and_then(|r| r.as_strong::<SizeBits>())
+            .and_then(|r| r.as_strong())
     }
-    /// Unmap a weak region.
+    /// Unmap a weak region. Its address space is free to be reused,
+    /// unless it was reserved with `reserve_address_range` or
+    /// `allocate_address_range`. Paging layers it leaves empty are
+    /// reclaimed as for `unmap_region`.
     pub fn weak_unmap_region<SS: SharedStatus>(
         &mut self,
         region: WeakMappedMemoryRegion<SS>,
@@ -436,23 +1007,92 @@ impl VSpace<vspace_state::Imaged, role::Local> {
             return Err(VSpaceError::ASIDMismatch);
         }
         let start_cptr = region.caps.start_cptr;
-        let size_bits = region.size_bits();
-        for page_cap in region.caps.into_iter() {
-            let _ = self.unmap_page(page_cap)?;
+        let (size_bits, frame_bits) = (region.size_bits(), region.frame_bits());
+        let (vaddr, size_bytes) = (region.vaddr(), region.size_bytes());
+        for frame_cap in region.frames() {
+            let _ = self.unmap_frame(frame_cap, frame_bits)?;
         }
+        self.address_allocator
+            .release_unreserved(vaddr, size_bytes)?;
         Ok(WeakMemoryRegion::unchecked_new(
             start_cptr,
             page_state::Unmapped,
             region.kind,
             size_bits,
+            frame_bits,
         ))
     }
 
-    fn unmap_page(
+    /// Change the rights and attributes a region is mapped with, in
+    /// place, as for sealing freshly written code read-only or taking
+    /// write access away from a shared buffer. The region comes back
+    /// typed with its new rights. If a frame fails to remap, the ones
+    /// before it are put back to the old rights and attributes and the
+    /// region is handed back unchanged.
+    pub fn remap_region<SizeBits: Unsigned, SS: SharedStatus, Rt: Rights, NewRt: Rights>(
         &mut self,
-        page: LocalCap<Page<page_state::Mapped>>,
-    ) -> Result<LocalCap<Page<page_state::Unmapped>>, SeL4Error> {
-        page.unmap()
+        region: MappedMemoryRegion<SizeBits, SS, role::Local, Rt>,
+        _rights: NewRt,
+        vm_attributes: arch::VMAttributes,
+    ) -> Result<
+        MappedMemoryRegion<SizeBits, SS, role::Local, NewRt>,
+        (
+            VSpaceError,
+            MappedMemoryRegion<SizeBits, SS, role::Local, Rt>,
+        ),
+    >
+    where
+        SizeBits: IsGreaterOrEqual<PageBits>,
+        SizeBits: Sub<PageBits>,
+        <SizeBits as Sub<PageBits>>::Output: Unsigned,
+        <SizeBits as Sub<PageBits>>::Output: _Pow,
+        Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+    {
+        let mut region = region.weaken();
+        match self.weak_remap_region(&mut region, NewRt::as_caprights(), vm_attributes) {
+            Ok(()) => Ok(region
+                .as_strong()
+                .expect("The region's size is unchanged by remapping")),
+            Err(e) => Err((
+                e,
+                region
+                    .as_strong()
+                    .expect("The region's size is unchanged by remapping"),
+            )),
+        }
+    }
+
+    /// Change the rights and attributes a weak region is mapped with,
+    /// in place. See `remap_region`.
+    ///
+    /// The kernel treats mapping a page again at the address it's
+    /// already mapped at as a remap, so each frame is simply mapped
+    /// again with the new rights and attributes.
+    pub fn weak_remap_region<SS: SharedStatus>(
+        &mut self,
+        region: &mut WeakMappedMemoryRegion<SS>,
+        rights: CapRights,
+        vm_attributes: arch::VMAttributes,
+    ) -> Result<(), VSpaceError> {
+        if self.asid != region.asid() {
+            return Err(VSpaceError::ASIDMismatch);
+        }
+        let old_rights = region.rights();
+        let old_vm_attributes = region.vm_attributes();
+        let mut remapped_frames = 0;
+        for mut frame in region.frames() {
+            if let Err(e) = frame.remap(&mut self.root, rights, vm_attributes) {
+                // Rollback the frames we've remapped thus far.
+                for mut frame in region.frames().take(remapped_frames) {
+                    let _ = frame.remap(&mut self.root, old_rights, old_vm_attributes);
+                }
+                return Err(e.into());
+            }
+            remapped_frames += 1;
+        }
+        region.caps.start_cap_data.state.rights = rights;
+        region.caps.start_cap_data.state.vm_attributes = vm_attributes;
+        Ok(())
     }
 
     // This function will move the caps into the child's CSpace so
@@ -470,7 +1110,10 @@ impl VSpace<vspace_state::Imaged, role::Local> {
             layers,
             untyped,
             slots: _,
-            available_address_range,
+            address_allocator,
+            tls_base,
+            load_bias,
+            startup_block_vaddr,
             ..
         } = self;
         let child_root = root.move_to_slot(src_cnode, child_root_slot)?;
@@ -489,11 +1132,39 @@ impl VSpace<vspace_state::Imaged, role::Local> {
             layers,
             untyped: child_untyped,
             slots: child_paging_slots,
-            available_address_range,
+            address_allocator,
+            // The paging objects built so far stay behind in the local
+            // CSpace, so the child can't reclaim them.
+            layer_tracker: LayerTracker::new(),
+            tls_base,
+            load_bias,
+            startup_block_vaddr,
             _state: PhantomData,
         })
     }
 
+    /// Build the address space for one instance of an elf process.
+    ///
+    /// Read-only segments are mapped straight from the pages of the
+    /// `UserImage`, so every instance shares them. Only the writable
+    /// segments are copied, into pages retyped from `elf_writable_mem`.
+    ///
+    /// If the image has a `PT_TLS` segment, a TLS block for the initial
+    /// thread is built from those pages too, and `StandardProcess::new`
+    /// points the new thread at it.
+    ///
+    /// If the image links in ferros' startup block slot, announced by a
+    /// `PT_NOTE` so that stripped images keep it, a page is reserved for
+    /// the block, to be filled in by `StandardProcess::write_startup_block`.
+    ///
+    /// Position-independent (`ET_DYN`) images are loaded at the lowest
+    /// free address above the first page, and their relative relocations
+    /// are applied as the writable segments are copied. Such images must
+    /// be statically linked, and keep anything that needs relocating out
+    /// of their read-only segments.
+    ///
+    /// `page_slots` must cover the shared and instance pages together,
+    /// and `elf_writable_mem` must be exactly the instance pages.
     pub fn new_from_elf<E: ElfProc>(
         paging_root: LocalCap<PagingRoot>,
         asid: LocalCap<UnassignedASID>,
@@ -506,7 +1177,12 @@ impl VSpace<vspace_state::Imaged, role::Local> {
         user_image: &UserImage<role::Local>,
         parent_cnode: &LocalCap<LocalCNode>,
         local_vspace_scratch: &mut ScratchRegion,
-    ) -> Result<Self, VSpaceError> {
+    ) -> Result<Self, VSpaceError>
+    where
+        E::SharedPages: Add<E::InstancePages, Output = E::RequiredPages>,
+        E::RequiredMemoryBits: Sub<PageBits>,
+        Diff<E::RequiredMemoryBits, PageBits>: _Pow<Output = E::InstancePages>,
+    {
         Self::new_from_elf_weak(
             paging_root,
             asid,
@@ -541,6 +1217,56 @@ impl VSpace<vspace_state::Imaged, role::Local> {
 
         let elf = xmas_elf::ElfFile::new(elf_data).map_err(VSpaceError::ElfParseError)?;
 
+        let (link_start, link_end) = elf
+            .program_iter()
+            .filter(|h| h.get_type() == Ok(xmas_elf::program::Type::Load))
+            .fold((core::usize::MAX, 0), |(start, end), h| {
+                (
+                    core::cmp::min(start, h.virtual_addr() as usize & !PAGE_MASK),
+                    core::cmp::max(end, (h.virtual_addr() + h.mem_size()) as usize),
+                )
+            });
+        let link_start = core::cmp::min(link_start, link_end);
+
+        // Position-independent images go wherever there's room.
+        let relocations =
+            if elf.header.pt2.type_().as_type() == xmas_elf::header::Type::SharedObject {
+                let load_start = vspace
+                    .address_allocator
+                    .lowest_fit(link_end - link_start, PageBytes::USIZE)
+                    .ok_or(VSpaceError::InsufficientAddressSpaceAvailableToMapRegion)?;
+                vspace.load_bias = load_start.wrapping_sub(link_start);
+                Some(reloc::Relocations::new(&elf, vspace.load_bias)?)
+            } else {
+                None
+            };
+
+        // If the image has somewhere to be told where its startup block
+        // is, reserve a page for the block past the end of the image and
+        // fill in its address as the segments are copied.
+        let startup_block = match find_startup_slot(&elf, elf_data)? {
+            Some(slot_vaddr) => {
+                let slot_end = slot_vaddr + core::mem::size_of::<usize>();
+                let writable = elf
+                    .program_iter()
+                    .filter(|h| h.get_type() == Ok(xmas_elf::program::Type::Load))
+                    .any(|h| {
+                        let start = h.virtual_addr() as usize;
+                        h.flags().is_write()
+                            && slot_vaddr >= start
+                            && slot_end <= start + h.mem_size() as usize
+                    });
+                if !writable {
+                    return Err(VSpaceError::StartupBlockSlotNotWritable);
+                }
+                let image_end = link_end + vspace.load_bias;
+                // Leave a padding page between the image and the block
+                let block_vaddr = ((image_end + PAGE_MASK) & !PAGE_MASK) + PageBytes::USIZE;
+                Some((slot_vaddr, block_vaddr))
+            }
+            None => None,
+        };
+
         let mut writable_segment_pages_iter =
             elf_writable_mem.retype_pages(&mut page_slots)?.into_iter();
 
@@ -587,7 +1313,7 @@ impl VSpace<vspace_state::Imaged, role::Local> {
                         .ok_or(VSpaceError::InsufficientResourcesForElf)?;
 
                     let mut unmapped_region = dest_page.to_region();
-                    let _ = local_vspace_scratch.temporarily_map_region::<PageBits, _, _>(
+                    local_vspace_scratch.temporarily_map_region::<PageBits, _, _>(
                         &mut unmapped_region,
                         |temp_mapped_region| {
                             let dest_mem = temp_mapped_region.as_mut_slice();
@@ -617,20 +1343,28 @@ impl VSpace<vspace_state::Imaged, role::Local> {
                                 dest_slice.copy_from_slice(&elf_data[src_start..src_end]);
                             }
 
+                            let relocated = match relocations {
+                                Some(ref relocations) => {
+                                    relocations.apply(dest_mem, curr_page_vaddr)
+                                }
+                                None => Ok(()),
+                            };
+                            if let Some((slot_vaddr, block_vaddr)) = startup_block {
+                                patch_word(dest_mem, curr_page_vaddr, slot_vaddr, block_vaddr);
+                            }
+
                             temp_mapped_region.flush().unwrap();
+                            relocated
                         },
-                    );
+                    )??;
 
-                    let _ = vspace.map_page_at_addr_without_watermarking(
+                    let load_vaddr = curr_page_vaddr + vspace.load_bias;
+                    let _ = vspace.map_page_at_addr_without_claiming(
                         unmapped_region.to_page(),
-                        curr_page_vaddr,
+                        load_vaddr,
                         CapRights::RW,
                         vm_attrs,
                     )?;
-
-                    vspace
-                        .available_address_range
-                        .observe_mapping(curr_page_vaddr, PageBits::U8)?;
                 }
             } else {
                 // If the elf headers say to map something as read only, we can map in the pages
@@ -652,7 +1386,7 @@ impl VSpace<vspace_state::Imaged, role::Local> {
                 {
                     let page_vaddr_here = user_image_page.cap_data.state.vaddr;
                     let page_offset = page_vaddr_here - start_page_vaddr_here;
-                    let child_vaddr = target_vaddr + page_offset;
+                    let child_vaddr = target_vaddr + page_offset + vspace.load_bias;
 
                     let copied_page_cap = user_image_page.copy(
                         parent_cnode,
@@ -666,15 +1400,12 @@ impl VSpace<vspace_state::Imaged, role::Local> {
                         CapRights::R,
                     )?;
 
-                    let _ = vspace.map_page_at_addr_without_watermarking(
+                    let _ = vspace.map_page_at_addr_without_claiming(
                         copied_page_cap,
                         child_vaddr,
                         CapRights::R,
                         vm_attrs,
                     )?;
-                    vspace
-                        .available_address_range
-                        .observe_mapping(child_vaddr, arch::PageBits::U8)?;
                 }
             }
         }
@@ -685,13 +1416,64 @@ impl VSpace<vspace_state::Imaged, role::Local> {
             layers: vspace.layers,
             untyped: vspace.untyped,
             slots: vspace.slots,
-            available_address_range: vspace.available_address_range,
+            address_allocator: vspace.address_allocator,
+            layer_tracker: vspace.layer_tracker,
+            tls_base: vspace.tls_base,
+            load_bias: vspace.load_bias,
+            startup_block_vaddr: vspace.startup_block_vaddr,
             _state: PhantomData,
         };
 
+        // Keep everything mapped later above the image and its startup
+        // block, where the free space is contiguous, so that guard pages
+        // land next to what they guard.
+        let image_end = match startup_block {
+            Some((_, block_vaddr)) => {
+                vspace.startup_block_vaddr = Some(block_vaddr);
+                block_vaddr + PageBytes::USIZE
+            }
+            None => (link_end + vspace.load_bias + PAGE_MASK) & !PAGE_MASK,
+        };
+        vspace.address_allocator.mark_used(0, image_end)?;
+
         // allocate a padding page
         vspace.skip_pages(1)?;
 
+        if let Some(tls_image) = TLSImage::from_elf_file(&elf)? {
+            // The initial thread's TLS block comes out of the same
+            // memory as the writable segments.
+            let block_vaddr = vspace
+                .address_allocator
+                .allocate(tls_image.block_pages() * PageBytes::USIZE, PageBytes::USIZE)?;
+            for page_index in 0..tls_image.block_pages() {
+                let page_vaddr = block_vaddr + page_index * PageBytes::USIZE;
+                let dest_page = writable_segment_pages_iter
+                    .next()
+                    .ok_or(VSpaceError::InsufficientResourcesForElf)?;
+
+                let mut unmapped_region = dest_page.to_region();
+                local_vspace_scratch.temporarily_map_region::<PageBits, _, _>(
+                    &mut unmapped_region,
+                    |temp_mapped_region| {
+                        tls_image.fill(
+                            temp_mapped_region.as_mut_slice(),
+                            page_index * PageBytes::USIZE,
+                            block_vaddr,
+                        );
+                        temp_mapped_region.flush()
+                    },
+                )??;
+
+                let _ = vspace.map_page_at_addr_without_claiming(
+                    unmapped_region.to_page(),
+                    page_vaddr,
+                    CapRights::RW,
+                    arch::vm_attributes::DEFAULT | arch::vm_attributes::EXECUTE_NEVER,
+                )?;
+            }
+            vspace.tls_base = Some(tls_image.tls_base(block_vaddr));
+        }
+
         Ok(vspace)
     }
 
@@ -720,15 +1502,12 @@ impl VSpace<vspace_state::Imaged, role::Local> {
                 {
                     let address = user_image_page.cap_data.state.vaddr;
                     let copied_page_cap = user_image_page.copy(parent_cnode, slot, CapRights::R)?;
-                    let _ = vspace.map_page_at_addr_without_watermarking(
+                    let _ = vspace.map_page_at_addr_without_claiming(
                         copied_page_cap,
                         address,
                         CapRights::R,
                         arch::vm_attributes::DEFAULT,
                     )?;
-                    vspace
-                        .available_address_range
-                        .observe_mapping(address, PageBits::U8)?;
                 }
             }
             ProcessCodeImageConfig::ReadWritable {
@@ -766,26 +1545,35 @@ impl VSpace<vspace_state::Imaged, role::Local> {
                             };
                         },
                     )?;
-                    let _ = vspace.map_page_at_addr_without_watermarking(
+                    let _ = vspace.map_page_at_addr_without_claiming(
                         unmapped_region.to_page(),
                         address,
                         CapRights::RW,
                         arch::vm_attributes::DEFAULT,
                     )?;
-                    vspace
-                        .available_address_range
-                        .observe_mapping(address, PageBits::U8)?;
                 }
             }
         }
 
+        // Keep everything mapped later above the image, as for elf images
+        let image_end = user_image
+            .pages_iter()
+            .map(|p| p.cap_data.state.vaddr + PageBytes::USIZE)
+            .max()
+            .unwrap_or(0);
+        vspace.address_allocator.mark_used(0, image_end)?;
+
         Ok(VSpace {
             root: vspace.root,
             asid: vspace.asid,
             layers: vspace.layers,
             untyped: vspace.untyped,
             slots: vspace.slots,
-            available_address_range: vspace.available_address_range,
+            address_allocator: vspace.address_allocator,
+            layer_tracker: vspace.layer_tracker,
+            tls_base: vspace.tls_base,
+            load_bias: vspace.load_bias,
+            startup_block_vaddr: vspace.startup_block_vaddr,
             _state: PhantomData,
         })
     }
@@ -798,10 +1586,7 @@ impl VSpace<vspace_state::Imaged, role::Local> {
         asid: LocalCap<AssignedASID>,
         ut: LocalCap<WUntyped<memory_kind::General>>,
     ) -> Self {
-        let available_address_range = AvailableAddressRange {
-            bottom: next_addr,
-            ..Default::default()
-        };
+        let address_allocator = AddressAllocator::new(next_addr);
         VSpace {
             layers: AddressSpace::new(),
             root: Cap {
@@ -811,20 +1596,24 @@ impl VSpace<vspace_state::Imaged, role::Local> {
             },
             untyped: ut_buddy::weak_ut_buddy(ut),
             slots: cslots,
-            available_address_range,
+            address_allocator,
+            layer_tracker: LayerTracker::new(),
+            tls_base: None,
+            load_bias: 0,
+            startup_block_vaddr: None,
             asid: asid.cap_data.asid,
             _state: PhantomData,
         }
     }
 
-    pub fn map_region_at_addr<SizeBits: Unsigned, SS: SharedStatus>(
+    pub fn map_region_at_addr<SizeBits: Unsigned, SS: SharedStatus, Rt: Rights>(
         &mut self,
         region: UnmappedMemoryRegion<SizeBits, SS>,
         vaddr: usize,
-        rights: CapRights,
+        _rights: Rt,
         vm_attributes: arch::VMAttributes,
     ) -> Result<
-        MappedMemoryRegion<SizeBits, SS>,
+        MappedMemoryRegion<SizeBits, SS, role::Local, Rt>,
         (VSpaceError, Option<UnmappedMemoryRegion<SizeBits, SS>>),
     >
     where
@@ -834,9 +1623,14 @@ impl VSpace<vspace_state::Imaged, role::Local> {
         <SizeBits as Sub<PageBits>>::Output: _Pow,
         Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
     {
-        match self.weak_map_region_at_addr(region.weaken(), vaddr, rights, vm_attributes) {
-            Ok(r) => Ok(r.as_strong::<SizeBits>().map_err(|e| (e, None))?),
-            Err((e, r)) => Err((e, r.as_strong::<SizeBits>().ok())),
+        match self.weak_map_region_at_addr(
+            region.weaken(),
+            vaddr,
+            Rt::as_caprights(),
+            vm_attributes,
+        ) {
+            Ok(r) => Ok(r.as_strong().map_err(|e| (e, None))?),
+            Err((e, r)) => Err((e, r.as_strong().ok())),
         }
     }
 
@@ -851,109 +1645,71 @@ impl VSpace<vspace_state::Imaged, role::Local> {
             return Err((VSpaceError::InvalidRegionSize, region));
         }
 
+        // Each frame takes a single paging entry, which can only map
+        // it at a multiple of its size.
+        let frame_bits = region.frame_bits();
+        if vaddr % (1 << frame_bits) != 0 {
+            return Err((
+                VSpaceError::MappingError(MappingError::AddrNotPageAligned),
+                region,
+            ));
+        }
+
         // Verify that we can fit this region into the address space.
         if vaddr.checked_add(region.size_bytes()) == None {
             return Err((VSpaceError::ExceededAddressableSpace, region));
         }
 
-        let mut mapping_vaddr = vaddr;
         let cptr = region.caps.start_cptr;
         let size_bits = region.size_bits();
 
-        // N.B. Currently expect a single continuous cap range of all pages.
-        // Revisit this size if heterogenous granule types / ranges begin to back memory
-        // regions.
-        let mut mapped_pages: Option<WeakCapRange<Page<page_state::Mapped>, role::Local>> = None;
-
-        fn unmap_mapped_page_cptrs(
-            mapped_pages: Option<WeakCapRange<Page<page_state::Mapped>, role::Local>>,
-        ) -> Result<(), SeL4Error> {
-            if let Some(mapped_pages) = mapped_pages {
-                mapped_pages
-                    .into_iter()
-                    .try_for_each(|page| page.unmap().map(|_p| ()))
-            } else {
-                Ok(())
-            }
-        }
-        let kind = region.kind;
-
-        for page in region.caps.into_iter() {
-            match self.layers.map_layer(
-                &page,
+        // N.B. Currently expect a single continuous cap range of all
+        // frames, so the ones mapped thus far are just counted.
+        let mut mapped_frames = 0;
+        let mut mapping_vaddr = vaddr;
+        for frame in region.frames() {
+            if let Err(e) = self.map_frame_through_layers(
+                &frame,
+                frame_bits,
                 mapping_vaddr,
-                &mut self.root,
                 rights,
                 vm_attributes,
-                &mut self.untyped,
-                &mut self.slots,
             ) {
-                Err(MappingError::PageMapFailure(e))
-                | Err(MappingError::IntermediateLayerFailure(e)) => {
-                    // Rollback the pages we've mapped thus far.
-                    let _ = unmap_mapped_page_cptrs(mapped_pages);
-                    return Err((
-                        VSpaceError::SeL4Error(e),
-                        WeakMemoryRegion::unchecked_new(
-                            cptr,
-                            page_state::Unmapped,
-                            kind,
-                            size_bits,
-                        ),
-                    ));
-                }
-                Err(e) => {
-                    // Rollback the pages we've mapped thus far.
-                    let _ = unmap_mapped_page_cptrs(mapped_pages);
-                    return Err((
-                        VSpaceError::MappingError(e),
-                        WeakMemoryRegion::unchecked_new(
-                            cptr,
-                            page_state::Unmapped,
-                            kind,
-                            size_bits,
-                        ),
-                    ));
-                }
-                Ok(_) => {
-                    // save pages we've mapped thus far so we can roll
-                    // them back if we fail to map all of this
-                    // region. I.e., something was previously mapped
-                    // here.
-                    match mapped_pages {
-                        None => {
-                            mapped_pages = Some(WeakCapRange::new(
-                                page.cptr,
-                                Page {
-                                    state: page_state::Mapped {
-                                        vaddr: mapping_vaddr,
-                                        asid: self.asid(),
-                                        rights,
-                                    },
-                                },
-                                1,
-                            ));
-                        }
-                        Some(mut already_mapped_pages) => {
-                            already_mapped_pages.len += 1;
-                            mapped_pages = Some(already_mapped_pages)
-                        }
+                // Rollback the frames we've mapped thus far.
+                let _ = self.unmap_mapped_frames(
+                    cptr,
+                    vaddr,
+                    mapped_frames,
+                    frame_bits,
+                    rights,
+                    vm_attributes,
+                );
+                let e = match e {
+                    MappingError::PageMapFailure(e) | MappingError::IntermediateLayerFailure(e) => {
+                        VSpaceError::SeL4Error(e)
                     }
-                }
-            };
-            mapping_vaddr += PageBytes::USIZE;
+                    e => VSpaceError::MappingError(e),
+                };
+                return Err((e, region));
+            }
+            mapped_frames += 1;
+            mapping_vaddr += 1 << frame_bits;
         }
 
         if let Err(e) = self
-            .available_address_range
-            .observe_mapping(vaddr, size_bits)
+            .address_allocator
+            .mark_used(vaddr, bytes_from_size_bits(size_bits))
         {
-            // Rollback the pages we've mapped thus far.
-            let _ = unmap_mapped_page_cptrs(mapped_pages);
-            return Err((
-                e,
-                WeakMemoryRegion::unchecked_new(cptr, page_state::Unmapped, kind, size_bits),
-            ));
+            // Rollback the frames we've mapped thus far.
+            let _ = self.unmap_mapped_frames(
+                cptr,
+                vaddr,
+                mapped_frames,
+                frame_bits,
+                rights,
+                vm_attributes,
+            );
+            return Err((e, region));
         }
 
         Ok(WeakMappedMemoryRegion::unchecked_new(
@@ -962,19 +1718,82 @@ impl VSpace<vspace_state::Imaged, role::Local> {
                 vaddr,
                 asid: self.asid,
                 rights,
+                vm_attributes,
             },
-            kind,
+            region.kind,
             size_bits,
+            frame_bits,
         ))
     }
 
+    /// Retype a device untyped of any number of pages into a region
+    /// and map it uncached at `vaddr`, as for a peripheral's register
+    /// block. Should mapping fail, the unmapped region is handed back
+    /// with the error. Should retyping fail, the kernel has created no
+    /// pages, but `ut` and `slots` are used up all the same.
+    pub fn map_device_region_at_addr<SizeBits: Unsigned, Rt: Rights>(
+        &mut self,
+        ut: LocalCap<Untyped<SizeBits, memory_kind::Device>>,
+        slots: LocalCNodeSlots<NumPages<SizeBits>>,
+        vaddr: usize,
+        rights: Rt,
+    ) -> Result<
+        MappedMemoryRegion<SizeBits, shared_status::Exclusive, role::Local, Rt>,
+        (
+            VSpaceError,
+            Option<UnmappedMemoryRegion<SizeBits, shared_status::Exclusive>>,
+        ),
+    >
+    where
+        SizeBits: IsGreaterOrEqual<PageBits>,
+        SizeBits: Sub<PageBits>,
+        <SizeBits as Sub<PageBits>>::Output: Unsigned,
+        <SizeBits as Sub<PageBits>>::Output: _Pow,
+        Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+        Pow<<SizeBits as Sub<PageBits>>::Output>:
+            IsLessOrEqual<KernelRetypeFanOutLimit, Output = True>,
+    {
+        let region = UnmappedMemoryRegion::new_device(ut, slots).map_err(|e| (e.into(), None))?;
+        self.map_region_at_addr(region, vaddr, rights, DEVICE_VM_ATTRIBUTES)
+    }
+
+    /// Take the device memory in `address_range` from
+    /// `device_allocator` and map it uncached at `vaddr`. The range may
+    /// span several of the allocator's untypeds, but must be a power of
+    /// two in size. Its page caps, and any untyped split off along the
+    /// way, take their slots from `slots`.
+    ///
+    /// Should taking the memory fail, it's put back as described for
+    /// `WeakMemoryRegion::new_device_range`. Should mapping fail, the
+    /// unmapped region is handed back with the error, and its memory
+    /// stays out of `device_allocator`.
+    pub fn weak_map_device_region_at_addr(
+        &mut self,
+        device_allocator: &mut DeviceAllocator,
+        address_range: PageAlignedAddressRange,
+        slots: &mut WCNodeSlots,
+        vaddr: usize,
+        rights: CapRights,
+    ) -> Result<
+        WeakMappedMemoryRegion<shared_status::Exclusive>,
+        (
+            VSpaceError,
+            Option<WeakUnmappedMemoryRegion<shared_status::Exclusive>>,
+        ),
+    > {
+        let region = WeakMemoryRegion::new_device_range(device_allocator, address_range, slots)
+            .map_err(|e| (e, None))?;
+        self.weak_map_region_at_addr(region, vaddr, rights, DEVICE_VM_ATTRIBUTES)
+            .map_err(|(e, region)| (e, Some(region)))
+    }
+
     /// Map a region of memory at some address, I don't care where.
-    pub fn map_region<SizeBits: Unsigned>(
+    pub fn map_region<SizeBits: Unsigned, Rt: Rights>(
         &mut self,
         region: UnmappedMemoryRegion<SizeBits, shared_status::Exclusive>,
-        rights: CapRights,
+        rights: Rt,
         vm_attributes: arch::VMAttributes,
-    ) -> Result<MappedMemoryRegion<SizeBits, shared_status::Exclusive>, VSpaceError>
+    ) -> Result<MappedMemoryRegion<SizeBits, shared_status::Exclusive, role::Local, Rt>, VSpaceError>
     where
         SizeBits: IsGreaterOrEqual<PageBits>,
         SizeBits: Sub<PageBits>,
@@ -982,7 +1801,7 @@ impl VSpace<vspace_state::Imaged, role::Local> {
         <SizeBits as Sub<PageBits>>::Output: _Pow,
         Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
     {
-        self.map_region_internal(region, rights, vm_attributes)
+        self.map_region_internal(region, rights, vm_attributes, PageBytes::USIZE)
     }
 
     /// Map a weak region of memory at some address, I don't care where.
@@ -992,19 +1811,50 @@ impl VSpace<vspace_state::Imaged, role::Local> {
         rights: CapRights,
         vm_attributes: arch::VMAttributes,
     ) -> Result<WeakMappedMemoryRegion<shared_status::Exclusive>, VSpaceError> {
-        self.weak_map_region_internal(region, rights, vm_attributes)
+        self.weak_map_region_internal(region, rights, vm_attributes, PageBytes::USIZE)
+    }
+
+    /// Map a region of memory at the lowest free address that is a
+    /// multiple of `align` bytes, which must be a power of two.
+    pub fn map_region_aligned<SizeBits: Unsigned, Rt: Rights>(
+        &mut self,
+        region: UnmappedMemoryRegion<SizeBits, shared_status::Exclusive>,
+        rights: Rt,
+        vm_attributes: arch::VMAttributes,
+        align: usize,
+    ) -> Result<MappedMemoryRegion<SizeBits, shared_status::Exclusive, role::Local, Rt>, VSpaceError>
+    where
+        SizeBits: IsGreaterOrEqual<PageBits>,
+        SizeBits: Sub<PageBits>,
+        <SizeBits as Sub<PageBits>>::Output: Unsigned,
+        <SizeBits as Sub<PageBits>>::Output: _Pow,
+        Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+    {
+        self.map_region_internal(region, rights, vm_attributes, align)
+    }
+
+    /// Map a weak region of memory at the lowest free address that is
+    /// a multiple of `align` bytes, which must be a power of two.
+    pub fn weak_map_region_aligned(
+        &mut self,
+        region: WeakUnmappedMemoryRegion<shared_status::Exclusive>,
+        rights: CapRights,
+        vm_attributes: arch::VMAttributes,
+        align: usize,
+    ) -> Result<WeakMappedMemoryRegion<shared_status::Exclusive>, VSpaceError> {
+        self.weak_map_region_internal(region, rights, vm_attributes, align)
     }
 
     /// Map a region of memory at some address, then move it to a
     /// different cspace.
-    pub fn map_region_and_move<SizeBits: Unsigned, Role: CNodeRole>(
+    pub fn map_region_and_move<SizeBits: Unsigned, Role: CNodeRole, Rt: Rights>(
         &mut self,
         region: UnmappedMemoryRegion<SizeBits, shared_status::Exclusive>,
-        rights: CapRights,
+        _rights: Rt,
         vm_attributes: arch::VMAttributes,
         src_cnode: &LocalCap<LocalCNode>,
         dest_slots: CNodeSlots<NumPages<SizeBits>, Role>,
-    ) -> Result<MappedMemoryRegion<SizeBits, shared_status::Exclusive>, VSpaceError>
+    ) -> Result<MappedMemoryRegion<SizeBits, shared_status::Exclusive, role::Local, Rt>, VSpaceError>
     where
         SizeBits: IsGreaterOrEqual<PageBits>,
         SizeBits: Sub<PageBits>,
@@ -1014,12 +1864,12 @@ impl VSpace<vspace_state::Imaged, role::Local> {
     {
         self.weak_map_region_and_move(
             region.weaken(),
-            rights,
+            Rt::as_caprights(),
             vm_attributes,
             src_cnode,
             &mut dest_slots.weaken(),
         )
-        .and_then(|r| r.as_strong::<SizeBits>())
+        .and_then(|r| r.as_strong())
     }
     /// Map a weak region of memory at some address, then move it to a
     /// different cspace.
@@ -1031,24 +1881,21 @@ impl VSpace<vspace_state::Imaged, role::Local> {
         src_cnode: &LocalCap<LocalCNode>,
         dest_slots: &mut LocalCap<WCNodeSlotsData<Role>>,
     ) -> Result<WeakMappedMemoryRegion<shared_status::Exclusive>, VSpaceError> {
-        if dest_slots.size()
-            < num_pages(region.size_bits()).map_err(|_| VSpaceError::InvalidRegionSize)?
-        {
+        if dest_slots.size() < region.caps.len() {
             return Err(VSpaceError::InsufficientCNodeSlots);
         }
         let kind = region.kind;
-        let size_bits = region.size_bits();
+        let (size_bits, frame_bits) = (region.size_bits(), region.frame_bits());
         let mapped_region: WeakMappedMemoryRegion<shared_status::Exclusive> =
-            self.weak_map_region_internal(region, rights, vm_attributes)?;
+            self.weak_map_region_internal(region, rights, vm_attributes, PageBytes::USIZE)?;
         let vaddr = mapped_region.vaddr();
         let dest_init_cptr = dest_slots.cap_data.offset;
 
-        for (page, slot) in mapped_region
-            .caps
-            .into_iter()
+        for (frame, slot) in mapped_region
+            .frames()
             .zip(dest_slots.incrementally_consuming_iter())
         {
-            let _ = page.move_to_slot(src_cnode, slot)?;
+            let _ = frame.move_to_slot(src_cnode, slot)?;
         }
 
         Ok(WeakMappedMemoryRegion::unchecked_new(
@@ -1057,9 +1904,11 @@ impl VSpace<vspace_state::Imaged, role::Local> {
                 vaddr,
                 asid: self.asid,
                 rights,
+                vm_attributes,
             },
             kind,
             size_bits,
+            frame_bits,
         ))
     }
 
@@ -1069,14 +1918,14 @@ impl VSpace<vspace_state::Imaged, role::Local> {
     /// The incoming `UnmappedMemoryRegion` is only borrowed and one
     /// also gets back a new `MappedMemoryRegion` indexed with the
     /// status `Shared`.
-    pub fn map_shared_region<SizeBits: Unsigned>(
+    pub fn map_shared_region<SizeBits: Unsigned, Rt: Rights>(
         &mut self,
         region: &UnmappedMemoryRegion<SizeBits, shared_status::Shared>,
-        rights: CapRights,
+        rights: Rt,
         vm_attributes: arch::VMAttributes,
         slots: LocalCNodeSlots<NumPages<SizeBits>>,
         cnode: &LocalCap<LocalCNode>,
-    ) -> Result<MappedMemoryRegion<SizeBits, shared_status::Shared>, VSpaceError>
+    ) -> Result<MappedMemoryRegion<SizeBits, shared_status::Shared, role::Local, Rt>, VSpaceError>
     where
         SizeBits: IsGreaterOrEqual<PageBits>,
         SizeBits: Sub<PageBits>,
@@ -1084,9 +1933,18 @@ impl VSpace<vspace_state::Imaged, role::Local> {
         <SizeBits as Sub<PageBits>>::Output: _Pow,
         Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
     {
+        let slots_offset = slots.cap_data.offset;
+        for (slot, frame) in slots.iter().zip(region.frames()) {
+            let _ = frame.copy(cnode, slot, Rt::as_caprights())?;
+        }
         let unmapped_sr: UnmappedMemoryRegion<_, shared_status::Shared> =
-            UnmappedMemoryRegion::from_caps(region.caps.copy(cnode, slots, rights)?, region.kind);
-        self.map_region_internal(unmapped_sr, rights, vm_attributes)
+            MemoryRegion::unchecked_new(
+                slots_offset,
+                page_state::Unmapped,
+                region.kind,
+                region.frame_bits(),
+            );
+        self.map_region_internal(unmapped_sr, rights, vm_attributes, PageBytes::USIZE)
     }
     /// Map a _shared_ region of memory at some address, I don't care
     /// where. When `map_shared_region` is called, the caps making up
@@ -1110,9 +1968,14 @@ impl VSpace<vspace_state::Imaged, role::Local> {
                 WeakCopyError::SeL4Error(e) => VSpaceError::SeL4Error(e),
             })?;
         let unmapped_sr: WeakUnmappedMemoryRegion<shared_status::Shared> =
-            WeakMemoryRegion::try_from_caps(caps_copy, region.kind, region.size_bits())
-                .map_err(|_| VSpaceError::InvalidRegionSize)?;
-        self.weak_map_region_internal(unmapped_sr, rights, vm_attributes)
+            WeakMemoryRegion::try_from_caps(
+                caps_copy,
+                region.kind,
+                region.size_bits(),
+                region.frame_bits(),
+            )
+            .map_err(|_| VSpaceError::InvalidRegionSize)?;
+        self.weak_map_region_internal(unmapped_sr, rights, vm_attributes, PageBytes::USIZE)
     }
 
     /// For cases when one does not want to continue to duplicate the
@@ -1120,12 +1983,12 @@ impl VSpace<vspace_state::Imaged, role::Local> {
     /// address space in which this region will be mapped—that
     /// unmapped region can be consumed and a mapped region is
     /// returned.
-    pub fn map_shared_region_and_consume<SizeBits: Unsigned>(
+    pub fn map_shared_region_and_consume<SizeBits: Unsigned, Rt: Rights>(
         &mut self,
         region: UnmappedMemoryRegion<SizeBits, shared_status::Shared>,
-        rights: CapRights,
+        rights: Rt,
         vm_attributes: arch::VMAttributes,
-    ) -> Result<MappedMemoryRegion<SizeBits, shared_status::Shared>, VSpaceError>
+    ) -> Result<MappedMemoryRegion<SizeBits, shared_status::Shared, role::Local, Rt>, VSpaceError>
     where
         SizeBits: IsGreaterOrEqual<PageBits>,
         SizeBits: Sub<PageBits>,
@@ -1133,15 +1996,21 @@ impl VSpace<vspace_state::Imaged, role::Local> {
         <SizeBits as Sub<PageBits>>::Output: _Pow,
         Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
     {
-        self.map_region_internal(region, rights, vm_attributes)
+        self.map_region_internal(region, rights, vm_attributes, PageBytes::USIZE)
     }
 
-    fn map_region_internal<SizeBits: Unsigned, SSIn: SharedStatus, SSOut: SharedStatus>(
+    fn map_region_internal<
+        SizeBits: Unsigned,
+        SSIn: SharedStatus,
+        SSOut: SharedStatus,
+        Rt: Rights,
+    >(
         &mut self,
         region: UnmappedMemoryRegion<SizeBits, SSIn>,
-        rights: CapRights,
+        _rights: Rt,
         vm_attributes: arch::VMAttributes,
-    ) -> Result<MappedMemoryRegion<SizeBits, SSOut>, VSpaceError>
+        align: usize,
+    ) -> Result<MappedMemoryRegion<SizeBits, SSOut, role::Local, Rt>, VSpaceError>
     where
         SizeBits: IsGreaterOrEqual<PageBits>,
         SizeBits: Sub<PageBits>,
@@ -1149,19 +2018,24 @@ impl VSpace<vspace_state::Imaged, role::Local> {
         <SizeBits as Sub<PageBits>>::Output: _Pow,
         Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
     {
-        self.weak_map_region_internal(region.weaken(), rights, vm_attributes)
-            .and_then(|r| r.as_strong::<SizeBits>())
+        self.weak_map_region_internal(region.weaken(), Rt::as_caprights(), vm_attributes, align)
+            .and_then(|r| r.as_strong())
     }
     fn weak_map_region_internal<SSIn: SharedStatus, SSOut: SharedStatus>(
         &mut self,
         region: WeakUnmappedMemoryRegion<SSIn>,
         rights: CapRights,
         vm_attributes: arch::VMAttributes,
+        align: usize,
     ) -> Result<WeakMappedMemoryRegion<SSOut>, VSpaceError> {
+        if !align.is_power_of_two() {
+            return Err(VSpaceError::InvalidRegionSize);
+        }
+        // Each frame can only be mapped at a multiple of its size.
+        let frame_bits = region.frame_bits();
         let starting_address = self
-            .available_address_range
-            .auto_propose_region_start(region.size_bits())
-            .map_err(|_| VSpaceError::InsufficientAddressSpaceAvailableToMapRegion)?;
+            .address_allocator
+            .allocate(region.size_bytes(), core::cmp::max(align, 1 << frame_bits))?;
 
         // create the mapped region first because we need to pluck out
         // the `start_cptr` before the iteration below consumes the
@@ -1172,49 +2046,76 @@ impl VSpace<vspace_state::Imaged, role::Local> {
                 vaddr: starting_address,
                 asid: self.asid(),
                 rights,
+                vm_attributes,
             },
             region.kind,
             region.size_bits(),
+            frame_bits,
         );
 
+        let mut mapped_frames = 0;
         let mut vaddr = starting_address;
-        for page_cap in region.caps.into_iter() {
-            match self.layers.map_layer(
-                &page_cap,
-                vaddr,
-                &mut self.root,
-                rights,
-                vm_attributes,
-                &mut self.untyped,
-                &mut self.slots,
-            ) {
-                Err(MappingError::PageMapFailure(e))
-                | Err(MappingError::IntermediateLayerFailure(e)) => {
-                    return Err(VSpaceError::SeL4Error(e))
-                }
-                Err(e) => return Err(VSpaceError::MappingError(e)),
-                Ok(_) => self
-                    .available_address_range
-                    .observe_mapping(vaddr, PageBits::U8)?,
-            };
+        for frame_cap in region.frames() {
+            if let Err(e) =
+                self.map_frame_through_layers(&frame_cap, frame_bits, vaddr, rights, vm_attributes)
+            {
+                // Rollback the frames we've mapped thus far, and give
+                // the range back.
+                let _ = self.unmap_mapped_frames(
+                    mapped_region.caps.start_cptr,
+                    starting_address,
+                    mapped_frames,
+                    frame_bits,
+                    rights,
+                    vm_attributes,
+                );
+                let _ = self
+                    .address_allocator
+                    .release_unreserved(starting_address, mapped_region.size_bytes());
+                return Err(match e {
+                    MappingError::PageMapFailure(e) | MappingError::IntermediateLayerFailure(e) => {
+                        VSpaceError::SeL4Error(e)
+                    }
+                    e => VSpaceError::MappingError(e),
+                });
+            }
+            mapped_frames += 1;
             // It's safe to do a direct addition as we've already
             // determined that this region will fit here.
-            vaddr += PageBytes::USIZE;
+            vaddr += 1 << frame_bits;
         }
 
         Ok(mapped_region)
     }
 
-    pub(crate) fn skip_pages(&mut self, count: usize) -> Result<(), VSpaceError> {
-        for _ in 0..count {
-            let starting_address = self
-                .available_address_range
-                .auto_propose_region_start(PageBits::U8)
-                .map_err(|_| VSpaceError::ExceededAddressableSpace)?;
-            self.available_address_range
-                .observe_mapping(starting_address, PageBits::U8)?;
-        }
-        Ok(())
+    /// Unmap the first `count` frames of a region mapped from
+    /// `start_vaddr`, as when backing out of a mapping that failed
+    /// partway.
+    fn unmap_mapped_frames(
+        &mut self,
+        start_cptr: usize,
+        start_vaddr: usize,
+        count: usize,
+        frame_bits: u8,
+        rights: CapRights,
+        vm_attributes: arch::VMAttributes,
+    ) -> Result<(), SeL4Error> {
+        let asid = self.asid;
+        (0..count).try_for_each(|index| {
+            let frame = Cap {
+                cptr: start_cptr + index,
+                _role: PhantomData,
+                cap_data: Page {
+                    state: page_state::Mapped {
+                        vaddr: start_vaddr + (index << frame_bits),
+                        asid,
+                        rights,
+                        vm_attributes,
+                    },
+                },
+            };
+            self.unmap_frame(frame, frame_bits).map(|_f| ())
+        })
     }
 
     pub fn reserve<PageCount: Unsigned>(
@@ -1226,6 +2127,45 @@ impl VSpace<vspace_state::Imaged, role::Local> {
     {
         ReservedRegion::new(self, sacrificial_page)
     }
+
+    /// Claim a range of address space without mapping anything into
+    /// it, leaving its pages to be backed on first touch by a `Pager`.
+    pub fn reserve_lazy<SizeBits: Unsigned>(&mut self) -> Result<LazyRegion<SizeBits>, VSpaceError>
+    where
+        SizeBits: IsGreaterOrEqual<PageBits, Output = True>,
+    {
+        let size_bytes = bytes_from_size_bits(SizeBits::U8);
+        let vaddr = self
+            .address_allocator
+            .allocate(size_bytes, PageBytes::USIZE)?;
+        Ok(LazyRegion {
+            vaddr,
+            asid: self.asid,
+            _size_bits: PhantomData,
+        })
+    }
+}
+
+/// A range of a VSpace's address space that has been claimed but left
+/// unmapped, so that its pages may be committed on demand.
+pub struct LazyRegion<SizeBits: Unsigned> {
+    vaddr: usize,
+    asid: InternalASID,
+    _size_bits: PhantomData<SizeBits>,
+}
+
+impl<SizeBits: Unsigned> LazyRegion<SizeBits> {
+    pub fn vaddr(&self) -> usize {
+        self.vaddr
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        bytes_from_size_bits(SizeBits::U8)
+    }
+
+    pub(crate) fn asid(&self) -> InternalASID {
+        self.asid
+    }
 }
 
 /// A region of memory in a VSpace that has been reserved
@@ -1256,31 +2196,37 @@ where
         vspace: &mut VSpace,
         sacrificial_page: LocalCap<Page<page_state::Unmapped>>,
     ) -> Result<Self, VSpaceError> {
-        let mut unmapped_region = sacrificial_page.to_region();
-        let mut first_vaddr = None;
-        // Map (and then unmap) each page in the reserved range
-        // in order to trigger the instantiation of the backing paging
-        // structures.
+        let vaddr = vspace
+            .address_allocator
+            .allocate(PageCount::USIZE * PageBytes::USIZE, PageBytes::USIZE)?;
+        let mut page = sacrificial_page;
+        // Map (and then unmap) a page at each address in the reserved
+        // range in order to trigger the instantiation of the backing
+        // paging structures. The page is unmapped behind the VSpace's
+        // back, so that those structures still count as in use and are
+        // never reclaimed from under the scratch mappings.
         for i in 0..PageCount::USIZE {
-            let mapped_region =
-                vspace.map_region(unmapped_region, CapRights::RW, arch::vm_attributes::DEFAULT)?;
-            match first_vaddr {
-                None => {
-                    first_vaddr = Some(mapped_region.vaddr());
-                }
-                Some(start_vaddr) => {
-                    // N.B. This checks the assumption that repeated calls to map_region
-                    // (as above) will produce a continuous range of allocated address space
-                    // and backing intermediate paging structures
-                    assert_eq!(start_vaddr + i * PageBytes::USIZE, mapped_region.vaddr(),
-                        "Repeated calls to VSpace::map_region should produce a continuous range of addresses");
+            let remapped = vspace
+                .map_page_at_addr_without_claiming(
+                    page,
+                    vaddr + i * PageBytes::USIZE,
+                    CapRights::RW,
+                    arch::vm_attributes::DEFAULT,
+                )
+                .and_then(|mapped| mapped.unmap().map_err(VSpaceError::from));
+            page = match remapped {
+                Ok(page) => page,
+                Err(e) => {
+                    // Give the range back rather than lose it for good.
+                    let _ = vspace
+                        .address_allocator
+                        .release_unreserved(vaddr, PageCount::USIZE * PageBytes::USIZE);
+                    return Err(e);
                 }
-            }
-            unmapped_region = vspace.unmap_region(mapped_region)?;
+            };
         }
         Ok(ReservedRegion {
-            // Due to the type constraint that ensures PageCount >= 1, first_vaddr must be Some
-            vaddr: first_vaddr.unwrap(),
+            vaddr,
             asid: vspace.asid(),
             _page_count: PhantomData,
         })
@@ -1325,6 +2271,9 @@ impl<PageCount: Unsigned> ScratchRegion<PageCount> {
     /// sharing this page and mapping it into other address
     /// spaces. This enforced order ought to prevent one from
     /// forgetting to do the region-filling initialization.
+    ///
+    /// The scratch region only has the paging structures to map
+    /// pages, so a region made of larger frames can't be mapped here.
     pub fn temporarily_map_region<SizeBits: Unsigned, F, Out>(
         &mut self,
         region: &mut UnmappedMemoryRegion<SizeBits, shared_status::Exclusive>,
@@ -1338,10 +2287,13 @@ impl<PageCount: Unsigned> ScratchRegion<PageCount> {
         Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
         F: Fn(&mut MappedMemoryRegion<SizeBits, shared_status::Exclusive>) -> Out,
     {
+        if region.frame_bits() != PageBits::U8 {
+            return Err(VSpaceError::FrameTooLarge);
+        }
         let start_vaddr = self.reserved_region.vaddr;
         let mut next_addr = start_vaddr;
 
-        let res: Result<(), SeL4Error> = region.caps.for_each::<SeL4Error, _>(|page| {
+        for page in region.frames() {
             unsafe {
                 page.unchecked_page_map(
                     next_addr,
@@ -1351,11 +2303,7 @@ impl<PageCount: Unsigned> ScratchRegion<PageCount> {
                 )?;
             }
             next_addr += arch::PageBytes::USIZE;
-            let res: Result<(), SeL4Error> = Ok(());
-            res
-        });
-
-        res?;
+        }
 
         // synthesize a MappedMemoryRegion to pass to the callback
         let mut mapped_region = MemoryRegion::unchecked_new(
@@ -1364,14 +2312,16 @@ impl<PageCount: Unsigned> ScratchRegion<PageCount> {
                 vaddr: start_vaddr,
                 asid: self.reserved_region.asid,
                 rights: CapRights::RW,
+                vm_attributes: arch::vm_attributes::DEFAULT,
             },
             region.kind,
+            PageBits::U8,
         );
 
         let res = f(&mut mapped_region);
 
         // unmap everything
-        for page in mapped_region.caps.into_iter() {
+        for page in mapped_region.frames() {
             page.unmap()?;
         }
 
@@ -1379,63 +2329,6 @@ impl<PageCount: Unsigned> ScratchRegion<PageCount> {
     }
 }
 
-/// A dual-cursor address range tracker that maintains
-/// watermarks tracking an unallocated middle-region.
-#[derive(Debug, Clone)]
-struct AvailableAddressRange {
-    /// Watermark for the lowest starting address available
-    bottom: usize,
-    /// Watermark for the highest ending address available
-    top: usize,
-}
-
-impl Default for AvailableAddressRange {
-    fn default() -> Self {
-        AvailableAddressRange {
-            bottom: 0,
-            top: core::usize::MAX,
-        }
-    }
-}
-
-impl AvailableAddressRange {
-    fn observe_mapping(&mut self, start: usize, size_bits: u8) -> Result<(), VSpaceError> {
-        let size_bytes = bytes_from_size_bits(size_bits);
-        let end = start
-            .checked_add(size_bytes)
-            .ok_or(VSpaceError::ExceededAddressableSpace)?;
-        if end < self.bottom || start > self.top {
-            return Ok(());
-        }
-
-        let distance_from_top = self.top - start;
-        let distance_from_bottom = end - self.bottom;
-        if distance_from_bottom < distance_from_top {
-            self.bottom = core::cmp::max(self.bottom, end);
-        } else {
-            self.top = core::cmp::min(self.top, start);
-        }
-        Ok(())
-    }
-
-    fn auto_propose_region_start(&self, size_bits: u8) -> Result<usize, CouldNotAllocateRegion> {
-        if self.bottom > self.top {
-            return Err(CouldNotAllocateRegion);
-        }
-        let size_bytes = bytes_from_size_bits(size_bits);
-        let proposed_start = self.bottom;
-        let proposed_end = proposed_start
-            .checked_add(size_bytes)
-            .ok_or(CouldNotAllocateRegion)?;
-        if proposed_end > self.top {
-            return Err(CouldNotAllocateRegion);
-        }
-        Ok(proposed_start)
-    }
-}
-
-struct CouldNotAllocateRegion;
-
 fn bytes_from_size_bits(size_bits: u8) -> usize {
     2usize.pow(u32::from(size_bits))
 }
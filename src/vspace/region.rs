@@ -2,18 +2,22 @@ use core::cmp;
 use core::marker::PhantomData;
 use core::ops::Sub;
 
+use arrayvec::ArrayVec;
+use selfe_sys::{seL4_CNode_Revoke, seL4_WordBits};
 use typenum::*;
 
 use super::{KernelRetypeFanOutLimit, NumPages, VSpaceError};
-use crate::arch::{self, PageBits, PageBytes};
+use crate::alloc::micro_alloc::{DeviceAllocator, DeviceRangeAllocError, PageAlignedAddressRange};
+use crate::arch::{self, PageBits};
 use crate::cap::{
-    memory_kind, page_state, role, CNode, CNodeRole, CNodeSlots, Cap, CapRange, InternalASID,
-    LocalCNodeSlots, LocalCap, MemoryKind, Page, PageState, RetypeError, Untyped, WCNodeSlots,
-    WUntyped, WeakCapRange, WeakMemoryKind,
+    memory_kind, page_state, role, CNode, CNodeRole, CNodeSlots, Cap, CapRange, Frame, FrameSize,
+    InternalASID, LocalCNode, LocalCNodeSlots, LocalCap, MemoryKind, Page, PageState, RetypeError,
+    Untyped, WCNodeSlots, WUntyped, WeakCapRange, WeakCopyError, WeakMemoryKind,
 };
-use crate::error::SeL4Error;
+use crate::error::{ErrorExt, SeL4Error};
 
 use crate::pow::{Pow, _Pow};
+use crate::userland::rights::{self, Rights, Writable};
 use crate::userland::CapRights;
 
 pub trait SharedStatus: private::SealedSharedStatus {}
@@ -37,16 +41,21 @@ mod private {
 /// A `1 << SizeBits` bytes region of unmapped memory. It can be
 /// shared or owned exclusively. The ramifications of its shared
 /// status are described more completely in the `mapped_shared_region`
-/// function description.
+/// function description. It has no rights of its own until it is
+/// mapped, so its rights parameter is left at `RW`.
 #[allow(type_alias_bounds)]
 pub type UnmappedMemoryRegion<SizeBits, ShStatus, CapRole: CNodeRole = role::Local> =
-    MemoryRegion<page_state::Unmapped, SizeBits, ShStatus, CapRole>;
+    MemoryRegion<page_state::Unmapped, SizeBits, ShStatus, CapRole, rights::RW>;
 /// A memory region which is mapped into an address space, meaning it
 /// has a virtual address and an associated asid in which that virtual
-/// address is valid.
+/// address is valid, with the rights `Rt`.
 #[allow(type_alias_bounds)]
-pub type MappedMemoryRegion<SizeBits, ShStatus, CapRole: CNodeRole = role::Local> =
-    MemoryRegion<page_state::Mapped, SizeBits, ShStatus, CapRole>;
+pub type MappedMemoryRegion<
+    SizeBits,
+    ShStatus,
+    CapRole: CNodeRole = role::Local,
+    Rt: Rights = rights::RW,
+> = MemoryRegion<page_state::Mapped, SizeBits, ShStatus, CapRole, Rt>;
 #[allow(type_alias_bounds)]
 pub type WeakUnmappedMemoryRegion<ShStatus, CapRole: CNodeRole = role::Local> =
     WeakMemoryRegion<page_state::Unmapped, ShStatus, CapRole>;
@@ -58,11 +67,18 @@ pub type WeakMappedMemoryRegion<ShStatus, CapRole: CNodeRole = role::Local> =
 /// shared or owned exclusively. The ramifications of its shared
 /// status are described more completely in the `mapped_shared_region`
 /// function description.
+///
+/// The region is made up of frames of a single size, pages unless it
+/// was built with `new_in_frames` or `from_frame`; see `frame_bits`.
+///
+/// Once mapped, `Rt` is the rights it is mapped with, so that only a
+/// writable mapping can be written through.
 pub struct MemoryRegion<
     State: PageState,
     SizeBits: Unsigned,
     SS: SharedStatus,
     CapRole: CNodeRole = role::Local,
+    Rt: Rights = rights::RW,
 > where
     // Forces regions to be page-aligned.
     SizeBits: IsGreaterOrEqual<PageBits>,
@@ -71,14 +87,16 @@ pub struct MemoryRegion<
     <SizeBits as Sub<PageBits>>::Output: _Pow,
     Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
 {
-    pub(super) caps: CapRange<Page<State>, CapRole, NumPages<SizeBits>>,
+    pub(super) caps: WeakCapRange<Page<State>, CapRole>,
     pub(super) kind: WeakMemoryKind,
+    frame_bits: u8,
     _size_bits: PhantomData<SizeBits>,
     _shared_status: PhantomData<SS>,
+    _rights: PhantomData<Rt>,
 }
 
-impl<State: PageState, SizeBits: Unsigned, SS: SharedStatus, CapRole: CNodeRole>
-    MemoryRegion<State, SizeBits, SS, CapRole>
+impl<State: PageState, SizeBits: Unsigned, SS: SharedStatus, CapRole: CNodeRole, Rt: Rights>
+    MemoryRegion<State, SizeBits, SS, CapRole, Rt>
 where
     SizeBits: IsGreaterOrEqual<PageBits>,
     SizeBits: Sub<PageBits>,
@@ -98,15 +116,23 @@ where
         Self::SIZE_BYTES
     }
 
+    /// The number of bits needed to address each of the frames making
+    /// up this region.
+    pub fn frame_bits(&self) -> u8 {
+        self.frame_bits
+    }
+
     pub(super) fn from_caps(
         caps: CapRange<Page<State>, CapRole, NumPages<SizeBits>>,
         kind: WeakMemoryKind,
-    ) -> MemoryRegion<State, SizeBits, SS, CapRole> {
+    ) -> MemoryRegion<State, SizeBits, SS, CapRole, Rt> {
         MemoryRegion {
-            caps,
+            caps: caps.weaken(),
             kind,
+            frame_bits: PageBits::U8,
             _size_bits: PhantomData,
             _shared_status: PhantomData,
+            _rights: PhantomData,
         }
     }
 
@@ -114,17 +140,45 @@ where
         local_page_caps_offset_cptr: usize,
         state: State,
         kind: WeakMemoryKind,
+        frame_bits: u8,
     ) -> Self {
         MemoryRegion {
-            caps: CapRange::new(local_page_caps_offset_cptr, Page { state }),
+            caps: WeakCapRange::new(
+                local_page_caps_offset_cptr,
+                Page { state },
+                1 << (SizeBits::USIZE - usize::from(frame_bits)),
+            ),
             kind,
+            frame_bits,
             _size_bits: PhantomData,
             _shared_status: PhantomData,
+            _rights: PhantomData,
         }
     }
     pub fn weaken(self) -> WeakMemoryRegion<State, SS, CapRole> {
-        WeakMemoryRegion::try_from_caps(self.caps.weaken(), self.kind, SizeBits::U8)
-            .expect("Cap page slots to memory region size invariant maintained by type signature")
+        WeakMemoryRegion::try_from_caps(self.caps, self.kind, SizeBits::U8, self.frame_bits)
+            .expect("Cap frame slots to memory region size invariant maintained by constructors")
+    }
+
+    /// Only sound once every copy of the region's caps made by sharing
+    /// it has been deleted.
+    pub(crate) unsafe fn to_exclusive(
+        self,
+    ) -> MemoryRegion<State, SizeBits, shared_status::Exclusive, CapRole, Rt> {
+        MemoryRegion {
+            caps: self.caps,
+            kind: self.kind,
+            frame_bits: self.frame_bits,
+            _size_bits: PhantomData,
+            _shared_status: PhantomData,
+            _rights: PhantomData,
+        }
+    }
+
+    /// The caps to each of the frames making up this region, in
+    /// address order.
+    pub(super) fn frames(&self) -> impl Iterator<Item = Cap<Page<State>, CapRole>> {
+        frames_of(&self.caps, self.frame_bits)
     }
 
     /// N.B. until MemoryKind tracking is added to Page, this is a lossy
@@ -151,7 +205,9 @@ where
 
     /// In the Ok case, returns a shared, unmapped copy of the memory
     /// region (backed by fresh page-caps) along with this self-same
-    /// memory region, marked as shared.
+    /// memory region, marked as shared. A region made of frames larger
+    /// than a page only uses as many of `slots` as it has frames; the
+    /// weak `share` takes no more slots than that to begin with.
     pub fn share<CNodeSlotCount: Unsigned, DestRole: CNodeRole>(
         self,
         slots: CNodeSlots<CNodeSlotCount, DestRole>,
@@ -159,43 +215,68 @@ where
         rights: CapRights,
     ) -> Result<
         (
-            MemoryRegion<page_state::Unmapped, SizeBits, shared_status::Shared, DestRole>,
-            MemoryRegion<State, SizeBits, shared_status::Shared, CapRole>,
+            UnmappedMemoryRegion<SizeBits, shared_status::Shared, DestRole>,
+            MemoryRegion<State, SizeBits, shared_status::Shared, CapRole, Rt>,
         ),
         VSpaceError,
     >
     where
         CNodeSlotCount: IsEqual<NumPages<SizeBits>, Output = True>,
     {
-        let pages_offset = self.caps.start_cptr;
-        let original_mapped_state = self.caps.start_cap_data.state;
         let slots_offset = slots.cap_data.offset;
-        for (slot, page) in slots.iter().zip(self.caps.into_iter()) {
-            let _ = page.copy(cnode, slot, rights)?;
+        for (slot, frame) in slots.iter().zip(self.frames()) {
+            let _ = frame.copy(cnode, slot, rights)?;
         }
 
         Ok((
-            MemoryRegion::unchecked_new(slots_offset, page_state::Unmapped, self.kind),
-            MemoryRegion::from_caps(
-                CapRange::new(
-                    pages_offset,
-                    Page {
-                        state: original_mapped_state,
-                    },
-                ),
+            MemoryRegion::unchecked_new(
+                slots_offset,
+                page_state::Unmapped,
                 self.kind,
+                self.frame_bits,
             ),
+            MemoryRegion {
+                caps: self.caps,
+                kind: self.kind,
+                frame_bits: self.frame_bits,
+                _size_bits: PhantomData,
+                _shared_status: PhantomData,
+                _rights: PhantomData,
+            },
         ))
     }
 }
 
+impl<State: PageState, SizeBits: Unsigned, SS: SharedStatus, Rt: Rights>
+    MemoryRegion<State, SizeBits, SS, role::Local, Rt>
+where
+    SizeBits: IsGreaterOrEqual<PageBits>,
+    SizeBits: Sub<PageBits>,
+    <SizeBits as Sub<PageBits>>::Output: Unsigned,
+    <SizeBits as Sub<PageBits>>::Output: _Pow,
+    Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+{
+    /// The physical address of each of the frames making up this
+    /// region, in virtual address order. A region retyped from several
+    /// untypeds needn't be physically contiguous, so a device that
+    /// reads or writes it by DMA needs all of them.
+    pub fn frame_paddrs(&self) -> impl Iterator<Item = Result<usize, SeL4Error>> {
+        self.frames().map(|frame| frame.paddr())
+    }
+}
+
 impl LocalCap<Page<page_state::Unmapped>> {
     /// N.B. until MemoryKind tracking is added to Page, this is a lossy
     /// conversion that will assume the Page was for General memory
     pub(crate) fn to_region(
         self,
     ) -> MemoryRegion<page_state::Unmapped, PageBits, shared_status::Exclusive> {
-        MemoryRegion::unchecked_new(self.cptr, self.cap_data.state, WeakMemoryKind::General)
+        MemoryRegion::unchecked_new(
+            self.cptr,
+            self.cap_data.state,
+            WeakMemoryKind::General,
+            PageBits::U8,
+        )
     }
 }
 
@@ -235,15 +316,47 @@ where
         Ok(UnmappedMemoryRegion::from_caps(page_caps, kind.weaken()))
     }
 
+    /// Retype the untyped into as few frames as will cover it, each
+    /// of the largest size the paging structures can map, and return
+    /// the unmapped region. Mapping it takes one paging entry and one
+    /// CNode slot per frame rather than per page, which suits large
+    /// buffers such as framebuffers and DMA pools.
+    pub fn new_in_frames<MemKind: MemoryKind>(
+        ut: LocalCap<Untyped<SizeBits, MemKind>>,
+        slots: &mut WCNodeSlots,
+    ) -> Result<Self, VSpaceError> {
+        WeakMemoryRegion::new_in_frames(ut.weaken(), slots)?.as_strong()
+    }
+
+    /// N.B. until MemoryKind tracking is added to Frame, this is a
+    /// lossy conversion that will assume the Frame was for General
+    /// memory
+    pub fn from_frame<Size: FrameSize<SizeBits = SizeBits>>(frame: LocalCap<Frame<Size>>) -> Self {
+        MemoryRegion::unchecked_new(
+            frame.cptr,
+            frame.cap_data.state,
+            WeakMemoryKind::General,
+            SizeBits::U8,
+        )
+    }
+
     /// A shared region of memory can be duplicated. When it is
     /// mapped, it's _borrowed_ rather than consumed allowing for its
     /// remapping into other address spaces.
     pub fn to_shared(self) -> UnmappedMemoryRegion<SizeBits, shared_status::Shared> {
-        UnmappedMemoryRegion::from_caps(self.caps, self.kind)
+        MemoryRegion {
+            caps: self.caps,
+            kind: self.kind,
+            frame_bits: self.frame_bits,
+            _size_bits: PhantomData,
+            _shared_status: PhantomData,
+            _rights: PhantomData,
+        }
     }
 }
 
-impl<SizeBits: Unsigned, SS: SharedStatus> MappedMemoryRegion<SizeBits, SS>
+impl<SizeBits: Unsigned, SS: SharedStatus, Rt: Rights>
+    MappedMemoryRegion<SizeBits, SS, role::Local, Rt>
 where
     SizeBits: IsGreaterOrEqual<PageBits>,
     SizeBits: Sub<PageBits>,
@@ -259,46 +372,84 @@ where
         self.caps.start_cap_data.state.asid
     }
 
+    /// The cptr of the cap to the first page; the rest follow on
+    /// contiguously.
+    pub(crate) fn start_cptr(&self) -> usize {
+        self.caps.start_cptr
+    }
+
     pub fn rights(&self) -> CapRights {
         self.caps.start_cap_data.state.rights
     }
 
-    pub fn as_slice(&self) -> &[u8] {
-        unsafe { core::slice::from_raw_parts(self.vaddr() as *const u8, self.size_bytes()) }
+    pub fn vm_attributes(&self) -> arch::VMAttributes {
+        self.caps.start_cap_data.state.vm_attributes
     }
 
-    pub fn as_mut_slice(&mut self) -> &mut [u8] {
-        unsafe { core::slice::from_raw_parts_mut(self.vaddr() as *mut u8, self.size_bytes()) }
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.vaddr() as *const u8, self.size_bytes()) }
     }
 
     pub fn flush(&self) -> Result<(), SeL4Error> {
-        self.caps.for_each::<SeL4Error, _>(|cap| {
+        let frame_bytes = 1 << self.frame_bits;
+        for frame in self.frames() {
             unsafe {
-                arch::flush_page(cap.cptr)?;
+                arch::flush_frame(frame.cptr, frame_bytes)?;
             }
-            Ok(())
-        })?;
+        }
 
         Ok(())
     }
 
     pub fn flush_range(&self, vaddr: usize, size: usize) -> Result<(), SeL4Error> {
-        let bottom = vaddr & !0xFFF;
-        let top = vaddr + cmp::max(PageBytes::USIZE, size);
+        let frame_bytes = 1 << self.frame_bits;
+        let bottom = vaddr & !(frame_bytes - 1);
+        let top = vaddr + cmp::max(frame_bytes, size);
         let range = bottom..top;
-        self.caps.for_each::<SeL4Error, _>(|cap| {
-            if range.contains(&cap.vaddr()) {
+        for frame in self.frames() {
+            if range.contains(&frame.vaddr()) {
                 unsafe {
-                    arch::flush_page(cap.cptr)?;
+                    arch::flush_frame(frame.cptr, frame_bytes)?;
                 }
             }
-            Ok(())
-        })?;
+        }
 
         Ok(())
     }
 
-    #[cfg(feature = "test_support")]
+    /// Write the data cache back to memory over `size` bytes from
+    /// `vaddr`, as before a device reads them by DMA. Only the part of
+    /// the range inside this region is touched.
+    pub fn clean_range(&self, vaddr: usize, size: usize) -> Result<(), SeL4Error> {
+        for_frames_in_range(&self.caps, self.frame_bits, vaddr, size, arch::clean_frame)
+    }
+
+    /// Discard the data cache over `size` bytes from `vaddr` without
+    /// writing it back, as after a device writes them by DMA. Only the
+    /// part of the range inside this region is touched.
+    pub fn invalidate_range(&self, vaddr: usize, size: usize) -> Result<(), SeL4Error> {
+        for_frames_in_range(
+            &self.caps,
+            self.frame_bits,
+            vaddr,
+            size,
+            arch::invalidate_frame,
+        )
+    }
+
+    /// Make the instruction cache coherent with the data cache over
+    /// `size` bytes from `vaddr`, as after loading code into them.
+    /// Only the part of the range inside this region is touched.
+    pub fn unify_instruction_range(&self, vaddr: usize, size: usize) -> Result<(), SeL4Error> {
+        for_frames_in_range(
+            &self.caps,
+            self.frame_bits,
+            vaddr,
+            size,
+            arch::unify_instruction_frame,
+        )
+    }
+
     /// Super dangerous copy-aliasing
     pub(crate) unsafe fn dangerous_internal_alias(&mut self) -> Self {
         MappedMemoryRegion::unchecked_new(
@@ -307,18 +458,21 @@ where
                 vaddr: self.vaddr(),
                 asid: self.asid(),
                 rights: self.rights(),
+                vm_attributes: self.vm_attributes(),
             },
             self.kind,
+            self.frame_bits,
         )
     }
 
-    /// Halve a region into two regions.
+    /// Halve a region into two regions. A region made of a single
+    /// frame can't be split.
     pub fn split(
         self,
     ) -> Result<
         (
-            MappedMemoryRegion<op!(SizeBits - U1), SS>,
-            MappedMemoryRegion<op!(SizeBits - U1), SS>,
+            MappedMemoryRegion<op!(SizeBits - U1), SS, role::Local, Rt>,
+            MappedMemoryRegion<op!(SizeBits - U1), SS, role::Local, Rt>,
         ),
         VSpaceError,
     >
@@ -340,39 +494,34 @@ where
             return Err(VSpaceError::ExceededAddressableSpace);
         };
 
+        if self.caps.len() < 2 {
+            return Err(VSpaceError::FrameTooLarge);
+        }
         let new_offset = self.caps.start_cptr + (self.caps.len() / 2);
 
         Ok((
-            MappedMemoryRegion {
-                caps: CapRange::new(
-                    self.caps.start_cptr,
-                    Page {
-                        state: page_state::Mapped {
-                            vaddr: self.vaddr(),
-                            asid: self.asid(),
-                            rights: self.rights(),
-                        },
-                    },
-                ),
-                kind: self.kind,
-                _size_bits: PhantomData,
-                _shared_status: PhantomData,
-            },
-            MappedMemoryRegion {
-                caps: CapRange::new(
-                    new_offset,
-                    Page {
-                        state: page_state::Mapped {
-                            vaddr: new_region_vaddr,
-                            asid: self.asid(),
-                            rights: self.rights(),
-                        },
-                    },
-                ),
-                kind: self.kind,
-                _size_bits: PhantomData,
-                _shared_status: PhantomData,
-            },
+            MappedMemoryRegion::unchecked_new(
+                self.caps.start_cptr,
+                page_state::Mapped {
+                    vaddr: self.vaddr(),
+                    asid: self.asid(),
+                    rights: self.rights(),
+                    vm_attributes: self.vm_attributes(),
+                },
+                self.kind,
+                self.frame_bits,
+            ),
+            MappedMemoryRegion::unchecked_new(
+                new_offset,
+                page_state::Mapped {
+                    vaddr: new_region_vaddr,
+                    asid: self.asid(),
+                    rights: self.rights(),
+                    vm_attributes: self.vm_attributes(),
+                },
+                self.kind,
+                self.frame_bits,
+            ),
         ))
     }
 
@@ -394,8 +543,8 @@ where
         self,
     ) -> Result<
         (
-            MappedMemoryRegion<TargetSize, SS>,
-            MappedMemoryRegion<op!(SizeBits - U1), SS>,
+            MappedMemoryRegion<TargetSize, SS, role::Local, Rt>,
+            MappedMemoryRegion<op!(SizeBits - U1), SS, role::Local, Rt>,
         ),
         VSpaceError,
     >
@@ -415,32 +564,47 @@ where
         Pow<<<SizeBits as Sub<U1>>::Output as Sub<PageBits>>::Output>: Unsigned,
     {
         let (a, b) = self.split()?;
+        if TargetSize::U8 < a.frame_bits {
+            return Err(VSpaceError::FrameTooLarge);
+        }
 
         Ok((
-            MappedMemoryRegion {
-                caps: CapRange::new(
-                    a.caps.start_cptr,
-                    Page {
-                        state: page_state::Mapped {
-                            vaddr: a.vaddr(),
-                            asid: a.asid(),
-                            rights: a.rights(),
-                        },
-                    },
-                ),
-                kind: a.kind,
-                _size_bits: PhantomData,
-                _shared_status: PhantomData,
-            },
+            MappedMemoryRegion::unchecked_new(
+                a.caps.start_cptr,
+                page_state::Mapped {
+                    vaddr: a.vaddr(),
+                    asid: a.asid(),
+                    rights: a.rights(),
+                    vm_attributes: a.vm_attributes(),
+                },
+                a.kind,
+                a.frame_bits,
+            ),
             b,
         ))
     }
 }
 
+impl<SizeBits: Unsigned, SS: SharedStatus, Rt: Writable>
+    MappedMemoryRegion<SizeBits, SS, role::Local, Rt>
+where
+    SizeBits: IsGreaterOrEqual<PageBits>,
+    SizeBits: Sub<PageBits>,
+    <SizeBits as Sub<PageBits>>::Output: Unsigned,
+    <SizeBits as Sub<PageBits>>::Output: _Pow,
+    Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+{
+    /// Only a writable mapping can be written through; see `Writable`.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.vaddr() as *mut u8, self.size_bytes()) }
+    }
+}
+
 pub struct WeakMemoryRegion<State: PageState, SS: SharedStatus, CapRole: CNodeRole = role::Local> {
     pub(super) caps: WeakCapRange<Page<State>, CapRole>,
     pub(super) kind: WeakMemoryKind,
     size_bits: u8,
+    frame_bits: u8,
     _shared_status: PhantomData<SS>,
 }
 
@@ -456,26 +620,216 @@ impl WeakMemoryRegion<page_state::Unmapped, shared_status::Exclusive> {
             caps,
             kind,
             size_bits,
+            frame_bits: PageBits::U8,
+            _shared_status: PhantomData,
+        })
+    }
+
+    /// Like `new`, but made of as few frames as will cover the
+    /// untyped, each of the largest size the paging structures can
+    /// map.
+    pub fn new_in_frames<MemKind: MemoryKind>(
+        untyped: LocalCap<WUntyped<MemKind>>,
+        slots: &mut WCNodeSlots,
+    ) -> Result<Self, RetypeError> {
+        let kind = untyped.cap_data.kind.weaken();
+        let size_bits = untyped.size_bits();
+        let (caps, frame_bits) = untyped.retype_largest_frames(slots)?;
+        Ok(WeakMemoryRegion {
+            caps,
+            kind,
+            size_bits,
+            frame_bits,
             _shared_status: PhantomData,
         })
     }
+
+    /// Take the device memory in `address_range` from
+    /// `device_allocator` as a region of pages. The range may span
+    /// several of the allocator's untypeds, but must be a power of two
+    /// in size. Its page caps, and any untyped split off along the way,
+    /// take their slots from `slots`.
+    ///
+    /// Should it fail part way through, the pages retyped so far are
+    /// deleted, the untypeds go back to `device_allocator` and the page
+    /// slots go back to `slots`. Untypeds split apart along the way
+    /// stay split, so the slots the splits took stay taken.
+    pub fn new_device_range(
+        device_allocator: &mut DeviceAllocator,
+        address_range: PageAlignedAddressRange,
+        slots: &mut WCNodeSlots,
+    ) -> Result<Self, VSpaceError> {
+        let start = address_range.start();
+        let size_bytes = address_range.size_bytes();
+        if !size_bytes.is_power_of_two() {
+            return Err(DeviceRangeAllocError::RangeSizeNotAPowerOfTwo.into());
+        }
+        let end = start
+            .checked_add(size_bytes)
+            .ok_or(VSpaceError::ExceededAddressableSpace)?;
+
+        let mut untypeds = ArrayVec::new();
+        if let Err(e) = take_device_untypeds(device_allocator, start, end, slots, &mut untypeds) {
+            give_back_device_untypeds(device_allocator, slots, &untypeds);
+            return Err(e);
+        }
+
+        // Every untyped's pages go into the same run of slots, so that
+        // they make up a single region.
+        let num_pages = size_bytes >> PageBits::USIZE;
+        let mut page_slots = match slots.alloc(num_pages) {
+            Ok(page_slots) => page_slots,
+            Err(_) => {
+                give_back_device_untypeds(device_allocator, slots, &untypeds);
+                return Err(VSpaceError::InsufficientCNodeSlots);
+            }
+        };
+        let start_cptr = page_slots.cap_data.offset;
+        for &(cptr, chunk) in &untypeds {
+            if let Err(e) = device_untyped(cptr, chunk).retype_pages(&mut page_slots) {
+                give_back_device_untypeds(device_allocator, slots, &untypeds);
+                // The page slots were the last taken from the front of
+                // `slots`, so they can go straight back.
+                slots.cap_data.offset = start_cptr;
+                slots.cap_data.size += num_pages;
+                return Err(e.into());
+            }
+        }
+
+        Ok(WeakMemoryRegion::unchecked_new(
+            start_cptr,
+            page_state::Unmapped,
+            WeakMemoryKind::Device { paddr: start },
+            size_bytes.trailing_zeros() as u8,
+            PageBits::U8,
+        ))
+    }
 }
+
+/// The most device untypeds a range given to
+/// `WeakMemoryRegion::new_device_range` may be made up of.
+pub type MaxDeviceRangeUntypeds = U32;
+
+type DeviceRangeUntypeds =
+    ArrayVec<[(usize, PageAlignedAddressRange); MaxDeviceRangeUntypeds::USIZE]>;
+
+/// Take the device untypeds covering `start` to `end` from
+/// `device_allocator`, recording each one's cptr and address range in
+/// `untypeds`.
+fn take_device_untypeds(
+    device_allocator: &mut DeviceAllocator,
+    start: usize,
+    end: usize,
+    slots: &mut WCNodeSlots,
+    untypeds: &mut DeviceRangeUntypeds,
+) -> Result<(), VSpaceError> {
+    let max_chunk = KernelRetypeFanOutLimit::USIZE << PageBits::USIZE;
+    let mut paddr = start;
+    while paddr < end {
+        let untyped_end = device_allocator
+            .end_of_untyped_containing(paddr)
+            .ok_or(DeviceRangeAllocError::AddressStartNotFound)?;
+        // Take the largest aligned untyped which runs past neither
+        // the range nor the untyped the allocator holds it in.
+        let limit = cmp::min(end, untyped_end) - paddr;
+        let mut chunk = (limit + 1).next_power_of_two() >> 1;
+        while paddr % chunk != 0 || chunk > max_chunk {
+            chunk >>= 1;
+        }
+        let chunk_range = PageAlignedAddressRange::new_by_size(paddr, chunk)
+            .expect("Chunks are page-aligned and at least a page in size");
+        if untypeds.is_full() {
+            return Err(DeviceRangeAllocError::TooManyDeviceUntypeds.into());
+        }
+        let untyped = device_allocator.get_untyped_by_address_range(chunk_range, slots)?;
+        untypeds.push((untyped.cptr, chunk_range));
+        paddr += chunk;
+    }
+    Ok(())
+}
+
+/// Revoke each of `untypeds`, deleting any pages retyped from them,
+/// and return them to `device_allocator`. Any that can't be revoked or
+/// taken back are lost.
+fn give_back_device_untypeds(
+    device_allocator: &mut DeviceAllocator,
+    slots: &WCNodeSlots,
+    untypeds: &DeviceRangeUntypeds,
+) {
+    for &(cptr, chunk) in untypeds {
+        let revoked = unsafe { seL4_CNode_Revoke(slots.cptr, cptr, seL4_WordBits as u8) };
+        if revoked.as_result().is_ok() {
+            let _ = device_allocator.give_back(device_untyped(cptr, chunk));
+        }
+    }
+}
+
+fn device_untyped(
+    cptr: usize,
+    chunk: PageAlignedAddressRange,
+) -> LocalCap<WUntyped<memory_kind::Device>> {
+    Cap {
+        cptr,
+        cap_data: WUntyped {
+            kind: memory_kind::Device {
+                paddr: chunk.start(),
+            },
+            size_bits: chunk.size_bytes().trailing_zeros() as u8,
+        },
+        _role: PhantomData,
+    }
+}
+
 impl<State: PageState, SS: SharedStatus> WeakMemoryRegion<State, SS, role::Local> {
     pub(super) fn unchecked_new(
         local_page_caps_offset_cptr: usize,
         state: State,
         kind: WeakMemoryKind,
         size_bits: u8,
+        frame_bits: u8,
     ) -> Self {
-        let num_pages = num_pages(size_bits)
-            .expect("Calling functions maintain the invariant that the size_bits is over the size of a page");
+        let num_frames = num_frames(size_bits, frame_bits)
+            .expect("Calling functions maintain the invariant that the size_bits is over the size of a frame");
         WeakMemoryRegion {
-            caps: WeakCapRange::new(local_page_caps_offset_cptr, Page { state }, num_pages),
+            caps: WeakCapRange::new(local_page_caps_offset_cptr, Page { state }, num_frames),
             kind,
             size_bits,
+            frame_bits,
             _shared_status: PhantomData,
         }
     }
+
+    /// In the Ok case, returns a shared, unmapped copy of the memory
+    /// region (backed by fresh frame caps) along with this self-same
+    /// memory region, marked as shared. Only as many slots are taken
+    /// from `slots` as the region has frames.
+    pub fn share(
+        self,
+        slots: &mut WCNodeSlots,
+        cnode: &LocalCap<LocalCNode>,
+        rights: CapRights,
+    ) -> Result<
+        (
+            WeakUnmappedMemoryRegion<shared_status::Shared>,
+            WeakMemoryRegion<State, shared_status::Shared>,
+        ),
+        VSpaceError,
+    > {
+        let caps_copy = self.caps.copy(cnode, slots, rights).map_err(|e| match e {
+            WeakCopyError::NotEnoughSlots => VSpaceError::InsufficientCNodeSlots,
+            WeakCopyError::SeL4Error(e) => VSpaceError::SeL4Error(e),
+        })?;
+        Ok((
+            WeakMemoryRegion {
+                caps: caps_copy,
+                kind: self.kind,
+                size_bits: self.size_bits,
+                frame_bits: self.frame_bits,
+                _shared_status: PhantomData,
+            },
+            self.to_shared(),
+        ))
+    }
 }
 impl<State: PageState, SS: SharedStatus, CapRole: CNodeRole> WeakMemoryRegion<State, SS, CapRole> {
     /// The number of bits needed to address this region
@@ -487,25 +841,59 @@ impl<State: PageState, SS: SharedStatus, CapRole: CNodeRole> WeakMemoryRegion<St
     pub fn size_bytes(&self) -> usize {
         2usize.pow(u32::from(self.size_bits))
     }
+
+    /// The number of bits needed to address each of the frames making
+    /// up this region.
+    pub fn frame_bits(&self) -> u8 {
+        self.frame_bits
+    }
+
     pub(super) fn try_from_caps(
         caps: WeakCapRange<Page<State>, CapRole>,
         kind: WeakMemoryKind,
         size_bits: u8,
+        frame_bits: u8,
     ) -> Result<WeakMemoryRegion<State, SS, CapRole>, InvalidSizeBits> {
-        if num_pages(size_bits)? != caps.len() {
+        if num_frames(size_bits, frame_bits)? != caps.len() {
             return Err(InvalidSizeBits::SizeBitsMismatchPageCapCount);
         }
         Ok(WeakMemoryRegion {
             caps,
             kind,
             size_bits,
+            frame_bits,
             _shared_status: PhantomData,
         })
     }
 
-    pub(super) fn as_strong<SizeBits: Unsigned>(
+    /// The caps to each of the frames making up this region, in
+    /// address order.
+    pub(super) fn frames(&self) -> impl Iterator<Item = Cap<Page<State>, CapRole>> {
+        frames_of(&self.caps, self.frame_bits)
+    }
+
+    /// It's up to the caller that `Rt` matches the rights a mapped
+    /// region was mapped with.
+    pub(crate) fn as_strong<SizeBits: Unsigned, Rt: Rights>(
+        self,
+    ) -> Result<MemoryRegion<State, SizeBits, SS, CapRole, Rt>, VSpaceError>
+    where
+        // Forces regions to be page-aligned.
+        SizeBits: IsGreaterOrEqual<PageBits>,
+        SizeBits: Sub<PageBits>,
+        <SizeBits as Sub<PageBits>>::Output: Unsigned,
+        <SizeBits as Sub<PageBits>>::Output: _Pow,
+        Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+    {
+        self.try_as_strong()
+            .map_err(|_| VSpaceError::InvalidRegionSize)
+    }
+
+    /// As `as_strong`, handing the region back if it isn't `SizeBits`
+    /// in size.
+    pub(crate) fn try_as_strong<SizeBits: Unsigned, Rt: Rights>(
         self,
-    ) -> Result<MemoryRegion<State, SizeBits, SS, CapRole>, VSpaceError>
+    ) -> Result<MemoryRegion<State, SizeBits, SS, CapRole, Rt>, Self>
     where
         // Forces regions to be page-aligned.
         SizeBits: IsGreaterOrEqual<PageBits>,
@@ -515,12 +903,16 @@ impl<State: PageState, SS: SharedStatus, CapRole: CNodeRole> WeakMemoryRegion<St
         Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
     {
         if self.size_bits != SizeBits::U8 {
-            return Err(VSpaceError::InvalidRegionSize);
+            return Err(self);
         }
-        Ok(MemoryRegion::from_caps(
-            CapRange::new(self.caps.start_cptr, self.caps.start_cap_data),
-            self.kind,
-        ))
+        Ok(MemoryRegion {
+            caps: self.caps,
+            kind: self.kind,
+            frame_bits: self.frame_bits,
+            _size_bits: PhantomData,
+            _shared_status: PhantomData,
+            _rights: PhantomData,
+        })
     }
 
     pub fn to_shared(self) -> WeakMemoryRegion<State, shared_status::Shared, CapRole> {
@@ -528,6 +920,21 @@ impl<State: PageState, SS: SharedStatus, CapRole: CNodeRole> WeakMemoryRegion<St
             caps: self.caps,
             kind: self.kind,
             size_bits: self.size_bits,
+            frame_bits: self.frame_bits,
+            _shared_status: PhantomData,
+        }
+    }
+
+    /// Only sound once every copy of the region's caps made by sharing
+    /// it has been deleted.
+    pub(crate) unsafe fn to_exclusive(
+        self,
+    ) -> WeakMemoryRegion<State, shared_status::Exclusive, CapRole> {
+        WeakMemoryRegion {
+            caps: self.caps,
+            kind: self.kind,
+            size_bits: self.size_bits,
+            frame_bits: self.frame_bits,
             _shared_status: PhantomData,
         }
     }
@@ -542,12 +949,56 @@ impl<SS: SharedStatus, CapRole: CNodeRole> WeakMappedMemoryRegion<SS, CapRole> {
         self.caps.start_cap_data.state.asid
     }
 
+    pub fn rights(&self) -> CapRights {
+        self.caps.start_cap_data.state.rights
+    }
+
+    pub fn vm_attributes(&self) -> arch::VMAttributes {
+        self.caps.start_cap_data.state.vm_attributes
+    }
+
     pub fn as_slice(&self) -> &[u8] {
         unsafe { core::slice::from_raw_parts(self.vaddr() as *const u8, self.size_bytes()) }
     }
 
-    pub fn as_mut_slice(&mut self) -> &mut [u8] {
-        unsafe { core::slice::from_raw_parts_mut(self.vaddr() as *mut u8, self.size_bytes()) }
+    /// The region's memory, if it is mapped writable. Unlike a strong
+    /// region's, a weak region's rights are only known at runtime.
+    pub fn as_mut_slice(&mut self) -> Option<&mut [u8]> {
+        if !self.rights().is_writable() {
+            return None;
+        }
+        Some(unsafe { core::slice::from_raw_parts_mut(self.vaddr() as *mut u8, self.size_bytes()) })
+    }
+
+    /// Write the data cache back to memory over `size` bytes from
+    /// `vaddr`. See `MappedMemoryRegion::clean_range`.
+    pub fn clean_range(&self, vaddr: usize, size: usize) -> Result<(), SeL4Error> {
+        for_frames_in_range(&self.caps, self.frame_bits, vaddr, size, arch::clean_frame)
+    }
+
+    /// Discard the data cache over `size` bytes from `vaddr` without
+    /// writing it back. See `MappedMemoryRegion::invalidate_range`.
+    pub fn invalidate_range(&self, vaddr: usize, size: usize) -> Result<(), SeL4Error> {
+        for_frames_in_range(
+            &self.caps,
+            self.frame_bits,
+            vaddr,
+            size,
+            arch::invalidate_frame,
+        )
+    }
+
+    /// Make the instruction cache coherent with the data cache over
+    /// `size` bytes from `vaddr`. See
+    /// `MappedMemoryRegion::unify_instruction_range`.
+    pub fn unify_instruction_range(&self, vaddr: usize, size: usize) -> Result<(), SeL4Error> {
+        for_frames_in_range(
+            &self.caps,
+            self.frame_bits,
+            vaddr,
+            size,
+            arch::unify_instruction_frame,
+        )
     }
 }
 
@@ -558,11 +1009,57 @@ pub(super) enum InvalidSizeBits {
     SizeBitsTooBig,
 }
 
-pub(super) fn num_pages(size_bits: u8) -> Result<usize, InvalidSizeBits> {
-    if size_bits < PageBits::U8 {
+pub(super) fn num_frames(size_bits: u8, frame_bits: u8) -> Result<usize, InvalidSizeBits> {
+    if frame_bits < PageBits::U8 || size_bits < frame_bits {
         return Err(InvalidSizeBits::TooSmallToRepresentAPage);
     }
     2usize
-        .checked_pow(u32::from(size_bits) - PageBits::U32)
+        .checked_pow(u32::from(size_bits - frame_bits))
         .ok_or(InvalidSizeBits::SizeBitsTooBig)
 }
+
+/// The caps in `caps`, each to a frame `1 << frame_bits` bytes on from
+/// the last.
+fn frames_of<State: PageState, CapRole: CNodeRole>(
+    caps: &WeakCapRange<Page<State>, CapRole>,
+    frame_bits: u8,
+) -> impl Iterator<Item = Cap<Page<State>, CapRole>> {
+    let (start_cptr, start_state) = (caps.start_cptr, caps.start_cap_data.state);
+    (0..caps.len()).map(move |index| Cap {
+        cptr: start_cptr + index,
+        cap_data: Page {
+            state: start_state
+                .offset_by(index << frame_bits)
+                .expect("Earlier checks confirm the memory fits into available space"),
+        },
+        _role: PhantomData,
+    })
+}
+
+/// Call `op` with the cptr of each frame in `caps` which overlaps the
+/// `size` bytes from `vaddr`, along with the start and end offsets of
+/// that overlap into the frame.
+fn for_frames_in_range<CapRole: CNodeRole>(
+    caps: &WeakCapRange<Page<page_state::Mapped>, CapRole>,
+    frame_bits: u8,
+    vaddr: usize,
+    size: usize,
+    op: unsafe fn(usize, usize, usize) -> Result<(), SeL4Error>,
+) -> Result<(), SeL4Error> {
+    let region_start = caps.start_cap_data.state.vaddr;
+    let region_end = region_start + (caps.len() << frame_bits);
+    let bottom = cmp::max(vaddr, region_start);
+    let top = cmp::min(vaddr.saturating_add(size), region_end);
+    if bottom >= top {
+        return Ok(());
+    }
+    let first = (bottom - region_start) >> frame_bits;
+    let last = (top - 1 - region_start) >> frame_bits;
+    for index in first..=last {
+        let frame_start = region_start + (index << frame_bits);
+        let start = cmp::max(bottom, frame_start) - frame_start;
+        let end = cmp::min(top, frame_start + (1 << frame_bits)) - frame_start;
+        unsafe { op(caps.start_cptr + index, start, end)? };
+    }
+    Ok(())
+}
@@ -0,0 +1,315 @@
+//! Relocation of position-independent elf images.
+//!
+//! The relocation tables are found through the image's `PT_DYNAMIC`
+//! segment rather than its section headers, so that stripped images
+//! can be loaded too.
+use xmas_elf::header::Class;
+use xmas_elf::program::{self, ProgramHeader};
+use xmas_elf::ElfFile;
+
+use crate::vspace::{patch_word, VSpaceError};
+
+/// The relocation which adds the load bias to a word of the image;
+/// the only kind a statically linked position-independent executable
+/// needs.
+#[cfg(target_arch = "arm")]
+const RELATIVE: u32 = 23; // R_ARM_RELATIVE
+#[cfg(target_arch = "aarch64")]
+const RELATIVE: u32 = 1027; // R_AARCH64_RELATIVE
+#[cfg(target_arch = "x86_64")]
+const RELATIVE: u32 = 8; // R_X86_64_RELATIVE
+#[cfg(target_arch = "riscv64")]
+const RELATIVE: u32 = 3; // R_RISCV_RELATIVE
+
+/// A relocation that does nothing, on every architecture.
+const NONE: u32 = 0;
+
+// The dynamic section tags locating the relocation tables
+const DT_NULL: usize = 0;
+const DT_RELA: usize = 7;
+const DT_RELASZ: usize = 8;
+const DT_RELAENT: usize = 9;
+const DT_REL: usize = 17;
+const DT_RELSZ: usize = 18;
+const DT_RELENT: usize = 19;
+
+const WORD: usize = core::mem::size_of::<usize>();
+
+struct Relocation {
+    /// The link-time address of the word to relocate
+    offset: usize,
+    kind: u32,
+    /// `None` for `Rel` entries, whose addend is the word itself
+    addend: Option<usize>,
+}
+
+/// One of an image's relocation tables, as laid out in the file.
+struct Table<'a> {
+    entries: &'a [u8],
+    /// Whether these are `Rela` entries, which carry their addend
+    explicit_addend: bool,
+}
+
+impl<'a> Table<'a> {
+    fn empty(explicit_addend: bool) -> Self {
+        Table {
+            entries: &[],
+            explicit_addend,
+        }
+    }
+
+    fn entry_size(&self) -> usize {
+        if self.explicit_addend {
+            3 * WORD
+        } else {
+            2 * WORD
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len() / self.entry_size()
+    }
+
+    fn get(&self, index: usize) -> Relocation {
+        let entry = &self.entries[index * self.entry_size()..];
+        Relocation {
+            offset: read_word(entry, 0),
+            kind: relocation_type(read_word(entry, 1)),
+            addend: if self.explicit_addend {
+                Some(read_word(entry, 2))
+            } else {
+                None
+            },
+        }
+    }
+
+    /// The index of the first relocation which touches memory at or
+    /// past `vaddr`, provided the table is sorted by offset.
+    fn first_reaching(&self, vaddr: usize) -> usize {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.get(mid).offset + WORD <= vaddr {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    fn is_sorted(&self) -> bool {
+        (1..self.len()).all(|i| self.get(i - 1).offset <= self.get(i).offset)
+    }
+}
+
+fn read_word(bytes: &[u8], index: usize) -> usize {
+    let mut word = [0u8; WORD];
+    word.copy_from_slice(&bytes[index * WORD..(index + 1) * WORD]);
+    usize::from_ne_bytes(word)
+}
+
+#[cfg(target_pointer_width = "32")]
+fn relocation_type(info: usize) -> u32 {
+    (info & 0xff) as u32
+}
+
+#[cfg(target_pointer_width = "64")]
+fn relocation_type(info: usize) -> u32 {
+    (info & 0xffff_ffff) as u32
+}
+
+/// The relocations of a position-independent image, along with the
+/// bias they are applied with.
+pub(crate) struct Relocations<'a, 'e> {
+    elf: &'e ElfFile<'a>,
+    bias: usize,
+    rel: Table<'a>,
+    rela: Table<'a>,
+    /// Whether both tables are sorted by offset, as linkers lay them
+    /// out, so that `apply` can go straight to the relocations for the
+    /// part of the image at hand
+    sorted: bool,
+}
+
+impl<'a, 'e> Relocations<'a, 'e> {
+    /// Find the image's relocation tables and check that every
+    /// relocation in them is one we can apply while copying its
+    /// segments: a relative relocation of a writable segment. Read-only
+    /// segments are shared straight from the user image, so there's
+    /// nowhere to apply relocations to them.
+    pub(crate) fn new(elf: &'e ElfFile<'a>, bias: usize) -> Result<Self, VSpaceError> {
+        let word_class = if WORD == 4 {
+            Class::ThirtyTwo
+        } else {
+            Class::SixtyFour
+        };
+        if elf.header.pt1.class() != word_class {
+            return Err(VSpaceError::ElfParseError(
+                "Image word size doesn't match the target's",
+            ));
+        }
+
+        let mut relocations = Relocations {
+            elf,
+            bias,
+            rel: Table::empty(false),
+            rela: Table::empty(true),
+            sorted: false,
+        };
+        if let Some(dynamic) = elf
+            .program_iter()
+            .find(|h| h.get_type() == Ok(program::Type::Dynamic))
+        {
+            relocations.find_tables(dynamic)?;
+        }
+        relocations.sorted = relocations.rel.is_sorted() && relocations.rela.is_sorted();
+
+        relocations.try_for_each(|r| match r.kind {
+            NONE => Ok(()),
+            RELATIVE => match relocations.segment_containing(r.offset) {
+                Some(h) if h.flags().is_write() => Ok(()),
+                _ => Err(VSpaceError::RelocationOutsideWritableSegment(r.offset)),
+            },
+            kind => Err(VSpaceError::UnsupportedRelocation(kind)),
+        })?;
+        Ok(relocations)
+    }
+
+    fn find_tables(&mut self, dynamic: ProgramHeader<'a>) -> Result<(), VSpaceError> {
+        let start = dynamic.offset() as usize;
+        let entries = self
+            .elf
+            .input
+            .get(start..start + dynamic.file_size() as usize)
+            .ok_or(VSpaceError::ElfParseError(
+                "Dynamic segment extends past the end of the file",
+            ))?;
+
+        let (mut rel, mut rel_size, mut rel_entry) = (None, 0, 2 * WORD);
+        let (mut rela, mut rela_size, mut rela_entry) = (None, 0, 3 * WORD);
+        for entry in entries.chunks_exact(2 * WORD) {
+            let value = read_word(entry, 1);
+            match read_word(entry, 0) {
+                DT_NULL => break,
+                DT_REL => rel = Some(value),
+                DT_RELSZ => rel_size = value,
+                DT_RELENT => rel_entry = value,
+                DT_RELA => rela = Some(value),
+                DT_RELASZ => rela_size = value,
+                DT_RELAENT => rela_entry = value,
+                _ => (),
+            }
+        }
+        if rel_entry != self.rel.entry_size() || rela_entry != self.rela.entry_size() {
+            return Err(VSpaceError::ElfParseError(
+                "Unexpected relocation entry size",
+            ));
+        }
+        if let Some(vaddr) = rel {
+            self.rel.entries = self.file_bytes(vaddr, rel_size)?;
+        }
+        if let Some(vaddr) = rela {
+            self.rela.entries = self.file_bytes(vaddr, rela_size)?;
+        }
+        Ok(())
+    }
+
+    /// The `size` bytes of the file loaded at the link-time address
+    /// `vaddr`.
+    fn file_bytes(&self, vaddr: usize, size: usize) -> Result<&'a [u8], VSpaceError> {
+        let header = self
+            .segment_containing(vaddr)
+            .ok_or(VSpaceError::ElfParseError(
+                "Relocation table outside the loaded segments",
+            ))?;
+        let offset_in_segment = vaddr - header.virtual_addr() as usize;
+        if offset_in_segment + size > header.file_size() as usize {
+            return Err(VSpaceError::ElfParseError(
+                "Relocation table extends past its segment",
+            ));
+        }
+        let start = header.offset() as usize + offset_in_segment;
+        self.elf
+            .input
+            .get(start..start + size)
+            .ok_or(VSpaceError::ElfParseError(
+                "Relocation table extends past the end of the file",
+            ))
+    }
+
+    /// Apply the relocations which fall within `dest`, which holds the
+    /// part of the image linked at `link_vaddr`.
+    pub(crate) fn apply(&self, dest: &mut [u8], link_vaddr: usize) -> Result<(), VSpaceError> {
+        let dest_end = link_vaddr + dest.len();
+        for table in [&self.rel, &self.rela].iter() {
+            let first = if self.sorted {
+                table.first_reaching(link_vaddr)
+            } else {
+                0
+            };
+            for index in first..table.len() {
+                let r = table.get(index);
+                if r.offset >= dest_end {
+                    if self.sorted {
+                        break;
+                    }
+                    continue;
+                }
+                if r.kind == NONE || r.offset + WORD <= link_vaddr {
+                    continue;
+                }
+                let addend = match r.addend {
+                    Some(addend) => addend,
+                    None => self.implicit_addend(r.offset)?,
+                };
+                patch_word(dest, link_vaddr, r.offset, self.bias.wrapping_add(addend));
+            }
+        }
+        Ok(())
+    }
+
+    fn try_for_each<F>(&self, mut f: F) -> Result<(), VSpaceError>
+    where
+        F: FnMut(Relocation) -> Result<(), VSpaceError>,
+    {
+        for table in [&self.rel, &self.rela].iter() {
+            for index in 0..table.len() {
+                f(table.get(index))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The word at the link-time address `vaddr`, as found in the file.
+    fn implicit_addend(&self, vaddr: usize) -> Result<usize, VSpaceError> {
+        let mut word = 0usize.to_ne_bytes();
+        let header = self
+            .segment_containing(vaddr)
+            .ok_or(VSpaceError::RelocationOutsideWritableSegment(vaddr))?;
+        let offset_in_segment = vaddr - header.virtual_addr() as usize;
+        // Words beyond the file-backed part of the segment are zeroed
+        if offset_in_segment + word.len() <= header.file_size() as usize {
+            let start = header.offset() as usize + offset_in_segment;
+            let bytes =
+                self.elf
+                    .input
+                    .get(start..start + word.len())
+                    .ok_or(VSpaceError::ElfParseError(
+                        "Relocation extends past the end of the file",
+                    ))?;
+            word.copy_from_slice(bytes);
+        }
+        Ok(usize::from_ne_bytes(word))
+    }
+
+    fn segment_containing(&self, vaddr: usize) -> Option<ProgramHeader<'a>> {
+        self.elf
+            .program_iter()
+            .filter(|h| h.get_type() == Ok(program::Type::Load))
+            .find(|h| {
+                let start = h.virtual_addr() as usize;
+                vaddr >= start && vaddr < start + h.mem_size() as usize
+            })
+    }
+}
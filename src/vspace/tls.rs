@@ -0,0 +1,180 @@
+//! Thread-local storage blocks, laid out according to the TLS ABI
+//! of the target architecture and initialized from the `PT_TLS`
+//! template of an elf image.
+use core::cmp::{max, min};
+use core::ops::Sub;
+
+use typenum::*;
+
+use crate::arch::{PageBits, PageBytes};
+use crate::pow::{Pow, _Pow};
+use crate::vspace::{shared_status, MappedMemoryRegion, VSpaceError};
+
+/// The space the ABI reserves for the implementation between the
+/// thread pointer and the start of the TLS data (variant I).
+#[cfg(target_arch = "aarch64")]
+const TCB_SIZE: usize = 16;
+#[cfg(target_arch = "arm")]
+const TCB_SIZE: usize = 8;
+#[cfg(any(target_arch = "riscv64", target_arch = "x86_64"))]
+const TCB_SIZE: usize = 0;
+
+/// Whether the TLS data sits below the thread pointer, which points
+/// at a word holding its own value (variant II).
+#[cfg(target_arch = "x86_64")]
+const DATA_BELOW_THREAD_POINTER: bool = true;
+#[cfg(not(target_arch = "x86_64"))]
+const DATA_BELOW_THREAD_POINTER: bool = false;
+
+/// The thread-local storage template of an elf image: the initialized
+/// data (`.tdata`) every thread's block starts out with, followed by
+/// zeroed space (`.tbss`).
+#[derive(Debug, Clone, Copy)]
+pub struct TLSImage<'a> {
+    data: &'a [u8],
+    mem_size: usize,
+    align: usize,
+}
+
+/// Where things go within a TLS block, as offsets from its start.
+struct Layout {
+    data: usize,
+    thread_pointer: usize,
+    size: usize,
+}
+
+impl<'a> TLSImage<'a> {
+    /// Find the TLS template of an elf image, if it has one.
+    pub fn from_elf(elf_data: &'a [u8]) -> Result<Option<Self>, VSpaceError> {
+        let elf = xmas_elf::ElfFile::new(elf_data).map_err(VSpaceError::ElfParseError)?;
+        Self::from_elf_file(&elf)
+    }
+
+    pub(crate) fn from_elf_file(elf: &xmas_elf::ElfFile<'a>) -> Result<Option<Self>, VSpaceError> {
+        let header = match elf
+            .program_iter()
+            .find(|h| h.get_type() == Ok(xmas_elf::program::Type::Tls))
+        {
+            Some(h) => h,
+            None => return Ok(None),
+        };
+
+        // The initialized data is the start of the block, so it can't
+        // run past the end of it.
+        if header.file_size() > header.mem_size() {
+            return Err(VSpaceError::ElfParseError(
+                "TLS segment has more file data than memory",
+            ));
+        }
+
+        let offset = header.offset() as usize;
+        let data = elf
+            .input
+            .get(offset..offset + header.file_size() as usize)
+            .ok_or(VSpaceError::ElfParseError(
+                "TLS segment extends past the end of the file",
+            ))?;
+
+        // An alignment of zero means there's no constraint
+        let align = max(header.align() as usize, 1);
+        // Blocks are page aligned, so any alignment up to a page is met
+        // by laying the block out relative to its start.
+        if !align.is_power_of_two() || align > PageBytes::USIZE {
+            return Err(VSpaceError::ElfParseError(
+                "TLS segment alignment is not supported",
+            ));
+        }
+
+        Ok(Some(TLSImage {
+            data,
+            mem_size: header.mem_size() as usize,
+            align,
+        }))
+    }
+
+    fn layout(&self) -> Layout {
+        if DATA_BELOW_THREAD_POINTER {
+            let thread_pointer = round_up(self.mem_size, self.align);
+            Layout {
+                data: thread_pointer - self.mem_size,
+                thread_pointer,
+                size: thread_pointer + core::mem::size_of::<usize>(),
+            }
+        } else {
+            let data = round_up(TCB_SIZE, self.align);
+            Layout {
+                data,
+                thread_pointer: 0,
+                size: data + self.mem_size,
+            }
+        }
+    }
+
+    /// The number of bytes each thread's block needs.
+    pub fn block_size(&self) -> usize {
+        self.layout().size
+    }
+
+    /// The number of pages each thread's block needs.
+    pub fn block_pages(&self) -> usize {
+        round_up(self.block_size(), PageBytes::USIZE) / PageBytes::USIZE
+    }
+
+    /// Initialize a block for one thread in `region`, which must be
+    /// mapped at the same address in the thread's VSpace as it is in
+    /// this one. Returns the TLS base to give the thread's TCB.
+    pub fn initialize_region<SizeBits: Unsigned>(
+        &self,
+        region: &mut MappedMemoryRegion<SizeBits, shared_status::Exclusive>,
+    ) -> Result<usize, VSpaceError>
+    where
+        SizeBits: IsGreaterOrEqual<PageBits>,
+        SizeBits: Sub<PageBits>,
+        <SizeBits as Sub<PageBits>>::Output: Unsigned,
+        <SizeBits as Sub<PageBits>>::Output: _Pow,
+        Pow<<SizeBits as Sub<PageBits>>::Output>: Unsigned,
+    {
+        if region.size_bytes() < self.block_size() {
+            return Err(VSpaceError::TLSBlockTooSmall);
+        }
+        let block_vaddr = region.vaddr();
+        self.fill(region.as_mut_slice(), 0, block_vaddr);
+        region.flush()?;
+        Ok(self.tls_base(block_vaddr))
+    }
+
+    /// The TLS base of a thread whose block starts at `block_vaddr`.
+    pub fn tls_base(&self, block_vaddr: usize) -> usize {
+        block_vaddr + self.layout().thread_pointer
+    }
+
+    /// Write the part of a block starting at `block_vaddr` that lies
+    /// `offset` bytes into the block to `dest`.
+    pub(crate) fn fill(&self, dest: &mut [u8], offset: usize, block_vaddr: usize) {
+        let layout = self.layout();
+        for byte in dest.iter_mut() {
+            *byte = 0;
+        }
+
+        let start = max(offset, layout.data);
+        let end = min(offset + dest.len(), layout.data + self.data.len());
+        if start < end {
+            dest[start - offset..end - offset]
+                .copy_from_slice(&self.data[start - layout.data..end - layout.data]);
+        }
+
+        if DATA_BELOW_THREAD_POINTER {
+            let self_pointer = (block_vaddr + layout.thread_pointer).to_ne_bytes();
+            for (i, byte) in self_pointer.iter().enumerate() {
+                let at = layout.thread_pointer + i;
+                if at >= offset && at < offset + dest.len() {
+                    dest[at - offset] = *byte;
+                }
+            }
+        }
+    }
+}
+
+fn round_up(n: usize, align: usize) -> usize {
+    (n + align - 1) & !(align - 1)
+}